@@ -51,6 +51,7 @@ pub async fn run_api_server(
             .configure(controller::init_metrics_receiver_controller)
             .configure(controller::init_inflow_controller)
             .configure(controller::init_definition_controller)
+            .configure(controller::init_expression_controller)
     })
     .workers(1)
     .bind((host.clone(), port));
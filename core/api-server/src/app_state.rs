@@ -1,13 +1,24 @@
 use actix_web::web;
 use data_layer::data_layer::DataLayer;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
+
+// A partitioned leader must not hang the ingestion of the followers
+const FORWARD_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+pub const FORWARD_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct AppState {
     pub data_layer: Arc<DataLayer>,
+    // The shared HTTP client to forward the metrics to the leader(High Availability)
+    pub http_client: reqwest::Client,
 }
 
 pub fn get_app_state(shared_data_layer: Arc<DataLayer>) -> web::Data<AppState> {
     web::Data::new(AppState {
         data_layer: shared_data_layer,
+        http_client: reqwest::Client::builder()
+            .connect_timeout(FORWARD_CONNECT_TIMEOUT)
+            .timeout(FORWARD_TIMEOUT)
+            .build()
+            .unwrap_or_default(),
     })
 }
@@ -0,0 +1,92 @@
+use crate::app_state::AppState;
+use actix_web::{post, web, HttpResponse, Responder};
+use data_layer::types::expression_evaluation::ExpressionEvaluationRequest;
+use tracing::{debug, error};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(post_expressions_evaluate);
+}
+
+// Evaluate an expression in a fresh engine with the buffered metrics (e.g. "try expression" in the web app)
+// The errors of the expression are in the result. The error status is only for the failures of the evaluator.
+#[post("/api/expressions/evaluate")]
+async fn post_expressions_evaluate(
+    request: web::Json<ExpressionEvaluationRequest>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    debug!("Evaluating expression: {:?}", request);
+    if request.expression.trim().is_empty() {
+        return HttpResponse::BadRequest().body("expression is empty");
+    }
+    let result = app_state
+        .data_layer
+        .evaluate_expression(request.into_inner())
+        .await;
+    if result.is_err() {
+        error!("Failed to evaluate expression: {:?}", result);
+        return HttpResponse::ServiceUnavailable().body(format!("{:?}", result));
+    }
+    HttpResponse::Ok().json(result.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::init;
+    use crate::utils::test_utils::get_app_state_for_test;
+    use actix_web::{test, App};
+    use data_layer::types::expression_evaluation::{
+        ExpressionEvaluationError, ExpressionEvaluationResult,
+    };
+    use serde_json::json;
+
+    #[actix_web::test]
+    #[tracing_test::traced_test]
+    async fn test_post_expressions_evaluate() {
+        let app_state = get_app_state_for_test().await;
+        // The evaluator of wave-autoscale
+        let mut receiver = app_state
+            .data_layer
+            .take_expression_evaluation_receiver()
+            .unwrap();
+        tokio::spawn(async move {
+            while let Some(job) = receiver.recv().await {
+                let result = if job.request.expression == "1 +" {
+                    ExpressionEvaluationResult::from_error(ExpressionEvaluationError {
+                        message: "SyntaxError".to_string(),
+                        line: Some(1),
+                        column: None,
+                    })
+                } else {
+                    ExpressionEvaluationResult {
+                        result: Some(json!(2)),
+                        ..Default::default()
+                    }
+                };
+                let _ = job.result_sender.send(result);
+            }
+        });
+        let app = test::init_service(App::new().app_data(app_state).configure(init)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/expressions/evaluate")
+            .set_json(json!({ "expression": "1 + 1", "time_offset_sec": 60 }))
+            .to_request();
+        let result: ExpressionEvaluationResult = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(result.result, Some(json!(2)));
+        assert!(result.error.is_none());
+
+        let req = test::TestRequest::post()
+            .uri("/api/expressions/evaluate")
+            .set_json(json!({ "expression": "1 +" }))
+            .to_request();
+        let result: ExpressionEvaluationResult = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(result.error.unwrap().line, Some(1));
+
+        let req = test::TestRequest::post()
+            .uri("/api/expressions/evaluate")
+            .set_json(json!({ "expression": " " }))
+            .to_request();
+        let response = test::call_service(&app, req).await;
+        assert!(response.status().is_client_error());
+    }
+}
@@ -1,8 +1,9 @@
 use crate::app_state::AppState;
 use actix_web::{
+    http::StatusCode,
     post,
     web::{self, Bytes},
-    HttpResponse, Responder,
+    HttpRequest, HttpResponse, Responder,
 };
 use serde::Deserialize;
 use serde_json::json;
@@ -18,19 +19,87 @@ pub fn init(cfg: &mut web::ServiceConfig) {
 //     metrics: Vec<MetricDefinition>,
 // }
 
+// The header of the metrics forwarded by a follower. The leader never forwards them again.
+const FORWARDED_HEADER: &str = "x-wave-autoscale-forwarded";
+
 #[derive(Deserialize)]
 struct PostMetricsReceiverQuery {
     collector: String,
     metric_id: String,
 }
 
+// Forward the metrics to the leader(High Availability) and return the status code of the leader
+async fn forward_metrics_to_leader(
+    http_client: &reqwest::Client,
+    metrics_forward_url: &str,
+    query: &PostMetricsReceiverQuery,
+    bytes: &Bytes,
+) -> Result<StatusCode, reqwest::Error> {
+    let response = http_client
+        .post(metrics_forward_url)
+        .query(&[
+            ("collector", query.collector.as_str()),
+            ("metric_id", query.metric_id.as_str()),
+        ])
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(FORWARDED_HEADER, "1")
+        .body(bytes.to_vec())
+        .send()
+        .await?;
+    Ok(StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY))
+}
+
 #[post("/api/metrics-receiver")]
 async fn post_metrics_receiver(
+    request: HttpRequest,
     query: web::Query<PostMetricsReceiverQuery>,
     bytes: Bytes,
     app_state: web::Data<AppState>,
 ) -> impl Responder {
     debug!("Received metrics from collector: {}", query.collector);
+
+    // If this instance is not the leader, forward the metrics to the leader.
+    // If it fails, save the metrics into this instance so that they are not lost.
+    // If it times out, the leader may have saved them, so the collector retries them with 503.
+    // The forwarded metrics are saved into this instance even if it is not the leader(e.g. the instances disagree on the leader),
+    // so that they don't loop between the instances.
+    let is_forwarded = request.headers().contains_key(FORWARDED_HEADER);
+    let metrics_forward_url = if is_forwarded {
+        None
+    } else {
+        app_state.data_layer.get_metrics_forward_url()
+    };
+    if let Some(metrics_forward_url) = metrics_forward_url {
+        match forward_metrics_to_leader(
+            &app_state.http_client,
+            metrics_forward_url.as_str(),
+            &query,
+            &bytes,
+        )
+        .await
+        {
+            Ok(status_code) => {
+                debug!(
+                    "[api-server] Forwarded metrics to the leader: {} - {}",
+                    metrics_forward_url, status_code
+                );
+                return HttpResponse::build(status_code).finish();
+            }
+            Err(error) if error.is_timeout() => {
+                error!(
+                    "Timed out forwarding metrics to the leader({}): {}",
+                    metrics_forward_url, error
+                );
+                return HttpResponse::ServiceUnavailable().body("Timed out forwarding metrics");
+            }
+            Err(error) => {
+                error!(
+                    "Failed to forward metrics to the leader({}): {}",
+                    metrics_forward_url, error
+                );
+            }
+        }
+    }
     let (collector, metric_id) = (query.collector.clone(), query.metric_id.clone());
     let body_text = String::from_utf8(bytes.to_vec());
     if body_text.is_err() {
@@ -198,6 +267,7 @@ async fn post_metrics_receiver(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::app_state::FORWARD_TIMEOUT;
     use crate::utils::test_utils::get_app_state_for_test;
     use actix_web::{http, test, App};
 
@@ -262,6 +332,91 @@ mod tests {
         // TODO: Check database
     }
 
+    #[actix_web::test]
+    #[tracing_test::traced_test]
+    async fn test_post_metrics_receiver_forward_failed() {
+        let app_state = get_app_state_for_test().await;
+        // Nobody listens on the port, so it saves the metrics into this instance
+        app_state
+            .data_layer
+            .set_metrics_forward_url(Some("http://127.0.0.1:1/api/metrics-receiver".to_string()));
+        let app = test::init_service(App::new().app_data(app_state).configure(init)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/metrics-receiver?collector=vector&metric_id=metric_forward")
+            .set_payload(
+                r#"{
+                    "metrics": [
+                        {
+                            "name": "metric1",
+                            "gauge": {
+                                "value": 1
+                            }
+                        }
+                    ]
+                }"#,
+            )
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    #[tracing_test::traced_test]
+    async fn test_post_metrics_receiver_forward_timed_out() {
+        // The leader accepts the connection but never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let leader = tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(FORWARD_TIMEOUT * 2).await;
+        });
+        let app_state = get_app_state_for_test().await;
+        app_state
+            .data_layer
+            .set_metrics_forward_url(Some(format!("http://{}/api/metrics-receiver", address)));
+        let app = test::init_service(App::new().app_data(app_state).configure(init)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/metrics-receiver?collector=vector&metric_id=metric_forward_timeout")
+            .set_payload(r#"{ "metrics": [{ "name": "metric1", "gauge": { "value": 1 } }] }"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+        leader.abort();
+    }
+
+    #[actix_web::test]
+    #[tracing_test::traced_test]
+    async fn test_post_metrics_receiver_forwarded_once() {
+        let app_state = get_app_state_for_test().await;
+        app_state
+            .data_layer
+            .set_metrics_forward_url(Some("http://127.0.0.1:1/api/metrics-receiver".to_string()));
+        let app = test::init_service(App::new().app_data(app_state).configure(init)).await;
+
+        // The metrics forwarded already are saved without forwarding again
+        let req = test::TestRequest::post()
+            .uri("/api/metrics-receiver?collector=vector&metric_id=metric_forwarded")
+            .insert_header((FORWARDED_HEADER, "1"))
+            .set_payload(
+                r#"{
+                    "metrics": [
+                        {
+                            "name": "metric1",
+                            "gauge": {
+                                "value": 1
+                            }
+                        }
+                    ]
+                }"#,
+            )
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert!(!logs_contain("Failed to forward metrics to the leader"));
+    }
+
     #[actix_web::test]
     #[tracing_test::traced_test]
     async fn test_post_metrics_receiver_invalid_collector() {
@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE expression_library (
+  db_id TEXT PRIMARY KEY,
+  id TEXT UNIQUE,
+  language TEXT,
+  metadata TEXT,
+  code TEXT,
+  enabled BOOLEAN,
+  created_at timestamptz,
+  updated_at timestamptz
+);
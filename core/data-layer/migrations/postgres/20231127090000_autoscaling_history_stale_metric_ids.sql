@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE autoscaling_history ADD COLUMN stale_metric_ids_json TEXT;
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE autoscaling_history ADD COLUMN logs_json TEXT;
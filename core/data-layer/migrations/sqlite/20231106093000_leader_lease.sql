@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE leader_lease (
+  id TEXT PRIMARY KEY,
+  holder_id TEXT NOT NULL,
+  holder_endpoint TEXT,
+  expires_at BIGINT NOT NULL,
+  updated_at TEXT
+);
\ No newline at end of file
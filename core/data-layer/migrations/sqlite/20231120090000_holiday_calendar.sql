@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE holiday_calendar (
+  db_id TEXT PRIMARY KEY,
+  id TEXT UNIQUE,
+  metadata TEXT,
+  dates TEXT,
+  enabled BOOLEAN,
+  created_at TEXT,
+  updated_at TEXT
+);
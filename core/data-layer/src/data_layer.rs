@@ -1,11 +1,17 @@
 use crate::{
     reader::wave_definition_reader::read_definition_yaml,
     types::{
-        autoscaling_history_definition::AutoscalingHistoryDefinition, object_kind::ObjectKind,
+        autoscaling_history_definition::AutoscalingHistoryDefinition,
+        expression_evaluation::{
+            ExpressionEvaluationJob, ExpressionEvaluationRequest, ExpressionEvaluationResult,
+        },
+        leader_lease_definition::LeaderLeaseDefinition,
+        object_kind::ObjectKind,
         source_metrics::SourceMetrics,
     },
     variable_mapper::{execute_variable_mapper, get_variable_mapper},
-    MetricDefinition, ScalingComponentDefinition, ScalingPlanDefinition,
+    ExpressionLibraryDefinition, HolidayCalendarDefinition, MetricDefinition,
+    ScalingComponentDefinition, ScalingPlanDefinition,
 };
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
@@ -36,6 +42,9 @@ use uuid::Uuid;
 
 const DEFAULT_DB_URL: &str = "sqlite://wave.db";
 const DEFAULT_METRIC_BUFFER_SIZE_KB: u64 = 500_000;
+const EXPRESSION_EVALUATION_TIMEOUT_SEC: u64 = 10;
+// The id of the lease row for the leader election(High Availability)
+const LEADER_LEASE_ID: &str = "wave-autoscale";
 
 #[derive(Debug)]
 pub struct SourceMetricsData {
@@ -65,6 +74,15 @@ pub struct DataLayer {
     pool: AnyPool,
     source_metrics_data: SharedSourceMetricsData,
     action_sender: tokio::sync::broadcast::Sender<serde_json::Value>,
+    // Send the metric_id when the metrics are ingested (for the plans triggered on metric updates)
+    metric_ingestion_sender: tokio::sync::broadcast::Sender<String>,
+    // The URL of the metrics receiver of the leader to forward the ingested metrics(High Availability)
+    // None means that this instance processes the metrics by itself.
+    metrics_forward_url: Arc<RwLock<Option<String>>>,
+    // The ad-hoc evaluations of the expressions from the API server are processed by the evaluator of wave-autoscale
+    expression_evaluation_sender: tokio::sync::mpsc::Sender<ExpressionEvaluationJob>,
+    expression_evaluation_receiver:
+        Arc<std::sync::Mutex<Option<tokio::sync::mpsc::Receiver<ExpressionEvaluationJob>>>>,
 }
 
 impl DataLayer {
@@ -90,11 +108,20 @@ impl DataLayer {
             source_metrics_data.enable_metrics_log = enable_metrics_log;
         }
         let (action_sender, _) = tokio::sync::broadcast::channel::<serde_json::Value>(16);
+        let (metric_ingestion_sender, _) = tokio::sync::broadcast::channel::<String>(1024);
+        let (expression_evaluation_sender, expression_evaluation_receiver) =
+            tokio::sync::mpsc::channel::<ExpressionEvaluationJob>(16);
 
         DataLayer {
             pool: DataLayer::get_pool(sql_url).await,
             source_metrics_data: SOURCE_METRICS_DATA.clone(),
             action_sender,
+            metric_ingestion_sender,
+            metrics_forward_url: Arc::new(RwLock::new(None)),
+            expression_evaluation_sender,
+            expression_evaluation_receiver: Arc::new(std::sync::Mutex::new(Some(
+                expression_evaluation_receiver,
+            ))),
         }
     }
 
@@ -200,10 +227,10 @@ impl DataLayer {
                 // watch all data for changed definition
                 let query_string = match database_kind {
                     AnyKind::Postgres => {
-                        "(SELECT updated_at FROM metric) UNION (SELECT updated_at FROM scaling_component) UNION (SELECT updated_at FROM plan)"
+                        "(SELECT updated_at FROM metric) UNION (SELECT updated_at FROM scaling_component) UNION (SELECT updated_at FROM plan) UNION (SELECT updated_at FROM expression_library) UNION (SELECT updated_at FROM holiday_calendar)"
                     }
                     AnyKind::Sqlite => {
-                        "SELECT updated_at FROM metric; SELECT updated_at FROM scaling_component; SELECT updated_at FROM plan;"
+                        "SELECT updated_at FROM metric; SELECT updated_at FROM scaling_component; SELECT updated_at FROM plan; SELECT updated_at FROM expression_library; SELECT updated_at FROM holiday_calendar;"
                     }
                     AnyKind::MySql => {
                         // Return error because MySQL is not supported yet
@@ -250,6 +277,16 @@ impl DataLayer {
         }
         let parser_result = parser_result.unwrap();
 
+        // Validate the holiday calendars before saving any definitions
+        let holiday_calendar_dates_result =
+            resolve_holiday_calendar_dates(&parser_result.holiday_calendar_definitions);
+        if holiday_calendar_dates_result.is_err() {
+            return Err(anyhow!(
+                "Failed to save holiday calendar definitions into DataLayer: {}",
+                holiday_calendar_dates_result.err().unwrap()
+            ));
+        }
+
         // Save definitions into DataLayer
         let metric_definitions = parser_result.metric_definitions.clone();
         let metric_definitions_result = self.add_metrics(metric_definitions).await;
@@ -276,6 +313,29 @@ impl DataLayer {
                 "Failed to save scaling plan definitions into DataLayer"
             ));
         }
+
+        // Save definitions into DataLayer
+        let expression_library_definitions = parser_result.expression_library_definitions.clone();
+        let expression_library_definitions_result = self
+            .add_expression_libraries(expression_library_definitions)
+            .await;
+        if expression_library_definitions_result.is_err() {
+            return Err(anyhow!(
+                "Failed to save expression library definitions into DataLayer"
+            ));
+        }
+
+        // Save definitions into DataLayer
+        let holiday_calendar_definitions = parser_result.holiday_calendar_definitions.clone();
+        let holiday_calendar_definitions_result = self
+            .add_holiday_calendars(holiday_calendar_definitions)
+            .await;
+        if holiday_calendar_definitions_result.is_err() {
+            return Err(anyhow!(
+                "Failed to save holiday calendar definitions into DataLayer: {}",
+                holiday_calendar_definitions_result.err().unwrap()
+            ));
+        }
         Ok(())
     }
 
@@ -806,12 +866,175 @@ impl DataLayer {
         }
         Ok(result)
     }
+    // Add multiple expression libraries to the database
+    pub async fn add_expression_libraries(
+        &self,
+        expression_libraries: Vec<ExpressionLibraryDefinition>,
+    ) -> Result<()> {
+        for expression_library in expression_libraries {
+            let metadata_string = serde_json::to_string(&expression_library.metadata).unwrap();
+            let query_string = "INSERT INTO expression_library (db_id, id, language, metadata, code, enabled, created_at, updated_at) VALUES ($1,$2,$3,$4,$5,$6,$7,$8) ON CONFLICT (id) DO UPDATE SET (language, metadata, code, enabled, updated_at) = ($9,$10,$11,$12,$13)";
+            let db_id = Uuid::new_v4().to_string();
+            let updated_at = Utc::now();
+            let result = sqlx::query(query_string)
+                // Values for insert
+                .bind(db_id)
+                .bind(expression_library.id)
+                .bind(expression_library.language.clone())
+                .bind(metadata_string.clone())
+                .bind(expression_library.code.clone())
+                .bind(expression_library.enabled)
+                .bind(updated_at)
+                .bind(updated_at)
+                // Values for update
+                .bind(expression_library.language)
+                .bind(metadata_string)
+                .bind(expression_library.code)
+                .bind(expression_library.enabled)
+                .bind(updated_at)
+                .execute(&self.pool)
+                .await;
+            if result.is_err() {
+                return Err(anyhow!(result.err().unwrap().to_string()));
+            }
+        }
+        Ok(())
+    }
+    // Get all expression libraries from the database
+    pub async fn get_all_expression_libraries(&self) -> Result<Vec<ExpressionLibraryDefinition>> {
+        let mut expression_libraries: Vec<ExpressionLibraryDefinition> = Vec::new();
+        let query_string =
+            "SELECT db_id, id, language, metadata, code, enabled FROM expression_library";
+        let result = sqlx::query(query_string).fetch_all(&self.pool).await;
+        if result.is_err() {
+            return Err(anyhow!(result.err().unwrap().to_string()));
+        }
+        let result = result.unwrap();
+        for row in result {
+            expression_libraries.push(ExpressionLibraryDefinition {
+                kind: ObjectKind::ExpressionLibrary,
+                db_id: row.get("db_id"),
+                id: row.get("id"),
+                language: row.get("language"),
+                metadata: serde_json::from_str(row.get("metadata")).unwrap(),
+                code: row.get("code"),
+                enabled: row.get("enabled"),
+            });
+        }
+        Ok(expression_libraries)
+    }
+    // Get enabled expression libraries
+    pub async fn get_enabled_expression_libraries(
+        &self,
+    ) -> Result<Vec<ExpressionLibraryDefinition>> {
+        let expression_libraries = self.get_all_expression_libraries().await?;
+        let expression_libraries = expression_libraries
+            .into_iter()
+            .filter(|expression_library| expression_library.enabled)
+            .collect::<Vec<ExpressionLibraryDefinition>>();
+        Ok(expression_libraries)
+    }
+    // Delete all expression libraries from the database
+    pub async fn delete_all_expression_libraries(&self) -> Result<()> {
+        let query_string = "DELETE FROM expression_library";
+        let result = sqlx::query(query_string).execute(&self.pool).await;
+        if result.is_err() {
+            return Err(anyhow!(result.err().unwrap().to_string()));
+        }
+        Ok(())
+    }
+    // Add multiple holiday calendars to the database
+    // The dates from 'dates', 'ics' and 'ics_path' are resolved and saved as a JSON array
+    // All calendars are validated first and saved in a transaction, so an invalid calendar saves nothing
+    pub async fn add_holiday_calendars(
+        &self,
+        holiday_calendars: Vec<HolidayCalendarDefinition>,
+    ) -> Result<()> {
+        let holiday_calendar_dates = resolve_holiday_calendar_dates(&holiday_calendars)?;
+        let transaction = self.pool.begin().await;
+        if transaction.is_err() {
+            return Err(anyhow!(transaction.err().unwrap().to_string()));
+        }
+        let mut transaction = transaction.unwrap();
+        for (holiday_calendar, dates) in holiday_calendars.into_iter().zip(holiday_calendar_dates) {
+            let metadata_string = serde_json::to_string(&holiday_calendar.metadata).unwrap();
+            let dates_string = serde_json::to_string(&dates).unwrap();
+            let query_string = "INSERT INTO holiday_calendar (db_id, id, metadata, dates, enabled, created_at, updated_at) VALUES ($1,$2,$3,$4,$5,$6,$7) ON CONFLICT (id) DO UPDATE SET (metadata, dates, enabled, updated_at) = ($8,$9,$10,$11)";
+            let db_id = Uuid::new_v4().to_string();
+            let updated_at = Utc::now();
+            let result = sqlx::query(query_string)
+                // Values for insert
+                .bind(db_id)
+                .bind(holiday_calendar.id)
+                .bind(metadata_string.clone())
+                .bind(dates_string.clone())
+                .bind(holiday_calendar.enabled)
+                .bind(updated_at)
+                .bind(updated_at)
+                // Values for update
+                .bind(metadata_string)
+                .bind(dates_string)
+                .bind(holiday_calendar.enabled)
+                .bind(updated_at)
+                .execute(&mut transaction)
+                .await;
+            if result.is_err() {
+                return Err(anyhow!(result.err().unwrap().to_string()));
+            }
+        }
+        let result = transaction.commit().await;
+        if result.is_err() {
+            return Err(anyhow!(result.err().unwrap().to_string()));
+        }
+        Ok(())
+    }
+    // Get all holiday calendars from the database
+    pub async fn get_all_holiday_calendars(&self) -> Result<Vec<HolidayCalendarDefinition>> {
+        let mut holiday_calendars: Vec<HolidayCalendarDefinition> = Vec::new();
+        let query_string = "SELECT db_id, id, metadata, dates, enabled FROM holiday_calendar";
+        let result = sqlx::query(query_string).fetch_all(&self.pool).await;
+        if result.is_err() {
+            return Err(anyhow!(result.err().unwrap().to_string()));
+        }
+        let result = result.unwrap();
+        for row in result {
+            holiday_calendars.push(HolidayCalendarDefinition {
+                kind: ObjectKind::HolidayCalendar,
+                db_id: row.get("db_id"),
+                id: row.get("id"),
+                metadata: serde_json::from_str(row.get("metadata")).unwrap(),
+                dates: serde_json::from_str(row.get("dates")).unwrap(),
+                ics: None,
+                ics_path: None,
+                enabled: row.get("enabled"),
+            });
+        }
+        Ok(holiday_calendars)
+    }
+    // Get enabled holiday calendars
+    pub async fn get_enabled_holiday_calendars(&self) -> Result<Vec<HolidayCalendarDefinition>> {
+        let holiday_calendars = self.get_all_holiday_calendars().await?;
+        let holiday_calendars = holiday_calendars
+            .into_iter()
+            .filter(|holiday_calendar| holiday_calendar.enabled)
+            .collect::<Vec<HolidayCalendarDefinition>>();
+        Ok(holiday_calendars)
+    }
+    // Delete all holiday calendars from the database
+    pub async fn delete_all_holiday_calendars(&self) -> Result<()> {
+        let query_string = "DELETE FROM holiday_calendar";
+        let result = sqlx::query(query_string).execute(&self.pool).await;
+        if result.is_err() {
+            return Err(anyhow!(result.err().unwrap().to_string()));
+        }
+        Ok(())
+    }
     // Add AutoscalingHistory to the database
     pub async fn add_autoscaling_history(
         &self,
         autoscaling_history: AutoscalingHistoryDefinition,
     ) -> Result<()> {
-        let query_string = "INSERT INTO autoscaling_history (id, plan_db_id, plan_id, plan_item_json, metric_values_json, metadata_values_json, fail_message) VALUES ($1,$2,$3,$4,$5,$6,$7)";
+        let query_string = "INSERT INTO autoscaling_history (id, plan_db_id, plan_id, plan_item_json, metric_values_json, metadata_values_json, fail_message, stale_metric_ids_json, logs_json) VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9)";
         let id = Ulid::new().to_string();
         let result = sqlx::query(query_string)
             // INTO
@@ -822,6 +1045,8 @@ impl DataLayer {
             .bind(autoscaling_history.metric_values_json)
             .bind(autoscaling_history.metadata_values_json)
             .bind(autoscaling_history.fail_message)
+            .bind(autoscaling_history.stale_metric_ids_json)
+            .bind(autoscaling_history.logs_json)
             .execute(&self.pool)
             .await;
 
@@ -836,7 +1061,7 @@ impl DataLayer {
         plan_id: String,
     ) -> Result<Vec<AutoscalingHistoryDefinition>> {
         let mut autoscaling_history: Vec<AutoscalingHistoryDefinition> = Vec::new();
-        let query_string = "SELECT id, plan_db_id, plan_id, plan_item_json, metric_values_json, metadata_values_json, fail_message FROM autoscaling_history WHERE plan_id=$1";
+        let query_string = "SELECT id, plan_db_id, plan_id, plan_item_json, metric_values_json, metadata_values_json, fail_message, stale_metric_ids_json, logs_json FROM autoscaling_history WHERE plan_id=$1";
         let result = sqlx::query(query_string)
             .bind(plan_id)
             .fetch_all(&self.pool)
@@ -856,6 +1081,8 @@ impl DataLayer {
                 metric_values_json: row.get("metric_values_json"),
                 metadata_values_json: row.get("metadata_values_json"),
                 fail_message: row.get("fail_message"),
+                stale_metric_ids_json: row.get("stale_metric_ids_json"),
+                logs_json: row.get("logs_json"),
             });
         }
         Ok(autoscaling_history)
@@ -875,7 +1102,7 @@ impl DataLayer {
         let to = Ulid::from_parts(to_date.timestamp_millis() as u64, 0).to_string();
 
         // Query
-        let query_string = "SELECT id, plan_db_id, plan_id, plan_item_json, metric_values_json, metadata_values_json, fail_message FROM autoscaling_history WHERE id BETWEEN $1 AND $2";
+        let query_string = "SELECT id, plan_db_id, plan_id, plan_item_json, metric_values_json, metadata_values_json, fail_message, stale_metric_ids_json, logs_json FROM autoscaling_history WHERE id BETWEEN $1 AND $2";
         let result = sqlx::query(query_string)
             .bind(from)
             .bind(to)
@@ -896,6 +1123,8 @@ impl DataLayer {
                 metric_values_json: row.get("metric_values_json"),
                 metadata_values_json: row.get("metadata_values_json"),
                 fail_message: row.get("fail_message"),
+                stale_metric_ids_json: row.get("stale_metric_ids_json"),
+                logs_json: row.get("logs_json"),
             });
         }
         Ok(autoscaling_history)
@@ -925,6 +1154,8 @@ impl DataLayer {
                 } else {
                     None
                 },
+                stale_metric_ids_json: None,
+                logs_json: None,
             };
             self.add_autoscaling_history(autoscaling_history).await?;
         }
@@ -945,6 +1176,92 @@ impl DataLayer {
         Ok(())
     }
 
+    // Try to acquire or renew the leader lease. It returns true if the holder is the leader.
+    // The lease can be taken only if it is held by the same holder or it has expired.
+    pub async fn try_acquire_leader_lease(
+        &self,
+        holder_id: &str,
+        holder_endpoint: &str,
+        lease_duration_ms: u64,
+    ) -> Result<bool> {
+        let query_string = "INSERT INTO leader_lease (id, holder_id, holder_endpoint, expires_at, updated_at) VALUES ($1,$2,$3,$4,$5) ON CONFLICT (id) DO UPDATE SET (holder_id, holder_endpoint, expires_at, updated_at) = ($6,$7,$8,$9) WHERE leader_lease.holder_id=$10 OR leader_lease.expires_at<$11";
+        let updated_at = Utc::now();
+        let now_ms = updated_at.timestamp_millis();
+        let expires_at = now_ms + lease_duration_ms as i64;
+        let result = sqlx::query(query_string)
+            // Values for insert
+            .bind(LEADER_LEASE_ID)
+            .bind(holder_id)
+            .bind(holder_endpoint)
+            .bind(expires_at)
+            .bind(updated_at)
+            // Values for update
+            .bind(holder_id)
+            .bind(holder_endpoint)
+            .bind(expires_at)
+            .bind(updated_at)
+            // WHERE
+            .bind(holder_id)
+            .bind(now_ms)
+            .execute(&self.pool)
+            .await;
+        if result.is_err() {
+            return Err(anyhow!(result.err().unwrap().to_string()));
+        }
+        Ok(result.unwrap().rows_affected() > 0)
+    }
+    // Get the current leader lease
+    pub async fn get_leader_lease(&self) -> Result<Option<LeaderLeaseDefinition>> {
+        let query_string =
+            "SELECT holder_id, holder_endpoint, expires_at FROM leader_lease WHERE id=$1";
+        let result = sqlx::query(query_string)
+            .bind(LEADER_LEASE_ID)
+            .fetch_all(&self.pool)
+            .await;
+        if result.is_err() {
+            return Err(anyhow!(result.err().unwrap().to_string()));
+        }
+        let result = result.unwrap();
+        let result = result.get(0).map(|row| LeaderLeaseDefinition {
+            holder_id: row.get("holder_id"),
+            holder_endpoint: row.get("holder_endpoint"),
+            expires_at: row.get("expires_at"),
+        });
+        Ok(result)
+    }
+    // Release the leader lease so that another instance can take over immediately
+    pub async fn release_leader_lease(&self, holder_id: &str) -> Result<()> {
+        let query_string =
+            "UPDATE leader_lease SET expires_at=$1, updated_at=$2 WHERE id=$3 AND holder_id=$4";
+        let result = sqlx::query(query_string)
+            .bind(0_i64)
+            .bind(Utc::now())
+            .bind(LEADER_LEASE_ID)
+            .bind(holder_id)
+            .execute(&self.pool)
+            .await;
+        if result.is_err() {
+            return Err(anyhow!(result.err().unwrap().to_string()));
+        }
+        Ok(())
+    }
+    // Set the URL of the metrics receiver of the leader. None if this instance is the leader.
+    pub fn set_metrics_forward_url(&self, metrics_forward_url: Option<String>) {
+        let Ok(mut shared_metrics_forward_url) = self.metrics_forward_url.write() else {
+            error!("[set_metrics_forward_url] Failed to get the lock of metrics_forward_url");
+            return;
+        };
+        *shared_metrics_forward_url = metrics_forward_url;
+    }
+    // Get the URL of the metrics receiver of the leader to forward the ingested metrics
+    pub fn get_metrics_forward_url(&self) -> Option<String> {
+        let Ok(metrics_forward_url) = self.metrics_forward_url.read() else {
+            error!("[get_metrics_forward_url] Failed to get the lock of metrics_forward_url");
+            return None;
+        };
+        metrics_forward_url.clone()
+    }
+
     // Source Metrics
     pub async fn add_source_metrics_in_data_layer(
         &self,
@@ -1038,6 +1355,10 @@ impl DataLayer {
         // update source_metric_size
         source_metrics_data.source_metrics_size = total_source_metrics_size;
 
+        // Notify the subscribers that the metrics are ingested.
+        // It returns an error when there is no subscriber, so the result is ignored.
+        let _ = self.metric_ingestion_sender.send(metric_id.to_string());
+
         // debug!("Save Metric\n{:?}", source_metric_insert_data);
 
         // Save to database
@@ -1229,8 +1550,67 @@ impl DataLayer {
     pub fn subscribe_action(&self) -> tokio::sync::broadcast::Receiver<serde_json::Value> {
         self.action_sender.subscribe()
     }
+    // Get an receiver of the metric_id of the ingested metrics
+    pub fn subscribe_metric_ingestion(&self) -> tokio::sync::broadcast::Receiver<String> {
+        self.metric_ingestion_sender.subscribe()
+    }
+    // Take the receiver of the ad-hoc evaluation jobs. Only the first caller(the evaluator) gets it.
+    pub fn take_expression_evaluation_receiver(
+        &self,
+    ) -> Option<tokio::sync::mpsc::Receiver<ExpressionEvaluationJob>> {
+        let Ok(mut receiver) = self.expression_evaluation_receiver.lock() else {
+            error!("[DataLayer] Failed to get the lock of expression_evaluation_receiver");
+            return None;
+        };
+        receiver.take()
+    }
+    // Evaluate an expression by the evaluator and wait for the result
+    pub async fn evaluate_expression(
+        &self,
+        request: ExpressionEvaluationRequest,
+    ) -> Result<ExpressionEvaluationResult> {
+        let (result_sender, result_receiver) = tokio::sync::oneshot::channel();
+        let job = ExpressionEvaluationJob {
+            request,
+            result_sender,
+        };
+        let evaluation = async {
+            if self.expression_evaluation_sender.send(job).await.is_err() {
+                return Err(anyhow!("The expression evaluator is not running"));
+            }
+            result_receiver
+                .await
+                .map_err(|_| anyhow!("The expression evaluator dropped the job"))
+        };
+        let result = tokio::time::timeout(
+            Duration::from_secs(EXPRESSION_EVALUATION_TIMEOUT_SEC),
+            evaluation,
+        )
+        .await;
+        if result.is_err() {
+            return Err(anyhow!("The expression evaluation timed out"));
+        }
+        result.unwrap()
+    }
 }
 
+// The dates of each holiday calendar. The error has the id of the invalid calendar.
+fn resolve_holiday_calendar_dates(
+    holiday_calendars: &[HolidayCalendarDefinition],
+) -> Result<Vec<Vec<String>>> {
+    holiday_calendars
+        .iter()
+        .map(|holiday_calendar| {
+            holiday_calendar.resolve_dates().map_err(|error| {
+                anyhow!(
+                    "Invalid holiday calendar({}): {}",
+                    holiday_calendar.id,
+                    error
+                )
+            })
+        })
+        .collect()
+}
 #[cfg(test)]
 mod tests {
     use super::DataLayer;
@@ -1284,6 +1664,8 @@ mod tests {
             metric_values_json: "test_metric_values_json".to_string(),
             metadata_values_json: "test_metadata_values_json".to_string(),
             fail_message: Some("test_fail_message".to_string()),
+            stale_metric_ids_json: Some(json!(["test_metric_id"]).to_string()),
+            logs_json: Some(json!(["test_log"]).to_string()),
         }
     }
 
@@ -1332,6 +1714,14 @@ mod tests {
         assert!(result.is_ok());
         let result = result.unwrap();
         assert_eq!(result[0].plan_id, autoscaling_history_definition.plan_id);
+        assert_eq!(
+            result[0].stale_metric_ids_json,
+            autoscaling_history_definition.stale_metric_ids_json
+        );
+        assert_eq!(
+            result[0].logs_json,
+            autoscaling_history_definition.logs_json
+        );
 
         // Remove the old AutoscalingHistory from the database
         let result = data_layer.remove_old_autoscaling_history(to_date).await;
@@ -1344,6 +1734,56 @@ mod tests {
         assert_eq!(result.len(), 0);
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn test_leader_lease() {
+        let data_layer = get_data_layer_with_sqlite().await;
+        test_leader_lease_with_data_layer(data_layer).await;
+
+        let data_layer = get_data_layer_with_postgres().await;
+        test_leader_lease_with_data_layer(data_layer).await;
+    }
+
+    async fn test_leader_lease_with_data_layer(data_layer: DataLayer) {
+        let holder_1 = Ulid::new().to_string();
+        let holder_2 = Ulid::new().to_string();
+        let _ = data_layer.release_leader_lease(&holder_1).await;
+
+        // The first holder acquires the lease and the second one can't take it
+        let result = data_layer
+            .try_acquire_leader_lease(&holder_1, "http://holder_1:3024", 60 * 1000)
+            .await;
+        assert!(result.unwrap());
+        let result = data_layer
+            .try_acquire_leader_lease(&holder_2, "http://holder_2:3024", 60 * 1000)
+            .await;
+        assert!(!result.unwrap());
+
+        // The holder can renew its own lease
+        let result = data_layer
+            .try_acquire_leader_lease(&holder_1, "http://holder_1:3024", 60 * 1000)
+            .await;
+        assert!(result.unwrap());
+        let leader_lease = data_layer.get_leader_lease().await.unwrap().unwrap();
+        assert_eq!(leader_lease.holder_id, holder_1);
+        assert_eq!(
+            leader_lease.holder_endpoint,
+            Some("http://holder_1:3024".to_string())
+        );
+        assert!(!leader_lease.is_expired());
+
+        // After the lease is released, another holder can take it
+        let result = data_layer.release_leader_lease(&holder_1).await;
+        assert!(result.is_ok());
+        let result = data_layer
+            .try_acquire_leader_lease(&holder_2, "http://holder_2:3024", 60 * 1000)
+            .await;
+        assert!(result.unwrap());
+        let leader_lease = data_layer.get_leader_lease().await.unwrap().unwrap();
+        assert_eq!(leader_lease.holder_id, holder_2);
+        let _ = data_layer.release_leader_lease(&holder_2).await;
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_get_source_metrics_values_all_metric_ids() {
@@ -1381,6 +1821,139 @@ mod tests {
         assert!(!source_metrics_filter_arr.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_expression_libraries() {
+        let data_layer = get_data_layer_with_sqlite().await;
+        let yaml = r#"
+kind: ExpressionLibrary
+id: expression_library_1
+enabled: true
+code: |
+  function double(value) { return value * 2; }
+---
+kind: ExpressionLibrary
+id: expression_library_2
+language: rhai
+code: |
+  fn double(value) { value * 2 }
+"#;
+        data_layer.add_definitions(yaml).await.unwrap();
+        let expression_libraries = data_layer.get_all_expression_libraries().await.unwrap();
+        assert_eq!(expression_libraries.len(), 2);
+        let expression_libraries = data_layer.get_enabled_expression_libraries().await.unwrap();
+        assert_eq!(expression_libraries.len(), 1);
+        assert_eq!(expression_libraries[0].id, "expression_library_1");
+        assert_eq!(expression_libraries[0].language, "javascript");
+
+        data_layer.delete_all_expression_libraries().await.unwrap();
+        let expression_libraries = data_layer.get_all_expression_libraries().await.unwrap();
+        assert!(expression_libraries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_holiday_calendars() {
+        let data_layer = get_data_layer_with_sqlite().await;
+        let yaml = r#"
+kind: HolidayCalendar
+id: holiday_calendar_1
+enabled: true
+dates:
+  - 2023-12-25
+ics: |
+  BEGIN:VCALENDAR
+  BEGIN:VEVENT
+  DTSTART;VALUE=DATE:20240101
+  END:VEVENT
+  END:VCALENDAR
+---
+kind: HolidayCalendar
+id: holiday_calendar_2
+dates:
+  - 2023-10-03
+"#;
+        data_layer.add_definitions(yaml).await.unwrap();
+        let holiday_calendars = data_layer.get_all_holiday_calendars().await.unwrap();
+        assert_eq!(holiday_calendars.len(), 2);
+        let holiday_calendars = data_layer.get_enabled_holiday_calendars().await.unwrap();
+        assert_eq!(holiday_calendars.len(), 1);
+        assert_eq!(holiday_calendars[0].id, "holiday_calendar_1");
+        assert_eq!(holiday_calendars[0].dates, vec!["2023-12-25", "2024-01-01"]);
+
+        data_layer.delete_all_holiday_calendars().await.unwrap();
+        let holiday_calendars = data_layer.get_all_holiday_calendars().await.unwrap();
+        assert!(holiday_calendars.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_holiday_calendars_with_invalid_calendar() {
+        let data_layer = get_data_layer_with_sqlite().await;
+        let yaml = r#"
+kind: Metric
+id: metric_with_invalid_calendar
+collector: vector
+---
+kind: HolidayCalendar
+id: holiday_calendar_valid
+dates:
+  - 2023-12-25
+---
+kind: HolidayCalendar
+id: holiday_calendar_invalid
+dates:
+  - 2023-13-45
+"#;
+        let result = data_layer.add_definitions(yaml).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("holiday_calendar_invalid"));
+        // Nothing is saved
+        assert!(data_layer.get_all_metrics().await.unwrap().is_empty());
+        assert!(data_layer
+            .get_all_holiday_calendars()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_metric_ingestion() {
+        let data_layer = get_data_layer_with_sqlite().await;
+        let mut receiver = data_layer.subscribe_metric_ingestion();
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "metric_ingestion_1", "[]")
+            .await;
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "metric_ingestion_2", "[]")
+            .await;
+        assert_eq!(receiver.recv().await.unwrap(), "metric_ingestion_1");
+        assert_eq!(receiver.recv().await.unwrap(), "metric_ingestion_2");
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_expression() {
+        let data_layer = get_data_layer_with_sqlite().await;
+        let mut receiver = data_layer.take_expression_evaluation_receiver().unwrap();
+        assert!(data_layer.take_expression_evaluation_receiver().is_none());
+        tokio::spawn(async move {
+            while let Some(job) = receiver.recv().await {
+                let result = ExpressionEvaluationResult {
+                    result: Some(json!(job.request.expression)),
+                    ..Default::default()
+                };
+                let _ = job.result_sender.send(result);
+            }
+        });
+        let result = data_layer
+            .evaluate_expression(ExpressionEvaluationRequest {
+                expression: "1 + 1".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.result, Some(json!("1 + 1")));
+    }
+
     #[tokio::test]
     async fn test_add_source_metrics_in_data_layer() {
         const DB_URL: &str = "sqlite://tests/temp/test.db";
@@ -2,6 +2,8 @@ pub mod data_layer;
 pub mod reader;
 pub mod types;
 pub mod variable_mapper;
+pub use crate::types::expression_library_definition::ExpressionLibraryDefinition;
+pub use crate::types::holiday_calendar_definition::HolidayCalendarDefinition;
 pub use crate::types::metric_definition::MetricDefinition;
 pub use crate::types::scaling_component_definition::ScalingComponentDefinition;
 pub use crate::types::scaling_plan_definition::ScalingPlanDefinition;
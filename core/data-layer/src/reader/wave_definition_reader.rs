@@ -1,4 +1,7 @@
-use crate::{MetricDefinition, ScalingComponentDefinition, ScalingPlanDefinition, SloDefinition};
+use crate::{
+    ExpressionLibraryDefinition, HolidayCalendarDefinition, MetricDefinition,
+    ScalingComponentDefinition, ScalingPlanDefinition, SloDefinition,
+};
 use anyhow::Result;
 use serde::Deserialize;
 use serde_valid::Validate;
@@ -12,6 +15,8 @@ pub struct ParserResult {
     pub slo_definitions: Vec<SloDefinition>,
     pub scaling_plan_definitions: Vec<ScalingPlanDefinition>,
     pub scaling_component_definitions: Vec<ScalingComponentDefinition>,
+    pub expression_library_definitions: Vec<ExpressionLibraryDefinition>,
+    pub holiday_calendar_definitions: Vec<HolidayCalendarDefinition>,
 }
 
 pub fn read_definition_yaml_file<P>(path: P) -> Result<ParserResult>
@@ -55,6 +60,16 @@ where
                     parsed.validate()?;
                     result.scaling_component_definitions.push(parsed);
                 }
+                "ExpressionLibrary" => {
+                    let parsed = serde_yaml::from_value::<ExpressionLibraryDefinition>(value)?;
+                    parsed.validate()?;
+                    result.expression_library_definitions.push(parsed);
+                }
+                "HolidayCalendar" => {
+                    let parsed = serde_yaml::from_value::<HolidayCalendarDefinition>(value)?;
+                    parsed.validate()?;
+                    result.holiday_calendar_definitions.push(parsed);
+                }
                 _ => error!("Not Found: {:?}", kind),
             }
         } else {
@@ -94,6 +109,16 @@ pub fn read_definition_yaml(yaml: &str) -> Result<ParserResult> {
                     parsed.validate()?;
                     result.scaling_component_definitions.push(parsed);
                 }
+                "ExpressionLibrary" => {
+                    let parsed = serde_yaml::from_value::<ExpressionLibraryDefinition>(value)?;
+                    parsed.validate()?;
+                    result.expression_library_definitions.push(parsed);
+                }
+                "HolidayCalendar" => {
+                    let parsed = serde_yaml::from_value::<HolidayCalendarDefinition>(value)?;
+                    parsed.validate()?;
+                    result.holiday_calendar_definitions.push(parsed);
+                }
                 _ => error!("Not Found: {:?}", kind),
             }
         } else {
@@ -149,11 +174,39 @@ plans:
       desired: "Math.floor(metric_id / 10)"
       min: 1
       max: 5
-      cooldown: 300"#;
+      cooldown: 300
+---
+kind: ExpressionLibrary
+id: expression_library_id
+code: |
+  function cpuAvg(service) {
+    return get({ metric_id: 'cpu', name: service, stats: 'avg' });
+  }
+---
+kind: HolidayCalendar
+id: holiday_calendar_id
+dates:
+  - 2023-12-25"#;
         let result = read_definition_yaml(yaml)?;
         assert_eq!(result.metric_definitions.len(), 1);
         assert_eq!(result.scaling_plan_definitions.len(), 1);
         assert_eq!(result.scaling_component_definitions.len(), 1);
+        assert_eq!(result.expression_library_definitions.len(), 1);
+        assert_eq!(
+            result.expression_library_definitions[0].language,
+            "javascript"
+        );
+        assert_eq!(result.holiday_calendar_definitions.len(), 1);
         Ok(())
     }
+
+    #[test]
+    fn test_read_definition_yaml_with_invalid_expression_library() {
+        // The code is required
+        let yaml = r#"
+kind: ExpressionLibrary
+id: expression_library_id
+code: ''"#;
+        assert!(read_definition_yaml(yaml).is_err());
+    }
 }
@@ -19,6 +19,12 @@ pub struct AutoscalingHistoryDefinition {
     pub metric_values_json: String,
     pub metadata_values_json: String,
     pub fail_message: Option<String>,
+    // The metrics without data in the evaluation (JSON array of metric ids)
+    #[serde(default)]
+    pub stale_metric_ids_json: Option<String>,
+    // The logs of log() in the expression (JSON array of strings)
+    #[serde(default)]
+    pub logs_json: Option<String>,
 }
 
 impl AutoscalingHistoryDefinition {
@@ -29,6 +35,8 @@ impl AutoscalingHistoryDefinition {
         metric_values_json: String,
         metadata_values_json: String,
         fail_message: Option<String>,
+        stale_metric_ids_json: Option<String>,
+        logs_json: Option<String>,
     ) -> Self {
         Self {
             id: uuid::Uuid::new_v4().to_string(),
@@ -38,6 +46,8 @@ impl AutoscalingHistoryDefinition {
             metric_values_json,
             metadata_values_json,
             fail_message,
+            stale_metric_ids_json,
+            logs_json,
         }
     }
 }
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use ts_rs::TS;
+
+// The request of the ad-hoc evaluation of an expression (POST /api/expressions/evaluate)
+#[derive(TS)]
+#[ts(
+    export,
+    export_to = "../web-app/src/types/bindings/expression-evaluation-request.ts"
+)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ExpressionEvaluationRequest {
+    pub expression: String,
+    // javascript(default) or rhai
+    #[serde(default)]
+    pub language: Option<String>,
+    // Evaluate against the buffered metrics as of {time_offset_sec} seconds ago
+    #[serde(default)]
+    pub time_offset_sec: Option<u64>,
+}
+
+#[derive(TS)]
+#[ts(
+    export,
+    export_to = "../web-app/src/types/bindings/expression-evaluation-error.ts"
+)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ExpressionEvaluationError {
+    pub message: String,
+    // The position in the expression if the engine reports it (1-based)
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+#[derive(TS)]
+#[ts(
+    export,
+    export_to = "../web-app/src/types/bindings/expression-evaluation-result.ts"
+)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ExpressionEvaluationResult {
+    #[ts(type = "any")]
+    pub result: Option<Value>,
+    // The values of the get() calls in the expression
+    pub get_values: Vec<HashMap<String, Option<f64>>>,
+    pub logs: Vec<String>,
+    pub stale_metric_ids: Vec<String>,
+    pub error: Option<ExpressionEvaluationError>,
+}
+
+impl ExpressionEvaluationResult {
+    pub fn from_error(error: ExpressionEvaluationError) -> Self {
+        Self {
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+// The evaluation job delivered from the API server to the evaluator of wave-autoscale
+#[derive(Debug)]
+pub struct ExpressionEvaluationJob {
+    pub request: ExpressionEvaluationRequest,
+    pub result_sender: tokio::sync::oneshot::Sender<ExpressionEvaluationResult>,
+}
@@ -0,0 +1,61 @@
+use super::{object_kind::ObjectKind, validate_id_regex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_valid::Validate;
+use std::collections::HashMap;
+use ts_rs::TS;
+
+fn default_kind() -> ObjectKind {
+    ObjectKind::ExpressionLibrary
+}
+fn default_language() -> String {
+    "javascript".to_string()
+}
+fn default_metadata() -> HashMap<String, Value> {
+    HashMap::new()
+}
+fn default_enabled() -> bool {
+    false
+}
+
+// Reusable functions for the expressions of the scaling plans.
+// The code is loaded into the expression engine of each scaling planner with the same language.
+#[derive(TS)]
+#[ts(
+    export,
+    export_to = "../web-app/src/types/bindings/expression-library-definition.ts"
+)]
+#[derive(Debug, Serialize, Deserialize, Clone, Validate)]
+pub struct ExpressionLibraryDefinition {
+    #[serde(default = "default_kind")]
+    pub kind: ObjectKind,
+    #[serde(default)]
+    pub db_id: String,
+    #[validate(custom(validate_id_regex))]
+    #[validate(min_length = 2)]
+    pub id: String,
+    // javascript(default) or rhai
+    #[serde(default = "default_language")]
+    pub language: String,
+    #[ts(type = "object")]
+    #[serde(default = "default_metadata")]
+    pub metadata: HashMap<String, Value>,
+    #[validate(min_length = 1)]
+    pub code: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for ExpressionLibraryDefinition {
+    fn default() -> Self {
+        Self {
+            kind: ObjectKind::ExpressionLibrary,
+            db_id: "".to_string(),
+            id: "".to_string(),
+            language: default_language(),
+            metadata: HashMap::new(),
+            code: "".to_string(),
+            enabled: true,
+        }
+    }
+}
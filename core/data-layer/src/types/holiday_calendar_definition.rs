@@ -0,0 +1,217 @@
+use super::{object_kind::ObjectKind, validate_id_regex};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_valid::Validate;
+use std::collections::{BTreeSet, HashMap};
+use ts_rs::TS;
+
+fn default_kind() -> ObjectKind {
+    ObjectKind::HolidayCalendar
+}
+fn default_metadata() -> HashMap<String, Value> {
+    HashMap::new()
+}
+fn default_enabled() -> bool {
+    false
+}
+
+// The holidays for is_holiday(calendar_id) in the expressions of the scaling plans.
+// The dates are merged from 'dates'(YYYY-MM-DD), 'ics'(iCalendar content) and 'ics_path'(iCalendar file)
+// when it is saved into the database.
+#[derive(TS)]
+#[ts(
+    export,
+    export_to = "../web-app/src/types/bindings/holiday-calendar-definition.ts"
+)]
+#[derive(Debug, Serialize, Deserialize, Clone, Validate)]
+pub struct HolidayCalendarDefinition {
+    #[serde(default = "default_kind")]
+    pub kind: ObjectKind,
+    #[serde(default)]
+    pub db_id: String,
+    #[validate(custom(validate_id_regex))]
+    #[validate(min_length = 2)]
+    pub id: String,
+    #[ts(type = "object")]
+    #[serde(default = "default_metadata")]
+    pub metadata: HashMap<String, Value>,
+    #[serde(default)]
+    pub dates: Vec<String>,
+    #[serde(default)]
+    pub ics: Option<String>,
+    #[serde(default)]
+    pub ics_path: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for HolidayCalendarDefinition {
+    fn default() -> Self {
+        Self {
+            kind: ObjectKind::HolidayCalendar,
+            db_id: "".to_string(),
+            id: "".to_string(),
+            metadata: HashMap::new(),
+            dates: vec![],
+            ics: None,
+            ics_path: None,
+            enabled: true,
+        }
+    }
+}
+
+impl HolidayCalendarDefinition {
+    // All dates(YYYY-MM-DD) from dates, ics and ics_path - sorted and deduplicated
+    pub fn resolve_dates(&self) -> Result<Vec<String>> {
+        let mut dates: BTreeSet<NaiveDate> = BTreeSet::new();
+        for date in self.dates.iter() {
+            let Some(parsed) = parse_date(date) else {
+                return Err(anyhow!("Invalid date in {}: {}", self.id, date));
+            };
+            dates.insert(parsed);
+        }
+        if let Some(ics) = self.ics.as_ref() {
+            let ics_dates = parse_ics_dates(ics)
+                .map_err(|error| anyhow!("Invalid ics in {}: {}", self.id, error))?;
+            dates.extend(ics_dates);
+        }
+        if let Some(ics_path) = self.ics_path.as_ref() {
+            let ics = std::fs::read_to_string(ics_path)
+                .map_err(|error| anyhow!("Failed to read {}: {}", ics_path, error))?;
+            let ics_dates = parse_ics_dates(ics.as_str())
+                .map_err(|error| anyhow!("Invalid ics in {}: {}", ics_path, error))?;
+            dates.extend(ics_dates);
+        }
+        Ok(dates.iter().map(|date| date.to_string()).collect())
+    }
+}
+
+// YYYY-MM-DD
+pub fn parse_date(date: &str) -> Option<NaiveDate> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year = parts.next()?.parse::<i32>().ok()?;
+    let month = parts.next()?.parse::<u32>().ok()?;
+    let day = parts.next()?.parse::<u32>().ok()?;
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+// The date of DTSTART or DTEND in iCalendar and whether it has a time after midnight
+// e.g. 20231225 or 20231225T000000Z
+fn parse_ics_date(value: &str) -> Option<(NaiveDate, bool)> {
+    let value = value.trim();
+    let date = NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()?;
+    let time = value.get(8..).unwrap_or_default();
+    let has_time =
+        time.starts_with('T') && time.trim_start_matches('T').trim_end_matches('Z') != "000000";
+    Some((date, has_time))
+}
+
+// The dates of the events in iCalendar(RFC 5545) from DTSTART to DTEND(exclusive)
+// e.g. DTSTART;VALUE=DATE:20231225 or DTSTART:20231225T000000Z
+// The recurring events(RRULE) are rejected instead of being dropped.
+pub fn parse_ics_dates(ics: &str) -> Result<Vec<NaiveDate>> {
+    let mut dates: Vec<NaiveDate> = Vec::new();
+    let mut start: Option<NaiveDate> = None;
+    let mut end: Option<NaiveDate> = None;
+    for line in ics.lines() {
+        let line = line.trim_end();
+        let (name, value) = line.split_once(':').unwrap_or((line, ""));
+        // The name can have parameters (e.g. DTSTART;VALUE=DATE)
+        let name = name.split(';').next().unwrap_or_default().to_uppercase();
+        match name.as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VEVENT") => {
+                start = None;
+                end = None;
+            }
+            "DTSTART" => {
+                let Some((date, _)) = parse_ics_date(value) else {
+                    return Err(anyhow!("Invalid DTSTART: {}", value));
+                };
+                start = Some(date);
+            }
+            "DTEND" => {
+                let Some((date, has_time)) = parse_ics_date(value) else {
+                    return Err(anyhow!("Invalid DTEND: {}", value));
+                };
+                // The event ends during the day, so the day is included
+                end = Some(if has_time {
+                    date.succ_opt().unwrap_or(date)
+                } else {
+                    date
+                });
+            }
+            "RRULE" | "RDATE" => {
+                return Err(anyhow!("The recurring events are not supported: {}", line));
+            }
+            "END" if value.eq_ignore_ascii_case("VEVENT") => {
+                let Some(start) = start.take() else {
+                    continue;
+                };
+                dates.push(start);
+                let end = end.take().unwrap_or(start);
+                let mut date = start;
+                while let Some(next) = date.succ_opt() {
+                    if next >= end {
+                        break;
+                    }
+                    dates.push(next);
+                    date = next;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(dates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_dates() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20231225\r\nSUMMARY:Christmas\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nDTSTART:20240101T000000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let holiday_calendar = HolidayCalendarDefinition {
+            id: "holiday_calendar".to_string(),
+            dates: vec!["2023-12-25".to_string(), "2023-10-03".to_string()],
+            ics: Some(ics.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            holiday_calendar.resolve_dates().unwrap(),
+            vec!["2023-10-03", "2023-12-25", "2024-01-01"]
+        );
+
+        let invalid_holiday_calendar = HolidayCalendarDefinition {
+            id: "holiday_calendar".to_string(),
+            dates: vec!["2023-13-25".to_string()],
+            ..Default::default()
+        };
+        assert!(invalid_holiday_calendar.resolve_dates().is_err());
+    }
+
+    #[test]
+    fn test_parse_ics_dates() {
+        // A multi-day event (DTEND is exclusive)
+        let ics = "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20240209\r\nDTEND;VALUE=DATE:20240212\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nDTSTART:20240301T090000Z\r\nDTEND:20240302T090000Z\r\nEND:VEVENT\r\n";
+        assert_eq!(
+            parse_ics_dates(ics).unwrap(),
+            vec![
+                NaiveDate::from_ymd_opt(2024, 2, 9).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 2, 10).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 2, 11).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 3, 2).unwrap(),
+            ]
+        );
+        // The recurring events are rejected
+        let ics =
+            "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20231225\r\nRRULE:FREQ=YEARLY\r\nEND:VEVENT\r\n";
+        assert!(parse_ics_dates(ics).is_err());
+        // Not a char boundary
+        assert!(parse_ics_dates("BEGIN:VEVENT\r\nDTSTART:2023122é\r\nEND:VEVENT\r\n").is_err());
+        assert!(parse_ics_dates("BEGIN:VEVENT\r\nDTSTART:202312\r\nEND:VEVENT\r\n").is_err());
+    }
+}
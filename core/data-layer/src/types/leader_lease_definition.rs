@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+// The lease row that elects a leader among the wave-autoscale instances sharing the database
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LeaderLeaseDefinition {
+    pub holder_id: String,
+    // The endpoint of the API server of the leader. e.g. http://10.0.0.1:3024
+    pub holder_endpoint: Option<String>,
+    // Unix timestamp in milliseconds
+    pub expires_at: i64,
+}
+
+impl LeaderLeaseDefinition {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < chrono::Utc::now().timestamp_millis()
+    }
+}
@@ -1,4 +1,8 @@
 pub mod autoscaling_history_definition;
+pub mod expression_evaluation;
+pub mod expression_library_definition;
+pub mod holiday_calendar_definition;
+pub mod leader_lease_definition;
 pub mod metric;
 pub mod metric_definition;
 pub mod object_kind;
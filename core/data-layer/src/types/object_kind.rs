@@ -12,4 +12,6 @@ pub enum ObjectKind {
     ScalingPlan,
     ScalingComponent,
     SLO,
+    ExpressionLibrary,
+    HolidayCalendar,
 }
@@ -5,6 +5,10 @@ use std::collections::HashMap;
 use ts_rs::TS;
 
 pub const DEFAULT_PLAN_INTERVAL: u16 = 1000;
+// metadata.trigger: on_metric_update evaluates the plans when the metrics they reference are ingested
+pub const PLAN_TRIGGER_ON_METRIC_UPDATE: &str = "on_metric_update";
+// milliseconds to collect the metric updates before evaluating the plans
+pub const DEFAULT_PLAN_DEBOUNCE: u64 = 100;
 
 fn default_kind() -> ObjectKind {
     ObjectKind::ScalingPlan
@@ -18,6 +18,8 @@ const DEFAULT_WEB_UI: bool = true;
 const DEFAULT_WEB_UI_HOST: &str = "0.0.0.0";
 const DEFAULT_WEB_UI_PORT: u16 = 3025;
 const DEFAULT_RESET_DEFINITIONS_ON_STARTUP: bool = false;
+const DEFAULT_HA_ENABLED: bool = false;
+const DEFAULT_HA_LEASE_DURATION: u64 = 15000;
 
 fn default_debug() -> bool {
     DEFAULT_DEBUG
@@ -58,6 +60,12 @@ fn default_web_ui_port() -> u16 {
 fn default_reset_definitions_on_startup() -> bool {
     DEFAULT_RESET_DEFINITIONS_ON_STARTUP
 }
+fn default_ha_enabled() -> bool {
+    DEFAULT_HA_ENABLED
+}
+fn default_ha_lease_duration() -> u64 {
+    DEFAULT_HA_LEASE_DURATION
+}
 
 #[derive(Debug, PartialEq, Deserialize, Default, Clone)]
 struct DownloadUrlDefinition {
@@ -120,6 +128,20 @@ pub struct WaveConfig {
     #[serde(default = "default_web_ui_port")]
     pub web_ui_port: u16,
 
+    //
+    // High Availability
+    //
+    // Run multiple instances sharing the same database(Postgres). Only the leader runs the plans and the collectors.
+    #[serde(default = "default_ha_enabled")]
+    pub ha_enabled: bool,
+    // milliseconds. The leader is replaced if it doesn't renew the lease within this duration.
+    #[serde(default = "default_ha_lease_duration")]
+    pub ha_lease_duration: u64,
+    // The endpoint of the API server that the other instances can reach. e.g. http://10.0.0.1:3024
+    // If it is empty, it is made from 'host' and 'port'.
+    #[serde(default)]
+    pub ha_advertise_url: String,
+
     //
     // Metrics Collector
     //
@@ -145,6 +167,9 @@ impl Default for WaveConfig {
             web_ui: DEFAULT_WEB_UI,
             web_ui_host: DEFAULT_WEB_UI_HOST.to_string(),
             web_ui_port: DEFAULT_WEB_UI_PORT,
+            ha_enabled: DEFAULT_HA_ENABLED,
+            ha_lease_duration: DEFAULT_HA_LEASE_DURATION,
+            ha_advertise_url: String::new(),
             vector: DownloadUrlDefinition::default(),
             telegraf: DownloadUrlDefinition::default(),
         }
@@ -182,6 +207,13 @@ impl WaveConfig {
         }
         wave_config
     }
+    // The endpoint of the API server that the other instances can reach
+    pub fn get_ha_advertise_url(&self) -> String {
+        if !self.ha_advertise_url.is_empty() {
+            return self.ha_advertise_url.clone();
+        }
+        format!("http://{}:{}", self.host, self.port)
+    }
     pub fn get_download_url(&self, name: &str) -> &str {
         match name {
             "vector_macos_x64_64" => self.vector.macos_x86_64.as_str(),
@@ -227,5 +259,7 @@ mod tests {
         assert_eq!(wave_config.web_ui, DEFAULT_WEB_UI);
         assert_eq!(wave_config.web_ui_host, DEFAULT_WEB_UI_HOST);
         assert_eq!(wave_config.web_ui_port, DEFAULT_WEB_UI_PORT);
+        assert_eq!(wave_config.ha_enabled, DEFAULT_HA_ENABLED);
+        assert_eq!(wave_config.ha_lease_duration, DEFAULT_HA_LEASE_DURATION);
     }
 }
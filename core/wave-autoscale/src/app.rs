@@ -6,15 +6,20 @@
  * 2. 생성자 함수: new 함수는 App 인스턴스를 생성합니다.
     이 함수는 WaveConfig와 DataLayer를 인자로 받아, 이를 기반으로 MetricUpdater, ScalingComponentManager, ScalingPlannerManager를 생성하고 초기화합니다.
  * 3. 애플리케이션 실행 함수: run 함수는 애플리케이션을 실행합니다.
-    이 함수는 MetricUpdater를 중지하고, DataLayer에서 스케일링 컴포넌트와 스케일링 계획을 로드하여 ScalingComponentManager와 ScalingPlannerManager에 설정합니다.
- * 4. 자동 스케일링 이력 관리 함수: run_autoscaling_history_cron_job 함수와 stop_autoscaling_history_cron_job 함수는 자동 스케일링 이력을 관리하는 작업을 시작하고 중지합니다.
- * 5. 테스트용 함수: get_data_layer, get_scaling_component_manager, get_scaling_planner_manager 함수는 단위 테스트를 위해 제공되며, 각각 DataLayer, ScalingComponentManager, ScalingPlannerManager의 참조를 반환합니다.
+    이 함수는 DataLayer에서 스케일링 컴포넌트와 스케일링 계획을 로드하여 실행 중인 정의와 id 및 내용 해시로 비교합니다.
+    추가, 변경, 삭제된 컴포넌트와 플래너만 ScalingComponentManager와 ScalingPlannerManager에 다시 설정합니다.
+    표현식 라이브러리(ExpressionLibrary)는 로드할 때 검증하며, 유효하지 않은 라이브러리는 제외됩니다.
+    휴일 캘린더(HolidayCalendar)는 표현식의 is_holiday() 함수에서 사용됩니다.
+ * 4. 애플리케이션 중지 함수: stop 함수는 ScalingPlanner와 MetricUpdater를 중지합니다. (예: 고가용성 모드에서 리더가 아닐 때)
+ * 5. 자동 스케일링 이력 관리 함수: run_autoscaling_history_cron_job 함수와 stop_autoscaling_history_cron_job 함수는 자동 스케일링 이력을 관리하는 작업을 시작하고 중지합니다.
+ * 6. 테스트용 함수: get_data_layer, get_scaling_component_manager, get_scaling_planner_manager 함수는 단위 테스트를 위해 제공되며, 각각 DataLayer, ScalingComponentManager, ScalingPlannerManager의 참조를 반환합니다.
  */
 use crate::{
     metric_updater::{MetricUpdater, SharedMetricUpdater},
     scaling_component::{ScalingComponentManager, SharedScalingComponentManager},
-    scaling_planner::scaling_planner_manager::{
-        ScalingPlannerManager, SharedScalingPlannerManager,
+    scaling_planner::{
+        expression_engine::validate_expression_library,
+        scaling_planner_manager::{ScalingPlannerManager, SharedScalingPlannerManager},
     },
 };
 use data_layer::data_layer::DataLayer;
@@ -59,14 +64,9 @@ impl App {
     }
 
     // Run the application with ScalingComponentManager, MetricUpdater, and ScalingPlannerManager
+    // It reconciles the running components and planners with the definitions in DataLayer.
+    // Only the ones that are added, changed or removed are restarted.
     pub async fn run(&mut self) {
-        // Stop Metric Updater
-        {
-            // Scope for shared_metric_updater_writer(RwLock)
-            let mut updater_writer = self.shared_metric_updater.write().await;
-            updater_writer.stop();
-        }
-
         // Scaling Component Manager
         {
             // Scope for shared_scaling_component_manager_writer(RwLock)
@@ -84,38 +84,58 @@ impl App {
                 return;
             }
 
-            // Remove all existing scaling component definitions
-            let mut manager_writer = self.shared_scaling_component_manager.write().await;
-            manager_writer.remove_all();
-
-            // Add new scaling component definitions
+            // Reconcile the scaling components with the new definitions
             let scaling_component_definitions = scaling_component_definitions.unwrap();
             let number_of_component_definitions = scaling_component_definitions.len();
             info!(
                 "[app] {} scaling component definitions",
                 number_of_component_definitions
             );
-            if number_of_component_definitions != 0 {
-                let scaling_component_result =
-                    manager_writer.add_definitions(scaling_component_definitions);
-
-                if scaling_component_result.is_err() {
-                    let error = scaling_component_result.err().unwrap();
-                    error!("Error adding scaling component definitions: {}", error);
-                    return;
-                }
+            let mut manager_writer = self.shared_scaling_component_manager.write().await;
+            let diff = manager_writer.reconcile_definitions(scaling_component_definitions);
+            info!("[app] Scaling components reconciled - {}", diff);
+        }
+
+        // Expression Libraries
+        let expression_library_definitions = self
+            .shared_data_layer
+            .get_enabled_expression_libraries()
+            .await;
+        if expression_library_definitions.is_err() {
+            let error = expression_library_definitions.err().unwrap();
+            error!("Error getting expression library definitions: {}", error);
+            return;
+        }
+        // Validate the expression libraries. The invalid ones are not loaded into the scaling planners.
+        let mut valid_expression_library_definitions = Vec::new();
+        for expression_library_definition in expression_library_definitions.unwrap() {
+            match validate_expression_library(&expression_library_definition).await {
+                Ok(_) => valid_expression_library_definitions.push(expression_library_definition),
+                Err(error) => error!("Invalid expression library: {}", error),
             }
         }
+        info!(
+            "[app] {} expression library definitions",
+            valid_expression_library_definitions.len()
+        );
+
+        // Holiday Calendars
+        let holiday_calendar_definitions =
+            self.shared_data_layer.get_enabled_holiday_calendars().await;
+        if holiday_calendar_definitions.is_err() {
+            let error = holiday_calendar_definitions.err().unwrap();
+            error!("Error getting holiday calendar definitions: {}", error);
+            return;
+        }
+        let holiday_calendar_definitions = holiday_calendar_definitions.unwrap();
+        info!(
+            "[app] {} holiday calendar definitions",
+            holiday_calendar_definitions.len()
+        );
 
         // Scaling Planner Manager
         {
             // Scope for shared_scaling_plan_manager_writer(RwLock)
-
-            // Remove all existing scaling plan definitions
-            let mut manager_writer = self.shared_scaling_planner_manager.write().await;
-            manager_writer.stop();
-            manager_writer.remove_all();
-
             let plan_definitions = self.shared_data_layer.get_enabled_plans().await;
             if plan_definitions.is_err() {
                 let error = plan_definitions.err().unwrap();
@@ -126,26 +146,42 @@ impl App {
             let number_of_plans = plan_definitions.len();
             info!("[app] {} plan definitions", number_of_plans);
 
-            if number_of_plans != 0 {
-                // Run Metric Updater
-                {
-                    // Scope for shared_metric_updater_writer(RwLock)
-                    let mut updater_writer = self.shared_metric_updater.write().await;
+            // Reconcile the scaling planners with the new definitions
+            let mut manager_writer = self.shared_scaling_planner_manager.write().await;
+            manager_writer.set_expression_libraries(valid_expression_library_definitions);
+            manager_writer.set_holiday_calendars(holiday_calendar_definitions);
+            let diff = manager_writer.reconcile_definitions(plan_definitions);
+            info!("[app] ScalingPlans reconciled - {}", diff);
+
+            // Metric Updater
+            {
+                // Scope for shared_metric_updater_writer(RwLock)
+                let mut updater_writer = self.shared_metric_updater.write().await;
+                if number_of_plans == 0 {
+                    updater_writer.stop();
+                } else if !diff.is_empty() || updater_writer.is_metric_definitions_changed().await {
+                    // Rerun Metric Updater to refresh the metrics of the plans and the metric definitions
                     updater_writer.run().await;
                 }
-
-                let scaling_plan_result = manager_writer.add_definitions(plan_definitions);
-                if scaling_plan_result.is_err() {
-                    let error = scaling_plan_result.err().unwrap();
-                    error!("Error adding scaling plan definitions: {}", error);
-                    return;
-                }
-                manager_writer.run();
-                info!("[app] ScalingPlans started: {} plans", number_of_plans);
             }
         }
     }
 
+    // Stop the ScalingPlanners and the MetricUpdater (e.g. when this instance is not the leader)
+    pub async fn stop(&mut self) {
+        {
+            // Scope for shared_scaling_plan_manager_writer(RwLock)
+            let mut manager_writer = self.shared_scaling_planner_manager.write().await;
+            manager_writer.stop();
+            manager_writer.remove_all();
+        }
+        {
+            // Scope for shared_metric_updater_writer(RwLock)
+            let mut updater_writer = self.shared_metric_updater.write().await;
+            updater_writer.stop();
+        }
+    }
+
     // Run the cron job to remove the old Autoscaling History
     pub fn run_autoscaling_history_cron_job(&mut self, duration_string: String) {
         self.stop_autoscaling_history_cron_job();
@@ -0,0 +1,193 @@
+use data_layer::{data_layer::DataLayer, types::leader_lease_definition::LeaderLeaseDefinition};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::watch;
+use tracing::{debug, error, info};
+use ulid::Ulid;
+
+const METRICS_RECEIVER_PATH: &str = "/api/metrics-receiver";
+
+// LeaderElector elects a leader among the wave-autoscale instances sharing the same database.
+// It acquires or renews the lease row in the DataLayer every 1/3 of the lease duration.
+pub struct LeaderElector {
+    data_layer: Arc<DataLayer>,
+    holder_id: String,
+    holder_endpoint: String,
+    lease_duration: u64,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl LeaderElector {
+    pub fn new(data_layer: Arc<DataLayer>, holder_endpoint: &str, lease_duration: u64) -> Self {
+        LeaderElector {
+            data_layer,
+            holder_id: Ulid::new().to_string(),
+            holder_endpoint: holder_endpoint.to_string(),
+            lease_duration,
+            task: None,
+        }
+    }
+
+    // The receiver gets true when this instance becomes the leader and false when it loses the leadership.
+    pub fn run(&mut self) -> watch::Receiver<bool> {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        let (leader_sender, leader_receiver) = watch::channel(false);
+        let data_layer = self.data_layer.clone();
+        let holder_id = self.holder_id.clone();
+        let holder_endpoint = self.holder_endpoint.clone();
+        let lease_duration = self.lease_duration;
+        let renew_interval = Duration::from_millis(std::cmp::max(lease_duration / 3, 1));
+
+        let task = tokio::spawn(async move {
+            let mut last_renewed_at: Option<Instant> = None;
+            loop {
+                let result = data_layer
+                    .try_acquire_leader_lease(&holder_id, &holder_endpoint, lease_duration)
+                    .await;
+                let is_leader = match result {
+                    Ok(true) => {
+                        last_renewed_at = Some(Instant::now());
+                        true
+                    }
+                    Ok(false) => {
+                        last_renewed_at = None;
+                        false
+                    }
+                    Err(error) => {
+                        error!(
+                            "[LeaderElector] Failed to acquire the leader lease: {}",
+                            error
+                        );
+                        // Keep the leadership only until the lease renewed last time expires
+                        last_renewed_at.map_or(false, |last_renewed_at| {
+                            last_renewed_at.elapsed() < Duration::from_millis(lease_duration)
+                        })
+                    }
+                };
+
+                // The followers forward the ingested metrics to the leader
+                let metrics_forward_url = if is_leader {
+                    None
+                } else {
+                    match data_layer.get_leader_lease().await {
+                        Ok(leader_lease) => {
+                            get_metrics_forward_url(leader_lease, &holder_id, &holder_endpoint)
+                        }
+                        Err(_) => None,
+                    }
+                };
+                debug!(
+                    "[LeaderElector] is_leader: {}, metrics_forward_url: {:?}",
+                    is_leader, metrics_forward_url
+                );
+                data_layer.set_metrics_forward_url(metrics_forward_url);
+
+                if *leader_sender.borrow() != is_leader {
+                    info!(
+                        "[LeaderElector] {} - holder_id: {}",
+                        if is_leader {
+                            "Became the leader"
+                        } else {
+                            "Lost the leadership"
+                        },
+                        holder_id
+                    );
+                    let _ = leader_sender.send(is_leader);
+                }
+
+                tokio::time::sleep(renew_interval).await;
+            }
+        });
+        self.task = Some(task);
+        leader_receiver
+    }
+
+    // Stop renewing the lease and release it so that a follower can take over without waiting for the lease to expire
+    pub async fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        self.data_layer.set_metrics_forward_url(None);
+        match self.data_layer.release_leader_lease(&self.holder_id).await {
+            Ok(_) => info!(
+                "[LeaderElector] Released the leader lease - holder_id: {}",
+                self.holder_id
+            ),
+            Err(error) => error!(
+                "[LeaderElector] Failed to release the leader lease: {}",
+                error
+            ),
+        }
+    }
+}
+
+// The URL of the metrics receiver of the leader. None if the lease is expired or names this instance,
+// so that this instance doesn't forward the metrics to itself.
+fn get_metrics_forward_url(
+    leader_lease: Option<LeaderLeaseDefinition>,
+    holder_id: &str,
+    holder_endpoint: &str,
+) -> Option<String> {
+    let leader_lease = leader_lease?;
+    if leader_lease.is_expired() || leader_lease.holder_id == holder_id {
+        return None;
+    }
+    let endpoint = leader_lease.holder_endpoint?;
+    if endpoint.trim_end_matches('/') == holder_endpoint.trim_end_matches('/') {
+        return None;
+    }
+    Some(format!(
+        "{}{}",
+        endpoint.trim_end_matches('/'),
+        METRICS_RECEIVER_PATH
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_metrics_forward_url() {
+        let get_leader_lease = |holder_id: &str, endpoint: &str, expires_in_ms: i64| {
+            Some(LeaderLeaseDefinition {
+                holder_id: holder_id.to_string(),
+                holder_endpoint: Some(endpoint.to_string()),
+                expires_at: chrono::Utc::now().timestamp_millis() + expires_in_ms,
+            })
+        };
+        assert_eq!(
+            get_metrics_forward_url(
+                get_leader_lease("leader", "http://10.0.0.1:3024/", 60_000),
+                "follower",
+                "http://10.0.0.2:3024"
+            ),
+            Some("http://10.0.0.1:3024/api/metrics-receiver".to_string())
+        );
+        // The expired lease
+        assert!(get_metrics_forward_url(
+            get_leader_lease("leader", "http://10.0.0.1:3024", -1),
+            "follower",
+            "http://10.0.0.2:3024"
+        )
+        .is_none());
+        // The lease names this instance by the holder id or the endpoint
+        assert!(get_metrics_forward_url(
+            get_leader_lease("follower", "http://10.0.0.1:3024", 60_000),
+            "follower",
+            "http://10.0.0.2:3024"
+        )
+        .is_none());
+        assert!(get_metrics_forward_url(
+            get_leader_lease("leader", "http://10.0.0.2:3024", 60_000),
+            "follower",
+            "http://10.0.0.2:3024/"
+        )
+        .is_none());
+        assert!(get_metrics_forward_url(None, "follower", "http://10.0.0.2:3024").is_none());
+    }
+}
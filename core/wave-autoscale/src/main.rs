@@ -5,13 +5,17 @@
  * 2. 애플리케이션 구성 요소 초기화: DataLayer, MetricsCollectorManager, API 서버, 웹 앱 등의 주요 구성 요소를 초기화합니다.
     DataLayer는 데이터베이스와의 상호작용을 관리하며, MetricsCollectorManager는 메트릭 수집을 담당합니다.
     API 서버와 웹 앱은 별도의 비동기 태스크로 실행됩니다.
+    ExpressionEvaluator는 API로 요청된 표현식을 새 엔진에서 평가합니다.
  * 3. 작업 실행: 애플리케이션은 자동 스케일링 이력을 제거하고, 시작 시 정의를 리셋하며, 정의 파일을 동기화합니다.
     또한, 정의 파일의 변경을 감시하는 작업을 설정합니다.
  * 4. 메인 애플리케이션 실행: 메트릭 수집기를 업데이트하고 메인 애플리케이션을 실행합니다.
     이는 정의 파일의 변경을 감지하면 반복적으로 실행됩니다.
     메트릭 수집기는 활성화된 메트릭에 대해 실행되며, 메인 애플리케이션은 애플리케이션의 주요 로직을 실행합니다.
+ * 5. 고가용성 모드: ha_enabled가 설정되면 LeaderElector가 DataLayer의 리스(lease)를 통해 리더를 선출합니다.
+    리더만 메트릭 수집기와 메인 애플리케이션을 실행하며, 리더가 아닌 인스턴스는 API만 제공하고 수신한 메트릭을 리더에게 전달합니다.
  */
 mod app;
+mod leader_elector;
 mod metric_collector_manager;
 mod metric_updater;
 mod scaling_component;
@@ -21,10 +25,11 @@ mod web_app_runner;
 
 use api_server::app::run_api_server;
 use data_layer::data_layer::DataLayer;
+use leader_elector::LeaderElector;
 use metric_collector_manager::MetricsCollectorManager;
 use std::sync::Arc;
 use tokio::sync::watch;
-use tracing::{debug, error};
+use tracing::{debug, error, info};
 use utils::{config_path::find_file_in_wa, wave_config::WaveConfig};
 
 const LOCAL_DEFINITION_FILE_NAME: &str = "wave-definition.yaml";
@@ -35,9 +40,12 @@ async fn main() {
     // Initialize some features (Ctrl+C Signal, Command Line Arguments, Logger)
     //
 
-    // Handle Ctrl+C Signal
+    // Handle Ctrl+C and termination signals. The process exits after the shutdown(e.g. releasing the leader lease)
+    let (shutdown_sender, mut shutdown_receiver) = tokio::sync::mpsc::unbounded_channel::<()>();
     let _ = ctrlc::set_handler(move || {
-        std::process::exit(0);
+        if shutdown_sender.send(()).is_err() {
+            std::process::exit(0);
+        }
     });
 
     // WALog
@@ -86,6 +94,10 @@ async fn main() {
         let _ = run_api_server(wave_config_for_api_server, shared_data_layer_for_api_server);
     });
 
+    // Run Expression Evaluator for the ad-hoc evaluations from the API (all instances serve the API)
+    let _expression_evaluator_handle =
+        scaling_planner::expression_evaluator::run_expression_evaluator(shared_data_layer.clone());
+
     // Run Web App
     if wave_config.web_ui {
         let host = wave_config.web_ui_host.clone();
@@ -112,6 +124,8 @@ async fn main() {
         let _ = shared_data_layer.delete_all_metrics().await;
         let _ = shared_data_layer.delete_all_scaling_components().await;
         let _ = shared_data_layer.delete_all_plans().await;
+        let _ = shared_data_layer.delete_all_expression_libraries().await;
+        let _ = shared_data_layer.delete_all_holiday_calendars().await;
     }
 
     // Sync the definition file if it exists
@@ -134,9 +148,58 @@ async fn main() {
         None
     };
 
+    // High Availability
+    // Only the leader runs the ScalingPlanners and the metric collectors.
+    // All instances serve the API and the followers forward the ingested metrics to the leader.
+    let (mut leader_elector, mut leader_receiver) = if wave_config.ha_enabled {
+        let mut leader_elector = LeaderElector::new(
+            shared_data_layer.clone(),
+            wave_config.get_ha_advertise_url().as_str(),
+            wave_config.ha_lease_duration,
+        );
+        let leader_receiver = leader_elector.run();
+        info!("[HA] High availability mode is enabled. Waiting for the leader election");
+        (Some(leader_elector), Some(leader_receiver))
+    } else {
+        (None, None)
+    };
+
+    // Shutdown: release the leader lease so that a follower takes over without waiting for the lease to expire
+    tokio::spawn(async move {
+        let _ = shutdown_receiver.recv().await;
+        if let Some(leader_elector) = leader_elector.as_mut() {
+            leader_elector.stop().await;
+        }
+        std::process::exit(0);
+    });
+
     // Run the main application(controller) in a loop
     // If watch_duration is 0, run the main application(controller) only once
-    while watch_receiver.is_some() && watch_receiver.as_mut().unwrap().changed().await.is_ok() {
+    while let Some(watch_receiver) = watch_receiver.as_mut() {
+        // Wait for the definition changes or the leadership changes
+        let is_leader = if let Some(leader_receiver) = leader_receiver.as_mut() {
+            let result = tokio::select! {
+                result = watch_receiver.changed() => result,
+                result = leader_receiver.changed() => result,
+            };
+            if result.is_err() {
+                break;
+            }
+            *leader_receiver.borrow()
+        } else {
+            if watch_receiver.changed().await.is_err() {
+                break;
+            }
+            true
+        };
+
+        // The followers stop the collectors and the planners
+        if !is_leader {
+            metric_collector_manager.stop();
+            app.stop().await;
+            continue;
+        }
+
         // Update metric collectors
         // TODO: MetricCollectorManager could be moved into the app(controller)
         let shared_data_layer = shared_data_layer.clone();
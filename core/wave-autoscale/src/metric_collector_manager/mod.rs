@@ -1,6 +1,7 @@
 mod process;
 mod wa_generator;
 
+use crate::util::reconciler::{diff_definition_hashes, get_definition_hash};
 use data_layer::MetricDefinition;
 use flate2::read::GzDecoder;
 use futures_util::StreamExt;
@@ -20,10 +21,12 @@ pub struct MetricsCollectorManager {
     wave_config: WaveConfig,
     output_url: String,
     collector_log: bool,
-    // For external collectors
-    running_apps: Option<HashMap<String, std::process::Child>>,
-    // For internal collectors
-    running_tasks: Option<Vec<tokio::task::JoinHandle<()>>>,
+    // For external collectors (key: collector name)
+    running_apps: HashMap<String, std::process::Child>,
+    // The configuration of the running external collectors (key: collector name, value: config)
+    running_app_configs: HashMap<String, String>,
+    // For internal collectors (key: metric id, value: (content hash of the definition, task))
+    running_tasks: HashMap<String, (u64, tokio::task::JoinHandle<()>)>,
 }
 
 impl MetricsCollectorManager {
@@ -33,8 +36,9 @@ impl MetricsCollectorManager {
             wave_config,
             output_url: output_url.to_string(),
             collector_log,
-            running_apps: None,
-            running_tasks: None,
+            running_apps: HashMap::new(),
+            running_app_configs: HashMap::new(),
+            running_tasks: HashMap::new(),
         }
     }
 
@@ -329,6 +333,41 @@ impl MetricsCollectorManager {
         Ok(())
     }
 
+    // Kill the process of an external collector
+    fn kill_process(name: &str, child: &mut std::process::Child) {
+        // retry 3 times
+        for idx in 1..4 {
+            if child.kill().is_ok() {
+                break;
+            };
+            error!("Failed to kill {} - try {}, {:?}", name, idx, child);
+            if idx == 3 {
+                panic!("Failed to kill {}", name);
+            }
+        }
+        debug!("Killing {}", name);
+    }
+
+    // Whether the process of an external collector is still running
+    fn is_process_running(&mut self, name: &str) -> bool {
+        match self.running_apps.get_mut(name) {
+            Some(child) => matches!(child.try_wait(), Ok(None)),
+            None => false,
+        }
+    }
+
+    // Stop all the collectors (e.g. when this instance is not the leader)
+    pub fn stop(&mut self) {
+        for (name, child) in self.running_apps.iter_mut() {
+            Self::kill_process(name, child);
+        }
+        self.running_apps.clear();
+        self.running_app_configs.clear();
+        for (_, (_, task)) in self.running_tasks.drain() {
+            task.abort();
+        }
+    }
+
     pub async fn run(&mut self, metric_definitions: &Vec<MetricDefinition>) {
         info!(
             "[metric-collector-manager] {} metric definitions",
@@ -338,7 +377,9 @@ impl MetricsCollectorManager {
         // Prepare the collector binaries
         self.prepare_collector_binaries(metric_definitions).await;
 
-        let mut collector_processes: Vec<process::AppInfo> = Vec::new();
+        // The external collectors to run with their configurations
+        // Only the collectors whose configuration has changed are restarted.
+        let mut collector_configs: HashMap<String, String> = HashMap::new();
 
         // collector: vector
         let mut vector_metric_definitions: Vec<&MetricDefinition> = Vec::new();
@@ -348,8 +389,53 @@ impl MetricsCollectorManager {
             }
         }
         if !vector_metric_definitions.is_empty() {
+            let vector_config = convert_metric_definitions_to_vector_toml(
+                &vector_metric_definitions,
+                self.output_url.clone(),
+            );
+            collector_configs.insert(VECTOR_COLLECTOR.to_string(), vector_config);
+        }
+
+        // collector: telegraf
+        let mut telegraf_metric_definitions: Vec<&MetricDefinition> = Vec::new();
+        for metric_definition in metric_definitions {
+            if metric_definition.collector == TELEGRAF_COLLECTOR {
+                telegraf_metric_definitions.push(metric_definition);
+            }
+        }
+        if !telegraf_metric_definitions.is_empty() {
+            let telegraf_config = convert_metric_definitions_to_telegraf_toml(
+                &telegraf_metric_definitions,
+                self.output_url.clone(),
+            );
+            collector_configs.insert(TELEGRAF_COLLECTOR.to_string(), telegraf_config);
+        }
+
+        // kill agent process if it is removed or its configuration has changed
+        let mut killed = false;
+        for name in [VECTOR_COLLECTOR, TELEGRAF_COLLECTOR] {
+            let is_unchanged = collector_configs.get(name).is_some()
+                && collector_configs.get(name) == self.running_app_configs.get(name);
+            if is_unchanged && self.is_process_running(name) {
+                debug!("[metric-collector-manager] {} is unchanged", name);
+                collector_configs.remove(name);
+                continue;
+            }
+            self.running_app_configs.remove(name);
+            if let Some(mut child) = self.running_apps.remove(name) {
+                Self::kill_process(name, &mut child);
+                killed = true;
+            }
+        }
+        if killed {
+            // sleep 2 seconds
+            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        }
+
+        let os_arch = self.get_os_arch();
+        let mut collector_processes: Vec<process::AppInfo> = Vec::new();
+        if let Some(vector_config) = collector_configs.get(VECTOR_COLLECTOR) {
             // Save the metric definitions to Vector config
-            let os_arch = self.get_os_arch();
             let vector_dir_path = format!("./vector_{}", os_arch);
             let vector_config_path = format!("{}/vector.toml", vector_dir_path);
             let save_result = self.save_metric_definitions_to_vector_config(
@@ -360,26 +446,19 @@ impl MetricsCollectorManager {
             if save_result.is_ok() {
                 // Run the collector binaries
                 let vector_app_info = process::AppInfo {
-                    name: "vector".to_string(),
+                    name: VECTOR_COLLECTOR.to_string(),
                     command: format!("{}/vector", vector_dir_path),
                     args: Some(vec!["--config-toml".to_string(), vector_config_path]),
                     envs: None,
                     output: self.collector_log,
                 };
                 collector_processes.push(vector_app_info);
+                self.running_app_configs
+                    .insert(VECTOR_COLLECTOR.to_string(), vector_config.clone());
             }
         }
-
-        // collector: telegraf
-        let mut telegraf_metric_definitions: Vec<&MetricDefinition> = Vec::new();
-        for metric_definition in metric_definitions {
-            if metric_definition.collector == TELEGRAF_COLLECTOR {
-                telegraf_metric_definitions.push(metric_definition);
-            }
-        }
-        if !telegraf_metric_definitions.is_empty() {
+        if let Some(telegraf_config) = collector_configs.get(TELEGRAF_COLLECTOR) {
             // Save the metric definitions to Telegraf config
-            let os_arch = self.get_os_arch();
             let telegraf_dir_path = format!("./telegraf_{}", os_arch);
             let telegraf_config_path = format!("{}/telegraf.conf", telegraf_dir_path);
             let save_result = self.save_metric_definitions_to_telegraf_config(
@@ -390,69 +469,59 @@ impl MetricsCollectorManager {
             if save_result.is_ok() {
                 // Run the collector binaries
                 let telegraf_app_info = process::AppInfo {
-                    name: "telegraf".to_string(),
+                    name: TELEGRAF_COLLECTOR.to_string(),
                     command: format!("{}/telegraf", telegraf_dir_path),
                     args: Some(vec!["--config".to_string(), telegraf_config_path]),
                     envs: None,
                     output: self.collector_log,
                 };
                 collector_processes.push(telegraf_app_info);
+                self.running_app_configs
+                    .insert(TELEGRAF_COLLECTOR.to_string(), telegraf_config.clone());
             }
         }
 
-        // kill agent process
-        if let Some(running_apps) = &mut self.running_apps {
-            running_apps
-                .iter_mut()
-                .for_each(|(name, &mut ref mut child)| {
-                    // retry 3 times
-                    for idx in 1..4 {
-                        if child.kill().is_ok() {
-                            break;
-                        };
-                        error!("Failed to kill {} - try {}, {:?}", name, idx, child);
-                        if idx == 3 {
-                            panic!("Failed to kill {}", name);
-                        }
-                    }
-                    debug!("Killing {}", name);
-                });
-            // sleep 2 seconds
-            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-        };
-        self.running_apps = None;
-
         if !collector_processes.is_empty() {
             // run agent process
             let running_apps = process::run_processes(&collector_processes);
-            self.running_apps = Some(running_apps);
+            self.running_apps.extend(running_apps);
         }
 
-        // Find the metric definitions that use WA Generator collector
+        // collector: wa-generator
+        // Only the tasks whose metric definitions are added, changed or removed are restarted.
+        let wa_generator_metric_definitions = metric_definitions
+            .iter()
+            .filter(|metric_definition| metric_definition.collector == WA_GENERATOR_COLLECTOR)
+            .map(|metric_definition| (metric_definition.id.clone(), metric_definition))
+            .collect::<HashMap<String, &MetricDefinition>>();
+        let running_hashes = self
+            .running_tasks
+            .iter()
+            .map(|(id, (hash, _))| (id.clone(), *hash))
+            .collect::<HashMap<String, u64>>();
+        let new_hashes = wa_generator_metric_definitions
+            .iter()
+            .map(|(id, metric_definition)| (id.clone(), get_definition_hash(metric_definition)))
+            .collect::<HashMap<String, u64>>();
+        let diff = diff_definition_hashes(&running_hashes, &new_hashes);
+        debug!("[metric-collector-manager] wa-generator - {}", diff);
 
         // Stop the running tasks
-        if let Some(running_tasks) = &mut self.running_tasks {
-            running_tasks.iter_mut().for_each(|task| task.abort());
-            running_tasks.clear();
-            self.running_tasks = None;
-        };
-
-        // collector: wa-generator
-        let mut wa_generator_metric_definitions: Vec<&MetricDefinition> = Vec::new();
-        for metric_definition in metric_definitions {
-            if metric_definition.collector == WA_GENERATOR_COLLECTOR {
-                wa_generator_metric_definitions.push(metric_definition);
+        for id in diff.get_ids_to_remove() {
+            if let Some((_, task)) = self.running_tasks.remove(&id) {
+                task.abort();
             }
         }
-        if !wa_generator_metric_definitions.is_empty() {
-            // Run the collector binaries
-            let mut running_tasks: Vec<tokio::task::JoinHandle<()>> = Vec::new();
-            for metric_definition in wa_generator_metric_definitions {
-                let output_url = self.output_url.clone();
-                let handle = wa_generator::run(metric_definition.clone(), output_url);
-                if let Ok(handle) = handle {
-                    running_tasks.push(handle);
-                }
+
+        // Run the collector tasks
+        for id in diff.get_ids_to_add() {
+            let (Some(metric_definition), Some(hash)) = (wa_generator_metric_definitions.get(&id), new_hashes.get(&id)) else {
+                continue;
+            };
+            let output_url = self.output_url.clone();
+            let handle = wa_generator::run((*metric_definition).clone(), output_url);
+            if let Ok(handle) = handle {
+                self.running_tasks.insert(id, (*hash, handle));
             }
         }
     }
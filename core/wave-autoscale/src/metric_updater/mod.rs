@@ -1,4 +1,5 @@
-use data_layer::data_layer::DataLayer;
+use crate::util::reconciler::get_definition_hash;
+use data_layer::{data_layer::DataLayer, MetricDefinition};
 use serde_json::Value;
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
@@ -13,6 +14,20 @@ pub struct MetricUpdater {
     data_layer: Arc<DataLayer>,
     polling_interval: u64,
     task: Option<tokio::task::JoinHandle<()>>,
+    // The content hashes of the metric definitions polled by the task (key: id)
+    metric_definition_hashes: HashMap<String, u64>,
+}
+
+fn get_metric_definition_hashes(metric_definitions: &[MetricDefinition]) -> HashMap<String, u64> {
+    metric_definitions
+        .iter()
+        .map(|metric_definition| {
+            (
+                metric_definition.id.clone(),
+                get_definition_hash(metric_definition),
+            )
+        })
+        .collect()
 }
 
 impl MetricUpdater {
@@ -23,6 +38,7 @@ impl MetricUpdater {
             data_layer,
             polling_interval,
             task: None,
+            metric_definition_hashes: HashMap::new(),
         }
     }
     pub fn new_shared(data_layer: Arc<DataLayer>, polling_interval: u64) -> SharedMetricUpdater {
@@ -42,6 +58,7 @@ impl MetricUpdater {
             return;
         }
         let metric_definitions = metric_definitions.unwrap();
+        self.metric_definition_hashes = get_metric_definition_hashes(&metric_definitions);
         let metric_ids = metric_definitions
             .iter()
             .map(|m| m.id.clone())
@@ -70,6 +87,7 @@ impl MetricUpdater {
 
     pub fn stop(&mut self) {
         self.metric_values = Arc::new(RwLock::new(HashMap::new()));
+        self.metric_definition_hashes.clear();
         if let Some(task) = self.task.take() {
             tokio::task::block_in_place(|| {
                 task.abort();
@@ -77,6 +95,16 @@ impl MetricUpdater {
         }
     }
 
+    // Whether the metric definitions are added, changed or removed since the last run
+    pub async fn is_metric_definitions_changed(&self) -> bool {
+        let metric_definitions = self.data_layer.get_all_metrics().await;
+        if metric_definitions.is_err() {
+            return false;
+        }
+        let metric_definitions = metric_definitions.unwrap();
+        get_metric_definition_hashes(&metric_definitions) != self.metric_definition_hashes
+    }
+
     pub async fn get_metric_values(&self) -> Result<HashMap<String, Value>, String> {
         let metric_values = self.metric_values.read().await;
         Ok(metric_values.clone())
@@ -131,5 +159,21 @@ mod tests {
         let metric_values: tokio::sync::RwLockReadGuard<'_, HashMap<String, Value>> =
             metric_updater.metric_values.read().await;
         assert_eq!(metric_values.len(), 1);
+        drop(metric_values);
+
+        // A new metric definition has to rerun the updater
+        assert!(!metric_updater.is_metric_definitions_changed().await);
+        let metric_definitions = vec![MetricDefinition {
+            id: "metric2".to_string(),
+            metadata: HashMap::new(),
+            kind: ObjectKind::Metric,
+            db_id: "".to_string(),
+            collector: "vector".to_string(),
+            ..Default::default()
+        }];
+        let _ = data_layer.add_metrics(metric_definitions).await;
+        assert!(metric_updater.is_metric_definitions_changed().await);
+        metric_updater.run().await;
+        assert!(!metric_updater.is_metric_definitions_changed().await);
     }
 }
@@ -1,15 +1,18 @@
 use super::ScalingComponent;
-use crate::util::aws::get_aws_config;
+use crate::util::aws::{get_aws_config_with_metadata, get_sdk_error};
 use anyhow::{Ok, Result};
 use async_trait::async_trait;
 
 use aws_config::SdkConfig;
 
-use aws_smithy_types::error::metadata::ProvideErrorMetadata;
-
 use aws_sdk_dynamodb::{
     operation::describe_table::DescribeTableOutput,
-    types::{BillingMode, ProvisionedThroughput},
+    types::{
+        BillingMode, GlobalSecondaryIndexUpdate, ProvisionedThroughput,
+        ProvisionedThroughputDescription, ProvisionedThroughputOverride,
+        ReplicaGlobalSecondaryIndex, ReplicationGroupUpdate, UpdateGlobalSecondaryIndexAction,
+        UpdateReplicationGroupMemberAction,
+    },
     Client as DynamoDbClient,
 };
 
@@ -18,7 +21,9 @@ use aws_sdk_applicationautoscaling::{
     types::{
         MetricType, MetricType::DynamoDbReadCapacityUtilization,
         MetricType::DynamoDbWriteCapacityUtilization, PolicyType, PredefinedMetricSpecification,
-        ScalableDimension, ScalableDimension::DynamoDbTableReadCapacityUnits,
+        ScalableDimension, ScalableDimension::DynamoDbIndexReadCapacityUnits,
+        ScalableDimension::DynamoDbIndexWriteCapacityUnits,
+        ScalableDimension::DynamoDbTableReadCapacityUnits,
         ScalableDimension::DynamoDbTableWriteCapacityUnits, ServiceNamespace,
         TargetTrackingScalingPolicyConfiguration,
     },
@@ -26,8 +31,10 @@ use aws_sdk_applicationautoscaling::{
 };
 
 use data_layer::ScalingComponentDefinition;
-use serde_json::json;
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 pub struct DynamoDbTableScalingComponent {
     definition: ScalingComponentDefinition,
@@ -141,38 +148,121 @@ fn handle_dynamodb_scaling_state(
     DynamoDbScalingState::new(capacity_mode, autoscaling_mode, capacity_unit)
 }
 
+// The table, the global secondary index or the replica in another region to scale
+#[derive(Debug, Clone)]
+struct DynamoDbTarget {
+    table_name: String,
+    index_name: Option<String>,
+    replica_region: Option<String>,
+}
+impl DynamoDbTarget {
+    fn is_table(&self) -> bool {
+        self.index_name.is_none() && self.replica_region.is_none()
+    }
+    fn resource_id(&self) -> String {
+        match &self.index_name {
+            Some(index_name) => format!("table/{}/index/{}", self.table_name, index_name),
+            None => format!("table/{}", self.table_name),
+        }
+    }
+    fn policy_name(&self) -> String {
+        match &self.index_name {
+            Some(index_name) => format!("${}-{}-scaling-policy", self.table_name, index_name),
+            None => format!("${}-scaling-policy", self.table_name),
+        }
+    }
+    fn read_dimension(&self) -> ScalableDimension {
+        match &self.index_name {
+            Some(_) => DynamoDbIndexReadCapacityUnits,
+            None => DynamoDbTableReadCapacityUnits,
+        }
+    }
+    fn write_dimension(&self) -> ScalableDimension {
+        match &self.index_name {
+            Some(_) => DynamoDbIndexWriteCapacityUnits,
+            None => DynamoDbTableWriteCapacityUnits,
+        }
+    }
+    // The write capacity is shared by the replicas, so only the read capacity can be set for a replica
+    fn validate_capacity_units(&self, write_capacity_units: Option<i64>) -> Result<()> {
+        if let (Some(replica_region), Some(_)) = (&self.replica_region, write_capacity_units) {
+            return Err(anyhow::anyhow!(
+                "Invalid params - write_capacity_units can't be set for the replica {}, the write capacity is shared by the replicas",
+                replica_region
+            ));
+        }
+        Ok(())
+    }
+}
+
+// The replica has its own table and scalable targets in its region
+fn get_dynamodb_client(shared_config: &SdkConfig, region: Option<&String>) -> DynamoDbClient {
+    match region {
+        Some(region) => DynamoDbClient::from_conf(
+            aws_sdk_dynamodb::config::Builder::from(shared_config)
+                .region(aws_sdk_dynamodb::config::Region::new(region.clone()))
+                .build(),
+        ),
+        None => DynamoDbClient::new(shared_config),
+    }
+}
+fn get_application_autoscaling_client(
+    shared_config: &SdkConfig,
+    target: &DynamoDbTarget,
+) -> ApplicationAutoScalingClient {
+    match &target.replica_region {
+        Some(region) => ApplicationAutoScalingClient::from_conf(
+            aws_sdk_applicationautoscaling::config::Builder::from(shared_config)
+                .region(aws_sdk_applicationautoscaling::config::Region::new(
+                    region.clone(),
+                ))
+                .build(),
+        ),
+        None => ApplicationAutoScalingClient::new(shared_config),
+    }
+}
+
 async fn update_table_to_on_demand_mode(
     shared_config: &SdkConfig,
-    table_name: &str,
+    target: &DynamoDbTarget,
 ) -> Result<(), anyhow::Error> {
+    // The capacity mode is for the whole table including the indexes and the replicas
+    if !target.is_table() {
+        return Err(anyhow::anyhow!(
+            "ON_DEMAND capacity_mode is not available with index_name or replica_region"
+        ));
+    }
     let client = DynamoDbClient::new(shared_config);
     // from provisioned mode to on-demand mode
     let result = client
         .update_table()
-        .table_name(table_name)
+        .table_name(&target.table_name)
         .billing_mode(BillingMode::PayPerRequest);
     let result = result.send().await;
     if let Err(error) = result {
-        let meta = error.meta();
-        let json = json!({
-            "message": meta.message(),
-            "code": meta.code(),
-            "extras": meta.to_string()
-        });
-        return Err(anyhow::anyhow!(json));
+        return Err(get_sdk_error(error));
     }
     Ok(())
 }
 async fn update_table_to_provisioned_mode(
     shared_config: &SdkConfig,
-    table_name: &str,
+    target: &DynamoDbTarget,
     read_capacity_units: Option<i64>,
     write_capacity_units: Option<i64>,
 ) -> Result<(), anyhow::Error> {
+    if !target.is_table() {
+        return update_index_or_replica_to_provisioned_mode(
+            shared_config,
+            target,
+            read_capacity_units,
+            write_capacity_units,
+        )
+        .await;
+    }
     let client = DynamoDbClient::new(shared_config);
     let mut result = client
         .update_table()
-        .table_name(table_name)
+        .table_name(&target.table_name)
         .billing_mode(BillingMode::Provisioned);
 
     match (read_capacity_units, write_capacity_units) {
@@ -203,184 +293,263 @@ async fn update_table_to_provisioned_mode(
 
     let result = result.send().await;
     if let Err(error) = result {
-        let meta = error.meta();
-        let json = json!({
-            "message": meta.message(),
-            "code": meta.code(),
-            "extras": meta.to_string()
-        });
-        return Err(anyhow::anyhow!(json));
+        return Err(get_sdk_error(error));
+    }
+    Ok(())
+}
+async fn update_index_or_replica_to_provisioned_mode(
+    shared_config: &SdkConfig,
+    target: &DynamoDbTarget,
+    read_capacity_units: Option<i64>,
+    write_capacity_units: Option<i64>,
+) -> Result<(), anyhow::Error> {
+    // The omitted capacity units are kept
+    let current = get_current_throughput(shared_config, target).await?;
+    let read_capacity_units = read_capacity_units.or(current
+        .as_ref()
+        .and_then(|current| current.read_capacity_units()));
+    let write_capacity_units = write_capacity_units.or(current
+        .as_ref()
+        .and_then(|current| current.write_capacity_units()));
+
+    let client = DynamoDbClient::new(shared_config);
+    let mut result = client.update_table().table_name(&target.table_name);
+    if let Some(replica_region) = target.replica_region.as_ref() {
+        // Only the read capacity can be overridden for a replica. The write capacity is shared by the replicas.
+        let Some(read_capacity_units) = read_capacity_units else {
+            return Err(anyhow::anyhow!(
+                "read_capacity_units is required for the replica {}",
+                replica_region
+            ));
+        };
+        let throughput_override = ProvisionedThroughputOverride::builder()
+            .read_capacity_units(read_capacity_units)
+            .build();
+        let mut replica_update =
+            UpdateReplicationGroupMemberAction::builder().region_name(replica_region);
+        replica_update = match target.index_name.as_ref() {
+            Some(index_name) => replica_update.global_secondary_indexes(
+                ReplicaGlobalSecondaryIndex::builder()
+                    .index_name(index_name)
+                    .provisioned_throughput_override(throughput_override)
+                    .build(),
+            ),
+            None => replica_update.provisioned_throughput_override(throughput_override),
+        };
+        result = result.replica_updates(
+            ReplicationGroupUpdate::builder()
+                .update(replica_update.build())
+                .build(),
+        );
+    } else if let Some(index_name) = target.index_name.as_ref() {
+        let (Some(read_capacity_units), Some(write_capacity_units)) =
+            (read_capacity_units, write_capacity_units)
+        else {
+            return Err(anyhow::anyhow!(
+                "Failed to get the capacity units of the index {}",
+                index_name
+            ));
+        };
+        result = result.global_secondary_index_updates(
+            GlobalSecondaryIndexUpdate::builder()
+                .update(
+                    UpdateGlobalSecondaryIndexAction::builder()
+                        .index_name(index_name)
+                        .provisioned_throughput(
+                            ProvisionedThroughput::builder()
+                                .read_capacity_units(read_capacity_units)
+                                .write_capacity_units(write_capacity_units)
+                                .build(),
+                        )
+                        .build(),
+                )
+                .build(),
+        );
+    }
+    let result = result.send().await;
+    if let Err(error) = result {
+        return Err(get_sdk_error(error));
     }
     Ok(())
 }
 async fn describe_data_from_table(
     shared_config: &SdkConfig,
     table_name: &str,
+    region: Option<&String>,
 ) -> Result<DescribeTableOutput, anyhow::Error> {
-    let client = DynamoDbClient::new(shared_config);
+    let client = get_dynamodb_client(shared_config, region);
     let result = client.describe_table().table_name(table_name);
     let result = result.send().await;
     if let Err(error) = result {
-        let meta = error.meta();
-        let json = json!({
-            "message": meta.message(),
-            "code": meta.code(),
-            "extras": meta.to_string()
-        });
-        Err(anyhow::anyhow!(json))
+        Err(get_sdk_error(error))
     } else {
         Ok(result?)
     }
 }
-async fn update_recent_table_to_provisioned_mode(
+// The provisioned throughput of the table or the index (in the region of the replica)
+async fn get_current_throughput(
     shared_config: &SdkConfig,
-    table_name: &str,
+    target: &DynamoDbTarget,
+) -> Result<Option<ProvisionedThroughputDescription>, anyhow::Error> {
+    let output = describe_data_from_table(
+        shared_config,
+        &target.table_name,
+        target.replica_region.as_ref(),
+    )
+    .await?;
+    let Some(table) = output.table else {
+        return Ok(None);
+    };
+    let throughput = match target.index_name.as_ref() {
+        Some(index_name) => {
+            let Some(index) = table.global_secondary_indexes().and_then(|indexes| {
+                indexes
+                    .iter()
+                    .find(|index| index.index_name() == Some(index_name.as_str()))
+            }) else {
+                return Err(anyhow::anyhow!(
+                    "The index {} is not found in the table {}",
+                    index_name,
+                    target.table_name
+                ));
+            };
+            index.provisioned_throughput().cloned()
+        }
+        None => table.provisioned_throughput().cloned(),
+    };
+    Ok(throughput)
+}
+
+// DynamoDB allows 4 decreases any time per UTC day and 1 more decrease per hour
+// if there was no decrease in the last hour (up to 27 decreases per day)
+const MAX_DECREASES_ANY_TIME_PER_DAY: i64 = 4;
+const MAX_DECREASES_PER_DAY: i64 = 27;
+const DECREASE_INTERVAL_SEC: i64 = 60 * 60;
+fn validate_throughput_decrease(
+    current: &ProvisionedThroughputDescription,
     read_capacity_units: Option<i64>,
     write_capacity_units: Option<i64>,
+    now_secs: i64,
 ) -> Result<(), anyhow::Error> {
-    match (read_capacity_units, write_capacity_units) {
-        (Some(read_capacity_units), Some(write_capacity_units)) => {
-            update_table_to_provisioned_mode(
-                shared_config,
-                table_name,
-                Some(read_capacity_units),
-                Some(write_capacity_units),
-            )
-            .await?;
-        }
-        (Some(read_capacity_units), None) => {
-            update_table_to_provisioned_mode(
-                shared_config,
-                table_name,
-                Some(read_capacity_units),
-                describe_data_from_table(shared_config, table_name)
-                    .await?
-                    .table
-                    .unwrap()
-                    .provisioned_throughput()
-                    .unwrap()
-                    .write_capacity_units(),
-            )
-            .await?;
-        }
-        (None, Some(write_capacity_units)) => {
-            update_table_to_provisioned_mode(
-                shared_config,
-                table_name,
-                describe_data_from_table(shared_config, table_name)
-                    .await?
-                    .table
-                    .unwrap()
-                    .provisioned_throughput()
-                    .unwrap()
-                    .read_capacity_units(),
-                Some(write_capacity_units),
-            )
-            .await?;
-        }
-        (None, None) => {
-            update_table_to_provisioned_mode(
-                shared_config,
-                table_name,
-                describe_data_from_table(shared_config, table_name)
-                    .await?
-                    .table
-                    .unwrap()
-                    .provisioned_throughput()
-                    .unwrap()
-                    .read_capacity_units(),
-                describe_data_from_table(shared_config, table_name)
-                    .await?
-                    .table
-                    .unwrap()
-                    .provisioned_throughput()
-                    .unwrap()
-                    .write_capacity_units(),
-            )
-            .await?;
+    let is_decrease = |target: Option<i64>, current: Option<i64>| match (target, current) {
+        (Some(target), Some(current)) => current > 0 && target < current,
+        _ => false,
+    };
+    if !is_decrease(read_capacity_units, current.read_capacity_units())
+        && !is_decrease(write_capacity_units, current.write_capacity_units())
+    {
+        return Ok(());
+    }
+    let number_of_decreases_today = current.number_of_decreases_today().unwrap_or(0);
+    if number_of_decreases_today < MAX_DECREASES_ANY_TIME_PER_DAY {
+        return Ok(());
+    }
+    if number_of_decreases_today >= MAX_DECREASES_PER_DAY {
+        return Err(anyhow::anyhow!(
+            "The capacity units have been decreased {} times today (max: {})",
+            number_of_decreases_today,
+            MAX_DECREASES_PER_DAY
+        ));
+    }
+    if let Some(last_decrease_date_time) = current.last_decrease_date_time() {
+        let elapsed = now_secs - last_decrease_date_time.secs();
+        if elapsed < DECREASE_INTERVAL_SEC {
+            return Err(anyhow::anyhow!(
+                "The capacity units have been decreased {} times today and the next decrease is available in {} seconds",
+                number_of_decreases_today,
+                DECREASE_INTERVAL_SEC - elapsed
+            ));
         }
     }
     Ok(())
 }
+
+async fn update_recent_table_to_provisioned_mode(
+    shared_config: &SdkConfig,
+    target: &DynamoDbTarget,
+    read_capacity_units: Option<i64>,
+    write_capacity_units: Option<i64>,
+) -> Result<(), anyhow::Error> {
+    // The omitted capacity units are kept
+    let current = get_current_throughput(shared_config, target).await?;
+    let Some(current) = current else {
+        return Err(anyhow::anyhow!("Failed to get the current capacity units"));
+    };
+    update_table_to_provisioned_mode(
+        shared_config,
+        target,
+        read_capacity_units.or(current.read_capacity_units()),
+        write_capacity_units.or(current.write_capacity_units()),
+    )
+    .await
+}
 async fn describe_scaling_policies_from_table(
     shared_config: &SdkConfig,
-    table_name: &str,
+    target: &DynamoDbTarget,
     scalable_dimension: ScalableDimension,
 ) -> Result<DescribeScalingPoliciesOutput, anyhow::Error> {
-    let client = ApplicationAutoScalingClient::new(shared_config);
+    let client = get_application_autoscaling_client(shared_config, target);
     let result = client
         .describe_scaling_policies()
         .set_scalable_dimension(Some(scalable_dimension))
         .set_service_namespace(Some(ServiceNamespace::Dynamodb))
-        .set_resource_id(Some("table/".to_owned() + table_name));
+        .set_resource_id(Some(target.resource_id()));
     let result = result.send().await;
     if let Err(error) = result {
-        let meta = error.meta();
-        let json = json!({
-            "message": meta.message(),
-            "code": meta.code(),
-            "extras": meta.to_string()
-        });
-        Err(anyhow::anyhow!(json))
+        Err(get_sdk_error(error))
     } else {
         Ok(result?)
     }
 }
 async fn delete_scaling_policy_from_table(
     shared_config: &SdkConfig,
-    table_name: &str,
+    target: &DynamoDbTarget,
     scalable_dimension: ScalableDimension,
 ) -> Result<(), anyhow::Error> {
-    let client = ApplicationAutoScalingClient::new(shared_config);
+    let client = get_application_autoscaling_client(shared_config, target);
     let result = client
         .delete_scaling_policy()
-        .policy_name("$".to_owned() + table_name + "-scaling-policy")
+        .policy_name(target.policy_name())
         .set_scalable_dimension(Some(scalable_dimension))
         .set_service_namespace(Some(ServiceNamespace::Dynamodb))
-        .set_resource_id(Some("table/".to_owned() + table_name));
+        .set_resource_id(Some(target.resource_id()));
     let result = result.send().await;
     if let Err(error) = result {
-        let meta = error.meta();
-        let json = json!({
-            "message": meta.message(),
-            "code": meta.code(),
-            "extras": meta.to_string()
-        });
-        return Err(anyhow::anyhow!(json));
+        return Err(get_sdk_error(error));
     }
     Ok(())
 }
 async fn describe_and_delete_scaling_policy(
     shared_config: &SdkConfig,
-    table_name: &str,
+    target: &DynamoDbTarget,
     scalable_dimension: ScalableDimension,
 ) -> Result<(), anyhow::Error> {
     if let Some(scaling_policies) =
-        describe_scaling_policies_from_table(shared_config, table_name, scalable_dimension.clone())
+        describe_scaling_policies_from_table(shared_config, target, scalable_dimension.clone())
             .await?
             .scaling_policies
     {
         if !scaling_policies.is_empty() {
-            delete_scaling_policy_from_table(shared_config, table_name, scalable_dimension).await?;
+            delete_scaling_policy_from_table(shared_config, target, scalable_dimension).await?;
         }
     }
     Ok(())
 }
 async fn put_scaling_policy_to_plan(
     shared_config: &SdkConfig,
-    table_name: &str,
+    target: &DynamoDbTarget,
     scalable_dimension: ScalableDimension,
     target_value: f64,
     predefined_metric_type: MetricType,
 ) -> Result<(), anyhow::Error> {
-    let client = ApplicationAutoScalingClient::new(shared_config);
+    let client = get_application_autoscaling_client(shared_config, target);
     let result = client
         .put_scaling_policy()
-        .policy_name("$".to_owned() + table_name + "-scaling-policy")
+        .policy_name(target.policy_name())
         .set_policy_type(Some(PolicyType::TargetTrackingScaling))
         .set_scalable_dimension(Some(scalable_dimension))
         .set_service_namespace(Some(ServiceNamespace::Dynamodb))
-        .set_resource_id(Some("table/".to_owned() + table_name))
+        .set_resource_id(Some(target.resource_id()))
         .set_target_tracking_scaling_policy_configuration(Some(
             TargetTrackingScalingPolicyConfiguration::builder()
                 .set_target_value(Some(target_value))
@@ -393,40 +562,28 @@ async fn put_scaling_policy_to_plan(
         ));
     let result = result.send().await;
     if let Err(error) = result {
-        let meta = error.meta();
-        let json = json!({
-            "message": meta.message(),
-            "code": meta.code(),
-            "extras": meta.to_string()
-        });
-        return Err(anyhow::anyhow!(json));
+        return Err(get_sdk_error(error));
     }
     Ok(())
 }
 async fn register_scalable_target_to_table(
     shared_config: &SdkConfig,
-    table_name: &str,
+    target: &DynamoDbTarget,
     min_capacity: i32,
     max_capacity: i32,
     scalable_dimension: ScalableDimension,
 ) -> Result<(), anyhow::Error> {
-    let client = ApplicationAutoScalingClient::new(shared_config);
+    let client = get_application_autoscaling_client(shared_config, target);
     let result = client
         .register_scalable_target()
         .set_service_namespace(Some(ServiceNamespace::Dynamodb))
-        .set_resource_id(Some("table/".to_owned() + table_name))
+        .set_resource_id(Some(target.resource_id()))
         .set_min_capacity(Some(min_capacity))
         .set_max_capacity(Some(max_capacity))
         .set_scalable_dimension(Some(scalable_dimension));
     let result = result.send().await;
     if let Err(error) = result {
-        let meta = error.meta();
-        let json = json!({
-            "message": meta.message(),
-            "code": meta.code(),
-            "extras": meta.to_string()
-        });
-        return Err(anyhow::anyhow!(json));
+        return Err(get_sdk_error(error));
     }
     Ok(())
 }
@@ -443,7 +600,6 @@ impl ScalingComponent for DynamoDbTableScalingComponent {
         let metadata: HashMap<String, serde_json::Value> = self.definition.metadata.clone();
 
         if let (
-            Some(serde_json::Value::String(region)),
             Some(serde_json::Value::String(table_name)),
             capacity_mode,
             autoscaling_mode,
@@ -457,7 +613,6 @@ impl ScalingComponent for DynamoDbTableScalingComponent {
             write_min_capacity,
             write_max_capacity,
         ) = (
-            metadata.get("region"),
             metadata.get("table_name"),
             params
                 .get("capacity_mode")
@@ -500,47 +655,74 @@ impl ScalingComponent for DynamoDbTableScalingComponent {
                 .and_then(serde_json::Value::as_i64)
                 .map(|v| v as i32),
         ) {
-            let access_key = metadata
-                .get("access_key")
-                .map(|access_key| access_key.to_string());
-            let secret_key = metadata
-                .get("secret_key")
-                .map(|secret_key| secret_key.to_string());
-
-            let config =
-                get_aws_config(Some(region.to_string()), access_key, secret_key, None, None).await;
+            let config = get_aws_config_with_metadata(&metadata).await;
             if config.is_err() {
                 let config_err = config.err().unwrap();
                 return Err(anyhow::anyhow!(config_err));
             }
             let shared_config = config.unwrap();
 
+            let target = DynamoDbTarget {
+                table_name: table_name.clone(),
+                index_name: metadata
+                    .get("index_name")
+                    .and_then(serde_json::Value::as_str)
+                    .filter(|index_name| !index_name.is_empty())
+                    .map(|index_name| index_name.to_string()),
+                replica_region: metadata
+                    .get("replica_region")
+                    .and_then(serde_json::Value::as_str)
+                    .filter(|replica_region| !replica_region.is_empty())
+                    .map(|replica_region| replica_region.to_string()),
+            };
+            target.validate_capacity_units(write_capacity_units)?;
+
             let handle_dynamodb_scaling_state =
                 handle_dynamodb_scaling_state(capacity_mode, autoscaling_mode, capacity_unit);
+
+            // Validate the daily decrease limits before updating the capacity units
+            let is_provisioned = !matches!(
+                handle_dynamodb_scaling_state,
+                None | Some(DynamoDbScalingState::OnDemand)
+            );
+            if is_provisioned && (read_capacity_units.is_some() || write_capacity_units.is_some()) {
+                if let Some(current) = get_current_throughput(&shared_config, &target).await? {
+                    let now_secs = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs() as i64;
+                    validate_throughput_decrease(
+                        &current,
+                        read_capacity_units,
+                        write_capacity_units,
+                        now_secs,
+                    )?;
+                }
+            }
             match handle_dynamodb_scaling_state {
                 Some(DynamoDbScalingState::OnDemand) => {
-                    update_table_to_on_demand_mode(&shared_config, table_name).await?;
+                    update_table_to_on_demand_mode(&shared_config, &target).await?;
                 }
                 Some(DynamoDbScalingState::ProvisionedOnRead) => {
                     update_table_to_provisioned_mode(
                         &shared_config,
-                        table_name,
+                        &target,
                         read_capacity_units,
                         None,
                     )
                     .await?;
                     register_scalable_target_to_table(
                         &shared_config,
-                        table_name,
+                        &target,
                         read_min_capacity.unwrap(),
                         read_max_capacity.unwrap(),
-                        DynamoDbTableReadCapacityUnits,
+                        target.read_dimension(),
                     )
                     .await?;
                     put_scaling_policy_to_plan(
                         &shared_config,
-                        table_name,
-                        DynamoDbTableReadCapacityUnits,
+                        &target,
+                        target.read_dimension(),
                         read_target_value.unwrap(),
                         DynamoDbReadCapacityUtilization,
                     )
@@ -549,23 +731,23 @@ impl ScalingComponent for DynamoDbTableScalingComponent {
                 Some(DynamoDbScalingState::ProvisionedOnWrite) => {
                     update_table_to_provisioned_mode(
                         &shared_config,
-                        table_name,
+                        &target,
                         None,
                         write_capacity_units,
                     )
                     .await?;
                     register_scalable_target_to_table(
                         &shared_config,
-                        table_name,
+                        &target,
                         write_min_capacity.unwrap(),
                         write_max_capacity.unwrap(),
-                        DynamoDbTableWriteCapacityUnits,
+                        target.write_dimension(),
                     )
                     .await?;
                     put_scaling_policy_to_plan(
                         &shared_config,
-                        table_name,
-                        DynamoDbTableWriteCapacityUnits,
+                        &target,
+                        target.write_dimension(),
                         write_target_value.unwrap(),
                         DynamoDbWriteCapacityUtilization,
                     )
@@ -574,39 +756,39 @@ impl ScalingComponent for DynamoDbTableScalingComponent {
                 Some(DynamoDbScalingState::ProvisionedOnReadWrite) => {
                     update_table_to_provisioned_mode(
                         &shared_config,
-                        table_name,
+                        &target,
                         read_capacity_units,
                         write_capacity_units,
                     )
                     .await?;
                     register_scalable_target_to_table(
                         &shared_config,
-                        table_name,
+                        &target,
                         read_min_capacity.unwrap(),
                         read_max_capacity.unwrap(),
-                        DynamoDbTableReadCapacityUnits,
+                        target.read_dimension(),
                     )
                     .await?;
                     put_scaling_policy_to_plan(
                         &shared_config,
-                        table_name,
-                        DynamoDbTableReadCapacityUnits,
+                        &target,
+                        target.read_dimension(),
                         read_target_value.unwrap(),
                         DynamoDbReadCapacityUtilization,
                     )
                     .await?;
                     register_scalable_target_to_table(
                         &shared_config,
-                        table_name,
+                        &target,
                         write_min_capacity.unwrap(),
                         write_max_capacity.unwrap(),
-                        DynamoDbTableWriteCapacityUnits,
+                        target.write_dimension(),
                     )
                     .await?;
                     put_scaling_policy_to_plan(
                         &shared_config,
-                        table_name,
-                        DynamoDbTableWriteCapacityUnits,
+                        &target,
+                        target.write_dimension(),
                         write_target_value.unwrap(),
                         DynamoDbWriteCapacityUtilization,
                     )
@@ -615,13 +797,13 @@ impl ScalingComponent for DynamoDbTableScalingComponent {
                 Some(DynamoDbScalingState::ProvisionedOffRead) => {
                     describe_and_delete_scaling_policy(
                         &shared_config,
-                        table_name,
-                        DynamoDbTableReadCapacityUnits,
+                        &target,
+                        target.read_dimension(),
                     )
                     .await?;
                     update_recent_table_to_provisioned_mode(
                         &shared_config,
-                        table_name,
+                        &target,
                         read_capacity_units,
                         None,
                     )
@@ -630,13 +812,13 @@ impl ScalingComponent for DynamoDbTableScalingComponent {
                 Some(DynamoDbScalingState::ProvisionedOffWrite) => {
                     describe_and_delete_scaling_policy(
                         &shared_config,
-                        table_name,
-                        DynamoDbTableWriteCapacityUnits,
+                        &target,
+                        target.write_dimension(),
                     )
                     .await?;
                     update_recent_table_to_provisioned_mode(
                         &shared_config,
-                        table_name,
+                        &target,
                         None,
                         write_capacity_units,
                     )
@@ -645,19 +827,19 @@ impl ScalingComponent for DynamoDbTableScalingComponent {
                 Some(DynamoDbScalingState::ProvisionedOffReadWrite) => {
                     describe_and_delete_scaling_policy(
                         &shared_config,
-                        table_name,
-                        DynamoDbTableReadCapacityUnits,
+                        &target,
+                        target.read_dimension(),
                     )
                     .await?;
                     describe_and_delete_scaling_policy(
                         &shared_config,
-                        table_name,
-                        DynamoDbTableWriteCapacityUnits,
+                        &target,
+                        target.write_dimension(),
                     )
                     .await?;
                     update_table_to_provisioned_mode(
                         &shared_config,
-                        table_name,
+                        &target,
                         read_capacity_units,
                         write_capacity_units,
                     )
@@ -676,7 +858,10 @@ impl ScalingComponent for DynamoDbTableScalingComponent {
 
 #[cfg(test)]
 mod test {
-    use super::DynamoDbTableScalingComponent;
+    use super::{
+        validate_throughput_decrease, DynamoDbTableScalingComponent, DynamoDbTarget,
+        ProvisionedThroughputDescription,
+    };
     use crate::scaling_component::ScalingComponent;
     use data_layer::ScalingComponentDefinition;
     use std::collections::HashMap;
@@ -1034,4 +1219,70 @@ mod test {
                 .await;
         assert!(dynamodb_table_scaling_component.is_err());
     }
+
+    #[test]
+    fn test_dynamodb_target() {
+        let target = DynamoDbTarget {
+            table_name: String::from("table"),
+            index_name: Some(String::from("index")),
+            replica_region: None,
+        };
+        assert!(!target.is_table());
+        assert_eq!(target.resource_id(), "table/table/index/index");
+        assert_eq!(
+            target.read_dimension().as_str(),
+            "dynamodb:index:ReadCapacityUnits"
+        );
+        assert!(target.validate_capacity_units(Some(10)).is_ok());
+
+        // Only the read capacity can be set for a replica
+        let target = DynamoDbTarget {
+            table_name: String::from("table"),
+            index_name: None,
+            replica_region: Some(String::from("us-west-2")),
+        };
+        assert!(target.validate_capacity_units(None).is_ok());
+        assert!(target.validate_capacity_units(Some(10)).is_err());
+    }
+
+    #[test]
+    fn test_validate_throughput_decrease() {
+        let now_secs = 1_700_000_000;
+        let current = |number_of_decreases_today: i64, last_decrease_secs: i64| {
+            ProvisionedThroughputDescription::builder()
+                .read_capacity_units(100)
+                .write_capacity_units(100)
+                .number_of_decreases_today(number_of_decreases_today)
+                .last_decrease_date_time(aws_smithy_types::DateTime::from_secs(last_decrease_secs))
+                .build()
+        };
+        // Increase
+        assert!(
+            validate_throughput_decrease(&current(27, now_secs), Some(200), None, now_secs).is_ok()
+        );
+        // 4 decreases any time
+        assert!(
+            validate_throughput_decrease(&current(3, now_secs), Some(50), None, now_secs).is_ok()
+        );
+        // 1 decrease per hour after 4 decreases
+        assert!(
+            validate_throughput_decrease(&current(4, now_secs - 60), None, Some(50), now_secs)
+                .is_err()
+        );
+        assert!(validate_throughput_decrease(
+            &current(4, now_secs - 3600),
+            None,
+            Some(50),
+            now_secs
+        )
+        .is_ok());
+        // Up to 27 decreases per day
+        assert!(validate_throughput_decrease(
+            &current(27, now_secs - 7200),
+            Some(50),
+            None,
+            now_secs
+        )
+        .is_err());
+    }
 }
@@ -1,13 +1,38 @@
-use super::ScalingComponent;
-use crate::util::aws::get_aws_config;
+/**
+ * [Scaling Component] Amazon EMR on EC2 Scaling Component
+ *
+ * It requires the following metadata:
+ * - cluster_id: The id of the cluster
+ * - instance_group_id or instance_fleet_id: The instance group or the instance fleet to resize
+ * - region, access_key, secret_key (optional, see util/aws)
+ * It accepts the following parameters:
+ * - instance_count: The instance count of the instance group
+ * - on_demand_capacity, spot_capacity: The target capacity of the instance fleet (the omitted one is kept)
+ * - on_demand_timeout_duration_minutes, spot_timeout_duration_minutes: The resize timeout of the instance fleet
+ * - step_concurrency_level: The step concurrency level of the cluster
+ * - managed_scaling: Put the EMR Managed Scaling policy or remove it with { remove: true }
+ *   e.g. { unit_type: InstanceFleetUnits, minimum_capacity_units: 2, maximum_capacity_units: 20 }
+ * The managed scaling policy is removed before resizing the instance group or the instance fleet.
+ * The current state is available as
+ * - instance group: $running_instance_count, $requested_instance_count
+ * - instance fleet: $provisioned_on_demand_capacity, $provisioned_spot_capacity, $target_on_demand_capacity, $target_spot_capacity
+ */
+use super::{
+    evaluate_expression_with_current_state, get_expression_language, get_param_expression,
+    ScalingComponent,
+};
+use crate::util::aws::get_aws_config_with_metadata;
 use anyhow::{Ok, Result};
 use async_trait::async_trait;
 use aws_sdk_emr::types::{
-    InstanceCollectionType, InstanceFleetModifyConfig, InstanceFleetResizingSpecifications,
-    InstanceGroupModifyConfig, OnDemandResizingSpecification, SpotResizingSpecification,
+    ComputeLimits, ComputeLimitsUnitType, InstanceCollectionType, InstanceFleetModifyConfig,
+    InstanceFleetResizingSpecifications, InstanceGroupModifyConfig, ManagedScalingPolicy,
+    OnDemandResizingSpecification, SpotResizingSpecification,
 };
 use aws_sdk_emr::Client;
 use data_layer::ScalingComponentDefinition;
+use futures::StreamExt;
+use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
 use tracing::{debug, error};
@@ -24,6 +49,26 @@ impl EMREC2AutoScalingComponent {
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct ManagedScalingParam {
+    // Instances, InstanceFleetUnits or VCPU
+    #[serde(default)]
+    unit_type: Option<String>,
+    #[serde(default)]
+    minimum_capacity_units: Option<i32>,
+    #[serde(default)]
+    maximum_capacity_units: Option<i32>,
+    #[serde(default)]
+    maximum_on_demand_capacity_units: Option<i32>,
+    #[serde(default)]
+    maximum_core_capacity_units: Option<i32>,
+    #[serde(default)]
+    remove: bool,
+}
+
+// The params to resize the instance group or the instance fleet
+const RESIZE_PARAMS: [&str; 3] = ["instance_count", "on_demand_capacity", "spot_capacity"];
+
 #[async_trait]
 impl ScalingComponent for EMREC2AutoScalingComponent {
     fn get_scaling_component_kind(&self) -> &str {
@@ -35,53 +80,88 @@ impl ScalingComponent for EMREC2AutoScalingComponent {
     async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
         let metadata = self.definition.metadata.clone();
 
-        if let (
-            Some(Value::String(region)),
-            Some(Value::String(cluster_id)),
-            Some(Value::String(instance_group_id)),
-            instance_count,
-            on_demand_capacity,
-            spot_capacity,
-            step_concurrency_level, // step concurrency level: min 1 / max 256
-            on_demand_timeout_duration_minutes, // timeout duration: min 5 / max 10,080 (7days)
-            spot_timeout_duration_minutes, // timeout duration: min 5 / max 10,080 (7days)
-        ) = (
-            metadata.get("region"),
-            metadata.get("cluster_id"),
-            metadata.get("instance_group_id"),
-            params.get("instance_count").and_then(Value::as_u64),
-            params.get("on_demand_capacity").and_then(Value::as_u64),
-            params.get("spot_capacity").and_then(Value::as_u64),
-            params.get("step_concurrency_level").and_then(Value::as_u64),
-            params
-                .get("on_demand_timeout_duration_minutes")
-                .and_then(Value::as_u64),
-            params
-                .get("spot_timeout_duration_minutes")
-                .and_then(Value::as_u64),
-        ) {
-            let access_key = metadata
-                .get("access_key")
-                .map(|access_key| access_key.to_string());
-            let secret_key = metadata
-                .get("secret_key")
-                .map(|secret_key| secret_key.to_string());
-
-            let config =
-                get_aws_config(Some(region.to_string()), access_key, secret_key, None, None).await;
-            if config.is_err() {
-                let config_err = config.err().unwrap();
-                error!("EMR - EC2 :: get_aws_config: {:?}", config_err);
-                return Err(anyhow::anyhow!(config_err));
+        let Some(Value::String(cluster_id)) = metadata.get("cluster_id") else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+        // The instance fleet id or the instance group id
+        let instance_id = metadata
+            .get("instance_fleet_id")
+            .or(metadata.get("instance_group_id"))
+            .and_then(Value::as_str)
+            .map(|instance_id| instance_id.to_string());
+        let step_concurrency_level = params.get("step_concurrency_level").and_then(Value::as_u64); // step concurrency level: min 1 / max 256
+        let on_demand_timeout_duration_minutes = params
+            .get("on_demand_timeout_duration_minutes")
+            .and_then(Value::as_u64); // timeout duration: min 5 / max 10,080 (7days)
+        let spot_timeout_duration_minutes = params
+            .get("spot_timeout_duration_minutes")
+            .and_then(Value::as_u64); // timeout duration: min 5 / max 10,080 (7days)
+        let managed_scaling = match params.get("managed_scaling") {
+            Some(value) => Some(get_managed_scaling_param(value)?),
+            None => None,
+        };
+        let resize_expressions = RESIZE_PARAMS
+            .iter()
+            .filter_map(|key| {
+                params
+                    .get(*key)
+                    .and_then(get_param_expression)
+                    .map(|expression| (*key, expression))
+            })
+            .collect::<HashMap<&str, String>>();
+        if resize_expressions.is_empty()
+            && managed_scaling.is_none()
+            && step_concurrency_level.is_none()
+        {
+            return Err(anyhow::anyhow!("Invalid params"));
+        }
+        if !resize_expressions.is_empty() {
+            if instance_id.is_none() {
+                return Err(anyhow::anyhow!(
+                    "Invalid metadata - instance_group_id or instance_fleet_id is required to resize"
+                ));
             }
-            let config = config.unwrap();
-            let client = Client::new(&config);
+            // EMR Managed Scaling resizes the cluster by itself
+            if managed_scaling
+                .as_ref()
+                .map_or(false, |managed_scaling| !managed_scaling.remove)
+            {
+                return Err(anyhow::anyhow!(
+                    "Invalid params - managed_scaling can't be put with {}",
+                    RESIZE_PARAMS.join(", ")
+                ));
+            }
+        }
 
+        let config = get_aws_config_with_metadata(&metadata).await;
+        if config.is_err() {
+            let config_err = config.err().unwrap();
+            error!(
+                "EMR - EC2 :: get_aws_config_with_metadata: {:?}",
+                config_err
+            );
+            return Err(anyhow::anyhow!(config_err));
+        }
+        let config = config.unwrap();
+        let client = Client::new(&config);
+
+        // 0. managed scaling put or remove (optional)
+        if let Some(managed_scaling) = managed_scaling.as_ref() {
+            if managed_scaling.remove {
+                managed_scaling_check_and_remove(client.clone(), cluster_id).await?;
+            } else {
+                put_managed_scaling_policy(client.clone(), cluster_id, managed_scaling).await?;
+            }
+        }
+
+        if let (Some(instance_id), false) = (instance_id.as_ref(), resize_expressions.is_empty()) {
             // 1. managed scaling check & remove
-            let managed_scaling_check_and_remove =
-                managed_scaling_check_and_remove(client.clone(), cluster_id).await;
-            if managed_scaling_check_and_remove.is_err() {
-                return Err(managed_scaling_check_and_remove.err().unwrap());
+            if managed_scaling.is_none() {
+                let managed_scaling_check_and_remove =
+                    managed_scaling_check_and_remove(client.clone(), cluster_id).await;
+                if managed_scaling_check_and_remove.is_err() {
+                    return Err(managed_scaling_check_and_remove.err().unwrap());
+                }
             }
 
             // 2. instance collection type check
@@ -90,17 +170,40 @@ impl ScalingComponent for EMREC2AutoScalingComponent {
             if instance_collection_type.is_err() {
                 return Err(instance_collection_type.err().unwrap());
             }
+            let instance_collection_type = instance_collection_type.unwrap();
 
-            match instance_collection_type.unwrap() {
+            // evaluate the params with the current state
+            let current_state_map = get_current_state_map(
+                client.clone(),
+                cluster_id,
+                instance_id,
+                &instance_collection_type,
+            )
+            .await?;
+            let mut evaluated: HashMap<&str, i64> = HashMap::new();
+            for (key, expression) in resize_expressions.iter() {
+                let value = evaluate_expression_with_current_state(
+                    expression,
+                    current_state_map.clone(),
+                    get_expression_language(&params),
+                )
+                .await;
+                if value.is_err() {
+                    return Err(value.unwrap_err());
+                }
+                evaluated.insert(key, value.unwrap());
+            }
+
+            match instance_collection_type {
                 // 2.1 update instance fleet
                 InstanceCollectionType::InstanceFleet => {
                     debug!("EMR - EC2 :: InstanceCollectionType::InstanceFleet");
                     let update_instance_fleet = update_instance_fleet(
                         on_demand_timeout_duration_minutes,
                         spot_timeout_duration_minutes,
-                        on_demand_capacity,
-                        spot_capacity,
-                        instance_group_id,
+                        evaluated.get("on_demand_capacity").copied(),
+                        evaluated.get("spot_capacity").copied(),
+                        instance_id,
                         cluster_id,
                         client.clone(),
                     )
@@ -113,8 +216,8 @@ impl ScalingComponent for EMREC2AutoScalingComponent {
                 InstanceCollectionType::InstanceGroup => {
                     debug!("EMR - EC2 :: InstanceCollectionType::InstanceGroup");
                     let update_instance_group = update_instance_group(
-                        instance_count,
-                        instance_group_id,
+                        evaluated.get("instance_count").copied(),
+                        instance_id,
                         cluster_id,
                         client.clone(),
                     )
@@ -125,24 +228,211 @@ impl ScalingComponent for EMREC2AutoScalingComponent {
                 }
                 _ => {}
             }
+        }
 
-            // 3. update cluster step concurrency level (optional)
-            if step_concurrency_level.is_some() {
-                let update_cluster_step_concurrency_level = update_cluster_step_concurrency_level(
-                    cluster_id,
-                    step_concurrency_level,
-                    client,
-                )
-                .await;
-                if update_cluster_step_concurrency_level.is_err() {
-                    return Err(update_cluster_step_concurrency_level.err().unwrap());
+        // 3. update cluster step concurrency level (optional)
+        if step_concurrency_level.is_some() {
+            let update_cluster_step_concurrency_level =
+                update_cluster_step_concurrency_level(cluster_id, step_concurrency_level, client)
+                    .await;
+            if update_cluster_step_concurrency_level.is_err() {
+                return Err(update_cluster_step_concurrency_level.err().unwrap());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn get_emr_error(message: &str, err_raw_response: String) -> anyhow::Error {
+    error!("EMR - EC2 :: {} - {:?}", message, err_raw_response);
+    anyhow::anyhow!(serde_json::json!({
+        "message": format!("EMR - EC2 :: {}", message),
+        "code": "500",
+        "extras": err_raw_response,
+    }))
+}
+
+fn get_managed_scaling_param(value: &Value) -> Result<ManagedScalingParam, anyhow::Error> {
+    let managed_scaling = serde_json::from_value::<ManagedScalingParam>(value.clone());
+    if let Err(e) = managed_scaling {
+        return Err(anyhow::anyhow!("Invalid params - managed_scaling: {}", e));
+    }
+    let managed_scaling = managed_scaling.unwrap();
+    if managed_scaling.remove {
+        return Ok(managed_scaling);
+    }
+    let (Some(unit_type), Some(minimum_capacity_units), Some(maximum_capacity_units)) = (
+        managed_scaling.unit_type.as_deref(),
+        managed_scaling.minimum_capacity_units,
+        managed_scaling.maximum_capacity_units,
+    ) else {
+        return Err(anyhow::anyhow!(
+            "Invalid params - managed_scaling needs unit_type, minimum_capacity_units and maximum_capacity_units"
+        ));
+    };
+    if !["Instances", "InstanceFleetUnits", "VCPU"].contains(&unit_type) {
+        return Err(anyhow::anyhow!(
+            "Invalid params - managed_scaling.unit_type should be Instances, InstanceFleetUnits or VCPU"
+        ));
+    }
+    if minimum_capacity_units < 1 || minimum_capacity_units > maximum_capacity_units {
+        return Err(anyhow::anyhow!(
+            "Invalid params - managed_scaling.minimum_capacity_units({}) should be between 1 and maximum_capacity_units({})",
+            minimum_capacity_units,
+            maximum_capacity_units
+        ));
+    }
+    for limit in [
+        managed_scaling.maximum_on_demand_capacity_units,
+        managed_scaling.maximum_core_capacity_units,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if limit > maximum_capacity_units {
+            return Err(anyhow::anyhow!(
+                "Invalid params - managed_scaling limits should be less than or equal to maximum_capacity_units({})",
+                maximum_capacity_units
+            ));
+        }
+    }
+    Ok(managed_scaling)
+}
+
+async fn put_managed_scaling_policy(
+    client: aws_sdk_emr::Client,
+    cluster_id: &String,
+    managed_scaling: &ManagedScalingParam,
+) -> Result<()> {
+    let compute_limits = ComputeLimits::builder()
+        .set_unit_type(
+            managed_scaling
+                .unit_type
+                .as_deref()
+                .map(ComputeLimitsUnitType::from),
+        )
+        .set_minimum_capacity_units(managed_scaling.minimum_capacity_units)
+        .set_maximum_capacity_units(managed_scaling.maximum_capacity_units)
+        .set_maximum_on_demand_capacity_units(managed_scaling.maximum_on_demand_capacity_units)
+        .set_maximum_core_capacity_units(managed_scaling.maximum_core_capacity_units)
+        .build();
+    let result = client
+        .put_managed_scaling_policy()
+        .cluster_id(cluster_id)
+        .managed_scaling_policy(
+            ManagedScalingPolicy::builder()
+                .compute_limits(compute_limits)
+                .build(),
+        )
+        .send()
+        .await;
+    if result.is_err() {
+        let err = result.err().unwrap();
+        return Err(get_emr_error(
+            "put_managed_scaling_policy error",
+            format!("{:?}", err.raw_response()),
+        ));
+    }
+    Ok(())
+}
+
+async fn get_current_state_map(
+    client: aws_sdk_emr::Client,
+    cluster_id: &String,
+    instance_id: &String,
+    instance_collection_type: &InstanceCollectionType,
+) -> Result<HashMap<String, i64>, anyhow::Error> {
+    let mut current_state_map: HashMap<String, i64> = HashMap::new();
+    match instance_collection_type {
+        InstanceCollectionType::InstanceFleet => {
+            // The instance fleets are paginated by the marker
+            let mut fleets = client
+                .list_instance_fleets()
+                .cluster_id(cluster_id)
+                .into_paginator()
+                .items()
+                .send();
+            let mut instance_fleet = None;
+            while let Some(fleet) = fleets.next().await {
+                if fleet.is_err() {
+                    let err = fleet.err().unwrap();
+                    return Err(get_emr_error(
+                        "list_instance_fleets error",
+                        format!("{:?}", err.raw_response()),
+                    ));
                 }
+                let fleet = fleet.unwrap();
+                if fleet.id() == Some(instance_id.as_str()) {
+                    instance_fleet = Some(fleet);
+                    break;
+                }
+            }
+            let Some(fleet) = instance_fleet else {
+                return Err(get_emr_error(
+                    "not found instance fleet",
+                    instance_id.to_string(),
+                ));
+            };
+            for (key, value) in [
+                (
+                    "$provisioned_on_demand_capacity",
+                    fleet.provisioned_on_demand_capacity(),
+                ),
+                (
+                    "$provisioned_spot_capacity",
+                    fleet.provisioned_spot_capacity(),
+                ),
+                (
+                    "$target_on_demand_capacity",
+                    fleet.target_on_demand_capacity(),
+                ),
+                ("$target_spot_capacity", fleet.target_spot_capacity()),
+            ] {
+                current_state_map.insert(key.to_string(), value.unwrap_or(0) as i64);
             }
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Invalid metadata"))
         }
+        InstanceCollectionType::InstanceGroup => {
+            // The instance groups are paginated by the marker
+            let mut groups = client
+                .list_instance_groups()
+                .cluster_id(cluster_id)
+                .into_paginator()
+                .items()
+                .send();
+            let mut instance_group = None;
+            while let Some(group) = groups.next().await {
+                if group.is_err() {
+                    let err = group.err().unwrap();
+                    return Err(get_emr_error(
+                        "list_instance_groups error",
+                        format!("{:?}", err.raw_response()),
+                    ));
+                }
+                let group = group.unwrap();
+                if group.id() == Some(instance_id.as_str()) {
+                    instance_group = Some(group);
+                    break;
+                }
+            }
+            let Some(group) = instance_group else {
+                return Err(get_emr_error(
+                    "not found instance group",
+                    instance_id.to_string(),
+                ));
+            };
+            for (key, value) in [
+                ("$running_instance_count", group.running_instance_count()),
+                (
+                    "$requested_instance_count",
+                    group.requested_instance_count(),
+                ),
+            ] {
+                current_state_map.insert(key.to_string(), value.unwrap_or(0) as i64);
+            }
+        }
+        _ => {}
     }
+    Ok(current_state_map)
 }
 
 async fn managed_scaling_check_and_remove(
@@ -240,8 +530,8 @@ async fn get_instance_collection_type(
 async fn update_instance_fleet(
     on_demand_timeout_duration_minutes: Option<u64>,
     spot_timeout_duration_minutes: Option<u64>,
-    on_demand_capacity: Option<u64>,
-    spot_capacity: Option<u64>,
+    on_demand_capacity: Option<i64>,
+    spot_capacity: Option<i64>,
     instance_group_id: &String,
     cluster_id: &String,
     client: aws_sdk_emr::Client,
@@ -263,13 +553,14 @@ async fn update_instance_fleet(
     if spot_spec.is_some() {
         resize_spec = resize_spec.set_spot_resize_specification(spot_spec);
     }
-    let option_on_demand_capacity = on_demand_capacity.map_or(0, |v| v as i32);
-    let option_spot_capacity = spot_capacity.map_or(0, |v| v as i32);
+    // The omitted capacity is kept
+    let option_on_demand_capacity = on_demand_capacity.map(|v| v as i32);
+    let option_spot_capacity = spot_capacity.map(|v| v as i32);
     let modify_config = InstanceFleetModifyConfig::builder()
         .instance_fleet_id(instance_group_id)
         .resize_specifications(resize_spec.build())
-        .target_on_demand_capacity(option_on_demand_capacity)
-        .target_spot_capacity(option_spot_capacity)
+        .set_target_on_demand_capacity(option_on_demand_capacity)
+        .set_target_spot_capacity(option_spot_capacity)
         .build();
     let modify_instance_fleet = client
         .modify_instance_fleet()
@@ -294,7 +585,7 @@ async fn update_instance_fleet(
 }
 
 async fn update_instance_group(
-    instance_count: Option<u64>,
+    instance_count: Option<i64>,
     instance_group_id: &String,
     cluster_id: &String,
     client: aws_sdk_emr::Client,
@@ -357,8 +648,100 @@ async fn update_cluster_step_concurrency_level(
 mod tests {
     use super::*;
     use crate::util::aws::get_aws_config;
+    use crate::util::mock_server::start_mock_server;
     use aws_config::SdkConfig;
 
+    #[tokio::test]
+    async fn test_get_current_state_map_with_pages() {
+        let (endpoint_url, handle) = start_mock_server(vec![
+            (
+                200,
+                r#"{ "InstanceGroups": [{ "Id": "ig-1", "RunningInstanceCount": 1 }], "Marker": "page-2" }"#,
+            ),
+            (
+                200,
+                r#"{ "InstanceGroups": [{ "Id": "ig-2", "RunningInstanceCount": 3, "RequestedInstanceCount": 4 }] }"#,
+            ),
+        ])
+        .await;
+        let metadata = HashMap::from([
+            ("region".to_string(), serde_json::json!("us-east-1")),
+            ("access_key".to_string(), serde_json::json!("access_key")),
+            ("secret_key".to_string(), serde_json::json!("secret_key")),
+            ("endpoint_url".to_string(), serde_json::json!(endpoint_url)),
+        ]);
+        let shared_config = get_aws_config_with_metadata(&metadata).await.unwrap();
+        let current_state_map = get_current_state_map(
+            Client::new(&shared_config),
+            &"j-cluster".to_string(),
+            &"ig-2".to_string(),
+            &InstanceCollectionType::InstanceGroup,
+        )
+        .await
+        .unwrap();
+        assert_eq!(current_state_map["$running_instance_count"], 3);
+        assert_eq!(current_state_map["$requested_instance_count"], 4);
+        let requests = handle.await.unwrap();
+        // The second page is requested with the marker
+        assert!(requests[1].contains(r#""Marker":"page-2""#));
+    }
+
+    #[test]
+    fn test_get_managed_scaling_param() {
+        let managed_scaling = get_managed_scaling_param(&serde_json::json!({
+            "unit_type": "InstanceFleetUnits",
+            "minimum_capacity_units": 2,
+            "maximum_capacity_units": 20,
+            "maximum_on_demand_capacity_units": 10
+        }));
+        assert!(managed_scaling.is_ok());
+        let managed_scaling = get_managed_scaling_param(&serde_json::json!({ "remove": true }));
+        assert!(managed_scaling.unwrap().remove);
+        // err: missing maximum_capacity_units
+        assert!(get_managed_scaling_param(&serde_json::json!({
+            "unit_type": "Instances",
+            "minimum_capacity_units": 2
+        }))
+        .is_err());
+        // err: invalid unit_type
+        assert!(get_managed_scaling_param(&serde_json::json!({
+            "unit_type": "Memory",
+            "minimum_capacity_units": 2,
+            "maximum_capacity_units": 20
+        }))
+        .is_err());
+        // err: minimum is greater than maximum
+        assert!(get_managed_scaling_param(&serde_json::json!({
+            "unit_type": "VCPU",
+            "minimum_capacity_units": 30,
+            "maximum_capacity_units": 20
+        }))
+        .is_err());
+        // err: the core limit is greater than maximum
+        assert!(get_managed_scaling_param(&serde_json::json!({
+            "unit_type": "Instances",
+            "minimum_capacity_units": 2,
+            "maximum_capacity_units": 20,
+            "maximum_core_capacity_units": 21
+        }))
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_emr_apply_invalid_params() {
+        let component = EMREC2AutoScalingComponent::new(ScalingComponentDefinition {
+            id: String::from("scaling-id"),
+            component_kind: String::from(EMREC2AutoScalingComponent::SCALING_KIND),
+            metadata: HashMap::from([("cluster_id".to_string(), serde_json::json!("j-1"))]),
+            ..Default::default()
+        });
+        // err: no params
+        assert!(component.apply(HashMap::new()).await.is_err());
+        // err: no instance_group_id or instance_fleet_id to resize
+        let params = HashMap::from([("instance_count".to_string(), serde_json::json!(2))]);
+        assert!(component.apply(params).await.is_err());
+    }
+
     async fn get_test_aws_config() -> SdkConfig {
         get_aws_config(
             Some("ap-northeast-1".to_string()),
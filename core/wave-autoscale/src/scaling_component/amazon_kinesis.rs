@@ -0,0 +1,375 @@
+/**
+ * [Scaling Component] Amazon Kinesis Data Streams Scaling Component
+ *
+ * This component changes the shard count of a stream(UpdateShardCount with uniform scaling)
+ * or switches the capacity mode between on-demand and provisioned(UpdateStreamMode)
+ * It requires the following metadata:
+ * - stream_name: The name of the stream
+ * - region, access_key, secret_key (optional, see util/aws)
+ * It accepts the following parameters (at least one of them):
+ * - stream_mode: ON_DEMAND or PROVISIONED
+ * - shard_count: The target shard count (provisioned mode only)
+ * The current state is available as $open_shard_count
+ *
+ * When both are changed, the stream mode is switched first and the shard count is changed
+ * after the stream becomes ACTIVE again in the same apply.
+ *
+ * AWS limits the shard count changes
+ * - The target shard count must be between half and double of the current open shard count
+ * - UpdateShardCount can be called 10 times per rolling 24 hours per stream
+ * - UpdateStreamMode can be called 2 times per rolling 24 hours per stream
+ * So the target is clamped to the allowed range and LimitExceededException is returned over the call limits
+ *
+ * References
+ * https://docs.aws.amazon.com/kinesis/latest/APIReference/API_UpdateShardCount.html
+ * https://docs.aws.amazon.com/kinesis/latest/APIReference/API_UpdateStreamMode.html
+ */
+use super::{
+    evaluate_expression_with_current_state, get_expression_language, get_param_expression,
+    ScalingComponent,
+};
+use crate::util::aws::{get_aws_config_with_metadata, get_sdk_error};
+use anyhow::{Ok, Result};
+use async_trait::async_trait;
+use aws_sdk_kinesis::{
+    error::ProvideErrorMetadata,
+    types::{ScalingType, StreamDescriptionSummary, StreamMode, StreamModeDetails, StreamStatus},
+    Client as KinesisClient,
+};
+use data_layer::ScalingComponentDefinition;
+use serde_json::Value;
+use std::{collections::HashMap, time::Duration};
+use tracing::debug;
+
+const LIMIT_EXCEEDED_ERROR_CODE: &str = "LimitExceededException";
+// The stream is UPDATING after UpdateStreamMode until it becomes ACTIVE
+const STREAM_ACTIVE_TIMEOUT: Duration = Duration::from_secs(60);
+const STREAM_ACTIVE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct KinesisStreamScalingComponent {
+    definition: ScalingComponentDefinition,
+}
+
+impl KinesisStreamScalingComponent {
+    // Static variables
+    pub const SCALING_KIND: &'static str = "amazon-kinesis";
+
+    // Functions
+    pub fn new(definition: ScalingComponentDefinition) -> Self {
+        KinesisStreamScalingComponent { definition }
+    }
+}
+
+fn parse_stream_mode(stream_mode: &str) -> Option<StreamMode> {
+    match stream_mode.to_uppercase().as_str() {
+        "ON_DEMAND" => Some(StreamMode::OnDemand),
+        "PROVISIONED" => Some(StreamMode::Provisioned),
+        _ => None,
+    }
+}
+
+// UpdateShardCount only accepts a target between half and double of the current open shard count
+fn clamp_shard_count(current: i64, target: i64) -> i64 {
+    if current <= 0 {
+        return target.max(1);
+    }
+    let min = (current + 1) / 2;
+    let max = current * 2;
+    target.clamp(min, max)
+}
+
+// AWS counts the calls per stream in the rolling 24 hours
+fn get_update_error<E: ProvideErrorMetadata + std::fmt::Display>(
+    error: E,
+    operation: &str,
+    stream_name: &str,
+) -> anyhow::Error {
+    if error.code() == Some(LIMIT_EXCEEDED_ERROR_CODE) {
+        return anyhow::anyhow!(
+            "{} exceeded the limit of the stream {} (e.g. the calls per 24 hours) - {}",
+            operation,
+            stream_name,
+            error.message().unwrap_or_default()
+        );
+    }
+    get_sdk_error(error)
+}
+
+async fn describe_stream_summary(
+    client: &KinesisClient,
+    stream_name: &str,
+) -> Result<StreamDescriptionSummary> {
+    let summary = client
+        .describe_stream_summary()
+        .stream_name(stream_name)
+        .send()
+        .await;
+    if let Err(error) = summary {
+        return Err(get_sdk_error(error));
+    }
+    let summary = summary.unwrap();
+    let Some(summary) = summary.stream_description_summary() else {
+        return Err(anyhow::anyhow!(
+            "Failed to describe the stream {} - summary none",
+            stream_name
+        ));
+    };
+    Ok(summary.clone())
+}
+
+// None if the stream is not ACTIVE in the timeout
+async fn wait_for_active_stream(
+    client: &KinesisClient,
+    stream_name: &str,
+    timeout: Duration,
+) -> Result<Option<StreamDescriptionSummary>> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let summary = describe_stream_summary(client, stream_name).await?;
+        if summary.stream_status() == Some(&StreamStatus::Active) {
+            return Ok(Some(summary));
+        }
+        if tokio::time::Instant::now() + STREAM_ACTIVE_POLL_INTERVAL > deadline {
+            return Ok(None);
+        }
+        tokio::time::sleep(STREAM_ACTIVE_POLL_INTERVAL).await;
+    }
+}
+
+#[async_trait]
+impl ScalingComponent for KinesisStreamScalingComponent {
+    fn get_scaling_component_kind(&self) -> &str {
+        &self.definition.component_kind
+    }
+    fn get_id(&self) -> &str {
+        &self.definition.id
+    }
+    async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
+        let metadata = &self.definition.metadata;
+        let Some(Value::String(stream_name)) = metadata.get("stream_name") else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+        let stream_mode = params.get("stream_mode").and_then(Value::as_str);
+        let stream_mode = match stream_mode {
+            Some(stream_mode) => {
+                let Some(stream_mode) = parse_stream_mode(stream_mode) else {
+                    return Err(anyhow::anyhow!(
+                        "Invalid params - stream_mode should be ON_DEMAND or PROVISIONED"
+                    ));
+                };
+                Some(stream_mode)
+            }
+            None => None,
+        };
+        let shard_count_expression = params.get("shard_count").and_then(get_param_expression);
+        if stream_mode.is_none() && shard_count_expression.is_none() {
+            return Err(anyhow::anyhow!(
+                "Invalid params - stream_mode or shard_count is required"
+            ));
+        }
+        if stream_mode == Some(StreamMode::OnDemand) && shard_count_expression.is_some() {
+            return Err(anyhow::anyhow!(
+                "Invalid params - shard_count is not available in ON_DEMAND mode"
+            ));
+        }
+
+        let config = get_aws_config_with_metadata(metadata).await;
+        if config.is_err() {
+            let config_err = config.err().unwrap();
+            return Err(anyhow::anyhow!(config_err));
+        }
+        let config = config.unwrap();
+        let client = KinesisClient::new(&config);
+
+        let mut summary = describe_stream_summary(&client, stream_name).await?;
+        // The stream can't be updated while it is being created, updated or deleted
+        if summary.stream_status() != Some(&StreamStatus::Active) {
+            return Err(anyhow::anyhow!(
+                "The stream {} is not ACTIVE - {:?}",
+                stream_name,
+                summary.stream_status()
+            ));
+        }
+        let current_stream_mode = summary
+            .stream_mode_details()
+            .and_then(|details| details.stream_mode())
+            .cloned()
+            .unwrap_or(StreamMode::Provisioned);
+
+        // Switch the capacity mode
+        if let Some(stream_mode) = stream_mode {
+            if stream_mode != current_stream_mode {
+                let Some(stream_arn) = summary.stream_arn() else {
+                    return Err(anyhow::anyhow!(
+                        "Failed to describe the stream {} - stream_arn none",
+                        stream_name
+                    ));
+                };
+                let result = client
+                    .update_stream_mode()
+                    .stream_arn(stream_arn)
+                    .stream_mode_details(
+                        StreamModeDetails::builder()
+                            .stream_mode(stream_mode.clone())
+                            .build(),
+                    )
+                    .send()
+                    .await;
+                if let Err(error) = result {
+                    return Err(get_update_error(error, "UpdateStreamMode", stream_name));
+                }
+                if shard_count_expression.is_none() {
+                    return Ok(());
+                }
+
+                // The stream is UPDATING now, so wait for it to change the shard count
+                let active_summary =
+                    wait_for_active_stream(&client, stream_name, STREAM_ACTIVE_TIMEOUT).await?;
+                let Some(active_summary) = active_summary else {
+                    return Err(anyhow::anyhow!(
+                        "Partially applied - the stream mode of {} is changed, but the stream is not ACTIVE in {} seconds to change shard_count",
+                        stream_name,
+                        STREAM_ACTIVE_TIMEOUT.as_secs()
+                    ));
+                };
+                summary = active_summary;
+            }
+        }
+
+        // Change the shard count (uniform scaling)
+        let Some(shard_count_expression) = shard_count_expression else {
+            return Ok(());
+        };
+        if stream_mode.is_none() && current_stream_mode == StreamMode::OnDemand {
+            return Err(anyhow::anyhow!(
+                "The stream {} is in ON_DEMAND mode - set stream_mode to PROVISIONED to change shard_count",
+                stream_name
+            ));
+        }
+        let open_shard_count = summary.open_shard_count().unwrap_or(0) as i64;
+        let current_state_map =
+            HashMap::from([("$open_shard_count".to_string(), open_shard_count)]);
+        let shard_count = evaluate_expression_with_current_state(
+            &shard_count_expression,
+            current_state_map,
+            get_expression_language(&params),
+        )
+        .await;
+        if shard_count.is_err() {
+            return Err(shard_count.unwrap_err());
+        }
+        let shard_count = shard_count.unwrap();
+        let target_shard_count = clamp_shard_count(open_shard_count, shard_count);
+        if target_shard_count != shard_count {
+            debug!(
+                "[amazon-kinesis] The shard count of {} is clamped from {} to {} (current: {})",
+                stream_name, shard_count, target_shard_count, open_shard_count
+            );
+        }
+        if target_shard_count == open_shard_count {
+            return Ok(());
+        }
+
+        let result = client
+            .update_shard_count()
+            .stream_name(stream_name)
+            .target_shard_count(target_shard_count as i32)
+            .scaling_type(ScalingType::UniformScaling)
+            .send()
+            .await;
+        if let Err(error) = result {
+            return Err(get_update_error(error, "UpdateShardCount", stream_name));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::mock_server::start_mock_server;
+    use serde_json::json;
+
+    #[test]
+    fn test_clamp_shard_count() {
+        assert_eq!(clamp_shard_count(4, 10), 8);
+        assert_eq!(clamp_shard_count(4, 1), 2);
+        assert_eq!(clamp_shard_count(5, 1), 3);
+        assert_eq!(clamp_shard_count(4, 6), 6);
+        assert_eq!(clamp_shard_count(0, 3), 3);
+    }
+
+    fn get_definition(endpoint_url: &str) -> ScalingComponentDefinition {
+        ScalingComponentDefinition {
+            id: String::from("scaling-id"),
+            component_kind: String::from(KinesisStreamScalingComponent::SCALING_KIND),
+            metadata: HashMap::from([
+                ("region".to_string(), json!("us-east-1")),
+                ("access_key".to_string(), json!("access_key")),
+                ("secret_key".to_string(), json!("secret_key")),
+                ("endpoint_url".to_string(), json!(endpoint_url)),
+                ("stream_name".to_string(), json!("stream")),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_stream_mode_and_shard_count_with_mock() {
+        let (endpoint_url, handle) = start_mock_server(vec![
+            (
+                200,
+                r#"{ "StreamDescriptionSummary": {
+                    "StreamName": "stream",
+                    "StreamARN": "arn:aws:kinesis:us-east-1:123456789012:stream/stream",
+                    "StreamStatus": "ACTIVE",
+                    "StreamModeDetails": { "StreamMode": "ON_DEMAND" },
+                    "OpenShardCount": 4
+                } }"#,
+            ),
+            (200, "{}"),
+            (
+                200,
+                r#"{ "StreamDescriptionSummary": {
+                    "StreamName": "stream",
+                    "StreamARN": "arn:aws:kinesis:us-east-1:123456789012:stream/stream",
+                    "StreamStatus": "ACTIVE",
+                    "StreamModeDetails": { "StreamMode": "PROVISIONED" },
+                    "OpenShardCount": 4
+                } }"#,
+            ),
+            (
+                200,
+                r#"{ "StreamName": "stream", "CurrentShardCount": 4, "TargetShardCount": 6 }"#,
+            ),
+        ])
+        .await;
+        let component = KinesisStreamScalingComponent::new(get_definition(&endpoint_url));
+        let params = HashMap::from([
+            ("stream_mode".to_string(), json!("PROVISIONED")),
+            ("shard_count".to_string(), json!("$open_shard_count + 2")),
+        ]);
+        assert!(component.apply(params).await.is_ok());
+        // Both of the stream mode and the shard count are changed in one apply
+        let requests = handle.await.unwrap();
+        assert!(requests[1].contains(r#""StreamMode":"PROVISIONED""#));
+        assert!(requests[3].contains(r#""TargetShardCount":6"#));
+    }
+
+    #[tokio::test]
+    async fn apply_invalid_params() {
+        let definition = ScalingComponentDefinition {
+            id: String::from("scaling-id"),
+            component_kind: String::from(KinesisStreamScalingComponent::SCALING_KIND),
+            metadata: HashMap::from([("stream_name".to_string(), json!("stream"))]),
+            ..Default::default()
+        };
+        let component = KinesisStreamScalingComponent::new(definition);
+        assert!(component.apply(HashMap::new()).await.is_err());
+        let params = HashMap::from([
+            ("stream_mode".to_string(), json!("ON_DEMAND")),
+            ("shard_count".to_string(), json!(4)),
+        ]);
+        assert!(component.apply(params).await.is_err());
+    }
+}
@@ -0,0 +1,576 @@
+/**
+ * [Scaling Component] AWS Application Auto Scaling Scaling Component
+ *
+ * This component covers every scalable target of Application Auto Scaling in one place
+ * e.g. Aurora replicas, SageMaker variants, ElastiCache, Keyspaces, MSK, Comprehend and custom resources
+ * It requires the following metadata:
+ * - service_namespace: e.g. rds, sagemaker, elasticache, cassandra, kafka, comprehend, custom-resource
+ * - resource_id: e.g. cluster:my-db-cluster, endpoint/my-endpoint/variant/AllTraffic
+ * - scalable_dimension: e.g. rds:cluster:ReadReplicaCount
+ * - region, access_key, secret_key (optional, see util/aws)
+ * It accepts the following parameters:
+ * - min_capacity, max_capacity: The capacity of the scalable target
+ * - target_tracking_policies: Target tracking scaling policies to put or delete
+ *   with predefined_metric_type or customized_metric (e.g. custom-resource and kafka have no predefined metric for some dimensions)
+ * - scheduled_actions: Scheduled actions to put or delete
+ * The current state is available as $min_capacity and $max_capacity
+ *
+ * References
+ * https://docs.aws.amazon.com/autoscaling/application/userguide/what-is-application-auto-scaling.html
+ */
+use super::{
+    evaluate_expression_with_current_state, get_expression_language, get_param_expression,
+    ScalingComponent,
+};
+use crate::util::aws::{get_aws_config_with_metadata, get_sdk_error};
+use anyhow::{Ok, Result};
+use async_trait::async_trait;
+use aws_sdk_applicationautoscaling::{
+    types::{
+        CustomizedMetricSpecification, MetricDimension, MetricStatistic, MetricType, PolicyType,
+        PredefinedMetricSpecification, ScalableDimension, ScalableTargetAction, ServiceNamespace,
+        TargetTrackingScalingPolicyConfiguration,
+    },
+    Client as ApplicationAutoScalingClient,
+};
+use data_layer::ScalingComponentDefinition;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub struct ApplicationAutoScalingComponent {
+    definition: ScalingComponentDefinition,
+}
+
+impl ApplicationAutoScalingComponent {
+    // Static variables
+    pub const SCALING_KIND: &'static str = "aws-application-autoscaling";
+
+    // Functions
+    pub fn new(definition: ScalingComponentDefinition) -> Self {
+        ApplicationAutoScalingComponent { definition }
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct TargetTrackingPolicyParam {
+    policy_name: String,
+    #[serde(default)]
+    target_value: Option<f64>,
+    // e.g. RDSReaderAverageCPUUtilization, SageMakerVariantInvocationsPerInstance
+    #[serde(default)]
+    predefined_metric_type: Option<String>,
+    #[serde(default)]
+    resource_label: Option<String>,
+    // The CloudWatch metric instead of predefined_metric_type
+    #[serde(default)]
+    customized_metric: Option<CustomizedMetricParam>,
+    #[serde(default)]
+    scale_in_cooldown: Option<i32>,
+    #[serde(default)]
+    scale_out_cooldown: Option<i32>,
+    #[serde(default)]
+    disable_scale_in: Option<bool>,
+    // Delete the policy instead of putting it
+    #[serde(default)]
+    delete: bool,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct CustomizedMetricParam {
+    metric_name: String,
+    namespace: String,
+    #[serde(default)]
+    dimensions: Vec<MetricDimensionParam>,
+    // Average, Minimum, Maximum, SampleCount, Sum
+    statistic: String,
+    #[serde(default)]
+    unit: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct MetricDimensionParam {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct ScheduledActionParam {
+    name: String,
+    // at(), rate() or cron() expression
+    #[serde(default)]
+    schedule: Option<String>,
+    #[serde(default)]
+    timezone: Option<String>,
+    #[serde(default)]
+    min_capacity: Option<i32>,
+    #[serde(default)]
+    max_capacity: Option<i32>,
+    // Delete the scheduled action instead of putting it
+    #[serde(default)]
+    delete: bool,
+}
+
+// Parse the optional array param (e.g. target_tracking_policies, scheduled_actions)
+fn get_array_param<T: serde::de::DeserializeOwned>(
+    params: &HashMap<String, Value>,
+    key: &str,
+) -> Result<Vec<T>> {
+    let Some(value) = params.get(key) else {
+        return Ok(Vec::new());
+    };
+    let items = serde_json::from_value::<Vec<T>>(value.clone());
+    if let Err(e) = items {
+        return Err(anyhow::anyhow!("Invalid params - {}: {}", key, e));
+    }
+    Ok(items.unwrap())
+}
+
+fn validate_target_tracking_policy(policy: &TargetTrackingPolicyParam) -> Result<()> {
+    if policy.delete {
+        return Ok(());
+    }
+    if policy.target_value.is_none() {
+        return Err(anyhow::anyhow!(
+            "Invalid params - target_value is required for the policy {}",
+            policy.policy_name
+        ));
+    }
+    if policy.predefined_metric_type.is_some() == policy.customized_metric.is_some() {
+        return Err(anyhow::anyhow!(
+            "Invalid params - either predefined_metric_type or customized_metric is required for the policy {}",
+            policy.policy_name
+        ));
+    }
+    Ok(())
+}
+
+fn get_target_tracking_policy_configuration(
+    policy: &TargetTrackingPolicyParam,
+) -> TargetTrackingScalingPolicyConfiguration {
+    let mut policy_configuration = TargetTrackingScalingPolicyConfiguration::builder()
+        .set_target_value(policy.target_value)
+        .set_scale_in_cooldown(policy.scale_in_cooldown)
+        .set_scale_out_cooldown(policy.scale_out_cooldown)
+        .set_disable_scale_in(policy.disable_scale_in);
+    if let Some(customized_metric) = policy.customized_metric.as_ref() {
+        let dimensions = customized_metric
+            .dimensions
+            .iter()
+            .map(|dimension| {
+                MetricDimension::builder()
+                    .name(&dimension.name)
+                    .value(&dimension.value)
+                    .build()
+            })
+            .collect::<Vec<MetricDimension>>();
+        let metric_specification = CustomizedMetricSpecification::builder()
+            .metric_name(&customized_metric.metric_name)
+            .namespace(&customized_metric.namespace)
+            .set_dimensions(Some(dimensions))
+            .statistic(MetricStatistic::from(customized_metric.statistic.as_str()))
+            .set_unit(customized_metric.unit.clone())
+            .build();
+        policy_configuration =
+            policy_configuration.customized_metric_specification(metric_specification);
+    } else {
+        let metric_specification = PredefinedMetricSpecification::builder()
+            .set_predefined_metric_type(
+                policy
+                    .predefined_metric_type
+                    .as_deref()
+                    .map(MetricType::from),
+            )
+            .set_resource_label(policy.resource_label.clone())
+            .build();
+        policy_configuration =
+            policy_configuration.predefined_metric_specification(metric_specification);
+    }
+    policy_configuration.build()
+}
+
+fn validate_scheduled_action(action: &ScheduledActionParam) -> Result<()> {
+    if action.delete {
+        return Ok(());
+    }
+    if action.schedule.is_none() {
+        return Err(anyhow::anyhow!(
+            "Invalid params - schedule is required for the scheduled action {}",
+            action.name
+        ));
+    }
+    if action.min_capacity.is_none() && action.max_capacity.is_none() {
+        return Err(anyhow::anyhow!(
+            "Invalid params - min_capacity or max_capacity is required for the scheduled action {}",
+            action.name
+        ));
+    }
+    if let (Some(min_capacity), Some(max_capacity)) = (action.min_capacity, action.max_capacity) {
+        if min_capacity > max_capacity {
+            return Err(anyhow::anyhow!(
+                "min_capacity({}) is greater than max_capacity({}) for the scheduled action {}",
+                min_capacity,
+                max_capacity,
+                action.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+// The evaluated capacity between 0 and i32::MAX
+fn get_capacity(key: &str, value: i64) -> Result<i32> {
+    let capacity = i32::try_from(value);
+    if capacity.is_err() || capacity.as_ref().map_or(false, |capacity| *capacity < 0) {
+        return Err(anyhow::anyhow!(
+            "Invalid {} - {} should be between 0 and {}",
+            key,
+            value,
+            i32::MAX
+        ));
+    }
+    Ok(capacity.unwrap())
+}
+
+#[async_trait]
+impl ScalingComponent for ApplicationAutoScalingComponent {
+    fn get_scaling_component_kind(&self) -> &str {
+        &self.definition.component_kind
+    }
+    fn get_id(&self) -> &str {
+        &self.definition.id
+    }
+    async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
+        let metadata = &self.definition.metadata;
+        let (
+            Some(Value::String(service_namespace)),
+            Some(Value::String(resource_id)),
+            Some(Value::String(scalable_dimension)),
+        ) = (
+            metadata.get("service_namespace"),
+            metadata.get("resource_id"),
+            metadata.get("scalable_dimension"),
+        )
+        else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+        let service_namespace = ServiceNamespace::from(service_namespace.as_str());
+        let scalable_dimension = ScalableDimension::from(scalable_dimension.as_str());
+
+        // Validate the params before calling the API
+        let policies: Vec<TargetTrackingPolicyParam> =
+            get_array_param(&params, "target_tracking_policies")?;
+        for policy in policies.iter() {
+            validate_target_tracking_policy(policy)?;
+        }
+        let scheduled_actions: Vec<ScheduledActionParam> =
+            get_array_param(&params, "scheduled_actions")?;
+        for action in scheduled_actions.iter() {
+            validate_scheduled_action(action)?;
+        }
+        let min_capacity_expression = params.get("min_capacity").and_then(get_param_expression);
+        let max_capacity_expression = params.get("max_capacity").and_then(get_param_expression);
+        if min_capacity_expression.is_none()
+            && max_capacity_expression.is_none()
+            && policies.is_empty()
+            && scheduled_actions.is_empty()
+        {
+            return Err(anyhow::anyhow!(
+                "Invalid params - min_capacity, max_capacity, target_tracking_policies or scheduled_actions is required"
+            ));
+        }
+
+        let config = get_aws_config_with_metadata(metadata).await;
+        if config.is_err() {
+            let config_err = config.err().unwrap();
+            return Err(anyhow::anyhow!(config_err));
+        }
+        let config = config.unwrap();
+        let client = ApplicationAutoScalingClient::new(&config);
+
+        // Current state of the scalable target (None if it is not registered yet)
+        let result = client
+            .describe_scalable_targets()
+            .service_namespace(service_namespace.clone())
+            .resource_ids(resource_id)
+            .scalable_dimension(scalable_dimension.clone())
+            .send()
+            .await;
+        if let Err(error) = result {
+            return Err(get_sdk_error(error));
+        }
+        let result = result.unwrap();
+        let current_target = result
+            .scalable_targets()
+            .and_then(|targets| targets.first());
+        let current_min_capacity = current_target.and_then(|target| target.min_capacity());
+        let current_max_capacity = current_target.and_then(|target| target.max_capacity());
+
+        // Register the scalable target (it also modifies the registered one)
+        if min_capacity_expression.is_some() || max_capacity_expression.is_some() {
+            let current_state_map = HashMap::from([
+                (
+                    "$min_capacity".to_string(),
+                    current_min_capacity.unwrap_or(0) as i64,
+                ),
+                (
+                    "$max_capacity".to_string(),
+                    current_max_capacity.unwrap_or(0) as i64,
+                ),
+            ]);
+            let language = get_expression_language(&params);
+            let mut evaluated: HashMap<&str, i32> = HashMap::new();
+            for (key, expression) in [
+                ("min_capacity", &min_capacity_expression),
+                ("max_capacity", &max_capacity_expression),
+            ] {
+                let Some(expression) = expression else {
+                    continue;
+                };
+                let value = evaluate_expression_with_current_state(
+                    expression,
+                    current_state_map.clone(),
+                    language,
+                )
+                .await;
+                if value.is_err() {
+                    return Err(value.unwrap_err());
+                }
+                let value = get_capacity(key, value.unwrap());
+                if value.is_err() {
+                    return Err(value.unwrap_err());
+                }
+                evaluated.insert(key, value.unwrap());
+            }
+            let min_capacity = evaluated
+                .get("min_capacity")
+                .copied()
+                .or(current_min_capacity);
+            let max_capacity = evaluated
+                .get("max_capacity")
+                .copied()
+                .or(current_max_capacity);
+            // Both are required to register a new scalable target
+            let (Some(min_capacity), Some(max_capacity)) = (min_capacity, max_capacity) else {
+                return Err(anyhow::anyhow!(
+                    "Invalid params - both min_capacity and max_capacity are required to register the scalable target {}",
+                    resource_id
+                ));
+            };
+            if min_capacity > max_capacity {
+                return Err(anyhow::anyhow!(
+                    "min_capacity({}) is greater than max_capacity({})",
+                    min_capacity,
+                    max_capacity
+                ));
+            }
+            let result = client
+                .register_scalable_target()
+                .service_namespace(service_namespace.clone())
+                .resource_id(resource_id)
+                .scalable_dimension(scalable_dimension.clone())
+                .min_capacity(min_capacity)
+                .max_capacity(max_capacity)
+                .send()
+                .await;
+            if let Err(error) = result {
+                return Err(get_sdk_error(error));
+            }
+        } else if current_target.is_none() {
+            return Err(anyhow::anyhow!(
+                "The scalable target {} is not registered - min_capacity and max_capacity are required",
+                resource_id
+            ));
+        }
+
+        // Target tracking scaling policies
+        for policy in policies.iter() {
+            if policy.delete {
+                let result = client
+                    .delete_scaling_policy()
+                    .policy_name(&policy.policy_name)
+                    .service_namespace(service_namespace.clone())
+                    .resource_id(resource_id)
+                    .scalable_dimension(scalable_dimension.clone())
+                    .send()
+                    .await;
+                if let Err(error) = result {
+                    return Err(get_sdk_error(error));
+                }
+                continue;
+            }
+            let policy_configuration = get_target_tracking_policy_configuration(policy);
+            // PutScalingPolicy creates the policy or updates the existing one
+            let result = client
+                .put_scaling_policy()
+                .policy_name(&policy.policy_name)
+                .policy_type(PolicyType::TargetTrackingScaling)
+                .service_namespace(service_namespace.clone())
+                .resource_id(resource_id)
+                .scalable_dimension(scalable_dimension.clone())
+                .target_tracking_scaling_policy_configuration(policy_configuration)
+                .send()
+                .await;
+            if let Err(error) = result {
+                return Err(get_sdk_error(error));
+            }
+        }
+
+        // Scheduled actions
+        for action in scheduled_actions.iter() {
+            if action.delete {
+                let result = client
+                    .delete_scheduled_action()
+                    .scheduled_action_name(&action.name)
+                    .service_namespace(service_namespace.clone())
+                    .resource_id(resource_id)
+                    .scalable_dimension(scalable_dimension.clone())
+                    .send()
+                    .await;
+                if let Err(error) = result {
+                    return Err(get_sdk_error(error));
+                }
+                continue;
+            }
+            // PutScheduledAction creates the scheduled action or updates the existing one
+            let result = client
+                .put_scheduled_action()
+                .scheduled_action_name(&action.name)
+                .service_namespace(service_namespace.clone())
+                .resource_id(resource_id)
+                .scalable_dimension(scalable_dimension.clone())
+                .set_schedule(action.schedule.clone())
+                .set_timezone(action.timezone.clone())
+                .scalable_target_action(
+                    ScalableTargetAction::builder()
+                        .set_min_capacity(action.min_capacity)
+                        .set_max_capacity(action.max_capacity)
+                        .build(),
+                )
+                .send()
+                .await;
+            if let Err(error) = result {
+                return Err(get_sdk_error(error));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_get_array_param() {
+        let params: HashMap<String, Value> = HashMap::from([
+            (
+                "target_tracking_policies".to_string(),
+                json!([{
+                    "policy_name": "reader-cpu",
+                    "target_value": 60.0,
+                    "predefined_metric_type": "RDSReaderAverageCPUUtilization",
+                    "scale_in_cooldown": 300
+                }, {
+                    "policy_name": "old-policy",
+                    "delete": true
+                }]),
+            ),
+            (
+                "scheduled_actions".to_string(),
+                json!([{ "name": "morning", "schedule": "cron(0 9 * * ? *)" }]),
+            ),
+        ]);
+        let policies: Vec<TargetTrackingPolicyParam> =
+            get_array_param(&params, "target_tracking_policies").unwrap();
+        assert_eq!(policies.len(), 2);
+        assert_eq!(policies[0].scale_in_cooldown, Some(300));
+        assert!(validate_target_tracking_policy(&policies[0]).is_ok());
+        assert!(validate_target_tracking_policy(&policies[1]).is_ok());
+
+        // The scheduled action without the capacity is invalid
+        let actions: Vec<ScheduledActionParam> =
+            get_array_param(&params, "scheduled_actions").unwrap();
+        assert!(validate_scheduled_action(&actions[0]).is_err());
+
+        let none: Vec<ScheduledActionParam> = get_array_param(&params, "not_exists").unwrap();
+        assert!(none.is_empty());
+        let invalid = HashMap::from([("scheduled_actions".to_string(), json!("invalid"))]);
+        assert!(get_array_param::<ScheduledActionParam>(&invalid, "scheduled_actions").is_err());
+    }
+
+    #[test]
+    fn test_customized_metric_policy() {
+        let params: HashMap<String, Value> = HashMap::from([(
+            "target_tracking_policies".to_string(),
+            json!([{
+                "policy_name": "custom-queue-depth",
+                "target_value": 100.0,
+                "customized_metric": {
+                    "metric_name": "QueueDepth",
+                    "namespace": "MyApp",
+                    "dimensions": [{ "name": "Queue", "value": "orders" }],
+                    "statistic": "Average"
+                }
+            }, {
+                "policy_name": "no-metric",
+                "target_value": 100.0
+            }, {
+                "policy_name": "both-metrics",
+                "target_value": 100.0,
+                "predefined_metric_type": "KafkaBrokerStorageUtilization",
+                "customized_metric": {
+                    "metric_name": "QueueDepth",
+                    "namespace": "MyApp",
+                    "statistic": "Average"
+                }
+            }]),
+        )]);
+        let policies: Vec<TargetTrackingPolicyParam> =
+            get_array_param(&params, "target_tracking_policies").unwrap();
+        assert!(validate_target_tracking_policy(&policies[0]).is_ok());
+        assert!(validate_target_tracking_policy(&policies[1]).is_err());
+        assert!(validate_target_tracking_policy(&policies[2]).is_err());
+
+        let policy_configuration = get_target_tracking_policy_configuration(&policies[0]);
+        assert!(policy_configuration
+            .predefined_metric_specification()
+            .is_none());
+        let metric_specification = policy_configuration
+            .customized_metric_specification()
+            .unwrap();
+        assert_eq!(metric_specification.metric_name(), Some("QueueDepth"));
+        assert_eq!(metric_specification.namespace(), Some("MyApp"));
+        assert_eq!(
+            metric_specification.statistic(),
+            Some(&MetricStatistic::Average)
+        );
+        let dimensions = metric_specification.dimensions().unwrap();
+        assert_eq!(dimensions[0].name(), Some("Queue"));
+        assert_eq!(dimensions[0].value(), Some("orders"));
+    }
+
+    #[test]
+    fn test_get_capacity() {
+        assert_eq!(get_capacity("min_capacity", 0).unwrap(), 0);
+        assert_eq!(get_capacity("max_capacity", 10).unwrap(), 10);
+        assert!(get_capacity("min_capacity", -1).is_err());
+        // It doesn't wrap around
+        assert!(get_capacity("max_capacity", i32::MAX as i64 + 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn apply_invalid_metadata() {
+        let definition = ScalingComponentDefinition {
+            id: String::from("scaling-id"),
+            component_kind: String::from(ApplicationAutoScalingComponent::SCALING_KIND),
+            metadata: HashMap::new(),
+            ..Default::default()
+        };
+        let result = ApplicationAutoScalingComponent::new(definition)
+            .apply(HashMap::new())
+            .await;
+        assert!(result.is_err());
+    }
+}
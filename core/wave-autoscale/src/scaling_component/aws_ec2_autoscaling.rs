@@ -1,11 +1,22 @@
 use super::ScalingComponent;
-use super::{evaluate_expression_with_current_state, filter_current_state_in_expression};
-use crate::util::aws::get_aws_config;
+use super::{
+    evaluate_expression_with_current_state, filter_current_state_in_expression,
+    get_expression_language, get_param_expression,
+};
+use crate::util::aws::{get_aws_config_with_metadata, get_sdk_error};
 use anyhow::{Ok, Result};
 use async_trait::async_trait;
-use aws_sdk_autoscaling::{error::ProvideErrorMetadata, Client};
+use aws_sdk_autoscaling::{
+    types::{
+        AutoScalingGroup, InstancesDistribution, LaunchTemplate, LaunchTemplateOverrides,
+        MixedInstancesPolicy, WarmPoolState,
+    },
+    Client,
+};
+use aws_smithy_types::{date_time::Format as DateTimeFormat, DateTime};
 use data_layer::ScalingComponentDefinition;
-use serde_json::{json, Value};
+use serde::Deserialize;
+use serde_json::Value;
 use std::collections::HashMap;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
@@ -52,87 +63,406 @@ impl ScalingComponent for EC2AutoScalingComponent {
     async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
         let metadata = self.definition.metadata.clone();
 
-        if let (
-            Some(Value::String(asg_name)),
-            Some(Value::String(region)),
-            Some(Value::String(desired)),
-        ) = (
-            metadata.get("asg_name"),
-            metadata.get("region"),
-            params.get("desired"),
-        ) {
-            // AWS Credentials
-            let access_key = metadata
-                .get("access_key")
-                .map(|access_key| access_key.to_string());
-            let secret_key = metadata
-                .get("secret_key")
-                .map(|secret_key| secret_key.to_string());
-
-            let config =
-                get_aws_config(Some(region.to_string()), access_key, secret_key, None, None).await;
-            if config.is_err() {
-                let config_err = config.err().unwrap();
-                return Err(anyhow::anyhow!(config_err));
-            }
-            let config = config.unwrap();
-            let client = Client::new(&config);
-
-            let current_state_key_array = EC2ComponentTargetValue::iter()
-                .map(|value| value.to_string())
-                .collect::<Vec<String>>();
-            // check target value contains enum variables
-            let current_state_array =
-                filter_current_state_in_expression(desired, current_state_key_array);
-            // save target value to map
-            let current_state_map =
-                get_current_state_map(current_state_array, client.clone(), asg_name.clone()).await;
-            if current_state_map.is_err() {
-                return Err(current_state_map.unwrap_err());
-            };
-
-            // evaluate target value
-            let desired =
-                evaluate_expression_with_current_state(desired, current_state_map.unwrap().clone())
-                    .await;
-            if desired.is_err() {
-                return Err(desired.unwrap_err());
-            }
-            let desired = desired.unwrap();
+        let Some(Value::String(asg_name)) = metadata.get("asg_name") else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+        let desired = params.get("desired").and_then(get_param_expression);
+        let min = params.get("min").and_then(Value::as_i64);
+        let max = params.get("max").and_then(Value::as_i64);
+        let warm_pool = match params.get("warm_pool") {
+            Some(value) => Some(get_warm_pool_param(value)?),
+            None => None,
+        };
+        let mixed_instances = match params.get("mixed_instances") {
+            Some(value) => Some(get_mixed_instances_param(value)?),
+            None => None,
+        };
+        let scheduled_actions = match params.get("scheduled_actions") {
+            Some(value) => get_scheduled_actions_param(value)?,
+            None => vec![],
+        };
+        if desired.is_none()
+            && min.is_none()
+            && max.is_none()
+            && warm_pool.is_none()
+            && mixed_instances.is_none()
+            && scheduled_actions.is_empty()
+        {
+            return Err(anyhow::anyhow!("Invalid params"));
+        }
+
+        // AWS Credentials
+        let config = get_aws_config_with_metadata(&metadata).await;
+        if config.is_err() {
+            let config_err = config.err().unwrap();
+            return Err(anyhow::anyhow!(config_err));
+        }
+        let config = config.unwrap();
+        let client = Client::new(&config);
 
+        // Capacity and MixedInstancesPolicy
+        if desired.is_some() || min.is_some() || max.is_some() || mixed_instances.is_some() {
             let mut result = client
                 .update_auto_scaling_group()
-                .auto_scaling_group_name(asg_name)
-                .desired_capacity(desired as i32);
+                .auto_scaling_group_name(asg_name);
+
+            if let Some(desired) = desired {
+                let current_state_key_array = EC2ComponentTargetValue::iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<String>>();
+                // check target value contains enum variables
+                let current_state_array =
+                    filter_current_state_in_expression(&desired, current_state_key_array);
+                // save target value to map
+                let current_state_map =
+                    get_current_state_map(current_state_array, client.clone(), asg_name.clone())
+                        .await;
+                if current_state_map.is_err() {
+                    return Err(current_state_map.unwrap_err());
+                };
 
-            if let Some(min) = params.get("min").and_then(Value::as_i64) {
+                // evaluate target value
+                let desired = evaluate_expression_with_current_state(
+                    &desired,
+                    current_state_map.unwrap().clone(),
+                    get_expression_language(&params),
+                )
+                .await;
+                if desired.is_err() {
+                    return Err(desired.unwrap_err());
+                }
+                result = result.desired_capacity(desired.unwrap() as i32);
+            }
+            if let Some(min) = min {
                 result = result.min_size(min as i32);
             }
-            if let Some(max) = params.get("max").and_then(Value::as_i64) {
+            if let Some(max) = max {
                 result = result.max_size(max as i32);
             }
+            if let Some(mixed_instances) = mixed_instances.as_ref() {
+                let group = describe_auto_scaling_group(&client, asg_name).await?;
+                let mixed_instances_policy = get_mixed_instances_policy(&group, mixed_instances)?;
+                result = result.mixed_instances_policy(mixed_instances_policy);
+            }
 
             let result = result.send().await;
+            if let Err(error) = result {
+                return Err(get_sdk_error(error));
+            }
+        }
 
-            if result.is_err() {
-                let error = result.err().unwrap();
-                // error.
-                let meta = error.meta();
-                // meta.ex
-                let json = json!({
-                    "message": meta.message(),
-                    "code": meta.code(),
-                    "extras": meta.to_string()
-                });
-                return Err(anyhow::anyhow!(json));
+        // Warm pool
+        if let Some(warm_pool) = warm_pool {
+            if warm_pool.delete {
+                let result = client
+                    .delete_warm_pool()
+                    .auto_scaling_group_name(asg_name)
+                    .send()
+                    .await;
+                if let Err(error) = result {
+                    return Err(get_sdk_error(error));
+                }
+            } else {
+                // PutWarmPool creates the warm pool or updates the existing one
+                let result = client
+                    .put_warm_pool()
+                    .auto_scaling_group_name(asg_name)
+                    .set_min_size(warm_pool.min_size)
+                    .set_max_group_prepared_capacity(warm_pool.max_group_prepared_capacity)
+                    .set_pool_state(warm_pool.pool_state.as_deref().map(WarmPoolState::from))
+                    .send()
+                    .await;
+                if let Err(error) = result {
+                    return Err(get_sdk_error(error));
+                }
+            }
+        }
+
+        // Scheduled actions are kept in the ASG, so the pre-scaling survives a wave-autoscale outage
+        for action in scheduled_actions.iter() {
+            if action.delete {
+                let result = client
+                    .delete_scheduled_action()
+                    .auto_scaling_group_name(asg_name)
+                    .scheduled_action_name(&action.name)
+                    .send()
+                    .await;
+                if let Err(error) = result {
+                    return Err(get_sdk_error(error));
+                }
+                continue;
+            }
+            let start_time = parse_date_time(action.start_time.as_deref())?;
+            let end_time = parse_date_time(action.end_time.as_deref())?;
+            // PutScheduledUpdateGroupAction creates the scheduled action or updates the existing one
+            let result = client
+                .put_scheduled_update_group_action()
+                .auto_scaling_group_name(asg_name)
+                .scheduled_action_name(&action.name)
+                .set_recurrence(action.recurrence.clone())
+                .set_start_time(start_time)
+                .set_end_time(end_time)
+                .set_time_zone(action.time_zone.clone())
+                .set_min_size(action.min_size)
+                .set_max_size(action.max_size)
+                .set_desired_capacity(action.desired_capacity)
+                .send()
+                .await;
+            if let Err(error) = result {
+                return Err(get_sdk_error(error));
             }
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Invalid metadata"))
         }
+        Ok(())
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct WarmPoolParam {
+    #[serde(default)]
+    min_size: Option<i32>,
+    // -1 means the difference between the max size and the desired capacity of the group
+    #[serde(default)]
+    max_group_prepared_capacity: Option<i32>,
+    // Stopped, Running or Hibernated
+    #[serde(default)]
+    pool_state: Option<String>,
+    #[serde(default)]
+    delete: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct MixedInstancesParam {
+    #[serde(default)]
+    on_demand_base_capacity: Option<i32>,
+    #[serde(default)]
+    on_demand_percentage_above_base_capacity: Option<i32>,
+    // e.g. price-capacity-optimized, capacity-optimized, lowest-price
+    #[serde(default)]
+    spot_allocation_strategy: Option<String>,
+    // Instance type overrides. The current overrides are kept if omitted.
+    // The current override of the same instance type is kept (e.g. WeightedCapacity, LaunchTemplateSpecification)
+    #[serde(default)]
+    instance_types: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduledActionParam {
+    name: String,
+    // Cron expression. e.g. "0 9 * * *"
+    #[serde(default)]
+    recurrence: Option<String>,
+    // ISO 8601. e.g. 2023-10-01T09:00:00Z
+    #[serde(default)]
+    start_time: Option<String>,
+    #[serde(default)]
+    end_time: Option<String>,
+    #[serde(default)]
+    time_zone: Option<String>,
+    #[serde(default)]
+    min_size: Option<i32>,
+    #[serde(default)]
+    max_size: Option<i32>,
+    #[serde(default)]
+    desired_capacity: Option<i32>,
+    #[serde(default)]
+    delete: bool,
+}
+
+fn get_warm_pool_param(value: &Value) -> Result<WarmPoolParam, anyhow::Error> {
+    let warm_pool = serde_json::from_value::<WarmPoolParam>(value.clone());
+    if let Err(e) = warm_pool {
+        return Err(anyhow::anyhow!("Invalid params - warm_pool: {}", e));
+    }
+    let warm_pool = warm_pool.unwrap();
+    if let Some(pool_state) = warm_pool.pool_state.as_deref() {
+        if !["Stopped", "Running", "Hibernated"].contains(&pool_state) {
+            return Err(anyhow::anyhow!(
+                "Invalid params - warm_pool.pool_state should be Stopped, Running or Hibernated"
+            ));
+        }
+    }
+    Ok(warm_pool)
+}
+
+fn get_mixed_instances_param(value: &Value) -> Result<MixedInstancesParam, anyhow::Error> {
+    let mixed_instances = serde_json::from_value::<MixedInstancesParam>(value.clone());
+    if let Err(e) = mixed_instances {
+        return Err(anyhow::anyhow!("Invalid params - mixed_instances: {}", e));
+    }
+    let mixed_instances = mixed_instances.unwrap();
+    if let Some(percentage) = mixed_instances.on_demand_percentage_above_base_capacity {
+        if !(0..=100).contains(&percentage) {
+            return Err(anyhow::anyhow!(
+                "Invalid params - mixed_instances.on_demand_percentage_above_base_capacity should be between 0 and 100"
+            ));
+        }
+    }
+    if let Some(instance_types) = mixed_instances.instance_types.as_ref() {
+        if instance_types.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Invalid params - mixed_instances.instance_types is empty"
+            ));
+        }
+    }
+    Ok(mixed_instances)
+}
+
+fn get_scheduled_actions_param(value: &Value) -> Result<Vec<ScheduledActionParam>, anyhow::Error> {
+    let actions = serde_json::from_value::<Vec<ScheduledActionParam>>(value.clone());
+    if let Err(e) = actions {
+        return Err(anyhow::anyhow!("Invalid params - scheduled_actions: {}", e));
+    }
+    let actions = actions.unwrap();
+    for action in actions.iter() {
+        if action.delete {
+            continue;
+        }
+        if action.recurrence.is_none() && action.start_time.is_none() {
+            return Err(anyhow::anyhow!(
+                "Invalid params - recurrence or start_time is required for the scheduled action {}",
+                action.name
+            ));
+        }
+        if action.min_size.is_none()
+            && action.max_size.is_none()
+            && action.desired_capacity.is_none()
+        {
+            return Err(anyhow::anyhow!(
+                "Invalid params - min_size, max_size or desired_capacity is required for the scheduled action {}",
+                action.name
+            ));
+        }
+    }
+    Ok(actions)
+}
+
+fn parse_date_time(value: Option<&str>) -> Result<Option<DateTime>, anyhow::Error> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let date_time = DateTime::from_str(value, DateTimeFormat::DateTime);
+    if let Err(e) = date_time {
+        return Err(anyhow::anyhow!("Invalid date time: {} - {}", value, e));
+    }
+    Ok(Some(date_time.unwrap()))
+}
+
+async fn describe_auto_scaling_group(
+    client: &Client,
+    asg_name: &str,
+) -> Result<AutoScalingGroup, anyhow::Error> {
+    let result = client
+        .describe_auto_scaling_groups()
+        .auto_scaling_group_names(asg_name)
+        .send()
+        .await;
+    if let Err(error) = result {
+        return Err(get_sdk_error(error));
+    }
+    let result = result.unwrap();
+    let Some(group) = result
+        .auto_scaling_groups()
+        .and_then(|groups| groups.first())
+    else {
+        return Err(anyhow::anyhow!(
+            "Failed to describe the auto scaling group {}",
+            asg_name
+        ));
+    };
+    Ok(group.clone())
+}
+
+// Merge the params into the current MixedInstancesPolicy of the group
+fn get_mixed_instances_policy(
+    group: &AutoScalingGroup,
+    mixed_instances: &MixedInstancesParam,
+) -> Result<MixedInstancesPolicy, anyhow::Error> {
+    let current_policy = group.mixed_instances_policy();
+    let current_distribution = current_policy.and_then(|policy| policy.instances_distribution());
+    let current_launch_template = current_policy.and_then(|policy| policy.launch_template());
+
+    let instances_distribution = InstancesDistribution::builder()
+        .set_on_demand_allocation_strategy(
+            current_distribution
+                .and_then(|distribution| distribution.on_demand_allocation_strategy())
+                .map(|value| value.to_string()),
+        )
+        .set_on_demand_base_capacity(mixed_instances.on_demand_base_capacity.or(
+            current_distribution.and_then(|distribution| distribution.on_demand_base_capacity()),
+        ))
+        .set_on_demand_percentage_above_base_capacity(
+            mixed_instances
+                .on_demand_percentage_above_base_capacity
+                .or(current_distribution.and_then(|distribution| {
+                    distribution.on_demand_percentage_above_base_capacity()
+                })),
+        )
+        .set_spot_allocation_strategy(
+            mixed_instances
+                .spot_allocation_strategy
+                .clone()
+                .or(current_distribution
+                    .and_then(|distribution| distribution.spot_allocation_strategy())
+                    .map(|value| value.to_string())),
+        )
+        .set_spot_instance_pools(
+            current_distribution.and_then(|distribution| distribution.spot_instance_pools()),
+        )
+        .set_spot_max_price(
+            current_distribution
+                .and_then(|distribution| distribution.spot_max_price())
+                .map(|value| value.to_string()),
+        )
+        .build();
+
+    // The group without the MixedInstancesPolicy uses its launch template
+    let launch_template_specification = current_launch_template
+        .and_then(|launch_template| launch_template.launch_template_specification())
+        .or(group.launch_template())
+        .cloned();
+    if launch_template_specification.is_none() {
+        return Err(anyhow::anyhow!(
+            "The auto scaling group {} doesn't have a launch template",
+            group.auto_scaling_group_name().unwrap_or_default()
+        ));
+    }
+    let current_overrides = current_launch_template
+        .and_then(|launch_template| launch_template.overrides())
+        .unwrap_or_default();
+    let overrides = match mixed_instances.instance_types.as_ref() {
+        Some(instance_types) => Some(
+            instance_types
+                .iter()
+                .map(|instance_type| {
+                    current_overrides
+                        .iter()
+                        .find(|current_override| {
+                            current_override.instance_type() == Some(instance_type.as_str())
+                        })
+                        .cloned()
+                        .unwrap_or_else(|| {
+                            LaunchTemplateOverrides::builder()
+                                .instance_type(instance_type)
+                                .build()
+                        })
+                })
+                .collect::<Vec<LaunchTemplateOverrides>>(),
+        ),
+        None => current_launch_template
+            .and_then(|launch_template| launch_template.overrides())
+            .map(|overrides| overrides.to_vec()),
+    };
+    let launch_template = LaunchTemplate::builder()
+        .set_launch_template_specification(launch_template_specification)
+        .set_overrides(overrides)
+        .build();
+
+    Ok(MixedInstancesPolicy::builder()
+        .launch_template(launch_template)
+        .instances_distribution(instances_distribution)
+        .build())
+}
+
 async fn get_current_state_map(
     current_state_array: Vec<String>,
     client: Client,
@@ -194,6 +524,7 @@ async fn get_auto_scaling_group_capacity(
 mod test {
     use super::super::ScalingComponentManager;
     use super::*;
+    use crate::util::aws::get_aws_config;
     use data_layer::types::object_kind::ObjectKind;
     use serde_json::{json, Value};
     use std::collections::HashMap;
@@ -244,6 +575,109 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_get_params() {
+        assert!(get_warm_pool_param(&json!({ "min_size": 2, "pool_state": "Stopped" })).is_ok());
+        assert!(get_warm_pool_param(&json!({ "pool_state": "Terminated" })).is_err());
+        assert!(get_mixed_instances_param(
+            &json!({ "on_demand_percentage_above_base_capacity": 120 })
+        )
+        .is_err());
+        let actions = get_scheduled_actions_param(&json!([
+            { "name": "pre-scale", "recurrence": "0 9 * * *", "min_size": 10 },
+            { "name": "old", "delete": true }
+        ]))
+        .unwrap();
+        assert_eq!(actions.len(), 2);
+        // No capacity
+        assert!(get_scheduled_actions_param(
+            &json!([{ "name": "pre-scale", "recurrence": "0 9 * * *" }])
+        )
+        .is_err());
+        assert!(parse_date_time(Some("2023-10-01T09:00:00Z"))
+            .unwrap()
+            .is_some());
+        assert!(parse_date_time(Some("tomorrow")).is_err());
+    }
+
+    #[test]
+    fn test_get_mixed_instances_policy() {
+        let group = AutoScalingGroup::builder()
+            .auto_scaling_group_name("asg")
+            .mixed_instances_policy(
+                MixedInstancesPolicy::builder()
+                    .launch_template(
+                        LaunchTemplate::builder()
+                            .launch_template_specification(
+                                aws_sdk_autoscaling::types::LaunchTemplateSpecification::builder()
+                                    .launch_template_name("template")
+                                    .build(),
+                            )
+                            .overrides(
+                                LaunchTemplateOverrides::builder()
+                                    .instance_type("m5.large")
+                                    .build(),
+                            )
+                            .overrides(
+                                LaunchTemplateOverrides::builder()
+                                    .instance_type("m5.2xlarge")
+                                    .weighted_capacity("4")
+                                    .launch_template_specification(
+                                        aws_sdk_autoscaling::types::LaunchTemplateSpecification::builder()
+                                            .launch_template_name("template-2xlarge")
+                                            .build(),
+                                    )
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .instances_distribution(
+                        InstancesDistribution::builder()
+                            .on_demand_base_capacity(1)
+                            .on_demand_percentage_above_base_capacity(50)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let mixed_instances =
+            get_mixed_instances_param(&json!({ "on_demand_percentage_above_base_capacity": 20 }))
+                .unwrap();
+        let policy = get_mixed_instances_policy(&group, &mixed_instances).unwrap();
+        let distribution = policy.instances_distribution().unwrap();
+        // Keep the omitted values
+        assert_eq!(distribution.on_demand_base_capacity(), Some(1));
+        assert_eq!(
+            distribution.on_demand_percentage_above_base_capacity(),
+            Some(20)
+        );
+        let overrides = policy.launch_template().unwrap().overrides().unwrap();
+        assert_eq!(overrides[0].instance_type(), Some("m5.large"));
+        assert_eq!(overrides.len(), 2);
+
+        // Keep the current override of the same instance type
+        let mixed_instances =
+            get_mixed_instances_param(&json!({ "instance_types": ["m5.2xlarge", "c5.large"] }))
+                .unwrap();
+        let policy = get_mixed_instances_policy(&group, &mixed_instances).unwrap();
+        let overrides = policy.launch_template().unwrap().overrides().unwrap();
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides[0].instance_type(), Some("m5.2xlarge"));
+        assert_eq!(overrides[0].weighted_capacity(), Some("4"));
+        assert_eq!(
+            overrides[0]
+                .launch_template_specification()
+                .and_then(|specification| specification.launch_template_name()),
+            Some("template-2xlarge")
+        );
+        assert_eq!(overrides[1].instance_type(), Some("c5.large"));
+        assert_eq!(overrides[1].weighted_capacity(), None);
+
+        // No launch template
+        let group = AutoScalingGroup::builder().build();
+        assert!(get_mixed_instances_policy(&group, &mixed_instances).is_err());
+    }
+
     #[ignore]
     #[tokio::test]
     async fn test_get_current_state_map() {
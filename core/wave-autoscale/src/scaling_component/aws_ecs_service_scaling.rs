@@ -1,11 +1,36 @@
-use super::ScalingComponent;
-use crate::util::aws::get_aws_config;
+/**
+ * [Scaling Component] Amazon ECS Service Scaling Component
+ *
+ * It requires the following metadata:
+ * - cluster_name, service_name
+ * - region, access_key, secret_key (optional, see util/aws)
+ * It accepts the following parameters (at least one of them):
+ * - desired: The desired count of the tasks
+ * - capacity_provider_strategy: e.g. [{ capacity_provider: FARGATE, weight: 1, base: 1 }, { capacity_provider: FARGATE_SPOT, weight: 3 }]
+ * - minimum_healthy_percent, maximum_percent: The deployment configuration
+ * - cpu, memory: The task size. A new revision of the task definition is registered and the service is rolled to it.
+ * The current state is available as $running_count, $desired_count, $pending_count, $cpu and $memory
+ */
+use super::{
+    evaluate_expression_with_current_state, filter_current_state_in_expression,
+    get_expression_language, get_param_expression, ScalingComponent,
+};
+use crate::util::aws::{get_aws_config_with_metadata, get_sdk_error};
 use anyhow::{Ok, Result};
 use async_trait::async_trait;
-use aws_sdk_ecs::{error::ProvideErrorMetadata, Client};
+use aws_sdk_ecs::{
+    types::{
+        CapacityProviderStrategyItem, DeploymentConfiguration, Service, Tag, TaskDefinition,
+        TaskDefinitionField,
+    },
+    Client,
+};
 use data_layer::ScalingComponentDefinition;
-use serde_json::{json, Value};
+use serde::Deserialize;
+use serde_json::Value;
 use std::collections::HashMap;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
 
 pub struct ECSServiceScalingComponent {
     definition: ScalingComponentDefinition,
@@ -19,6 +44,165 @@ impl ECSServiceScalingComponent {
     }
 }
 
+#[derive(Debug, EnumIter)]
+enum ECSComponentTargetValue {
+    RunningCount,
+    DesiredCount,
+    PendingCount,
+    Cpu,
+    Memory,
+}
+impl std::fmt::Display for ECSComponentTargetValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ECSComponentTargetValue::RunningCount => write!(f, "running_count"),
+            ECSComponentTargetValue::DesiredCount => write!(f, "desired_count"),
+            ECSComponentTargetValue::PendingCount => write!(f, "pending_count"),
+            ECSComponentTargetValue::Cpu => write!(f, "cpu"),
+            ECSComponentTargetValue::Memory => write!(f, "memory"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CapacityProviderStrategyParam {
+    capacity_provider: String,
+    #[serde(default)]
+    weight: i32,
+    #[serde(default)]
+    base: i32,
+}
+
+fn get_capacity_provider_strategy(
+    value: &Value,
+) -> Result<Vec<CapacityProviderStrategyItem>, anyhow::Error> {
+    let strategy = serde_json::from_value::<Vec<CapacityProviderStrategyParam>>(value.clone());
+    if let Err(e) = strategy {
+        return Err(anyhow::anyhow!(
+            "Invalid params - capacity_provider_strategy: {}",
+            e
+        ));
+    }
+    let strategy = strategy.unwrap();
+    if strategy.is_empty() || strategy.iter().all(|item| item.weight == 0) {
+        return Err(anyhow::anyhow!(
+            "Invalid params - capacity_provider_strategy needs a capacity provider with weight > 0"
+        ));
+    }
+    // Only one capacity provider can have a base
+    if strategy.iter().filter(|item| item.base > 0).count() > 1 {
+        return Err(anyhow::anyhow!(
+            "Invalid params - only one capacity provider can have a base"
+        ));
+    }
+    let mut items = vec![];
+    for item in strategy {
+        if !(0..=1000).contains(&item.weight) || !(0..=100000).contains(&item.base) {
+            return Err(anyhow::anyhow!(
+                "Invalid params - the weight(0-1000) or the base(0-100000) of {} is out of range",
+                item.capacity_provider
+            ));
+        }
+        items.push(
+            CapacityProviderStrategyItem::builder()
+                .capacity_provider(item.capacity_provider)
+                .weight(item.weight)
+                .base(item.base)
+                .build(),
+        );
+    }
+    Ok(items)
+}
+
+fn get_current_state_map(
+    service: &Service,
+    task_definition: Option<&TaskDefinition>,
+) -> HashMap<String, i64> {
+    let mut current_state_map: HashMap<String, i64> = HashMap::new();
+    for target_value in ECSComponentTargetValue::iter() {
+        let value = match target_value {
+            ECSComponentTargetValue::RunningCount => Some(service.running_count() as i64),
+            ECSComponentTargetValue::DesiredCount => Some(service.desired_count() as i64),
+            ECSComponentTargetValue::PendingCount => Some(service.pending_count() as i64),
+            // The task size is a string. e.g. "1024"
+            ECSComponentTargetValue::Cpu => task_definition
+                .and_then(|task_definition| task_definition.cpu())
+                .and_then(|cpu| cpu.parse::<i64>().ok()),
+            ECSComponentTargetValue::Memory => task_definition
+                .and_then(|task_definition| task_definition.memory())
+                .and_then(|memory| memory.parse::<i64>().ok()),
+        };
+        if let Some(value) = value {
+            current_state_map.insert(format!("${}", target_value), value);
+        }
+    }
+    current_state_map
+}
+
+// Register a new revision of the task definition with the task size
+// Every field of RegisterTaskDefinition is copied from the current revision (with its tags) except cpu and memory
+async fn register_task_definition_with_size(
+    client: &Client,
+    task_definition: &TaskDefinition,
+    tags: &[Tag],
+    cpu: Option<i64>,
+    memory: Option<i64>,
+) -> Result<String, anyhow::Error> {
+    let result = client
+        .register_task_definition()
+        .set_tags(Some(tags.to_vec()).filter(|tags| !tags.is_empty()))
+        .set_family(task_definition.family().map(|value| value.to_string()))
+        .set_task_role_arn(
+            task_definition
+                .task_role_arn()
+                .map(|value| value.to_string()),
+        )
+        .set_execution_role_arn(
+            task_definition
+                .execution_role_arn()
+                .map(|value| value.to_string()),
+        )
+        .set_network_mode(task_definition.network_mode().cloned())
+        .set_container_definitions(task_definition.container_definitions().map(|v| v.to_vec()))
+        .set_volumes(task_definition.volumes().map(|v| v.to_vec()))
+        .set_placement_constraints(task_definition.placement_constraints().map(|v| v.to_vec()))
+        .set_requires_compatibilities(
+            task_definition
+                .requires_compatibilities()
+                .map(|v| v.to_vec()),
+        )
+        .set_proxy_configuration(task_definition.proxy_configuration().cloned())
+        .set_inference_accelerators(task_definition.inference_accelerators().map(|v| v.to_vec()))
+        .set_pid_mode(task_definition.pid_mode().cloned())
+        .set_ipc_mode(task_definition.ipc_mode().cloned())
+        .set_ephemeral_storage(task_definition.ephemeral_storage().cloned())
+        .set_runtime_platform(task_definition.runtime_platform().cloned())
+        .set_cpu(
+            cpu.map(|cpu| cpu.to_string())
+                .or(task_definition.cpu().map(|value| value.to_string())),
+        )
+        .set_memory(
+            memory
+                .map(|memory| memory.to_string())
+                .or(task_definition.memory().map(|value| value.to_string())),
+        )
+        .send()
+        .await;
+    if let Err(error) = result {
+        return Err(get_sdk_error(error));
+    }
+    let result = result.unwrap();
+    let Some(task_definition_arn) = result
+        .task_definition()
+        .and_then(|task_definition| task_definition.task_definition_arn())
+    else {
+        return Err(anyhow::anyhow!(
+            "Failed to register the task definition - task_definition_arn none"
+        ));
+    };
+    Ok(task_definition_arn.to_string())
+}
+
 #[async_trait]
 impl ScalingComponent for ECSServiceScalingComponent {
     fn get_scaling_component_kind(&self) -> &str {
@@ -29,66 +213,206 @@ impl ScalingComponent for ECSServiceScalingComponent {
     }
     async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
         let metadata: HashMap<String, Value> = self.definition.metadata.clone();
-        if let (
-            Some(Value::String(region)),
-            Some(Value::String(cluster_name)),
-            Some(Value::String(service_name)),
-            Some(desired),
-        ) = (
-            metadata.get("region"),
-            metadata.get("cluster_name"),
-            metadata.get("service_name"),
-            params.get("desired").and_then(Value::as_i64),
-        ) {
-            let access_key = metadata
-                .get("access_key")
-                .map(|access_key| access_key.to_string());
-            let secret_key = metadata
-                .get("secret_key")
-                .map(|secret_key| secret_key.to_string());
-            let config =
-                get_aws_config(Some(region.to_string()), access_key, secret_key, None, None).await;
-            if config.is_err() {
-                let config_err = config.err().unwrap();
-                return Err(anyhow::anyhow!(config_err));
+        let (Some(Value::String(cluster_name)), Some(Value::String(service_name))) =
+            (metadata.get("cluster_name"), metadata.get("service_name"))
+        else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+        let desired = params.get("desired").and_then(get_param_expression);
+        let cpu = params.get("cpu").and_then(get_param_expression);
+        let memory = params.get("memory").and_then(get_param_expression);
+        let minimum_healthy_percent = params
+            .get("minimum_healthy_percent")
+            .and_then(Value::as_i64)
+            .map(|value| value as i32);
+        let maximum_percent = params
+            .get("maximum_percent")
+            .and_then(Value::as_i64)
+            .map(|value| value as i32);
+        let capacity_provider_strategy = match params.get("capacity_provider_strategy") {
+            Some(value) => Some(get_capacity_provider_strategy(value)?),
+            None => None,
+        };
+        if desired.is_none()
+            && cpu.is_none()
+            && memory.is_none()
+            && minimum_healthy_percent.is_none()
+            && maximum_percent.is_none()
+            && capacity_provider_strategy.is_none()
+        {
+            return Err(anyhow::anyhow!("Invalid params"));
+        }
+        if let Some(minimum_healthy_percent) = minimum_healthy_percent {
+            if !(0..=100).contains(&minimum_healthy_percent) {
+                return Err(anyhow::anyhow!(
+                    "Invalid params - minimum_healthy_percent should be between 0 and 100"
+                ));
+            }
+        }
+        if let Some(maximum_percent) = maximum_percent {
+            if maximum_percent < 100 {
+                return Err(anyhow::anyhow!(
+                    "Invalid params - maximum_percent should be greater than or equal to 100"
+                ));
             }
-            let config = config.unwrap();
+        }
 
-            let client = Client::new(&config);
+        let config = get_aws_config_with_metadata(&metadata).await;
+        if config.is_err() {
+            let config_err = config.err().unwrap();
+            return Err(anyhow::anyhow!(config_err));
+        }
+        let config = config.unwrap();
 
-            let result = client
-                .update_service()
-                .cluster(cluster_name)
-                .service(service_name)
-                .desired_count(desired as i32);
+        let client = Client::new(&config);
 
-            let result = result.send().await;
+        // Current state of the service
+        let services = client
+            .describe_services()
+            .cluster(cluster_name)
+            .services(service_name)
+            .send()
+            .await;
+        if let Err(error) = services {
+            return Err(get_sdk_error(error));
+        }
+        let services = services.unwrap();
+        let Some(service) = services.services().and_then(|services| services.first()) else {
+            return Err(anyhow::anyhow!(
+                "Failed to describe the service {} - service none",
+                service_name
+            ));
+        };
 
-            if result.is_err() {
-                let error = result.err().unwrap();
-                let meta = error.meta();
-                let json = json!({
-                    "message": meta.message(),
-                    "code": meta.code(),
-                    "extras": meta.to_string()
-                });
+        // The task definition is only needed for the task size
+        let current_state_key_array = ECSComponentTargetValue::iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<String>>();
+        let needs_task_definition = cpu.is_some()
+            || memory.is_some()
+            || desired.as_ref().map_or(false, |desired| {
+                filter_current_state_in_expression(desired, current_state_key_array)
+                    .iter()
+                    .any(|key| key == "$cpu" || key == "$memory")
+            });
+        let (task_definition, task_definition_tags) = if needs_task_definition {
+            let Some(task_definition_arn) = service.task_definition() else {
+                return Err(anyhow::anyhow!(
+                    "The service {} doesn't have a task definition",
+                    service_name
+                ));
+            };
+            let task_definition = client
+                .describe_task_definition()
+                .task_definition(task_definition_arn)
+                .include(TaskDefinitionField::Tags)
+                .send()
+                .await;
+            if let Err(error) = task_definition {
+                return Err(get_sdk_error(error));
+            }
+            let task_definition = task_definition.unwrap();
+            (
+                task_definition.task_definition().cloned(),
+                task_definition.tags().unwrap_or_default().to_vec(),
+            )
+        } else {
+            (None, vec![])
+        };
+        let current_state_map = get_current_state_map(service, task_definition.as_ref());
 
-                return Err(anyhow::anyhow!(json));
+        // Evaluate the expressions
+        let language = get_expression_language(&params);
+        let mut evaluated: HashMap<&str, i64> = HashMap::new();
+        for (key, expression) in [("desired", &desired), ("cpu", &cpu), ("memory", &memory)] {
+            let Some(expression) = expression else {
+                continue;
+            };
+            let value = evaluate_expression_with_current_state(
+                expression,
+                current_state_map.clone(),
+                language,
+            )
+            .await;
+            if value.is_err() {
+                return Err(value.unwrap_err());
             }
+            evaluated.insert(key, value.unwrap());
+        }
 
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Invalid metadata"))
+        let mut result = client
+            .update_service()
+            .cluster(cluster_name)
+            .service(service_name);
+
+        if let Some(desired) = evaluated.get("desired") {
+            result = result.desired_count(*desired as i32);
+        }
+
+        // Vertical scaling with a new revision of the task definition
+        let cpu = evaluated.get("cpu").copied();
+        let memory = evaluated.get("memory").copied();
+        let is_size_changed = |key: &str, value: Option<i64>| {
+            value.is_some() && value != current_state_map.get(key).copied()
+        };
+        if is_size_changed("$cpu", cpu) || is_size_changed("$memory", memory) {
+            let Some(task_definition) = task_definition.as_ref() else {
+                return Err(anyhow::anyhow!(
+                    "Failed to describe the task definition of the service {}",
+                    service_name
+                ));
+            };
+            let task_definition_arn = register_task_definition_with_size(
+                &client,
+                task_definition,
+                &task_definition_tags,
+                cpu,
+                memory,
+            )
+            .await?;
+            result = result.task_definition(task_definition_arn);
+        }
+
+        if minimum_healthy_percent.is_some() || maximum_percent.is_some() {
+            // Keep the current value for the omitted one
+            let current = service.deployment_configuration();
+            result = result.deployment_configuration(
+                DeploymentConfiguration::builder()
+                    .set_minimum_healthy_percent(
+                        minimum_healthy_percent
+                            .or(current.and_then(|current| current.minimum_healthy_percent())),
+                    )
+                    .set_maximum_percent(
+                        maximum_percent.or(current.and_then(|current| current.maximum_percent())),
+                    )
+                    .set_deployment_circuit_breaker(
+                        current.and_then(|current| current.deployment_circuit_breaker().cloned()),
+                    )
+                    .set_alarms(current.and_then(|current| current.alarms().cloned()))
+                    .build(),
+            );
+        }
+
+        if let Some(capacity_provider_strategy) = capacity_provider_strategy {
+            // Changing the capacity provider strategy needs a new deployment
+            result = result
+                .set_capacity_provider_strategy(Some(capacity_provider_strategy))
+                .force_new_deployment(true);
+        }
+
+        let result = result.send().await;
+        if let Err(error) = result {
+            return Err(get_sdk_error(error));
         }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::ECSServiceScalingComponent;
-    use crate::scaling_component::ScalingComponent;
-    use data_layer::ScalingComponentDefinition;
-    use std::collections::HashMap;
+    use super::*;
+    use crate::util::mock_server::start_mock_server;
+    use serde_json::json;
 
     // Purpose of the test is call apply function and fail test. just consists of test forms only.
     #[tokio::test]
@@ -108,4 +432,150 @@ mod test {
             .await;
         assert!(ecs_service_scaling_component.is_err());
     }
+
+    #[tokio::test]
+    async fn test_apply_desired_with_endpoint_url() {
+        let (endpoint_url, handle) = start_mock_server(vec![
+            (
+                200,
+                r#"{
+                    "services": [{
+                        "serviceName": "service",
+                        "desiredCount": 1,
+                        "runningCount": 1,
+                        "pendingCount": 0
+                    }],
+                    "failures": []
+                }"#,
+            ),
+            (
+                200,
+                r#"{ "service": { "serviceName": "service", "desiredCount": 3 } }"#,
+            ),
+        ])
+        .await;
+        let scaling_definition = ScalingComponentDefinition {
+            id: String::from("scaling-id"),
+            component_kind: String::from("amazon-ecs"),
+            metadata: HashMap::from([
+                ("region".to_string(), json!("us-east-1")),
+                ("access_key".to_string(), json!("access_key")),
+                ("secret_key".to_string(), json!("secret_key")),
+                ("endpoint_url".to_string(), json!(endpoint_url)),
+                ("cluster_name".to_string(), json!("cluster")),
+                ("service_name".to_string(), json!("service")),
+            ]),
+            ..Default::default()
+        };
+        let params = HashMap::from([("desired".to_string(), json!("$desired_count + 2"))]);
+        let result = ECSServiceScalingComponent::new(scaling_definition)
+            .apply(params)
+            .await;
+        assert!(result.is_ok());
+        let requests = handle.await.unwrap();
+        assert!(requests[0].contains(r#""services":["service"]"#));
+        assert!(requests[1].contains(r#""desiredCount":3"#));
+    }
+
+    #[tokio::test]
+    async fn test_apply_cpu_with_endpoint_url() {
+        let (endpoint_url, handle) = start_mock_server(vec![
+            (
+                200,
+                r#"{
+                    "services": [{
+                        "serviceName": "service",
+                        "taskDefinition": "arn:aws:ecs:us-east-1:123456789012:task-definition/family:1",
+                        "desiredCount": 1,
+                        "runningCount": 1,
+                        "pendingCount": 0
+                    }],
+                    "failures": []
+                }"#,
+            ),
+            (
+                200,
+                r#"{
+                    "taskDefinition": {
+                        "taskDefinitionArn": "arn:aws:ecs:us-east-1:123456789012:task-definition/family:1",
+                        "family": "family",
+                        "containerDefinitions": [{ "name": "app", "image": "nginx" }],
+                        "volumes": [{ "name": "data" }],
+                        "networkMode": "awsvpc",
+                        "requiresCompatibilities": ["FARGATE"],
+                        "ephemeralStorage": { "sizeInGiB": 30 },
+                        "runtimePlatform": { "cpuArchitecture": "ARM64", "operatingSystemFamily": "LINUX" },
+                        "cpu": "256",
+                        "memory": "512"
+                    },
+                    "tags": [{ "key": "team", "value": "platform" }]
+                }"#,
+            ),
+            (
+                200,
+                r#"{
+                    "taskDefinition": {
+                        "taskDefinitionArn": "arn:aws:ecs:us-east-1:123456789012:task-definition/family:2"
+                    }
+                }"#,
+            ),
+            (200, r#"{ "service": { "serviceName": "service" } }"#),
+        ])
+        .await;
+        let scaling_definition = ScalingComponentDefinition {
+            id: String::from("scaling-id"),
+            component_kind: String::from("amazon-ecs"),
+            metadata: HashMap::from([
+                ("region".to_string(), json!("us-east-1")),
+                ("access_key".to_string(), json!("access_key")),
+                ("secret_key".to_string(), json!("secret_key")),
+                ("endpoint_url".to_string(), json!(endpoint_url)),
+                ("cluster_name".to_string(), json!("cluster")),
+                ("service_name".to_string(), json!("service")),
+            ]),
+            ..Default::default()
+        };
+        let params = HashMap::from([("cpu".to_string(), json!("$cpu * 2"))]);
+        let result = ECSServiceScalingComponent::new(scaling_definition)
+            .apply(params)
+            .await;
+        assert!(result.is_ok());
+        let requests = handle.await.unwrap();
+        assert!(requests[1].contains(r#""include":["TAGS"]"#));
+        // Only cpu is changed and the other fields are copied
+        let register = &requests[2];
+        assert!(register.contains(r#""cpu":"512""#));
+        assert!(register.contains(r#""memory":"512""#));
+        assert!(register.contains(r#""tags":[{"key":"team","value":"platform"}]"#));
+        assert!(register.contains(r#""volumes":[{"name":"data"}]"#));
+        assert!(register.contains(r#""ephemeralStorage":{"sizeInGiB":30}"#));
+        assert!(register.contains(r#""cpuArchitecture":"ARM64""#));
+        assert!(requests[3].contains(
+            r#""taskDefinition":"arn:aws:ecs:us-east-1:123456789012:task-definition/family:2""#
+        ));
+    }
+
+    #[test]
+    fn test_get_capacity_provider_strategy() {
+        let strategy = get_capacity_provider_strategy(&json!([
+            { "capacity_provider": "FARGATE", "weight": 1, "base": 1 },
+            { "capacity_provider": "FARGATE_SPOT", "weight": 3 }
+        ]))
+        .unwrap();
+        assert_eq!(strategy.len(), 2);
+        assert_eq!(strategy[1].capacity_provider(), Some("FARGATE_SPOT"));
+        assert_eq!(strategy[1].weight(), 3);
+        assert_eq!(strategy[1].base(), 0);
+
+        // No weight
+        assert!(
+            get_capacity_provider_strategy(&json!([{ "capacity_provider": "FARGATE" }])).is_err()
+        );
+        // Two bases
+        assert!(get_capacity_provider_strategy(&json!([
+            { "capacity_provider": "FARGATE", "weight": 1, "base": 1 },
+            { "capacity_provider": "FARGATE_SPOT", "weight": 1, "base": 1 }
+        ]))
+        .is_err());
+    }
 }
@@ -1,18 +1,45 @@
-use super::ScalingComponent;
-use crate::util::aws::get_aws_config;
+/**
+ * [Scaling Component] AWS Lambda Function Scaling Component
+ *
+ * It requires the following metadata:
+ * - function_name: The name of the function
+ * - qualifier: The version or the alias for the provisioned concurrency (optional)
+ * - alias: The alias with the weighted routing (optional)
+ *   The provisioned concurrency is split between the versions of the alias by the routing weights
+ *   and the configs this component put for the versions no longer in the routing of the alias are deleted
+ * - region, access_key, secret_key (optional, see util/aws)
+ * It accepts the following parameters:
+ * - reserved_concurrency: The reserved concurrency of the function
+ * - provisioned_concurrency: The provisioned concurrency (0 deletes the config)
+ * - wait_until_ready: Wait until the status of the provisioned concurrency is READY (default: false)
+ * - wait_timeout_sec: The timeout of wait_until_ready (default: 60). The apply blocks the plan while waiting.
+ * The current state is available as $allocated_provisioned_concurrency
+ */
+use super::{
+    evaluate_expression_with_current_state, get_expression_language, get_param_expression,
+    ScalingComponent,
+};
+use crate::util::aws::{get_aws_config_with_metadata, get_sdk_error};
 use anyhow::{Ok, Result};
 use async_trait::async_trait;
 
 use aws_smithy_types::error::metadata::ProvideErrorMetadata;
 
-use aws_sdk_lambda::Client as LambdaClient;
+use aws_sdk_lambda::{types::ProvisionedConcurrencyStatusEnum, Client as LambdaClient};
 
 use data_layer::ScalingComponentDefinition;
-use serde_json::{json, Value};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::{collections::HashMap, time::Duration};
+use tokio::sync::Mutex;
+
+const DEFAULT_WAIT_TIMEOUT_SEC: u64 = 60;
+const WAIT_INTERVAL_SEC: u64 = 5;
+const NOT_FOUND_ERROR_CODE: &str = "ProvisionedConcurrencyConfigNotFoundException";
 
 pub struct LambdaFunctionScalingComponent {
     definition: ScalingComponentDefinition,
+    // The versions of the alias this component put the provisioned concurrency for
+    alias_versions: Mutex<Vec<String>>,
 }
 
 impl LambdaFunctionScalingComponent {
@@ -21,8 +48,174 @@ impl LambdaFunctionScalingComponent {
 
     // Functions
     pub fn new(definition: ScalingComponentDefinition) -> Self {
-        LambdaFunctionScalingComponent { definition }
+        LambdaFunctionScalingComponent {
+            definition,
+            alias_versions: Mutex::new(vec![]),
+        }
+    }
+}
+
+// Split the provisioned concurrency by the routing weights of the alias
+// The additional versions get the rounded share and the primary version gets the rest
+fn split_by_routing_weights(
+    provisioned_concurrency: i32,
+    primary_version: &str,
+    additional_version_weights: &HashMap<String, f64>,
+) -> Vec<(String, i32)> {
+    let mut versions: Vec<(String, i32)> = vec![];
+    let mut rest = provisioned_concurrency;
+    let mut additional_versions = additional_version_weights.iter().collect::<Vec<_>>();
+    additional_versions.sort_by(|a, b| a.0.cmp(b.0));
+    for (version, weight) in additional_versions {
+        let share = ((provisioned_concurrency as f64) * weight.clamp(0.0, 1.0)).round() as i32;
+        let share = share.min(rest);
+        rest -= share;
+        versions.push((version.clone(), share));
+    }
+    versions.insert(0, (primary_version.to_string(), rest));
+    versions
+}
+
+// The primary version and the additional version weights of the alias
+async fn get_alias_routing(
+    client: &LambdaClient,
+    function_name: &str,
+    alias: &str,
+) -> Result<(String, HashMap<String, f64>), anyhow::Error> {
+    let result = client
+        .get_alias()
+        .function_name(function_name)
+        .name(alias)
+        .send()
+        .await;
+    if let Err(error) = result {
+        return Err(get_sdk_error(error));
+    }
+    let result = result.unwrap();
+    let Some(primary_version) = result.function_version() else {
+        return Err(anyhow::anyhow!(
+            "Failed to get the alias {} - function_version none",
+            alias
+        ));
+    };
+    let additional_version_weights = result
+        .routing_config()
+        .and_then(|routing_config| routing_config.additional_version_weights())
+        .cloned()
+        .unwrap_or_default();
+    Ok((primary_version.to_string(), additional_version_weights))
+}
+
+async fn get_allocated_provisioned_concurrency(
+    client: &LambdaClient,
+    function_name: &str,
+    qualifiers: &[String],
+) -> Result<i64, anyhow::Error> {
+    let mut allocated: i64 = 0;
+    for qualifier in qualifiers {
+        let result = client
+            .get_provisioned_concurrency_config()
+            .function_name(function_name)
+            .qualifier(qualifier)
+            .send()
+            .await;
+        if let Err(error) = result {
+            if error.code() == Some(NOT_FOUND_ERROR_CODE) {
+                continue;
+            }
+            return Err(get_sdk_error(error));
+        }
+        allocated += result
+            .unwrap()
+            .allocated_provisioned_concurrent_executions()
+            .unwrap_or(0) as i64;
+    }
+    Ok(allocated)
+}
+
+async fn put_provisioned_concurrency(
+    client: &LambdaClient,
+    function_name: &str,
+    qualifier: &str,
+    provisioned_concurrency: i32,
+) -> Result<(), anyhow::Error> {
+    // The provisioned concurrency should be greater than 0, so 0 deletes the config
+    if provisioned_concurrency <= 0 {
+        let result = client
+            .delete_provisioned_concurrency_config()
+            .function_name(function_name)
+            .qualifier(qualifier)
+            .send()
+            .await;
+        if let Err(error) = result {
+            if error.code() == Some(NOT_FOUND_ERROR_CODE) {
+                return Ok(());
+            }
+            return Err(get_sdk_error(error));
+        }
+        return Ok(());
+    }
+    let result = client
+        .put_provisioned_concurrency_config()
+        .function_name(function_name)
+        .provisioned_concurrent_executions(provisioned_concurrency)
+        .qualifier(qualifier)
+        .send()
+        .await;
+    if let Err(error) = result {
+        return Err(get_sdk_error(error));
     }
+    Ok(())
+}
+
+// Poll GetProvisionedConcurrencyConfig until the status is READY
+async fn wait_until_ready(
+    client: &LambdaClient,
+    function_name: &str,
+    qualifiers: &[String],
+    timeout: Duration,
+) -> Result<(), anyhow::Error> {
+    let started_at = std::time::Instant::now();
+    for qualifier in qualifiers {
+        loop {
+            let result = client
+                .get_provisioned_concurrency_config()
+                .function_name(function_name)
+                .qualifier(qualifier)
+                .send()
+                .await;
+            if let Err(error) = result {
+                // The config is deleted
+                if error.code() == Some(NOT_FOUND_ERROR_CODE) {
+                    break;
+                }
+                return Err(get_sdk_error(error));
+            }
+            let result = result.unwrap();
+            match result.status() {
+                Some(ProvisionedConcurrencyStatusEnum::Ready) => break,
+                Some(ProvisionedConcurrencyStatusEnum::Failed) => {
+                    return Err(anyhow::anyhow!(
+                        "The provisioned concurrency of {}:{} is FAILED - {}",
+                        function_name,
+                        qualifier,
+                        result.status_reason().unwrap_or_default()
+                    ));
+                }
+                _ => {}
+            }
+            if started_at.elapsed() >= timeout {
+                return Err(anyhow::anyhow!(
+                    "The provisioned concurrency of {}:{} is not READY in {} seconds",
+                    function_name,
+                    qualifier,
+                    timeout.as_secs()
+                ));
+            }
+            tokio::time::sleep(Duration::from_secs(WAIT_INTERVAL_SEC)).await;
+        }
+    }
+    Ok(())
 }
 
 #[async_trait]
@@ -36,98 +229,171 @@ impl ScalingComponent for LambdaFunctionScalingComponent {
     async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
         let metadata: HashMap<String, Value> = self.definition.metadata.clone();
 
-        if let (
-            Some(Value::String(region)),
-            Some(Value::String(function_name)),
-            reserved_concurrency,
-            provisioned_concurrency,
-        ) = (
-            metadata.get("region"),
-            metadata.get("function_name"),
-            params
-                .get("reserved_concurrency")
-                .and_then(Value::as_i64)
-                .map(|v| v as i32),
-            params
-                .get("provisioned_concurrency")
-                .and_then(Value::as_i64)
-                .map(|v| v as i32),
-        ) {
-            let access_key = metadata
-                .get("access_key")
-                .map(|access_key| access_key.to_string());
-            let secret_key = metadata
-                .get("secret_key")
-                .map(|secret_key| secret_key.to_string());
-
-            let config =
-                get_aws_config(Some(region.to_string()), access_key, secret_key, None, None).await;
-            if config.is_err() {
-                let config_err = config.err().unwrap();
-                return Err(anyhow::anyhow!(config_err));
+        let Some(Value::String(function_name)) = metadata.get("function_name") else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+        let reserved_concurrency = params
+            .get("reserved_concurrency")
+            .and_then(Value::as_i64)
+            .map(|v| v as i32);
+        let provisioned_concurrency = params
+            .get("provisioned_concurrency")
+            .and_then(get_param_expression);
+        let qualifier = metadata
+            .get("qualifier")
+            .and_then(Value::as_str)
+            .filter(|qualifier| !qualifier.is_empty());
+        let alias = metadata
+            .get("alias")
+            .and_then(Value::as_str)
+            .filter(|alias| !alias.is_empty());
+        if provisioned_concurrency.is_some() && qualifier.is_none() && alias.is_none() {
+            return Err(anyhow::anyhow!(
+                "Invalid metadata - qualifier or alias is required for provisioned_concurrency"
+            ));
+        }
+
+        let config = get_aws_config_with_metadata(&metadata).await;
+        if config.is_err() {
+            let config_err = config.err().unwrap();
+            return Err(anyhow::anyhow!(config_err));
+        }
+        let shared_config = config.unwrap();
+
+        let client = LambdaClient::new(&shared_config);
+
+        // Set and put reserved concurrency if provided.
+        if let Some(reserved_concurrency) = reserved_concurrency {
+            let result = client
+                .put_function_concurrency()
+                .function_name(function_name)
+                .reserved_concurrent_executions(reserved_concurrency);
+            let result = result.send().await;
+            if let Err(error) = result {
+                return Err(get_sdk_error(error));
             }
-            let shared_config = config.unwrap();
-
-            let client = LambdaClient::new(&shared_config);
-
-            // Set and put reserved concurrency if provided.
-            if let Some(reserved_concurrency) = reserved_concurrency {
-                let result = client
-                    .put_function_concurrency()
-                    .function_name(function_name)
-                    .reserved_concurrent_executions(reserved_concurrency);
-                let result = result.send().await;
-                if result.is_err() {
-                    let error = result.err().unwrap();
-                    let meta = error.meta();
-                    let json = json!({
-                      "message": meta.message().unwrap_or(&error.to_string()),
-                      "code": meta.code(),
-                      "extras": meta.to_string()
-                    });
-                    return Err(anyhow::anyhow!(json));
-                }
+        }
+
+        let Some(provisioned_concurrency) = provisioned_concurrency else {
+            return Ok(());
+        };
+
+        // The versions of the alias or the qualifier
+        let alias_routing = match alias {
+            Some(alias) => Some(get_alias_routing(&client, function_name, alias).await?),
+            None => None,
+        };
+        let qualifiers = match alias_routing.as_ref() {
+            Some((primary_version, additional_version_weights)) => {
+                split_by_routing_weights(0, primary_version, additional_version_weights)
+                    .into_iter()
+                    .map(|(version, _)| version)
+                    .collect::<Vec<String>>()
             }
+            None => qualifier.into_iter().map(String::from).collect(),
+        };
 
-            // Set and put provisioned concurrency if provided regardless of qualifier.
-            if let Some(provisioned_concurrency) = provisioned_concurrency {
-                // Set qualifier if provided in meta data.
-                let qualifier = metadata
-                    .get("qualifier")
-                    .and_then(Value::as_str)
-                    .map(String::from)
-                    .unwrap_or_default();
-                // Error occurs when qualifier is not provided or assigned to empty string.
-                let result = client
-                    .put_provisioned_concurrency_config()
-                    .function_name(function_name)
-                    .provisioned_concurrent_executions(provisioned_concurrency)
-                    .qualifier(qualifier);
-                let result = result.send().await;
-                if result.is_err() {
-                    let error = result.err().unwrap();
-                    let meta = error.meta();
-                    let json = json!({
-                      "message": meta.message().unwrap_or(&error.to_string()),
-                      "code": meta.code(),
-                      "extras": meta.to_string()
-                    });
-                    return Err(anyhow::anyhow!(json));
+        // Evaluate the provisioned concurrency with the current state
+        let allocated_provisioned_concurrency =
+            get_allocated_provisioned_concurrency(&client, function_name, &qualifiers).await?;
+        let current_state_map = HashMap::from([(
+            "$allocated_provisioned_concurrency".to_string(),
+            allocated_provisioned_concurrency,
+        )]);
+        let provisioned_concurrency = evaluate_expression_with_current_state(
+            &provisioned_concurrency,
+            current_state_map,
+            get_expression_language(&params),
+        )
+        .await;
+        if provisioned_concurrency.is_err() {
+            return Err(provisioned_concurrency.unwrap_err());
+        }
+        let provisioned_concurrency = i32::try_from(provisioned_concurrency.unwrap());
+        if provisioned_concurrency.is_err()
+            || provisioned_concurrency
+                .as_ref()
+                .map_or(false, |value| *value < 0)
+        {
+            return Err(anyhow::anyhow!(
+                "Invalid provisioned_concurrency - {} should be between 0 and {}",
+                function_name,
+                i32::MAX
+            ));
+        }
+        let provisioned_concurrency = provisioned_concurrency.unwrap();
+
+        // The alias splits the provisioned concurrency between its versions by the routing weights
+        let targets = match alias_routing.as_ref() {
+            Some((primary_version, additional_version_weights)) => split_by_routing_weights(
+                provisioned_concurrency,
+                primary_version,
+                additional_version_weights,
+            ),
+            None => qualifiers
+                .iter()
+                .map(|qualifier| (qualifier.clone(), provisioned_concurrency))
+                .collect(),
+        };
+        for (qualifier, provisioned_concurrency) in targets.iter() {
+            put_provisioned_concurrency(
+                &client,
+                function_name,
+                qualifier,
+                *provisioned_concurrency,
+            )
+            .await?;
+        }
+        // Delete the configs this component put for the versions that left the routing of the alias
+        // (e.g. the previous version after the alias moved). The configs of other versions are kept.
+        if alias_routing.is_some() {
+            let mut alias_versions = self.alias_versions.lock().await;
+            for (version, _) in targets.iter() {
+                if !alias_versions.contains(version) {
+                    alias_versions.push(version.clone());
                 }
             }
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Invalid metadata"))
+            let stale_versions = alias_versions
+                .iter()
+                .filter(|version| !targets.iter().any(|(qualifier, _)| qualifier == *version))
+                .cloned()
+                .collect::<Vec<String>>();
+            for version in stale_versions.iter() {
+                put_provisioned_concurrency(&client, function_name, version, 0).await?;
+                alias_versions.retain(|alias_version| alias_version != version);
+            }
+        }
+
+        if params
+            .get("wait_until_ready")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            let timeout = params
+                .get("wait_timeout_sec")
+                .and_then(Value::as_u64)
+                .unwrap_or(DEFAULT_WAIT_TIMEOUT_SEC);
+            let qualifiers = targets
+                .into_iter()
+                .map(|(qualifier, _)| qualifier)
+                .collect::<Vec<String>>();
+            wait_until_ready(
+                &client,
+                function_name,
+                &qualifiers,
+                Duration::from_secs(timeout),
+            )
+            .await?;
         }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::LambdaFunctionScalingComponent;
-    use crate::scaling_component::ScalingComponent;
-    use data_layer::ScalingComponentDefinition;
-    use std::collections::HashMap;
+    use super::*;
+    use crate::util::mock_server::start_mock_server;
+    use serde_json::json;
 
     // Purpose of the test is call apply function and fail test. just consists of test forms only.
     #[tokio::test]
@@ -148,4 +414,112 @@ mod test {
                 .await;
         assert!(lambda_function_scaling_component.is_err());
     }
+
+    #[test]
+    fn test_split_by_routing_weights() {
+        let weights = HashMap::from([("2".to_string(), 0.25)]);
+        assert_eq!(
+            split_by_routing_weights(10, "1", &weights),
+            vec![("1".to_string(), 7), ("2".to_string(), 3)]
+        );
+        assert_eq!(
+            split_by_routing_weights(10, "1", &HashMap::new()),
+            vec![("1".to_string(), 10)]
+        );
+        assert_eq!(
+            split_by_routing_weights(0, "1", &weights),
+            vec![("1".to_string(), 0), ("2".to_string(), 0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_alias_with_endpoint_url() {
+        let (endpoint_url, handle) = start_mock_server(vec![
+            // The first apply puts the config of the version 1
+            (200, r#"{ "Name": "live", "FunctionVersion": "1" }"#),
+            (
+                200,
+                r#"{ "AllocatedProvisionedConcurrentExecutions": 5, "Status": "READY" }"#,
+            ),
+            (
+                202,
+                r#"{ "RequestedProvisionedConcurrentExecutions": 10, "Status": "IN_PROGRESS" }"#,
+            ),
+            // The second apply puts the config of the version 2 after the alias moved
+            (200, r#"{ "Name": "live", "FunctionVersion": "2" }"#),
+            (
+                200,
+                r#"{ "AllocatedProvisionedConcurrentExecutions": 0, "Status": "READY" }"#,
+            ),
+            (
+                202,
+                r#"{ "RequestedProvisionedConcurrentExecutions": 10, "Status": "IN_PROGRESS" }"#,
+            ),
+            (204, ""),
+        ])
+        .await;
+        let scaling_definition = ScalingComponentDefinition {
+            id: String::from("scaling-id"),
+            component_kind: String::from("aws-lambda"),
+            metadata: HashMap::from([
+                ("region".to_string(), json!("us-east-1")),
+                ("access_key".to_string(), json!("access_key")),
+                ("secret_key".to_string(), json!("secret_key")),
+                ("endpoint_url".to_string(), json!(endpoint_url)),
+                ("function_name".to_string(), json!("function")),
+                ("alias".to_string(), json!("live")),
+            ]),
+            ..Default::default()
+        };
+        let params = HashMap::from([(
+            "provisioned_concurrency".to_string(),
+            json!("$allocated_provisioned_concurrency * 2"),
+        )]);
+        let scaling_component = LambdaFunctionScalingComponent::new(scaling_definition);
+        let result = scaling_component.apply(params).await;
+        assert!(result.is_ok());
+        let params = HashMap::from([("provisioned_concurrency".to_string(), json!(10))]);
+        let result = scaling_component.apply(params).await;
+        assert!(result.is_ok());
+        let requests = handle.await.unwrap();
+        assert_eq!(requests.len(), 7);
+        assert!(requests[2].starts_with("PUT "));
+        assert!(requests[2].contains("Qualifier=1"));
+        assert!(requests[2].contains(r#""ProvisionedConcurrentExecutions":10"#));
+        assert!(requests[5].starts_with("PUT "));
+        assert!(requests[5].contains("Qualifier=2"));
+        // Only the config this component put for the version 1 is deleted
+        assert!(requests[6].starts_with("DELETE "));
+        assert!(requests[6].contains("Qualifier=1"));
+    }
+
+    #[tokio::test]
+    async fn test_apply_out_of_range_provisioned_concurrency() {
+        let (endpoint_url, handle) = start_mock_server(vec![(
+            200,
+            r#"{ "AllocatedProvisionedConcurrentExecutions": 0, "Status": "READY" }"#,
+        )])
+        .await;
+        let scaling_definition = ScalingComponentDefinition {
+            id: String::from("scaling-id"),
+            component_kind: String::from("aws-lambda"),
+            metadata: HashMap::from([
+                ("region".to_string(), json!("us-east-1")),
+                ("access_key".to_string(), json!("access_key")),
+                ("secret_key".to_string(), json!("secret_key")),
+                ("endpoint_url".to_string(), json!(endpoint_url)),
+                ("function_name".to_string(), json!("function")),
+                ("qualifier".to_string(), json!("live")),
+            ]),
+            ..Default::default()
+        };
+        let params = HashMap::from([("provisioned_concurrency".to_string(), json!(-1))]);
+        let result = LambdaFunctionScalingComponent::new(scaling_definition)
+            .apply(params)
+            .await;
+        assert!(result.is_err());
+        // The config is not put
+        let requests = handle.await.unwrap();
+        assert_eq!(requests.len(), 1);
+    }
 }
@@ -0,0 +1,467 @@
+/**
+ * [Scaling Component] Google Cloud SQL Instance Scaling Component
+ *
+ * It requires the following metadata:
+ * - project_name, instance_name: The primary instance
+ * - read_replica_name_prefix (optional): The name prefix of the read replicas (default: {instance_name}-replica)
+ * - operation_timeout_sec (optional): The time to wait for the operations of an apply (default: 1800)
+ * - api_base_url (optional): The Cloud SQL Admin API base URL (default: https://sqladmin.googleapis.com)
+ * It accepts the following parameters:
+ * - tier: The machine type of the primary instance (e.g. db-custom-2-7680)
+ * - read_replica_count: The number of the read replicas named {read_replica_name_prefix}-{n}
+ * - read_replica_tier (optional): The machine type of the new read replicas (default: the tier of the primary instance)
+ *
+ * Cloud SQL runs one operation at a time on an instance, so an apply makes the changes one by one
+ * and waits for each operation to be DONE
+ * - the tier first, then the read replicas are created or deleted one at a time until read_replica_count is reached
+ * - the other read replicas (e.g. created by the users) are neither counted nor deleted
+ * If the operations are not DONE in operation_timeout_sec, the apply returns an error with the changes made so far.
+ */
+use super::super::util::google_cloud::get_gcp_response_body;
+use super::super::util::google_cloud::google_cloud_sql_instance_helper::{
+    call_delete_cloud_sql_instance, call_get_cloud_sql_instance, call_get_cloud_sql_operation,
+    call_insert_cloud_sql_instance, call_patch_cloud_sql_instance, CloudSqlInstanceSetting,
+};
+use super::ScalingComponent;
+use anyhow::{Ok, Result};
+use async_trait::async_trait;
+use data_layer::ScalingComponentDefinition;
+use serde_json::{json, Value};
+use std::{collections::HashMap, time::Duration};
+use tokio::time::Instant;
+use tracing::debug;
+
+const DEFAULT_OPERATION_TIMEOUT_SEC: u64 = 30 * 60;
+const OPERATION_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct CloudSqlInstanceScalingComponent {
+    definition: ScalingComponentDefinition,
+}
+
+impl CloudSqlInstanceScalingComponent {
+    // Static variables
+    pub const SCALING_KIND: &'static str = "google-cloud-sql";
+
+    // Functions
+    pub fn new(definition: ScalingComponentDefinition) -> Self {
+        CloudSqlInstanceScalingComponent { definition }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ReadReplicaChange {
+    Create(String),
+    Delete(String),
+    None,
+}
+
+#[async_trait]
+impl ScalingComponent for CloudSqlInstanceScalingComponent {
+    fn get_scaling_component_kind(&self) -> &str {
+        &self.definition.component_kind
+    }
+
+    fn get_id(&self) -> &str {
+        &self.definition.id
+    }
+
+    async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
+        let metadata: HashMap<String, Value> = self.definition.metadata.clone();
+
+        let (Some(Value::String(project_name)), Some(Value::String(instance_name))) =
+            (metadata.get("project_name"), metadata.get("instance_name"))
+        else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+        let read_replica_name_prefix = metadata
+            .get("read_replica_name_prefix")
+            .and_then(Value::as_str)
+            .map(|prefix| prefix.to_string())
+            .unwrap_or(format!("{}-replica", instance_name));
+        let tier = params.get("tier").and_then(Value::as_str);
+        let read_replica_count = params.get("read_replica_count").and_then(Value::as_i64);
+        let read_replica_tier = params.get("read_replica_tier").and_then(Value::as_str);
+        if tier.is_none() && read_replica_count.is_none() {
+            return Err(anyhow::anyhow!(
+                "Invalid params - tier or read_replica_count is required"
+            ));
+        }
+        if read_replica_count.map_or(false, |count| count < 0) {
+            return Err(anyhow::anyhow!(
+                "Invalid params - read_replica_count should be greater than or equal to 0"
+            ));
+        }
+
+        let operation_timeout_sec = metadata
+            .get("operation_timeout_sec")
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_OPERATION_TIMEOUT_SEC);
+        let deadline = Instant::now() + Duration::from_secs(operation_timeout_sec);
+
+        let cloud_sql_instance_setting = CloudSqlInstanceSetting {
+            api_base_url: metadata
+                .get("api_base_url")
+                .and_then(Value::as_str)
+                .map(|api_base_url| api_base_url.to_string()),
+            project_name: project_name.to_string(),
+            instance_name: instance_name.to_string(),
+            payload: None,
+        };
+
+        let response = call_get_cloud_sql_instance(cloud_sql_instance_setting.clone()).await;
+        let body = get_gcp_response_body(response, "cloud sql instance get").await?;
+        let instance = serde_json::from_str::<Value>(&body);
+        if instance.is_err() {
+            return Err(anyhow::anyhow!(json!({
+                "message": "GCP API Call Error - cloud sql instance get",
+                "code": "500",
+                "extras": instance.unwrap_err().to_string()
+            })));
+        }
+        let instance = instance.unwrap();
+        let state = instance.get("state").and_then(Value::as_str);
+        if state != Some("RUNNABLE") {
+            return Err(anyhow::anyhow!(
+                "The instance {} is not RUNNABLE - {:?}",
+                instance_name,
+                state
+            ));
+        }
+        let current_tier = instance
+            .get("settings")
+            .and_then(|settings| settings.get("tier"))
+            .and_then(Value::as_str);
+
+        // The changes made so far for the error of the partial apply
+        let mut applied_changes: Vec<String> = Vec::new();
+
+        // Change the tier of the primary instance
+        if let Some(tier) = tier {
+            if Some(tier) != current_tier {
+                let mut cloud_sql_instance_setting = cloud_sql_instance_setting.clone();
+                cloud_sql_instance_setting.payload = Some(json!({ "settings": { "tier": tier } }));
+                let response =
+                    call_patch_cloud_sql_instance(cloud_sql_instance_setting.clone()).await;
+                let body = get_gcp_response_body(response, "cloud sql instance patch").await?;
+                wait_for_operation(&cloud_sql_instance_setting, &body, deadline).await?;
+                debug!(
+                    "[google-cloud-sql] The tier of {} is changed to {}",
+                    instance_name, tier
+                );
+                applied_changes.push(format!("tier: {}", tier));
+            }
+        }
+
+        // Create or delete the read replicas one by one
+        let Some(read_replica_count) = read_replica_count else {
+            return Ok(());
+        };
+        let mut replica_names = instance
+            .get("replicaNames")
+            .and_then(Value::as_array)
+            .map(|replica_names| {
+                replica_names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|replica_name| replica_name.to_string())
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
+        loop {
+            let result = match get_read_replica_change(
+                &replica_names,
+                &read_replica_name_prefix,
+                read_replica_count as usize,
+            ) {
+                ReadReplicaChange::Create(replica_name) => {
+                    let mut cloud_sql_instance_setting = cloud_sql_instance_setting.clone();
+                    cloud_sql_instance_setting.payload = Some(json!({
+                        "name": replica_name,
+                        "masterInstanceName": instance_name,
+                        "region": instance.get("region"),
+                        "databaseVersion": instance.get("databaseVersion"),
+                        "settings": { "tier": read_replica_tier.or(tier).or(current_tier) }
+                    }));
+                    let response =
+                        call_insert_cloud_sql_instance(cloud_sql_instance_setting.clone()).await;
+                    let result = create_or_delete_read_replica(
+                        response,
+                        "cloud sql read replica insert",
+                        &cloud_sql_instance_setting,
+                        deadline,
+                    )
+                    .await;
+                    result.map(|_| {
+                        applied_changes.push(format!("created {}", replica_name));
+                        replica_names.push(replica_name);
+                    })
+                }
+                ReadReplicaChange::Delete(replica_name) => {
+                    let mut replica_setting = cloud_sql_instance_setting.clone();
+                    replica_setting.instance_name = replica_name.clone();
+                    let response = call_delete_cloud_sql_instance(replica_setting).await;
+                    let result = create_or_delete_read_replica(
+                        response,
+                        "cloud sql read replica delete",
+                        &cloud_sql_instance_setting,
+                        deadline,
+                    )
+                    .await;
+                    result.map(|_| {
+                        applied_changes.push(format!("deleted {}", replica_name));
+                        replica_names.retain(|name| name != &replica_name);
+                    })
+                }
+                ReadReplicaChange::None => break,
+            };
+            if let Err(error) = result {
+                return Err(get_partial_apply_error(&applied_changes, error));
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn create_or_delete_read_replica(
+    response: Result<reqwest::Response, reqwest::Error>,
+    message: &str,
+    cloud_sql_instance_setting: &CloudSqlInstanceSetting,
+    deadline: Instant,
+) -> Result<()> {
+    let body = get_gcp_response_body(response, message).await?;
+    wait_for_operation(cloud_sql_instance_setting, &body, deadline).await
+}
+
+fn get_partial_apply_error(applied_changes: &[String], error: anyhow::Error) -> anyhow::Error {
+    if applied_changes.is_empty() {
+        return error;
+    }
+    anyhow::anyhow!(
+        "Partially applied ({}) - {}",
+        applied_changes.join(", "),
+        error
+    )
+}
+
+// Wait for the operation in the response body to be DONE until the deadline
+async fn wait_for_operation(
+    cloud_sql_instance_setting: &CloudSqlInstanceSetting,
+    body: &str,
+    deadline: Instant,
+) -> Result<()> {
+    let mut operation = serde_json::from_str::<Value>(body).unwrap_or_default();
+    let Some(operation_name) = operation
+        .get("name")
+        .and_then(Value::as_str)
+        .map(|operation_name| operation_name.to_string())
+    else {
+        return Err(anyhow::anyhow!("No operation in the response: {}", body));
+    };
+    let mut is_first_poll = true;
+    loop {
+        if operation.get("status").and_then(Value::as_str) == Some("DONE") {
+            if let Some(error) = operation.get("error") {
+                return Err(anyhow::anyhow!(
+                    "The operation {} failed - {}",
+                    operation_name,
+                    error
+                ));
+            }
+            return Ok(());
+        }
+        if !is_first_poll {
+            if Instant::now() + OPERATION_POLL_INTERVAL > deadline {
+                return Err(anyhow::anyhow!(
+                    "The operation {} is not DONE in operation_timeout_sec",
+                    operation_name
+                ));
+            }
+            tokio::time::sleep(OPERATION_POLL_INTERVAL).await;
+        }
+        is_first_poll = false;
+        let response =
+            call_get_cloud_sql_operation(cloud_sql_instance_setting.clone(), &operation_name).await;
+        let body = get_gcp_response_body(response, "cloud sql operation get").await?;
+        operation = serde_json::from_str::<Value>(&body).unwrap_or_default();
+    }
+}
+
+// The index of the read replica named {prefix}-{n}
+fn get_read_replica_index(replica_name: &str, prefix: &str) -> Option<u32> {
+    replica_name
+        .strip_prefix(prefix)
+        .and_then(|suffix| suffix.strip_prefix('-'))
+        .and_then(|index| index.parse::<u32>().ok())
+}
+
+// Only the replicas named {prefix}-{n} are counted.
+// Create the lowest unused index, or delete the highest index of them.
+fn get_read_replica_change(
+    replica_names: &[String],
+    prefix: &str,
+    read_replica_count: usize,
+) -> ReadReplicaChange {
+    let indexes = replica_names
+        .iter()
+        .filter_map(|replica_name| get_read_replica_index(replica_name, prefix))
+        .collect::<Vec<u32>>();
+    if indexes.len() < read_replica_count {
+        let index = (1..).find(|index| !indexes.contains(index)).unwrap_or(1);
+        return ReadReplicaChange::Create(format!("{}-{}", prefix, index));
+    }
+    if indexes.len() > read_replica_count {
+        if let Some(index) = indexes.iter().max() {
+            return ReadReplicaChange::Delete(format!("{}-{}", prefix, index));
+        }
+    }
+    ReadReplicaChange::None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::mock_server::start_mock_server;
+
+    const INSTANCE_RESPONSE: &str = r#"{
+        "name": "instance-1",
+        "state": "RUNNABLE",
+        "region": "asia-northeast3",
+        "databaseVersion": "MYSQL_8_0",
+        "settings": { "tier": "db-custom-2-7680" },
+        "replicaNames": ["instance-1-replica-1", "manual-replica"]
+    }"#;
+
+    fn get_definition(api_base_url: &str) -> ScalingComponentDefinition {
+        ScalingComponentDefinition {
+            id: String::from("scaling-id"),
+            component_kind: String::from(CloudSqlInstanceScalingComponent::SCALING_KIND),
+            metadata: HashMap::from([
+                ("project_name".to_string(), json!("wave-autoscale-test")),
+                ("instance_name".to_string(), json!("instance-1")),
+                ("api_base_url".to_string(), json!(api_base_url)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_read_replica_change() {
+        let replica_names = vec![
+            "db-replica-1".to_string(),
+            "db-replica-3".to_string(),
+            "manual-replica".to_string(),
+        ];
+        assert_eq!(
+            get_read_replica_change(&replica_names, "db-replica", 4),
+            ReadReplicaChange::Create("db-replica-2".to_string())
+        );
+        assert_eq!(
+            get_read_replica_change(&replica_names, "db-replica", 1),
+            ReadReplicaChange::Delete("db-replica-3".to_string())
+        );
+        assert_eq!(
+            get_read_replica_change(&replica_names, "db-replica", 3),
+            ReadReplicaChange::None
+        );
+        // The replicas not named {prefix}-{n} are neither counted nor deleted
+        assert_eq!(
+            get_read_replica_change(&["manual-replica".to_string()], "db-replica", 0),
+            ReadReplicaChange::None
+        );
+        assert_eq!(
+            get_read_replica_change(&["manual-replica".to_string()], "db-replica", 1),
+            ReadReplicaChange::Create("db-replica-1".to_string())
+        );
+    }
+
+    fn get_payload(request: &str) -> Value {
+        // "METHOD PATH BODY"
+        serde_json::from_str::<Value>(request.splitn(3, ' ').nth(2).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_apply_tier_and_read_replica_count_with_mock() {
+        let (api_base_url, handle) = start_mock_server(vec![
+            (200, INSTANCE_RESPONSE),
+            (200, r#"{"name":"op-1","status":"PENDING"}"#),
+            (200, r#"{"name":"op-1","status":"DONE"}"#),
+            (200, r#"{"name":"op-2","status":"PENDING"}"#),
+            (200, r#"{"name":"op-2","status":"DONE"}"#),
+            (200, r#"{"name":"op-3","status":"DONE"}"#),
+        ])
+        .await;
+        let component = CloudSqlInstanceScalingComponent::new(get_definition(&api_base_url));
+        let params = HashMap::from([
+            ("tier".to_string(), json!("db-custom-4-15360")),
+            ("read_replica_count".to_string(), json!(3)),
+        ]);
+        assert!(component.apply(params).await.is_ok());
+        let requests = handle.await.unwrap();
+        assert_eq!(requests.len(), 6);
+        // The tier is changed first and its operation is waited for
+        assert_eq!(
+            requests[1],
+            "PATCH /v1/projects/wave-autoscale-test/instances/instance-1 {\"settings\":{\"tier\":\"db-custom-4-15360\"}}"
+        );
+        assert!(requests[2].starts_with("GET /v1/projects/wave-autoscale-test/operations/op-1"));
+        // Then the read replicas are created in the same apply (manual-replica is not counted)
+        assert!(requests[3].starts_with("POST /v1/projects/wave-autoscale-test/instances "));
+        let payload = get_payload(&requests[3]);
+        assert_eq!(payload["name"], json!("instance-1-replica-2"));
+        assert_eq!(payload["masterInstanceName"], json!("instance-1"));
+        assert_eq!(payload["settings"]["tier"], json!("db-custom-4-15360"));
+        assert!(requests[4].starts_with("GET /v1/projects/wave-autoscale-test/operations/op-2"));
+        assert_eq!(
+            get_payload(&requests[5])["name"],
+            json!("instance-1-replica-3")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_read_replica_count_scale_in_with_mock() {
+        let (api_base_url, handle) = start_mock_server(vec![
+            (200, INSTANCE_RESPONSE),
+            (200, r#"{"name":"op-1","status":"DONE"}"#),
+        ])
+        .await;
+        let component = CloudSqlInstanceScalingComponent::new(get_definition(&api_base_url));
+        let params = HashMap::from([("read_replica_count".to_string(), json!(0))]);
+        assert!(component.apply(params).await.is_ok());
+        let requests = handle.await.unwrap();
+        // manual-replica is not deleted
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].starts_with(
+            "DELETE /v1/projects/wave-autoscale-test/instances/instance-1-replica-1 "
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_apply_failed_operation_with_mock() {
+        let (api_base_url, handle) = start_mock_server(vec![
+            (200, INSTANCE_RESPONSE),
+            (200, r#"{"name":"op-1","status":"DONE"}"#),
+            (
+                200,
+                r#"{"name":"op-2","status":"DONE","error":{"errors":[{"code":"QUOTA_EXCEEDED"}]}}"#,
+            ),
+        ])
+        .await;
+        let component = CloudSqlInstanceScalingComponent::new(get_definition(&api_base_url));
+        let params = HashMap::from([
+            ("tier".to_string(), json!("db-custom-4-15360")),
+            ("read_replica_count".to_string(), json!(2)),
+        ]);
+        let error = component.apply(params).await.unwrap_err().to_string();
+        assert!(error.starts_with("Partially applied (tier: db-custom-4-15360)"));
+        assert!(error.contains("QUOTA_EXCEEDED"));
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_apply_invalid_params() {
+        let component = CloudSqlInstanceScalingComponent::new(get_definition("http://127.0.0.1:1"));
+        assert!(component.apply(HashMap::new()).await.is_err());
+        let params = HashMap::from([("read_replica_count".to_string(), json!(-1))]);
+        assert!(component.apply(params).await.is_err());
+    }
+}
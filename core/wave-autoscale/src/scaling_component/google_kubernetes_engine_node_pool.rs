@@ -0,0 +1,244 @@
+/**
+ * [Scaling Component] Google Kubernetes Engine Node Pool Scaling Component
+ *
+ * It requires the following metadata:
+ * - project_name, location_name, cluster_name, node_pool_name
+ * - api_base_url (optional): The container API base URL (default: https://container.googleapis.com)
+ * It accepts the following parameters:
+ * - node_count: The node count per zone of the node pool (setSize)
+ * - min_node_count, max_node_count, autoscaling_enabled: The cluster autoscaler of the node pool (setAutoscaling)
+ * GKE runs one operation at a time on a cluster, so node_count can't be used with the autoscaling params.
+ */
+use super::super::util::google_cloud::get_gcp_response_body;
+use super::super::util::google_cloud::google_kubernetes_engine_node_pool_helper::{
+    call_get_gke_node_pool, call_post_gke_node_pool_set_autoscaling,
+    call_post_gke_node_pool_set_size, GkeNodePoolSetting,
+};
+use super::ScalingComponent;
+use anyhow::{Ok, Result};
+use async_trait::async_trait;
+use data_layer::ScalingComponentDefinition;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+pub struct GkeNodePoolScalingComponent {
+    definition: ScalingComponentDefinition,
+}
+
+impl GkeNodePoolScalingComponent {
+    // Static variables
+    pub const SCALING_KIND: &'static str = "google-kubernetes-engine-node-pool";
+
+    // Functions
+    pub fn new(definition: ScalingComponentDefinition) -> Self {
+        GkeNodePoolScalingComponent { definition }
+    }
+}
+
+#[async_trait]
+impl ScalingComponent for GkeNodePoolScalingComponent {
+    fn get_scaling_component_kind(&self) -> &str {
+        &self.definition.component_kind
+    }
+
+    fn get_id(&self) -> &str {
+        &self.definition.id
+    }
+
+    async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
+        let metadata: HashMap<String, Value> = self.definition.metadata.clone();
+
+        let (
+            Some(Value::String(project_name)),
+            Some(Value::String(location_name)),
+            Some(Value::String(cluster_name)),
+            Some(Value::String(node_pool_name)),
+        ) = (
+            metadata.get("project_name"),
+            metadata.get("location_name"),
+            metadata.get("cluster_name"),
+            metadata.get("node_pool_name"),
+        ) else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+        let node_count = params.get("node_count").and_then(Value::as_i64);
+        let min_node_count = params.get("min_node_count").and_then(Value::as_i64);
+        let max_node_count = params.get("max_node_count").and_then(Value::as_i64);
+        let autoscaling_enabled = params.get("autoscaling_enabled").and_then(Value::as_bool);
+        let has_autoscaling_params =
+            min_node_count.is_some() || max_node_count.is_some() || autoscaling_enabled.is_some();
+        if node_count.is_none() && !has_autoscaling_params {
+            return Err(anyhow::anyhow!("Invalid params"));
+        }
+        if node_count.is_some() && has_autoscaling_params {
+            return Err(anyhow::anyhow!(
+                "Invalid params - node_count can't be used with min_node_count, max_node_count or autoscaling_enabled"
+            ));
+        }
+
+        let gke_node_pool_setting = GkeNodePoolSetting {
+            api_base_url: metadata
+                .get("api_base_url")
+                .and_then(Value::as_str)
+                .map(|api_base_url| api_base_url.to_string()),
+            project_name: project_name.to_string(),
+            location_name: location_name.to_string(),
+            cluster_name: cluster_name.to_string(),
+            node_pool_name: node_pool_name.to_string(),
+            payload: None,
+        };
+
+        // setSize
+        if let Some(node_count) = node_count {
+            if node_count < 0 {
+                return Err(anyhow::anyhow!(
+                    "Invalid params - node_count should be greater than or equal to 0"
+                ));
+            }
+            let mut gke_node_pool_setting = gke_node_pool_setting.clone();
+            gke_node_pool_setting.payload = Some(json!({ "nodeCount": node_count }));
+            let response = call_post_gke_node_pool_set_size(gke_node_pool_setting).await;
+            get_gcp_response_body(response, "node pool setSize").await?;
+            return Ok(());
+        }
+
+        // setAutoscaling replaces the autoscaling config, so merge it with the current one
+        let response = call_get_gke_node_pool(gke_node_pool_setting.clone()).await;
+        let body = get_gcp_response_body(response, "node pool get").await?;
+        let node_pool = serde_json::from_str::<Value>(&body);
+        if node_pool.is_err() {
+            return Err(anyhow::anyhow!(json!({
+                "message": "GCP API Call Error - node pool get",
+                "code": "500",
+                "extras": node_pool.unwrap_err().to_string()
+            })));
+        }
+        let node_pool = node_pool.unwrap();
+        let autoscaling = get_autoscaling_payload(
+            node_pool.get("autoscaling"),
+            autoscaling_enabled,
+            min_node_count,
+            max_node_count,
+        )?;
+        let mut gke_node_pool_setting = gke_node_pool_setting.clone();
+        gke_node_pool_setting.payload = Some(json!({ "autoscaling": autoscaling }));
+        let response = call_post_gke_node_pool_set_autoscaling(gke_node_pool_setting).await;
+        get_gcp_response_body(response, "node pool setAutoscaling").await?;
+        Ok(())
+    }
+}
+
+fn get_autoscaling_payload(
+    current_autoscaling: Option<&Value>,
+    autoscaling_enabled: Option<bool>,
+    min_node_count: Option<i64>,
+    max_node_count: Option<i64>,
+) -> Result<Value> {
+    let mut autoscaling = match current_autoscaling {
+        Some(Value::Object(current_autoscaling)) => current_autoscaling.clone(),
+        _ => serde_json::Map::new(),
+    };
+    // The autoscaling params enable the autoscaler unless autoscaling_enabled is false
+    let enabled = autoscaling_enabled.unwrap_or(true);
+    autoscaling.insert("enabled".to_string(), json!(enabled));
+    if !enabled {
+        return Ok(Value::Object(autoscaling));
+    }
+    if let Some(min_node_count) = min_node_count {
+        autoscaling.insert("minNodeCount".to_string(), json!(min_node_count));
+    }
+    if let Some(max_node_count) = max_node_count {
+        autoscaling.insert("maxNodeCount".to_string(), json!(max_node_count));
+    }
+    let min_node_count = autoscaling
+        .get("minNodeCount")
+        .and_then(Value::as_i64)
+        .unwrap_or(0);
+    let Some(max_node_count) = autoscaling.get("maxNodeCount").and_then(Value::as_i64) else {
+        return Err(anyhow::anyhow!(
+            "Invalid params - max_node_count is required to enable the autoscaler"
+        ));
+    };
+    if min_node_count < 0 || min_node_count > max_node_count || max_node_count < 1 {
+        return Err(anyhow::anyhow!(
+            "Invalid params - min_node_count({}) and max_node_count({}) should be 0 <= min <= max, 1 <= max",
+            min_node_count,
+            max_node_count
+        ));
+    }
+    Ok(Value::Object(autoscaling))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::mock_server::start_mock_server;
+
+    fn get_definition(api_base_url: &str) -> ScalingComponentDefinition {
+        ScalingComponentDefinition {
+            id: String::from("scaling-id"),
+            component_kind: String::from(GkeNodePoolScalingComponent::SCALING_KIND),
+            metadata: HashMap::from([
+                ("project_name".to_string(), json!("wave-autoscale-test")),
+                ("location_name".to_string(), json!("asia-northeast3")),
+                ("cluster_name".to_string(), json!("cluster-1")),
+                ("node_pool_name".to_string(), json!("pool-1")),
+                ("api_base_url".to_string(), json!(api_base_url)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_autoscaling_payload() {
+        let current_autoscaling = json!({
+            "enabled": true,
+            "minNodeCount": 1,
+            "maxNodeCount": 3,
+            "locationPolicy": "BALANCED"
+        });
+        let autoscaling =
+            get_autoscaling_payload(Some(&current_autoscaling), None, None, Some(5)).unwrap();
+        assert_eq!(
+            autoscaling,
+            json!({
+                "enabled": true,
+                "minNodeCount": 1,
+                "maxNodeCount": 5,
+                "locationPolicy": "BALANCED"
+            })
+        );
+        let autoscaling =
+            get_autoscaling_payload(Some(&current_autoscaling), Some(false), None, None).unwrap();
+        assert_eq!(autoscaling["enabled"], json!(false));
+        // err: no max_node_count to enable the autoscaler
+        assert!(get_autoscaling_payload(None, None, Some(1), None).is_err());
+        // err: min is greater than max
+        assert!(get_autoscaling_payload(Some(&current_autoscaling), None, Some(4), None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_apply_invalid_params() {
+        let component = GkeNodePoolScalingComponent::new(get_definition("http://127.0.0.1:1"));
+        assert!(component.apply(HashMap::new()).await.is_err());
+        let params = HashMap::from([
+            ("node_count".to_string(), json!(3)),
+            ("max_node_count".to_string(), json!(5)),
+        ]);
+        assert!(component.apply(params).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_apply_set_size_with_mock() {
+        let (api_base_url, handle) = start_mock_server(vec![(200, "{}")]).await;
+
+        let component = GkeNodePoolScalingComponent::new(get_definition(&api_base_url));
+        let params = HashMap::from([("node_count".to_string(), json!(3))]);
+        assert!(component.apply(params).await.is_ok());
+        let requests = handle.await.unwrap();
+        assert_eq!(
+            requests[0],
+            "POST /v1/projects/wave-autoscale-test/locations/asia-northeast3/clusters/cluster-1/nodePools/pool-1:setSize {\"nodeCount\":3}"
+        );
+    }
+}
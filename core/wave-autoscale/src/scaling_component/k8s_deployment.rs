@@ -3,18 +3,22 @@
  *
  * This component is used to scale a Kubernetes Deployment
  * It requires the following metadata:
- * - api_server_endpoint: The API server endpoint
  * - namespace: The namespace of the deployment
  * - name: The name of the deployment
- * - ca_cert: The CA certificate of the API server
+ * The client is created with the optional metadata (see util/k8s):
+ * - api_server_endpoint, ca_cert, token, client_cert, client_key, insecure_skip_tls_verify
+ * - kubeconfig, context
+ * - Otherwise, the in-cluster config or KUBECONFIG is used
  * It requires the following parameters:
  * - replicas: The number of replicas to scale to
- * It requires the following environment variables:
- * - KUBECONFIG: The path to the kubeconfig file
  *
  */
 use super::ScalingComponent;
-use super::{evaluate_expression_with_current_state, filter_current_state_in_expression};
+use super::{
+    evaluate_expression_with_current_state, filter_current_state_in_expression,
+    get_expression_language,
+};
+use crate::util::k8s::K8sClientCache;
 use anyhow::{Ok, Result};
 use async_trait::async_trait;
 use data_layer::ScalingComponentDefinition;
@@ -30,25 +34,17 @@ use strum_macros::EnumIter;
 
 pub struct K8sDeploymentScalingComponent {
     definition: ScalingComponentDefinition,
+    client_cache: K8sClientCache,
 }
 
 impl K8sDeploymentScalingComponent {
     pub const SCALING_KIND: &'static str = "kubernetes-deployment";
 
     pub fn new(definition: ScalingComponentDefinition) -> Self {
-        K8sDeploymentScalingComponent { definition }
-    }
-
-    async fn get_client(
-        &self,
-        _api_server_endpoint: &String,
-        _ca_cert: &String,
-        _namespace: &String,
-    ) -> Result<kube::Client> {
-        // TODO: Use the metadata to create a Kubernetes Client
-        // Infer the runtime environment and try to create a Kubernetes Client
-        let client = Client::try_default().await?;
-        Ok(client)
+        K8sDeploymentScalingComponent {
+            definition,
+            client_cache: K8sClientCache::new(),
+        }
     }
 }
 
@@ -60,7 +56,7 @@ impl K8sDeploymentScalingComponent {
  * updatedReplicas - This represents the total number of non-terminated pods targeted by this deployment that have the desired template spec.
 */
 #[derive(Debug, EnumIter)]
-enum K8sComponentTargetValue {
+pub enum K8sComponentTargetValue {
     Replicas,
     UnavailableReplicas,
     AvailableReplicas,
@@ -78,6 +74,18 @@ impl std::fmt::Display for K8sComponentTargetValue {
         }
     }
 }
+impl K8sComponentTargetValue {
+    // The field in the status of the workloads (e.g. status.readyReplicas)
+    pub fn get_status_field(&self) -> &str {
+        match self {
+            K8sComponentTargetValue::Replicas => "replicas",
+            K8sComponentTargetValue::UnavailableReplicas => "unavailableReplicas",
+            K8sComponentTargetValue::AvailableReplicas => "availableReplicas",
+            K8sComponentTargetValue::ReadyReplicas => "readyReplicas",
+            K8sComponentTargetValue::UpdatedReplicas => "updatedReplicas",
+        }
+    }
+}
 
 #[async_trait]
 impl ScalingComponent for K8sDeploymentScalingComponent {
@@ -92,21 +100,17 @@ impl ScalingComponent for K8sDeploymentScalingComponent {
         let metadata = self.definition.metadata.clone();
 
         if let (
-            Some(Value::String(api_server_endpoint)),
             Some(Value::String(namespace)),
             Some(Value::String(name)),
-            Some(Value::String(ca_cert)),
             Some(Value::String(replicas)),
         ) = (
-            metadata.get("api_server_endpoint"),
             metadata.get("namespace"),
             metadata.get("name"),
-            metadata.get("ca_cert"),
             params.get("replicas"),
         ) {
-            // TODO: Use the metadata to create a Kubernetes Client
             let client = self
-                .get_client(api_server_endpoint, ca_cert, namespace)
+                .client_cache
+                .get_client(&self.definition.metadata)
                 .await;
             if let Err(e) = client {
                 return Err(anyhow::anyhow!(e));
@@ -135,6 +139,7 @@ impl ScalingComponent for K8sDeploymentScalingComponent {
             let replicas = evaluate_expression_with_current_state(
                 replicas,
                 current_state_map.unwrap().clone(),
+                get_expression_language(&params),
             )
             .await;
             if replicas.is_err() {
@@ -0,0 +1,302 @@
+/**
+ * [Scaling Component] Kubernetes HorizontalPodAutoscaler Scaling Component
+ *
+ * This component steers an existing HPA(autoscaling/v2) instead of replacing it
+ * e.g. raise minReplicas before events and lower maxReplicas during incidents
+ * It requires the following metadata:
+ * - namespace: The namespace of the HPA
+ * - name: The name of the HPA
+ * The client is created with the optional metadata (see util/k8s)
+ * It accepts the following parameters (at least one of them):
+ * - min_replicas: minReplicas
+ * - max_replicas: maxReplicas
+ * - cpu_utilization: averageUtilization of the cpu Resource metric
+ * - memory_utilization: averageUtilization of the memory Resource metric
+ *   The metric should have the Utilization target. The other target types (e.g. AverageValue) are not changed.
+ * The current state is available as $min_replicas, $max_replicas, $current_replicas and $desired_replicas
+ * The evaluated values should be between 1 and i32::MAX (minReplicas >= 1, averageUtilization > 0)
+ */
+use super::{
+    evaluate_expression_with_current_state, get_expression_language, get_param_expression,
+    ScalingComponent,
+};
+use crate::util::k8s::K8sClientCache;
+use anyhow::{Ok, Result};
+use async_trait::async_trait;
+use data_layer::ScalingComponentDefinition;
+use k8s_openapi::api::autoscaling::v2::{HorizontalPodAutoscaler, MetricSpec};
+use kube::api::{Api, Patch, PatchParams};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+pub struct K8sHpaScalingComponent {
+    definition: ScalingComponentDefinition,
+    client_cache: K8sClientCache,
+}
+
+impl K8sHpaScalingComponent {
+    pub const SCALING_KIND: &'static str = "kubernetes-hpa";
+
+    pub fn new(definition: ScalingComponentDefinition) -> Self {
+        K8sHpaScalingComponent {
+            definition,
+            client_cache: K8sClientCache::new(),
+        }
+    }
+}
+
+// The resource metrics whose target can be changed by the params
+const RESOURCE_METRIC_PARAMS: [(&str, &str); 2] =
+    [("cpu_utilization", "cpu"), ("memory_utilization", "memory")];
+
+#[async_trait]
+impl ScalingComponent for K8sHpaScalingComponent {
+    fn get_scaling_component_kind(&self) -> &str {
+        &self.definition.component_kind
+    }
+    fn get_id(&self) -> &str {
+        &self.definition.id
+    }
+
+    async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
+        let metadata = self.definition.metadata.clone();
+        let (Some(Value::String(namespace)), Some(Value::String(name))) =
+            (metadata.get("namespace"), metadata.get("name"))
+        else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+
+        let client = self
+            .client_cache
+            .get_client(&self.definition.metadata)
+            .await;
+        if let Err(e) = client {
+            return Err(anyhow::anyhow!(e));
+        }
+        let hpa_api: Api<HorizontalPodAutoscaler> = Api::namespaced(client.unwrap(), namespace);
+        let hpa = hpa_api.get(name).await;
+        if let Err(e) = hpa {
+            return Err(anyhow::anyhow!("Failed to get HPA {} - {}", name, e));
+        }
+        let hpa = hpa.unwrap();
+        let Some(spec) = hpa.spec else {
+            return Err(anyhow::anyhow!("Failed to get HPA {} - spec none", name));
+        };
+        let current_state_map = get_current_state_map(&hpa, spec.min_replicas, spec.max_replicas);
+        let language = get_expression_language(&params);
+
+        // Evaluate the params
+        let mut evaluated: HashMap<&str, i32> = HashMap::new();
+        let mut keys = vec!["min_replicas", "max_replicas"];
+        keys.extend(RESOURCE_METRIC_PARAMS.iter().map(|(key, _)| *key));
+        for key in keys {
+            let Some(expression) = params.get(key).and_then(get_param_expression) else {
+                continue;
+            };
+            let value = evaluate_expression_with_current_state(
+                &expression,
+                current_state_map.clone(),
+                language,
+            )
+            .await;
+            if value.is_err() {
+                return Err(value.unwrap_err());
+            }
+            // The replicas and the utilization should be positive
+            let value = get_positive_i32(key, value.unwrap());
+            if value.is_err() {
+                return Err(value.unwrap_err());
+            }
+            evaluated.insert(key, value.unwrap());
+        }
+        if evaluated.is_empty() {
+            return Err(anyhow::anyhow!("Invalid params - no HPA fields to patch"));
+        }
+
+        let min_replicas = evaluated.get("min_replicas").copied().or(spec.min_replicas);
+        let max_replicas = evaluated
+            .get("max_replicas")
+            .copied()
+            .unwrap_or(spec.max_replicas);
+        if min_replicas.unwrap_or(1) > max_replicas {
+            return Err(anyhow::anyhow!(
+                "min_replicas({:?}) is greater than max_replicas({})",
+                min_replicas,
+                max_replicas
+            ));
+        }
+
+        // https://kubernetes.io/docs/reference/kubernetes-api/workload-resources/horizontal-pod-autoscaler-v2/
+        let mut patch_spec = json!({
+            "minReplicas": min_replicas,
+            "maxReplicas": max_replicas,
+        });
+        let mut metrics = spec.metrics.unwrap_or_default();
+        let mut metrics_changed = false;
+        for (key, resource_name) in RESOURCE_METRIC_PARAMS.iter() {
+            let Some(utilization) = evaluated.get(key) else {
+                continue;
+            };
+            set_resource_utilization(&mut metrics, resource_name, *utilization)?;
+            metrics_changed = true;
+        }
+        if metrics_changed {
+            // The merge patch replaces the whole metrics array
+            patch_spec["metrics"] = json!(metrics);
+        }
+
+        let result = hpa_api
+            .patch(
+                name,
+                &PatchParams::default(),
+                &Patch::Merge(&json!({ "spec": patch_spec })),
+            )
+            .await;
+        if let Err(e) = result {
+            return Err(anyhow::anyhow!(e));
+        }
+        let result = result.unwrap();
+
+        // Verify the patch
+        let Some(result_spec) = result.spec else {
+            return Err(anyhow::anyhow!("Failed to patch HPA - spec none"));
+        };
+        if result_spec.min_replicas != min_replicas || result_spec.max_replicas != max_replicas {
+            return Err(anyhow::anyhow!(
+                "Failed to patch HPA - minReplicas: {:?}, maxReplicas: {}",
+                result_spec.min_replicas,
+                result_spec.max_replicas
+            ));
+        }
+        if metrics_changed && result_spec.metrics.unwrap_or_default() != metrics {
+            return Err(anyhow::anyhow!("Failed to patch HPA - metrics"));
+        }
+        Ok(())
+    }
+}
+
+fn get_current_state_map(
+    hpa: &HorizontalPodAutoscaler,
+    min_replicas: Option<i32>,
+    max_replicas: i32,
+) -> HashMap<String, i64> {
+    let (current_replicas, desired_replicas) = hpa
+        .status
+        .as_ref()
+        .map(|status| {
+            (
+                status.current_replicas.unwrap_or(0),
+                status.desired_replicas,
+            )
+        })
+        .unwrap_or((0, 0));
+    HashMap::from([
+        // minReplicas defaults to 1
+        (
+            "$min_replicas".to_string(),
+            min_replicas.unwrap_or(1) as i64,
+        ),
+        ("$max_replicas".to_string(), max_replicas as i64),
+        ("$current_replicas".to_string(), current_replicas as i64),
+        ("$desired_replicas".to_string(), desired_replicas as i64),
+    ])
+}
+
+// The evaluated value between 1 and i32::MAX
+fn get_positive_i32(key: &str, value: i64) -> Result<i32> {
+    let converted = i32::try_from(value);
+    if converted.is_err() || converted.as_ref().map_or(false, |converted| *converted < 1) {
+        return Err(anyhow::anyhow!(
+            "Invalid {} - {} should be between 1 and {}",
+            key,
+            value,
+            i32::MAX
+        ));
+    }
+    Ok(converted.unwrap())
+}
+
+// Set the averageUtilization of the Resource metric with the Utilization target
+fn set_resource_utilization(
+    metrics: &mut [MetricSpec],
+    resource_name: &str,
+    utilization: i32,
+) -> Result<()> {
+    let mut found = false;
+    for metric in metrics.iter_mut() {
+        let Some(resource) = metric.resource.as_mut() else {
+            continue;
+        };
+        if resource.name != resource_name {
+            continue;
+        }
+        // Keep the target type (e.g. AverageValue) that the HPA owner chose
+        if resource.target.type_ != "Utilization" {
+            return Err(anyhow::anyhow!(
+                "The target type of the {} Resource metric is {}, not Utilization",
+                resource_name,
+                resource.target.type_
+            ));
+        }
+        resource.target.average_utilization = Some(utilization);
+        found = true;
+    }
+    if !found {
+        return Err(anyhow::anyhow!(
+            "The HPA doesn't have the {} Resource metric",
+            resource_name
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_positive_i32() {
+        assert_eq!(get_positive_i32("min_replicas", 3).unwrap(), 3);
+        assert!(get_positive_i32("min_replicas", 0).is_err());
+        assert!(get_positive_i32("cpu_utilization", -10).is_err());
+        // It doesn't wrap around
+        assert!(get_positive_i32("max_replicas", i32::MAX as i64 + 2).is_err());
+    }
+
+    #[test]
+    fn test_set_resource_utilization() {
+        let mut metrics: Vec<MetricSpec> = serde_json::from_value(json!([
+            {
+                "type": "Resource",
+                "resource": {
+                    "name": "cpu",
+                    "target": { "type": "Utilization", "averageUtilization": 70 }
+                }
+            },
+            {
+                "type": "Resource",
+                "resource": {
+                    "name": "memory",
+                    "target": { "type": "AverageValue", "averageValue": "500Mi" }
+                }
+            }
+        ]))
+        .unwrap();
+        assert!(set_resource_utilization(&mut metrics, "cpu", 50).is_ok());
+        assert_eq!(
+            metrics[0]
+                .resource
+                .as_ref()
+                .unwrap()
+                .target
+                .average_utilization,
+            Some(50)
+        );
+        // The AverageValue target is kept
+        assert!(set_resource_utilization(&mut metrics, "memory", 50).is_err());
+        let memory_target = &metrics[1].resource.as_ref().unwrap().target;
+        assert_eq!(memory_target.type_, "AverageValue");
+        assert!(memory_target.average_utilization.is_none());
+        assert!(set_resource_utilization(&mut metrics, "ephemeral-storage", 50).is_err());
+    }
+}
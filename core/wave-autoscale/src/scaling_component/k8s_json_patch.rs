@@ -1,34 +1,27 @@
 use super::ScalingComponent;
+use crate::util::k8s::K8sClientCache;
 use async_trait::async_trait;
 use data_layer::ScalingComponentDefinition;
 use kube::{
     api::{Api, DynamicObject, Patch, PatchParams},
-    discovery, Client,
+    discovery,
 };
 use std::collections::HashMap;
 
 
 pub struct K8sPatchScalingComponent {
     definition: ScalingComponentDefinition,
+    client_cache: K8sClientCache,
 }
 
 impl K8sPatchScalingComponent {
     pub const SCALING_KIND: &'static str = "kubernetes-json-patch";
 
     pub fn new(definition: ScalingComponentDefinition) -> Self {
-        K8sPatchScalingComponent { definition }
-    }
-
-    async fn get_client(
-        &self,
-        _api_server_endpoint: Option<String>,
-        _ca_cert: Option<String>,
-        _namespace: Option<String>,
-    ) -> anyhow::Result<kube::Client> {
-        // TODO: Use the metadata to create a Kubernetes Client
-        // Infer the runtime environment and try to create a Kubernetes Client
-        let client = Client::try_default().await?;
-        Ok(client)
+        K8sPatchScalingComponent {
+            definition,
+            client_cache: K8sClientCache::new(),
+        }
     }
 }
 
@@ -42,8 +35,6 @@ impl ScalingComponent for K8sPatchScalingComponent {
     }
 
     async fn apply(&self, params: HashMap<String, serde_json::Value>) -> anyhow::Result<()> {
-        let metadata = self.definition.metadata.clone();
-
         let (
             Some(serde_json::Value::String(namespace)),
             Some(serde_json::Value::String(name)),
@@ -60,16 +51,14 @@ impl ScalingComponent for K8sPatchScalingComponent {
             return Err(anyhow::anyhow!("Invalid metadata"));
         };
 
-        // TODO: Use the metadata to create a Kubernetes Client
-        let api_server_endpoint = metadata
-                .get("api_server_endpoint")
-                .map(|api_server_endpoint| api_server_endpoint.to_string());
-        let ca_cert = metadata
-                .get("ca_cert")
-                .map(|ca_cert| ca_cert.to_string());
-        let Ok(client) = self.get_client(api_server_endpoint, ca_cert, Some(namespace.to_string())).await else {
-            return Err(anyhow::anyhow!("cannot create kubernetes client"));
-        };
+        let client = self
+            .client_cache
+            .get_client(&self.definition.metadata)
+            .await;
+        if let Err(e) = client {
+            return Err(anyhow::anyhow!("cannot create kubernetes client - {}", e));
+        }
+        let client = client.unwrap();
 
         let Some(api_group) = api_version.split('/').next() else {
             return Err(anyhow::anyhow!("api Group not found"));
@@ -0,0 +1,517 @@
+/**
+ * [Scaling Component] Kubernetes Node Capacity Scaling Component
+ *
+ * This component adjusts the node-level capacity ahead of the pod scale-outs
+ * It requires the following metadata:
+ * - provider: karpenter or cluster-autoscaler
+ * - name: The name of the NodePool(karpenter) or the node group resource(cluster-autoscaler)
+ * - api_version, kind: The resource (default: karpenter.sh/v1 NodePool, cluster.x-k8s.io/v1beta1 MachineDeployment)
+ * - namespace: The namespace of the node group resource (cluster-autoscaler)
+ * - node_selector: The label selector of the nodes for $node_count (default for karpenter: karpenter.sh/nodepool={name})
+ * The client is created with the optional metadata (see util/k8s)
+ *
+ * karpenter - NodePool
+ * - cpu_limit: spec.limits.cpu in millicores
+ * - memory_limit: spec.limits.memory in MiB
+ * - disruption_budget_nodes: nodes of the first disruption budget (e.g. 5 or "10%")
+ * cluster-autoscaler - the node group annotations of the Cluster API provider
+ * - min_size: cluster.x-k8s.io/cluster-api-autoscaler-node-group-min-size
+ * - max_size: cluster.x-k8s.io/cluster-api-autoscaler-node-group-max-size
+ * - config_map, min_size_key, max_size_key: (optional) the keys of the ConfigMap to update with min_size and max_size
+ *   The ConfigMap is rolled back if the annotations are not patched
+ *
+ * The current state is available as $node_count, $ready_node_count,
+ * $cpu_limit, $memory_limit (karpenter), $min_size and $max_size (cluster-autoscaler)
+ */
+use super::{
+    evaluate_expression_with_current_state, get_expression_language, get_param_expression,
+    ScalingComponent,
+};
+use crate::util::k8s::{get_status_i64, parse_cpu_millicores, parse_memory_mib, K8sClientCache};
+use anyhow::{Ok, Result};
+use async_trait::async_trait;
+use data_layer::ScalingComponentDefinition;
+use k8s_openapi::api::core::v1::{ConfigMap, Node};
+use kube::{
+    api::{Api, DynamicObject, ListParams, Patch, PatchParams},
+    Client,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+const PROVIDER_KARPENTER: &str = "karpenter";
+const PROVIDER_CLUSTER_AUTOSCALER: &str = "cluster-autoscaler";
+const KARPENTER_API_VERSION: &str = "karpenter.sh/v1";
+const KARPENTER_NODE_POOL_KIND: &str = "NodePool";
+const KARPENTER_NODE_POOL_LABEL: &str = "karpenter.sh/nodepool";
+const CLUSTER_API_VERSION: &str = "cluster.x-k8s.io/v1beta1";
+const CLUSTER_API_NODE_GROUP_KIND: &str = "MachineDeployment";
+const NODE_GROUP_MIN_SIZE_ANNOTATION: &str =
+    "cluster.x-k8s.io/cluster-api-autoscaler-node-group-min-size";
+const NODE_GROUP_MAX_SIZE_ANNOTATION: &str =
+    "cluster.x-k8s.io/cluster-api-autoscaler-node-group-max-size";
+
+pub struct K8sNodeCapacityScalingComponent {
+    definition: ScalingComponentDefinition,
+    client_cache: K8sClientCache,
+}
+
+impl K8sNodeCapacityScalingComponent {
+    pub const SCALING_KIND: &'static str = "kubernetes-node-capacity";
+
+    pub fn new(definition: ScalingComponentDefinition) -> Self {
+        K8sNodeCapacityScalingComponent {
+            definition,
+            client_cache: K8sClientCache::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ScalingComponent for K8sNodeCapacityScalingComponent {
+    fn get_scaling_component_kind(&self) -> &str {
+        &self.definition.component_kind
+    }
+    fn get_id(&self) -> &str {
+        &self.definition.id
+    }
+
+    async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
+        let metadata = self.definition.metadata.clone();
+        let (Some(Value::String(provider)), Some(Value::String(name))) =
+            (metadata.get("provider"), metadata.get("name"))
+        else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+        let get_metadata_str = |key: &str, default: &str| {
+            metadata
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or(default)
+                .to_string()
+        };
+        let namespace = get_metadata_str("namespace", "default");
+        let (api_version, kind, default_node_selector) = match provider.as_str() {
+            PROVIDER_KARPENTER => (
+                get_metadata_str("api_version", KARPENTER_API_VERSION),
+                get_metadata_str("kind", KARPENTER_NODE_POOL_KIND),
+                format!("{}={}", KARPENTER_NODE_POOL_LABEL, name),
+            ),
+            PROVIDER_CLUSTER_AUTOSCALER => (
+                get_metadata_str("api_version", CLUSTER_API_VERSION),
+                get_metadata_str("kind", CLUSTER_API_NODE_GROUP_KIND),
+                String::new(),
+            ),
+            _ => return Err(anyhow::anyhow!("Unknown provider: {}", provider)),
+        };
+        let node_selector = get_metadata_str("node_selector", default_node_selector.as_str());
+
+        let client = self
+            .client_cache
+            .get_client(&self.definition.metadata)
+            .await;
+        if let Err(e) = client {
+            return Err(anyhow::anyhow!(e));
+        }
+        let client = client.unwrap();
+        let api = self
+            .client_cache
+            .get_dynamic_api(&self.definition.metadata, &namespace, &api_version, &kind)
+            .await;
+        if let Err(e) = api {
+            return Err(anyhow::anyhow!(e));
+        }
+        let api = api.unwrap();
+        let object = api.get(name).await;
+        if let Err(e) = object {
+            return Err(anyhow::anyhow!("Failed to get {} {} - {}", kind, name, e));
+        }
+        let object = object.unwrap();
+
+        let mut current_state_map = if provider == PROVIDER_KARPENTER {
+            get_node_pool_state_map(&object)
+        } else {
+            get_node_group_state_map(&object)
+        };
+        if !node_selector.is_empty() {
+            let node_counts = get_node_counts(client.clone(), &node_selector).await;
+            if let Err(e) = node_counts {
+                return Err(anyhow::anyhow!(e));
+            }
+            let (node_count, ready_node_count) = node_counts.unwrap();
+            current_state_map.insert("$node_count".to_string(), node_count);
+            current_state_map.insert("$ready_node_count".to_string(), ready_node_count);
+        }
+
+        let language = get_expression_language(&params);
+        let evaluate = |key: &str| {
+            let expression = params.get(key).and_then(get_param_expression);
+            let current_state_map = current_state_map.clone();
+            async move {
+                match expression {
+                    Some(expression) => evaluate_expression_with_current_state(
+                        &expression,
+                        current_state_map,
+                        language,
+                    )
+                    .await
+                    .map(Some),
+                    None => Ok(None),
+                }
+            }
+        };
+
+        // The previous values of the ConfigMap to roll back if the annotations are not patched
+        let mut config_map_rollback: Option<(String, Value)> = None;
+        let patch = if provider == PROVIDER_KARPENTER {
+            let cpu_limit = evaluate("cpu_limit").await?;
+            let memory_limit = evaluate("memory_limit").await?;
+            // "10%" is used as it is
+            let disruption_budget_nodes = match params.get("disruption_budget_nodes") {
+                Some(Value::String(nodes)) if nodes.trim().ends_with('%') => {
+                    Some(nodes.trim().to_string())
+                }
+                _ => evaluate("disruption_budget_nodes")
+                    .await?
+                    .map(|nodes| nodes.to_string()),
+            };
+            get_node_pool_patch(&object, cpu_limit, memory_limit, disruption_budget_nodes)
+        } else {
+            let min_size = evaluate("min_size").await?;
+            let max_size = evaluate("max_size").await?;
+            let effective_min_size = min_size.unwrap_or(current_state_map["$min_size"]);
+            let effective_max_size = max_size.unwrap_or(current_state_map["$max_size"]);
+            if effective_min_size > effective_max_size {
+                return Err(anyhow::anyhow!(
+                    "min_size({}) is greater than max_size({})",
+                    effective_min_size,
+                    effective_max_size
+                ));
+            }
+            if let Some(Value::String(config_map)) = metadata.get("config_map") {
+                let result = patch_config_map(
+                    client.clone(),
+                    &namespace,
+                    config_map,
+                    &[
+                        (get_metadata_str("min_size_key", ""), min_size),
+                        (get_metadata_str("max_size_key", ""), max_size),
+                    ],
+                )
+                .await;
+                if let Err(e) = result {
+                    return Err(e);
+                }
+                config_map_rollback = result
+                    .unwrap()
+                    .map(|rollback| (config_map.to_string(), rollback));
+            }
+            get_node_group_patch(min_size, max_size)
+        };
+        let Some(patch) = patch else {
+            return Err(anyhow::anyhow!("Invalid params - no node capacity to patch"));
+        };
+
+        let result = api
+            .patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await;
+        let error = if let Err(e) = result {
+            anyhow::anyhow!(e)
+        } else {
+            let result: DynamicObject = result.unwrap();
+            // Verify the patch
+            let result_value = serde_json::to_value(&result)?;
+            if is_patch_applied(&result_value, &patch) {
+                return Ok(());
+            }
+            anyhow::anyhow!("Failed to patch {} {}", kind, name)
+        };
+        if let Some((config_map, rollback)) = config_map_rollback {
+            let result =
+                patch_config_map_data(client.clone(), &namespace, &config_map, rollback).await;
+            if let Err(e) = result {
+                return Err(anyhow::anyhow!(
+                    "{} - and failed to roll back ConfigMap {} - {}",
+                    error,
+                    config_map,
+                    e
+                ));
+            }
+        }
+        Err(error)
+    }
+}
+
+fn get_node_pool_state_map(node_pool: &DynamicObject) -> HashMap<String, i64> {
+    let limits = &node_pool.data["spec"]["limits"];
+    let cpu_limit = limits["cpu"]
+        .as_str()
+        .map(|cpu| cpu.to_string())
+        .or(limits["cpu"].as_i64().map(|cpu| cpu.to_string()))
+        .and_then(|cpu| parse_cpu_millicores(&cpu))
+        .unwrap_or(0);
+    let memory_limit = limits["memory"]
+        .as_str()
+        .and_then(parse_memory_mib)
+        .unwrap_or(0);
+    HashMap::from([
+        ("$node_count".to_string(), 0),
+        ("$ready_node_count".to_string(), 0),
+        ("$cpu_limit".to_string(), cpu_limit),
+        ("$memory_limit".to_string(), memory_limit),
+    ])
+}
+
+fn get_node_group_state_map(node_group: &DynamicObject) -> HashMap<String, i64> {
+    let annotations = node_group.metadata.annotations.clone().unwrap_or_default();
+    let get_annotation = |key: &str| {
+        annotations
+            .get(key)
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(0)
+    };
+    HashMap::from([
+        // The replicas of the node group (e.g. MachineDeployment)
+        (
+            "$node_count".to_string(),
+            get_status_i64(node_group, "replicas"),
+        ),
+        (
+            "$ready_node_count".to_string(),
+            get_status_i64(node_group, "readyReplicas"),
+        ),
+        (
+            "$min_size".to_string(),
+            get_annotation(NODE_GROUP_MIN_SIZE_ANNOTATION),
+        ),
+        (
+            "$max_size".to_string(),
+            get_annotation(NODE_GROUP_MAX_SIZE_ANNOTATION),
+        ),
+    ])
+}
+
+// The number of the nodes and the ready nodes
+async fn get_node_counts(client: Client, node_selector: &str) -> Result<(i64, i64)> {
+    let node_api: Api<Node> = Api::all(client);
+    let nodes = node_api
+        .list(&ListParams::default().labels(node_selector))
+        .await?;
+    let ready_node_count = nodes
+        .items
+        .iter()
+        .filter(|node| {
+            node.status
+                .as_ref()
+                .and_then(|status| status.conditions.as_ref())
+                .map_or(false, |conditions| {
+                    conditions
+                        .iter()
+                        .any(|condition| condition.type_ == "Ready" && condition.status == "True")
+                })
+        })
+        .count();
+    Ok((nodes.items.len() as i64, ready_node_count as i64))
+}
+
+// https://karpenter.sh/docs/concepts/nodepools/
+fn get_node_pool_patch(
+    node_pool: &DynamicObject,
+    cpu_limit: Option<i64>,
+    memory_limit: Option<i64>,
+    disruption_budget_nodes: Option<String>,
+) -> Option<Value> {
+    let mut spec = json!({});
+    if let Some(cpu_limit) = cpu_limit {
+        spec["limits"]["cpu"] = json!(format!("{}m", cpu_limit));
+    }
+    if let Some(memory_limit) = memory_limit {
+        spec["limits"]["memory"] = json!(format!("{}Mi", memory_limit));
+    }
+    if let Some(nodes) = disruption_budget_nodes {
+        // The merge patch replaces the whole budgets array, so only the first budget is changed
+        let mut budgets = node_pool.data["spec"]["disruption"]["budgets"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        if budgets.is_empty() {
+            budgets.push(json!({}));
+        }
+        budgets[0]["nodes"] = json!(nodes);
+        spec["disruption"]["budgets"] = json!(budgets);
+    }
+    if spec.as_object().map_or(true, |spec| spec.is_empty()) {
+        return None;
+    }
+    Some(json!({ "spec": spec }))
+}
+
+fn get_node_group_patch(min_size: Option<i64>, max_size: Option<i64>) -> Option<Value> {
+    let mut annotations = json!({});
+    if let Some(min_size) = min_size {
+        annotations[NODE_GROUP_MIN_SIZE_ANNOTATION] = json!(min_size.to_string());
+    }
+    if let Some(max_size) = max_size {
+        annotations[NODE_GROUP_MAX_SIZE_ANNOTATION] = json!(max_size.to_string());
+    }
+    if annotations
+        .as_object()
+        .map_or(true, |annotations| annotations.is_empty())
+    {
+        return None;
+    }
+    Some(json!({ "metadata": { "annotations": annotations } }))
+}
+
+// Patch the ConfigMap and return the data to roll it back (null removes the key that didn't exist)
+async fn patch_config_map(
+    client: Client,
+    namespace: &str,
+    name: &str,
+    values: &[(String, Option<i64>)],
+) -> Result<Option<Value>> {
+    let mut data = json!({});
+    for (key, value) in values.iter() {
+        if key.is_empty() {
+            continue;
+        }
+        if let Some(value) = value {
+            data[key] = json!(value.to_string());
+        }
+    }
+    if data.as_object().map_or(true, |data| data.is_empty()) {
+        return Ok(None);
+    }
+    let config_map_api: Api<ConfigMap> = Api::namespaced(client.clone(), namespace);
+    let config_map = config_map_api.get(name).await;
+    if let Err(e) = config_map {
+        return Err(anyhow::anyhow!("Failed to get ConfigMap {} - {}", name, e));
+    }
+    let rollback = get_config_map_rollback(&config_map.unwrap(), &data);
+    patch_config_map_data(client, namespace, name, data).await?;
+    Ok(Some(rollback))
+}
+
+fn get_config_map_rollback(config_map: &ConfigMap, data: &Value) -> Value {
+    let current_data = config_map.data.clone().unwrap_or_default();
+    let mut rollback = json!({});
+    if let Some(data) = data.as_object() {
+        for key in data.keys() {
+            rollback[key] = json!(current_data.get(key));
+        }
+    }
+    rollback
+}
+
+async fn patch_config_map_data(
+    client: Client,
+    namespace: &str,
+    name: &str,
+    data: Value,
+) -> Result<()> {
+    let config_map_api: Api<ConfigMap> = Api::namespaced(client, namespace);
+    let result = config_map_api
+        .patch(
+            name,
+            &PatchParams::default(),
+            &Patch::Merge(&json!({ "data": data })),
+        )
+        .await;
+    if let Err(e) = result {
+        return Err(anyhow::anyhow!(
+            "Failed to patch ConfigMap {} - {}",
+            name,
+            e
+        ));
+    }
+    Ok(())
+}
+
+// Whether the result has all the values in the patch
+fn is_patch_applied(result: &Value, patch: &Value) -> bool {
+    match (result, patch) {
+        (Value::Object(result), Value::Object(patch)) => patch.iter().all(|(key, value)| {
+            result
+                .get(key)
+                .map_or(false, |result| is_patch_applied(result, value))
+        }),
+        (result, patch) => result == patch,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use kube::api::ApiResource;
+    use kube::core::GroupVersion;
+
+    #[test]
+    fn test_node_pool_patch() {
+        let mut node_pool = DynamicObject::new(
+            "default",
+            &ApiResource::from_gvk(
+                &GroupVersion::gv("karpenter.sh", "v1").with_kind(KARPENTER_NODE_POOL_KIND),
+            ),
+        );
+        node_pool.data = json!({
+            "spec": {
+                "limits": { "cpu": "1000", "memory": "1000Gi" },
+                "disruption": { "budgets": [{ "nodes": "10%" }, { "nodes": "0", "schedule": "@daily", "duration": "10m" }] }
+            }
+        });
+        let state_map = get_node_pool_state_map(&node_pool);
+        assert_eq!(state_map["$cpu_limit"], 1000 * 1000);
+        assert_eq!(state_map["$memory_limit"], 1000 * 1024);
+
+        // The fractional limits are kept in millicores and MiB
+        node_pool.data["spec"]["limits"] = json!({ "cpu": "1500m", "memory": "1.5Gi" });
+        let state_map = get_node_pool_state_map(&node_pool);
+        assert_eq!(state_map["$cpu_limit"], 1500);
+        assert_eq!(state_map["$memory_limit"], 1536);
+
+        let patch =
+            get_node_pool_patch(&node_pool, Some(2500), Some(3072), Some("5".to_string())).unwrap();
+        assert_eq!(patch["spec"]["limits"]["cpu"], json!("2500m"));
+        assert_eq!(patch["spec"]["limits"]["memory"], json!("3072Mi"));
+        assert_eq!(
+            patch["spec"]["disruption"]["budgets"][0]["nodes"],
+            json!("5")
+        );
+        assert_eq!(
+            patch["spec"]["disruption"]["budgets"][1]["schedule"],
+            json!("@daily")
+        );
+        assert!(get_node_pool_patch(&node_pool, None, None, None).is_none());
+
+        let mut result = serde_json::to_value(&node_pool).unwrap();
+        assert!(!is_patch_applied(&result, &patch));
+        result["spec"] = patch["spec"].clone();
+        assert!(is_patch_applied(&result, &patch));
+    }
+
+    #[test]
+    fn test_node_group_patch() {
+        let patch = get_node_group_patch(Some(1), Some(10)).unwrap();
+        assert_eq!(
+            patch["metadata"]["annotations"][NODE_GROUP_MAX_SIZE_ANNOTATION],
+            json!("10")
+        );
+        assert!(get_node_group_patch(None, None).is_none());
+    }
+
+    #[test]
+    fn test_config_map_rollback() {
+        let config_map = ConfigMap {
+            data: Some(
+                [("min-size".to_string(), "1".to_string())]
+                    .into_iter()
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        let rollback =
+            get_config_map_rollback(&config_map, &json!({ "min-size": "2", "max-size": "10" }));
+        // The key that didn't exist is removed with null
+        assert_eq!(rollback, json!({ "min-size": "1", "max-size": null }));
+    }
+}
@@ -0,0 +1,477 @@
+/**
+ * [Scaling Component] Kubernetes Resources Scaling Component
+ *
+ * This component is used to scale a container of a Kubernetes Deployment or StatefulSet vertically
+ * It requires the following metadata:
+ * - namespace: The namespace of the workload
+ * - name: The name of the workload
+ * - container: The name of the container
+ * - kind: Deployment(default) or StatefulSet
+ * - resize_mode: rollout(default) or in_place
+ *   - rollout: patches the pod template, so the pods are replaced by the rollout
+ *   - in_place: resizes the running pods without restarting them (the clusters with in-place pod resize)
+ *     The pod template is not changed, so the next rollout restores the resources of the template.
+ *     The pods are resized {max_concurrent_resizes}(default: 5) at a time within 60 seconds.
+ * The client is created with the optional metadata (see util/k8s)
+ * It accepts the following parameters (at least one of them):
+ * - cpu_request, cpu_limit: millicores (e.g. 500)
+ * - memory_request, memory_limit: MiB (e.g. 512)
+ * - min_cpu, max_cpu, min_memory, max_memory: the bounds of the values above (also in the metadata)
+ * The current state is available as $cpu_request, $cpu_limit, $memory_request and $memory_limit (0 if not set)
+ * A float result is rounded to the nearest integer (e.g. cpu_request: $cpu_request * 1.5)
+ */
+use super::{
+    evaluate_expression_with_current_state, get_expression_language, get_param_expression,
+    ScalingComponent,
+};
+use crate::util::k8s::{parse_cpu_millicores, parse_memory_mib, K8sClientCache};
+use anyhow::{Ok, Result};
+use async_trait::async_trait;
+use data_layer::ScalingComponentDefinition;
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Pod;
+use kube::{
+    api::{Api, DynamicObject, ListParams, Patch, PatchParams},
+    Client,
+};
+use serde_json::{json, Value};
+use std::{collections::HashMap, time::Duration};
+
+const DEFAULT_WORKLOAD_KIND: &str = "Deployment";
+const RESIZE_MODE_IN_PLACE: &str = "in_place";
+const DEFAULT_MAX_CONCURRENT_RESIZES: usize = 5;
+const RESIZE_TIMEOUT_SEC: u64 = 60;
+
+// (param, requests or limits, resource name)
+const RESOURCE_PARAMS: [(&str, &str, &str); 4] = [
+    ("cpu_request", "requests", "cpu"),
+    ("cpu_limit", "limits", "cpu"),
+    ("memory_request", "requests", "memory"),
+    ("memory_limit", "limits", "memory"),
+];
+
+pub struct K8sResourcesScalingComponent {
+    definition: ScalingComponentDefinition,
+    client_cache: K8sClientCache,
+}
+
+impl K8sResourcesScalingComponent {
+    pub const SCALING_KIND: &'static str = "kubernetes-resources";
+
+    pub fn new(definition: ScalingComponentDefinition) -> Self {
+        K8sResourcesScalingComponent {
+            definition,
+            client_cache: K8sClientCache::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ScalingComponent for K8sResourcesScalingComponent {
+    fn get_scaling_component_kind(&self) -> &str {
+        &self.definition.component_kind
+    }
+    fn get_id(&self) -> &str {
+        &self.definition.id
+    }
+
+    async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
+        let metadata = self.definition.metadata.clone();
+        let (
+            Some(Value::String(namespace)),
+            Some(Value::String(name)),
+            Some(Value::String(container)),
+        ) = (
+            metadata.get("namespace"),
+            metadata.get("name"),
+            metadata.get("container"),
+        )
+        else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+        let kind = metadata
+            .get("kind")
+            .and_then(Value::as_str)
+            .unwrap_or(DEFAULT_WORKLOAD_KIND);
+        let is_in_place =
+            metadata.get("resize_mode").and_then(Value::as_str) == Some(RESIZE_MODE_IN_PLACE);
+
+        let client = self
+            .client_cache
+            .get_client(&self.definition.metadata)
+            .await;
+        if let Err(e) = client {
+            return Err(anyhow::anyhow!(e));
+        }
+        let client = client.unwrap();
+        let api = self
+            .client_cache
+            .get_dynamic_api(&self.definition.metadata, namespace, "apps/v1", kind)
+            .await;
+        if let Err(e) = api {
+            return Err(anyhow::anyhow!(e));
+        }
+        let api = api.unwrap();
+        let workload = api.get(name).await;
+        if let Err(e) = workload {
+            return Err(anyhow::anyhow!("Failed to get {} {} - {}", kind, name, e));
+        }
+        let workload = workload.unwrap();
+        let Some(current_resources) = get_container_resources(&workload.data["spec"]["template"], container) else {
+            return Err(anyhow::anyhow!("Container not found: {}", container));
+        };
+        let current_state_map = get_current_state_map(&current_resources);
+
+        // Evaluate the params and clamp them by the bounds
+        let language = get_expression_language(&params);
+        let get_bound = |key: &str| {
+            params
+                .get(key)
+                .or(metadata.get(key))
+                .and_then(Value::as_i64)
+        };
+        let mut resources = json!({});
+        for (param, resource_kind, resource_name) in RESOURCE_PARAMS.iter() {
+            let Some(expression) = params.get(*param).and_then(get_param_expression) else {
+                continue;
+            };
+            let value = evaluate_expression_with_current_state(
+                &expression,
+                current_state_map.clone(),
+                language,
+            )
+            .await;
+            if value.is_err() {
+                return Err(value.unwrap_err());
+            }
+            let mut value = value.unwrap();
+            if let Some(min) = get_bound(format!("min_{}", resource_name).as_str()) {
+                value = value.max(min);
+            }
+            if let Some(max) = get_bound(format!("max_{}", resource_name).as_str()) {
+                value = value.min(max);
+            }
+            if value <= 0 {
+                return Err(anyhow::anyhow!("Invalid {}: {}", param, value));
+            }
+            resources[resource_kind][resource_name] = json!(format_quantity(resource_name, value));
+        }
+        if resources
+            .as_object()
+            .map_or(true, |resources| resources.is_empty())
+        {
+            return Err(anyhow::anyhow!("Invalid params - no resources to patch"));
+        }
+        validate_requests_and_limits(&current_state_map, &resources)?;
+
+        let container_patch = json!({
+            "containers": [{
+                "name": container,
+                "resources": resources
+            }]
+        });
+        if is_in_place {
+            let selector = get_match_labels_selector(&workload);
+            if selector.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "The selector of {} {} is empty",
+                    kind,
+                    name
+                ));
+            }
+            let max_concurrent_resizes = metadata
+                .get("max_concurrent_resizes")
+                .and_then(Value::as_u64)
+                .filter(|max_concurrent_resizes| *max_concurrent_resizes > 0)
+                .map_or(DEFAULT_MAX_CONCURRENT_RESIZES, |max_concurrent_resizes| {
+                    max_concurrent_resizes as usize
+                });
+            let result = tokio::time::timeout(
+                Duration::from_secs(RESIZE_TIMEOUT_SEC),
+                resize_pods_in_place(
+                    client,
+                    namespace,
+                    &selector,
+                    container,
+                    &container_patch,
+                    max_concurrent_resizes,
+                ),
+            )
+            .await;
+            let Result::Ok(result) = result else {
+                return Err(anyhow::anyhow!(
+                    "Timed out resizing the pods of {} {} in {} seconds",
+                    kind,
+                    name,
+                    RESIZE_TIMEOUT_SEC
+                ));
+            };
+            return result;
+        }
+
+        // The strategic merge patch merges the containers by the name
+        let patch = json!({
+            "spec": {
+                "template": {
+                    "spec": container_patch
+                }
+            }
+        });
+        let result = api
+            .patch(name, &PatchParams::default(), &Patch::Strategic(&patch))
+            .await;
+        if let Err(e) = result {
+            return Err(anyhow::anyhow!(e));
+        }
+        let result: DynamicObject = result.unwrap();
+
+        // Verify the patch
+        let result_resources = get_container_resources(&result.data["spec"]["template"], container);
+        if !is_resources_applied(result_resources.as_ref(), &resources) {
+            return Err(anyhow::anyhow!(
+                "Failed to patch the resources of {} {}",
+                kind,
+                name
+            ));
+        }
+        Ok(())
+    }
+}
+
+// The resources of the container in the pod template or the pod
+fn get_container_resources(pod: &Value, container: &str) -> Option<Value> {
+    pod.get("spec")?
+        .get("containers")?
+        .as_array()?
+        .iter()
+        .find(|item| item.get("name").and_then(Value::as_str) == Some(container))
+        .map(|item| item.get("resources").cloned().unwrap_or(json!({})))
+}
+
+fn get_current_state_map(resources: &Value) -> HashMap<String, i64> {
+    RESOURCE_PARAMS
+        .iter()
+        .map(|(param, resource_kind, resource_name)| {
+            let value = resources
+                .get(resource_kind)
+                .and_then(|resources| resources.get(resource_name))
+                .and_then(Value::as_str)
+                .and_then(|quantity| parse_quantity(resource_name, quantity))
+                .unwrap_or(0);
+            (format!("${}", param), value)
+        })
+        .collect()
+}
+
+fn parse_quantity(resource_name: &str, quantity: &str) -> Option<i64> {
+    if resource_name == "cpu" {
+        parse_cpu_millicores(quantity)
+    } else {
+        parse_memory_mib(quantity)
+    }
+}
+
+fn format_quantity(resource_name: &str, value: i64) -> String {
+    if resource_name == "cpu" {
+        format!("{}m", value)
+    } else {
+        format!("{}Mi", value)
+    }
+}
+
+// The request can't be greater than the limit after the patch
+fn validate_requests_and_limits(
+    current_state_map: &HashMap<String, i64>,
+    resources: &Value,
+) -> Result<()> {
+    for resource_name in ["cpu", "memory"] {
+        let get_value = |resource_kind: &str, param: &str| {
+            resources
+                .get(resource_kind)
+                .and_then(|resources| resources.get(resource_name))
+                .and_then(Value::as_str)
+                .and_then(|quantity| parse_quantity(resource_name, quantity))
+                .unwrap_or(current_state_map[&format!("${}", param)])
+        };
+        let request = get_value("requests", format!("{}_request", resource_name).as_str());
+        let limit = get_value("limits", format!("{}_limit", resource_name).as_str());
+        // 0 means not set
+        if request > 0 && limit > 0 && request > limit {
+            return Err(anyhow::anyhow!(
+                "The {} request({}) is greater than the limit({})",
+                resource_name,
+                request,
+                limit
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn is_resources_applied(result_resources: Option<&Value>, resources: &Value) -> bool {
+    let (Some(result_resources), Some(resources)) = (result_resources, resources.as_object()) else {
+        return false;
+    };
+    resources.iter().all(|(resource_kind, values)| {
+        values.as_object().map_or(false, |values| {
+            values.iter().all(|(resource_name, quantity)| {
+                let result_quantity = result_resources
+                    .get(resource_kind)
+                    .and_then(|resources| resources.get(resource_name))
+                    .and_then(Value::as_str)
+                    .and_then(|quantity| parse_quantity(resource_name, quantity));
+                let quantity = quantity
+                    .as_str()
+                    .and_then(|quantity| parse_quantity(resource_name, quantity));
+                result_quantity.is_some() && result_quantity == quantity
+            })
+        })
+    })
+}
+
+// e.g. app=web,tier=frontend
+fn get_match_labels_selector(workload: &DynamicObject) -> String {
+    workload.data["spec"]["selector"]["matchLabels"]
+        .as_object()
+        .map(|labels| {
+            labels
+                .iter()
+                .filter_map(|(key, value)| value.as_str().map(|value| format!("{}={}", key, value)))
+                .collect::<Vec<String>>()
+                .join(",")
+        })
+        .unwrap_or_default()
+}
+
+// The resize subresource doesn't exist(404) or isn't allowed for the pod(422) before Kubernetes 1.33
+fn is_resize_subresource_unsupported(error: &kube::Error) -> bool {
+    matches!(error, kube::Error::Api(response) if response.code == 404 || response.code == 422)
+}
+
+// https://kubernetes.io/docs/tasks/configure-pod-container/resize-container-resources/
+async fn resize_pods_in_place(
+    client: Client,
+    namespace: &str,
+    selector: &str,
+    container: &str,
+    container_patch: &Value,
+    max_concurrent_resizes: usize,
+) -> Result<()> {
+    let pod_api: Api<Pod> = Api::namespaced(client, namespace);
+    let pods = pod_api.list(&ListParams::default().labels(selector)).await;
+    if let Err(e) = pods {
+        return Err(anyhow::anyhow!(e));
+    }
+    let pod_names = pods
+        .unwrap()
+        .items
+        .into_iter()
+        .filter_map(|pod| pod.metadata.name)
+        .collect::<Vec<String>>();
+    let patch = json!({ "spec": container_patch });
+    let results = futures::stream::iter(pod_names)
+        .map(|pod_name| resize_pod_in_place(&pod_api, pod_name, container, &patch))
+        .buffer_unordered(max_concurrent_resizes)
+        .collect::<Vec<Result<()>>>()
+        .await;
+    let errors = results
+        .into_iter()
+        .filter_map(|result| result.err().map(|error| error.to_string()))
+        .collect::<Vec<String>>();
+    if !errors.is_empty() {
+        return Err(anyhow::anyhow!(errors.join(", ")));
+    }
+    Ok(())
+}
+
+async fn resize_pod_in_place(
+    pod_api: &Api<Pod>,
+    pod_name: String,
+    container: &str,
+    patch: &Value,
+) -> Result<()> {
+    // The resize subresource is required since Kubernetes 1.33. The older clusters patch the pod itself.
+    let mut result = pod_api
+        .patch_subresource(
+            "resize",
+            &pod_name,
+            &PatchParams::default(),
+            &Patch::Strategic(patch),
+        )
+        .await;
+    if result
+        .as_ref()
+        .err()
+        .map_or(false, is_resize_subresource_unsupported)
+    {
+        result = pod_api
+            .patch(&pod_name, &PatchParams::default(), &Patch::Strategic(patch))
+            .await;
+    }
+    if let Err(e) = result {
+        return Err(anyhow::anyhow!(
+            "Failed to resize the pod {} - {}",
+            pod_name,
+            e
+        ));
+    }
+    let result = serde_json::to_value(result.unwrap())?;
+    let result_resources = get_container_resources(&result, container);
+    let resources = &patch["spec"]["containers"][0]["resources"];
+    if !is_resources_applied(result_resources.as_ref(), resources) {
+        return Err(anyhow::anyhow!("Failed to resize the pod {}", pod_name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resources() {
+        let pod_template = json!({
+            "spec": {
+                "containers": [{
+                    "name": "app",
+                    "resources": {
+                        "requests": { "cpu": "250m", "memory": "256Mi" },
+                        "limits": { "cpu": "1", "memory": "1Gi" }
+                    }
+                }]
+            }
+        });
+        let resources = get_container_resources(&pod_template, "app").unwrap();
+        assert!(get_container_resources(&pod_template, "sidecar").is_none());
+        let current_state_map = get_current_state_map(&resources);
+        assert_eq!(current_state_map["$cpu_request"], 250);
+        assert_eq!(current_state_map["$cpu_limit"], 1000);
+        assert_eq!(current_state_map["$memory_request"], 256);
+        assert_eq!(current_state_map["$memory_limit"], 1024);
+
+        let patch = json!({ "requests": { "cpu": "500m" } });
+        assert!(validate_requests_and_limits(&current_state_map, &patch).is_ok());
+        assert!(!is_resources_applied(Some(&resources), &patch));
+        let patch = json!({ "requests": { "memory": "2048Mi" } });
+        assert!(validate_requests_and_limits(&current_state_map, &patch).is_err());
+        let patch = json!({ "limits": { "cpu": "1000m", "memory": "1024Mi" } });
+        assert!(is_resources_applied(Some(&resources), &patch));
+    }
+
+    #[test]
+    fn test_is_resize_subresource_unsupported() {
+        let get_api_error = |code: u16| {
+            kube::Error::Api(kube::error::ErrorResponse {
+                status: "Failure".to_string(),
+                message: "".to_string(),
+                reason: "".to_string(),
+                code,
+            })
+        };
+        assert!(is_resize_subresource_unsupported(&get_api_error(404)));
+        assert!(is_resize_subresource_unsupported(&get_api_error(422)));
+        // e.g. Forbidden, Conflict
+        assert!(!is_resize_subresource_unsupported(&get_api_error(403)));
+        assert!(!is_resize_subresource_unsupported(&get_api_error(409)));
+    }
+}
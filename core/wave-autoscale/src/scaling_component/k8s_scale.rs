@@ -0,0 +1,250 @@
+/**
+ * [Scaling Component] Kubernetes Scale Subresource Scaling Component
+ *
+ * This component is used to scale any Kubernetes resource that exposes the /scale subresource
+ * e.g. StatefulSet, ReplicaSet, Argo Rollout and the custom resources with the scale subresource
+ * It requires the following metadata:
+ * - namespace: The namespace of the resource
+ * - name: The name of the resource
+ * - api_version: The apiVersion of the resource (e.g. apps/v1, argoproj.io/v1alpha1)
+ * - kind: The kind of the resource (e.g. StatefulSet, Rollout)
+ * The client is created with the optional metadata (see util/k8s)
+ * It requires the following parameters:
+ * - replicas: The number of replicas to scale to
+ *   The current state is available as $replicas, $unavailable_replicas, $available_replicas, $ready_replicas and $updated_replicas
+ *   (0 if the resource doesn't have the field in the status)
+ */
+use super::k8s_deployment::K8sComponentTargetValue;
+use super::ScalingComponent;
+use super::{
+    evaluate_expression_with_current_state, filter_current_state_in_expression,
+    get_expression_language, get_param_expression,
+};
+use crate::util::k8s::{get_status_i64, K8sClientCache};
+use anyhow::{Ok, Result};
+use async_trait::async_trait;
+use data_layer::ScalingComponentDefinition;
+use kube::api::{Api, DynamicObject, Patch, PatchParams};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use strum::IntoEnumIterator;
+
+pub struct K8sScaleScalingComponent {
+    definition: ScalingComponentDefinition,
+    client_cache: K8sClientCache,
+}
+
+impl K8sScaleScalingComponent {
+    pub const SCALING_KIND: &'static str = "kubernetes-scale";
+
+    pub fn new(definition: ScalingComponentDefinition) -> Self {
+        K8sScaleScalingComponent {
+            definition,
+            client_cache: K8sClientCache::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ScalingComponent for K8sScaleScalingComponent {
+    fn get_scaling_component_kind(&self) -> &str {
+        &self.definition.component_kind
+    }
+    fn get_id(&self) -> &str {
+        &self.definition.id
+    }
+
+    async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
+        let metadata = self.definition.metadata.clone();
+
+        let (
+            Some(Value::String(namespace)),
+            Some(Value::String(name)),
+            Some(Value::String(api_version)),
+            Some(Value::String(kind)),
+            Some(replicas),
+        ) = (
+            metadata.get("namespace"),
+            metadata.get("name"),
+            metadata.get("api_version"),
+            metadata.get("kind"),
+            params.get("replicas").and_then(get_param_expression),
+        ) else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+
+        let api = self
+            .client_cache
+            .get_dynamic_api(&self.definition.metadata, namespace, api_version, kind)
+            .await;
+        if let Err(e) = api {
+            return Err(anyhow::anyhow!(e));
+        }
+        let api = api.unwrap();
+
+        // check target value contains enum variables
+        let current_state_key_array = K8sComponentTargetValue::iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<String>>();
+        let current_state_array =
+            filter_current_state_in_expression(&replicas, current_state_key_array);
+        let current_state_map = get_current_state_map(current_state_array, &api, name).await;
+        if current_state_map.is_err() {
+            return Err(current_state_map.unwrap_err());
+        };
+
+        // evaluate target value
+        let replicas = evaluate_expression_with_current_state(
+            &replicas,
+            current_state_map.unwrap(),
+            get_expression_language(&params),
+        )
+        .await;
+        if replicas.is_err() {
+            return Err(replicas.unwrap_err());
+        };
+        let replicas = replicas.unwrap();
+        let replicas = i32::try_from(replicas);
+        if replicas.is_err() || replicas.as_ref().map_or(false, |replicas| *replicas < 0) {
+            return Err(anyhow::anyhow!(
+                "Invalid replicas - {} should be between 0 and {}",
+                name,
+                i32::MAX
+            ));
+        }
+        let replicas = replicas.unwrap();
+
+        let patch = get_scale_patch(replicas);
+        let result = api
+            .patch_scale(name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await;
+        if let Err(e) = result {
+            return Err(anyhow::anyhow!(e));
+        }
+        let result = result.unwrap();
+
+        // Verify the patch
+        let replicas_result = result.spec.and_then(|spec| spec.replicas);
+        if replicas_result != Some(replicas) {
+            return Err(anyhow::anyhow!(
+                "Failed to scale {} {} - replicas: {:?}",
+                kind,
+                name,
+                replicas_result
+            ));
+        }
+        Ok(())
+    }
+}
+
+// https://kubernetes.io/docs/tasks/access-kubernetes-api/custom-resources/custom-resource-definitions/#scale-subresource
+fn get_scale_patch(replicas: i32) -> Value {
+    json!({
+        "spec": {
+            "replicas": replicas
+        }
+    })
+}
+
+async fn get_current_state_map(
+    current_state_array: Vec<String>,
+    api: &Api<DynamicObject>,
+    name: &str,
+) -> Result<HashMap<String, i64>> {
+    let mut current_state_map: HashMap<String, i64> = HashMap::new();
+    if current_state_array.is_empty() {
+        return Ok(current_state_map);
+    }
+    let object = api.get(name).await;
+    if let Err(e) = object {
+        return Err(anyhow::anyhow!("Failed to get {} - {}", name, e));
+    }
+    let object = object.unwrap();
+    for current_state_kind in K8sComponentTargetValue::iter() {
+        let current_state = format!("${}", current_state_kind);
+        if !current_state_array.contains(&current_state) {
+            continue;
+        }
+        let value = get_status_i64(&object, current_state_kind.get_status_field());
+        current_state_map.insert(current_state, value);
+    }
+    Ok(current_state_map)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ScalingComponentManager;
+    use super::*;
+    use data_layer::types::object_kind::ObjectKind;
+
+    #[test]
+    fn test_get_scale_patch() {
+        assert_eq!(get_scale_patch(3), json!({ "spec": { "replicas": 3 } }));
+    }
+
+    #[tokio::test]
+    async fn test_apply_out_of_range_replicas() {
+        let definition = ScalingComponentDefinition {
+            id: "statefulset".to_string(),
+            component_kind: K8sScaleScalingComponent::SCALING_KIND.to_string(),
+            metadata: HashMap::from([
+                ("namespace".to_string(), json!("default")),
+                ("name".to_string(), json!("web")),
+                ("api_version".to_string(), json!("apps/v1")),
+                ("kind".to_string(), json!("StatefulSet")),
+            ]),
+            ..Default::default()
+        };
+        let component = K8sScaleScalingComponent::new(definition);
+        // The API is resolved once, so the apply doesn't need the cluster to check the replicas
+        let api_resource = kube::api::ApiResource::from_gvk(
+            &kube::core::GroupVersion::gv("apps", "v1").with_kind("StatefulSet"),
+        );
+        let client =
+            kube::Client::try_from(kube::Config::new("http://127.0.0.1:1".parse().unwrap()))
+                .unwrap();
+        component
+            .client_cache
+            .set_api(Api::namespaced_with(client, "default", &api_resource));
+        for replicas in ["4294967296", "-1"] {
+            // Rhai evaluates the integers as i64
+            let params = HashMap::from([
+                ("replicas".to_string(), json!(replicas)),
+                ("expression_language".to_string(), json!("rhai")),
+            ]);
+            let error = component.apply(params).await.unwrap_err();
+            assert!(error.to_string().starts_with("Invalid replicas"));
+        }
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_k8s_scale_statefulset() {
+        let scaling_component_metadata = HashMap::from([
+            ("namespace".to_string(), json!("default")),
+            ("name".to_string(), json!("web")),
+            ("api_version".to_string(), json!("apps/v1")),
+            ("kind".to_string(), json!("StatefulSet")),
+        ]);
+        let scaling_component_definitions = vec![ScalingComponentDefinition {
+            kind: ObjectKind::ScalingComponent,
+            db_id: "".to_string(),
+            id: "statefulset".to_string(),
+            component_kind: K8sScaleScalingComponent::SCALING_KIND.to_string(),
+            metadata: scaling_component_metadata,
+            ..Default::default()
+        }];
+
+        let mut scaling_component_manager = ScalingComponentManager::new();
+        scaling_component_manager.add_definitions(scaling_component_definitions);
+
+        let options = HashMap::from([(
+            "replicas".to_string(),
+            json!("Math.max($ready_replicas, $replicas) + 1"),
+        )]);
+        let result = scaling_component_manager
+            .apply_to("statefulset", options)
+            .await;
+        assert!(result.is_ok());
+    }
+}
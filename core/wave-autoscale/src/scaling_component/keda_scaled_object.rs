@@ -0,0 +1,234 @@
+/**
+ * [Scaling Component] KEDA ScaledObject Scaling Component
+ *
+ * This component steers an existing KEDA ScaledObject(keda.sh/v1alpha1)
+ * It requires the following metadata:
+ * - namespace: The namespace of the ScaledObject
+ * - name: The name of the ScaledObject
+ * The client is created with the optional metadata (see util/k8s)
+ * It accepts the following parameters (at least one of them):
+ * - min_replica_count: minReplicaCount
+ * - max_replica_count: maxReplicaCount
+ * - thresholds: metadata.threshold of the triggers by the name or the type of the trigger
+ *   e.g. { "prometheus": "$max_replica_count * 10", "cpu-trigger": 0.5, "kafka": "2.5" }
+ *   The numbers and the decimal strings are used as they are. The other strings are evaluated as expressions.
+ * The current state is available as $min_replica_count and $max_replica_count
+ */
+use super::{
+    evaluate_expression_with_current_state, get_expression_language, get_param_expression,
+    ScalingComponent,
+};
+use crate::util::k8s::K8sClientCache;
+use anyhow::{Ok, Result};
+use async_trait::async_trait;
+use data_layer::ScalingComponentDefinition;
+use kube::api::{DynamicObject, Patch, PatchParams};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+const KEDA_API_VERSION: &str = "keda.sh/v1alpha1";
+const KEDA_SCALED_OBJECT_KIND: &str = "ScaledObject";
+// The defaults of KEDA
+const DEFAULT_MIN_REPLICA_COUNT: i64 = 0;
+const DEFAULT_MAX_REPLICA_COUNT: i64 = 100;
+
+pub struct KedaScaledObjectScalingComponent {
+    definition: ScalingComponentDefinition,
+    client_cache: K8sClientCache,
+}
+
+impl KedaScaledObjectScalingComponent {
+    pub const SCALING_KIND: &'static str = "keda-scaled-object";
+
+    pub fn new(definition: ScalingComponentDefinition) -> Self {
+        KedaScaledObjectScalingComponent {
+            definition,
+            client_cache: K8sClientCache::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ScalingComponent for KedaScaledObjectScalingComponent {
+    fn get_scaling_component_kind(&self) -> &str {
+        &self.definition.component_kind
+    }
+    fn get_id(&self) -> &str {
+        &self.definition.id
+    }
+
+    async fn apply(&self, params: HashMap<String, Value>) -> Result<()> {
+        let metadata = self.definition.metadata.clone();
+        let (Some(Value::String(namespace)), Some(Value::String(name))) =
+            (metadata.get("namespace"), metadata.get("name"))
+        else {
+            return Err(anyhow::anyhow!("Invalid metadata"));
+        };
+
+        let api = self
+            .client_cache
+            .get_dynamic_api(
+                &self.definition.metadata,
+                namespace,
+                KEDA_API_VERSION,
+                KEDA_SCALED_OBJECT_KIND,
+            )
+            .await;
+        if let Err(e) = api {
+            return Err(anyhow::anyhow!(e));
+        }
+        let api = api.unwrap();
+        let scaled_object = api.get(name).await;
+        if let Err(e) = scaled_object {
+            return Err(anyhow::anyhow!(
+                "Failed to get ScaledObject {} - {}",
+                name,
+                e
+            ));
+        }
+        let scaled_object = scaled_object.unwrap();
+        let current_state_map = get_current_state_map(&scaled_object);
+        let language = get_expression_language(&params);
+
+        let evaluate = |expression: String| {
+            let current_state_map = current_state_map.clone();
+            async move {
+                evaluate_expression_with_current_state(&expression, current_state_map, language)
+                    .await
+            }
+        };
+
+        // https://keda.sh/docs/latest/concepts/scaling-deployments/#scaledobject-spec
+        let mut patch_spec = json!({});
+        if let Some(expression) = params
+            .get("min_replica_count")
+            .and_then(get_param_expression)
+        {
+            patch_spec["minReplicaCount"] = json!(evaluate(expression).await?);
+        }
+        if let Some(expression) = params
+            .get("max_replica_count")
+            .and_then(get_param_expression)
+        {
+            patch_spec["maxReplicaCount"] = json!(evaluate(expression).await?);
+        }
+        let min_replica_count = patch_spec["minReplicaCount"]
+            .as_i64()
+            .unwrap_or(current_state_map["$min_replica_count"]);
+        let max_replica_count = patch_spec["maxReplicaCount"]
+            .as_i64()
+            .unwrap_or(current_state_map["$max_replica_count"]);
+        if min_replica_count > max_replica_count {
+            return Err(anyhow::anyhow!(
+                "min_replica_count({}) is greater than max_replica_count({})",
+                min_replica_count,
+                max_replica_count
+            ));
+        }
+
+        if let Some(Value::Object(thresholds)) = params.get("thresholds") {
+            let mut triggers = scaled_object
+                .data
+                .get("spec")
+                .and_then(|spec| spec.get("triggers"))
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            for (trigger_key, expression) in thresholds.iter() {
+                let threshold = match expression {
+                    Value::Number(number) => number.to_string(),
+                    Value::String(threshold) if threshold.trim().parse::<f64>().is_ok() => {
+                        threshold.trim().to_string()
+                    }
+                    Value::String(expression) => evaluate(expression.clone()).await?.to_string(),
+                    _ => return Err(anyhow::anyhow!("Invalid threshold of {}", trigger_key)),
+                };
+                if !set_trigger_threshold(&mut triggers, trigger_key, &threshold) {
+                    return Err(anyhow::anyhow!("Trigger not found: {}", trigger_key));
+                }
+            }
+            // The merge patch replaces the whole triggers array
+            patch_spec["triggers"] = json!(triggers);
+        }
+        if patch_spec.as_object().map_or(true, |spec| spec.is_empty()) {
+            return Err(anyhow::anyhow!(
+                "Invalid params - no ScaledObject fields to patch"
+            ));
+        }
+
+        let result = api
+            .patch(
+                name,
+                &PatchParams::default(),
+                &Patch::Merge(&json!({ "spec": patch_spec.clone() })),
+            )
+            .await;
+        if let Err(e) = result {
+            return Err(anyhow::anyhow!(e));
+        }
+        let result: DynamicObject = result.unwrap();
+
+        // Verify the patch
+        let result_spec = result.data.get("spec").cloned().unwrap_or(Value::Null);
+        for (key, value) in patch_spec.as_object().unwrap().iter() {
+            if result_spec.get(key) != Some(value) {
+                return Err(anyhow::anyhow!("Failed to patch ScaledObject - {}", key));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn get_current_state_map(scaled_object: &DynamicObject) -> HashMap<String, i64> {
+    let spec = scaled_object.data.get("spec");
+    let get_count = |key: &str, default: i64| {
+        spec.and_then(|spec| spec.get(key))
+            .and_then(Value::as_i64)
+            .unwrap_or(default)
+    };
+    HashMap::from([
+        (
+            "$min_replica_count".to_string(),
+            get_count("minReplicaCount", DEFAULT_MIN_REPLICA_COUNT),
+        ),
+        (
+            "$max_replica_count".to_string(),
+            get_count("maxReplicaCount", DEFAULT_MAX_REPLICA_COUNT),
+        ),
+    ])
+}
+
+// Set metadata.threshold of the triggers with the name or the type. false if no trigger matches.
+fn set_trigger_threshold(triggers: &mut [Value], trigger_key: &str, threshold: &str) -> bool {
+    let mut found = false;
+    for trigger in triggers.iter_mut() {
+        let matches = [trigger.get("name"), trigger.get("type")]
+            .iter()
+            .any(|value| value.and_then(Value::as_str) == Some(trigger_key));
+        if !matches {
+            continue;
+        }
+        // The values of the trigger metadata are strings in KEDA
+        trigger["metadata"]["threshold"] = json!(threshold);
+        found = true;
+    }
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_set_trigger_threshold() {
+        let mut triggers = vec![
+            json!({ "type": "prometheus", "metadata": { "threshold": "100", "query": "sum(rate(http_requests_total[1m]))" } }),
+            json!({ "type": "cpu", "name": "cpu-trigger", "metadata": { "value": "60" } }),
+        ];
+        assert!(set_trigger_threshold(&mut triggers, "prometheus", "200"));
+        assert_eq!(triggers[0]["metadata"]["threshold"], json!("200"));
+        assert!(set_trigger_threshold(&mut triggers, "cpu-trigger", "0.5"));
+        assert_eq!(triggers[1]["metadata"]["threshold"], json!("0.5"));
+        assert!(!set_trigger_threshold(&mut triggers, "kafka", "10"));
+    }
+}
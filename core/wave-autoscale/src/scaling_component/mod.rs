@@ -1,5 +1,7 @@
 pub mod amazon_dynamodb_table;
 pub mod amazon_emr_ec2;
+pub mod amazon_kinesis;
+pub mod aws_application_autoscaling;
 pub mod aws_ec2_autoscaling;
 pub mod aws_ecs_service_scaling;
 pub mod aws_lambda_function;
@@ -10,14 +12,23 @@ pub mod cloudflare_rule;
 pub mod gcp_mig_autoscaling;
 pub mod google_cloud_functions_instance;
 pub mod google_cloud_run_service;
+pub mod google_cloud_sql_instance;
+pub mod google_kubernetes_engine_node_pool;
 pub mod k8s_deployment;
+pub mod k8s_hpa;
 pub mod k8s_json_patch;
+pub mod k8s_node_capacity;
+pub mod k8s_resources;
+pub mod k8s_scale;
+pub mod keda_scaled_object;
 pub mod netfunnel_segment;
 pub mod wa_logger;
 
 use self::{
     amazon_dynamodb_table::DynamoDbTableScalingComponent,
-    amazon_emr_ec2::EMREC2AutoScalingComponent, aws_ec2_autoscaling::EC2AutoScalingComponent,
+    amazon_emr_ec2::EMREC2AutoScalingComponent, amazon_kinesis::KinesisStreamScalingComponent,
+    aws_application_autoscaling::ApplicationAutoScalingComponent,
+    aws_ec2_autoscaling::EC2AutoScalingComponent,
     aws_ecs_service_scaling::ECSServiceScalingComponent,
     aws_lambda_function::LambdaFunctionScalingComponent, aws_wafv2::AWSWAFv2ScalingComponent,
     azure_functions_app::AzureFunctionsAppScalingComponent,
@@ -25,14 +36,24 @@ use self::{
     cloudflare_rule::CloudflareRuleScalingComponent, gcp_mig_autoscaling::MIGAutoScalingComponent,
     google_cloud_functions_instance::CloudFunctionsInstanceScalingComponent,
     google_cloud_run_service::CloudRunServiceScalingComponent,
-    k8s_deployment::K8sDeploymentScalingComponent, k8s_json_patch::K8sPatchScalingComponent,
+    google_cloud_sql_instance::CloudSqlInstanceScalingComponent,
+    google_kubernetes_engine_node_pool::GkeNodePoolScalingComponent,
+    k8s_deployment::K8sDeploymentScalingComponent, k8s_hpa::K8sHpaScalingComponent,
+    k8s_json_patch::K8sPatchScalingComponent, k8s_node_capacity::K8sNodeCapacityScalingComponent,
+    k8s_resources::K8sResourcesScalingComponent, k8s_scale::K8sScaleScalingComponent,
+    keda_scaled_object::KedaScaledObjectScalingComponent,
     netfunnel_segment::NetfunnelSegmentScalingComponent, wa_logger::WALoggerComponent,
 };
+use crate::scaling_planner::expression_engine::{
+    create_expression_engine, DEFAULT_EXPRESSION_LANGUAGE, EXPRESSION_LANGUAGE_PARAM,
+};
+use crate::util::reconciler::{diff_definition_hashes, get_definition_hash, DefinitionDiff};
 use anyhow::Result;
 use async_trait::async_trait;
 use data_layer::ScalingComponentDefinition;
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
+use tracing::error;
 
 // ScalingComponent can be used in multiple threads. So it needs to be Send + Sync.
 #[async_trait]
@@ -50,12 +71,15 @@ pub type SharedScalingComponentManager = Arc<RwLock<ScalingComponentManager>>;
 #[derive(Default)]
 pub struct ScalingComponentManager {
     scaling_components: HashMap<String, Box<dyn ScalingComponent>>,
+    // key: id, value: content hash of the definition
+    definition_hashes: HashMap<String, u64>,
 }
 
 impl ScalingComponentManager {
     pub fn new() -> Self {
         ScalingComponentManager {
             scaling_components: HashMap::new(),
+            definition_hashes: HashMap::new(),
         }
     }
     pub fn new_shared() -> SharedScalingComponentManager {
@@ -77,6 +101,21 @@ impl ScalingComponentManager {
             K8sPatchScalingComponent::SCALING_KIND => {
                 Ok(Box::new(K8sPatchScalingComponent::new(cloned_defintion)))
             }
+            K8sScaleScalingComponent::SCALING_KIND => {
+                Ok(Box::new(K8sScaleScalingComponent::new(cloned_defintion)))
+            }
+            K8sResourcesScalingComponent::SCALING_KIND => Ok(Box::new(
+                K8sResourcesScalingComponent::new(cloned_defintion),
+            )),
+            K8sNodeCapacityScalingComponent::SCALING_KIND => Ok(Box::new(
+                K8sNodeCapacityScalingComponent::new(cloned_defintion),
+            )),
+            K8sHpaScalingComponent::SCALING_KIND => {
+                Ok(Box::new(K8sHpaScalingComponent::new(cloned_defintion)))
+            }
+            KedaScaledObjectScalingComponent::SCALING_KIND => Ok(Box::new(
+                KedaScaledObjectScalingComponent::new(cloned_defintion),
+            )),
             // AWS
             EC2AutoScalingComponent::SCALING_KIND => {
                 Ok(Box::new(EC2AutoScalingComponent::new(cloned_defintion)))
@@ -96,6 +135,12 @@ impl ScalingComponentManager {
             AWSWAFv2ScalingComponent::SCALING_KIND => {
                 Ok(Box::new(AWSWAFv2ScalingComponent::new(cloned_defintion)))
             }
+            ApplicationAutoScalingComponent::SCALING_KIND => Ok(Box::new(
+                ApplicationAutoScalingComponent::new(cloned_defintion),
+            )),
+            KinesisStreamScalingComponent::SCALING_KIND => Ok(Box::new(
+                KinesisStreamScalingComponent::new(cloned_defintion),
+            )),
             // Google Cloud
             MIGAutoScalingComponent::SCALING_KIND => {
                 Ok(Box::new(MIGAutoScalingComponent::new(cloned_defintion)))
@@ -106,6 +151,12 @@ impl ScalingComponentManager {
             CloudRunServiceScalingComponent::SCALING_KIND => Ok(Box::new(
                 CloudRunServiceScalingComponent::new(cloned_defintion),
             )),
+            GkeNodePoolScalingComponent::SCALING_KIND => {
+                Ok(Box::new(GkeNodePoolScalingComponent::new(cloned_defintion)))
+            }
+            CloudSqlInstanceScalingComponent::SCALING_KIND => Ok(Box::new(
+                CloudSqlInstanceScalingComponent::new(cloned_defintion),
+            )),
             // Azure
             VMSSAutoScalingComponent::SCALING_KIND => {
                 Ok(Box::new(VMSSAutoScalingComponent::new(cloned_defintion)))
@@ -132,16 +183,31 @@ impl ScalingComponentManager {
         scaling_component_definition: ScalingComponentDefinition,
     ) -> Result<()> {
         let scaling_component = self.create_scaling_component(&scaling_component_definition)?;
+        self.definition_hashes.insert(
+            scaling_component_definition.id.clone(),
+            get_definition_hash(&scaling_component_definition),
+        );
         self.add_scaling_component(scaling_component);
         Ok(())
     }
 
+    // It adds all the valid definitions and returns the errors of the invalid ones together
     pub fn add_definitions(
         &mut self,
         scaling_component_definitions: Vec<ScalingComponentDefinition>,
     ) -> Result<()> {
+        let mut errors: Vec<String> = Vec::new();
         for scaling_component_definition in scaling_component_definitions {
-            self.add_definition(scaling_component_definition)?;
+            let id = scaling_component_definition.id.clone();
+            if let Err(error) = self.add_definition(scaling_component_definition) {
+                errors.push(format!("{}: {}", id, error));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Failed to create the scaling components - {}",
+                errors.join(", ")
+            ));
         }
         Ok(())
     }
@@ -157,6 +223,59 @@ impl ScalingComponentManager {
 
     pub fn remove_all(&mut self) {
         self.scaling_components.clear();
+        self.definition_hashes.clear();
+    }
+
+    pub fn remove_scaling_component(&mut self, id: &str) {
+        self.scaling_components.remove(id);
+        self.definition_hashes.remove(id);
+    }
+
+    // Compare the new definitions with the existing ones by id and content hash.
+    // Only the scaling components that are added, changed or removed are recreated.
+    // The new scaling components are created before the swap, so an invalid definition doesn't affect the others.
+    // The invalid one keeps the running scaling component(if any) and the old hash, so it is retried on the next reconcile.
+    pub fn reconcile_definitions(
+        &mut self,
+        scaling_component_definitions: Vec<ScalingComponentDefinition>,
+    ) -> DefinitionDiff {
+        let new_hashes = scaling_component_definitions
+            .iter()
+            .map(|definition| (definition.id.clone(), get_definition_hash(definition)))
+            .collect::<HashMap<String, u64>>();
+        let mut diff = diff_definition_hashes(&self.definition_hashes, &new_hashes);
+
+        let ids_to_add = diff.get_ids_to_add();
+        let mut scaling_components: Vec<Box<dyn ScalingComponent>> = Vec::new();
+        let mut failed: Vec<String> = Vec::new();
+        for scaling_component_definition in scaling_component_definitions.iter() {
+            if !ids_to_add.contains(&scaling_component_definition.id) {
+                continue;
+            }
+            match self.create_scaling_component(scaling_component_definition) {
+                Ok(scaling_component) => scaling_components.push(scaling_component),
+                Err(error) => {
+                    error!(
+                        "[ScalingComponentManager] Failed to create the scaling component({}): {}",
+                        scaling_component_definition.id, error
+                    );
+                    failed.push(scaling_component_definition.id.clone());
+                }
+            }
+        }
+
+        for id in diff.removed.iter() {
+            self.remove_scaling_component(id);
+        }
+        for scaling_component in scaling_components {
+            let id = scaling_component.get_id().to_string();
+            if let Some(new_hash) = new_hashes.get(&id) {
+                self.definition_hashes.insert(id, *new_hash);
+            }
+            self.add_scaling_component(scaling_component);
+        }
+        diff.set_failed(failed);
+        diff
     }
 
     pub fn get_scaling_component(&self, id: &str) -> Option<&Box<dyn ScalingComponent>> {
@@ -191,32 +310,32 @@ pub fn filter_current_state_in_expression(
     result_vec
 }
 
+// The expression of the param. e.g. 3 or "$replicas + 1"
+pub fn get_param_expression(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(expression) => Some(expression.clone()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+// The language of the expressions in the params which the scaling planner delivers (default: javascript)
+pub fn get_expression_language(params: &HashMap<String, serde_json::Value>) -> &str {
+    params
+        .get(EXPRESSION_LANGUAGE_PARAM)
+        .and_then(serde_json::Value::as_str)
+        .unwrap_or(DEFAULT_EXPRESSION_LANGUAGE)
+}
+
 pub async fn evaluate_expression_with_current_state(
     expression: &str,
     current_state_map: HashMap<String, i64>,
+    language: &str,
 ) -> Result<i64, anyhow::Error> {
-    let Result::Ok(runtime) = rquickjs::AsyncRuntime::new() else {
-        return Err(anyhow::anyhow!("rquickjs::AsyncRuntime::new() error"));
-    };
-    let Result::Ok(context) = rquickjs::AsyncContext::full(&runtime).await else {
-        return Err(anyhow::anyhow!("rquickjs::AsyncRuntime::full() error"));
-    };
-    rquickjs::async_with!(context => |ctx| {
-        current_state_map.iter().for_each(|(current_state_key, current_state_value)| {
-            let _ = ctx.globals().set(
-                current_state_key, current_state_value
-            );
-        })
-    })
-    .await;
-
-    rquickjs::async_with!(context => |ctx| {
-        let Result::Ok(result) = ctx.eval::<i64, _>(expression) else {
-            return Err(anyhow::anyhow!("Invalid target value"));
-        };
-        Ok(result)
-    })
-    .await
+    let expression_engine = create_expression_engine(language).await?;
+    expression_engine
+        .evaluate_i64_with_variables(expression, &current_state_map)
+        .await
 }
 
 #[cfg(test)]
@@ -256,6 +375,79 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_reconcile_definitions() {
+        let get_definition = |id: &str, value: &str| ScalingComponentDefinition {
+            id: id.to_string(),
+            component_kind: wa_logger::WALoggerComponent::SCALING_KIND.to_string(),
+            metadata: HashMap::from([("key".to_string(), serde_json::json!(value))]),
+            ..Default::default()
+        };
+        let mut scaling_component_manager = ScalingComponentManager::new();
+        let diff = scaling_component_manager.reconcile_definitions(vec![
+            get_definition("component_1", "value"),
+            get_definition("component_2", "value"),
+        ]);
+        assert_eq!(diff.added, vec!["component_1", "component_2"]);
+        assert_eq!(scaling_component_manager.get_scaling_components().len(), 2);
+
+        let diff = scaling_component_manager.reconcile_definitions(vec![
+            get_definition("component_1", "value"),
+            get_definition("component_2", "changed"),
+            get_definition("component_3", "value"),
+        ]);
+        assert_eq!(diff.unchanged, vec!["component_1"]);
+        assert_eq!(diff.changed, vec!["component_2"]);
+        assert_eq!(diff.added, vec!["component_3"]);
+        assert_eq!(scaling_component_manager.get_scaling_components().len(), 3);
+
+        let diff = scaling_component_manager
+            .reconcile_definitions(vec![get_definition("component_3", "value")]);
+        assert_eq!(diff.removed, vec!["component_1", "component_2"]);
+        assert!(scaling_component_manager
+            .get_scaling_component("component_1")
+            .is_none());
+        assert_eq!(scaling_component_manager.get_scaling_components().len(), 1);
+    }
+
+    #[test]
+    fn test_reconcile_definitions_with_invalid_definition() {
+        let get_definition = |id: &str, component_kind: &str| ScalingComponentDefinition {
+            id: id.to_string(),
+            component_kind: component_kind.to_string(),
+            ..Default::default()
+        };
+        let mut scaling_component_manager = ScalingComponentManager::new();
+        scaling_component_manager.reconcile_definitions(vec![get_definition(
+            "component_1",
+            wa_logger::WALoggerComponent::SCALING_KIND,
+        )]);
+
+        // The invalid definition doesn't stop the valid ones and the running one is kept
+        let diff = scaling_component_manager.reconcile_definitions(vec![
+            get_definition("component_1", "unknown-kind"),
+            get_definition("component_2", wa_logger::WALoggerComponent::SCALING_KIND),
+            get_definition("component_3", "unknown-kind"),
+        ]);
+        assert_eq!(diff.added, vec!["component_2"]);
+        assert!(diff.changed.is_empty());
+        assert_eq!(diff.failed, vec!["component_1", "component_3"]);
+        assert_eq!(scaling_component_manager.get_scaling_components().len(), 2);
+        assert!(scaling_component_manager
+            .get_scaling_component("component_1")
+            .is_some());
+
+        // The failed ones are retried on the next reconcile
+        let diff = scaling_component_manager.reconcile_definitions(vec![
+            get_definition("component_1", wa_logger::WALoggerComponent::SCALING_KIND),
+            get_definition("component_2", wa_logger::WALoggerComponent::SCALING_KIND),
+            get_definition("component_3", wa_logger::WALoggerComponent::SCALING_KIND),
+        ]);
+        assert_eq!(diff.added, vec!["component_3"]);
+        assert!(diff.failed.is_empty());
+        assert_eq!(scaling_component_manager.get_scaling_components().len(), 3);
+    }
+
     #[tokio::test]
     async fn test_evaluation_current_state() {
         let current_state_key_array = TestComponentTargetValue::iter()
@@ -274,26 +466,54 @@ mod test {
             }
         }
         assert_eq!(
-            evaluate_expression_with_current_state(expression, current_state_map.clone())
-                .await
-                .unwrap(),
+            evaluate_expression_with_current_state(
+                expression,
+                current_state_map.clone(),
+                DEFAULT_EXPRESSION_LANGUAGE
+            )
+            .await
+            .unwrap(),
             4
         );
 
         let expression2 = "2 * 4";
         assert_eq!(
-            evaluate_expression_with_current_state(expression2, current_state_map.clone())
-                .await
-                .unwrap(),
+            evaluate_expression_with_current_state(
+                expression2,
+                current_state_map.clone(),
+                DEFAULT_EXPRESSION_LANGUAGE
+            )
+            .await
+            .unwrap(),
             8
         );
 
         let expression3 = "4 * 4";
         assert_eq!(
-            evaluate_expression_with_current_state(expression3, HashMap::new())
-                .await
-                .unwrap(),
+            evaluate_expression_with_current_state(
+                expression3,
+                HashMap::new(),
+                DEFAULT_EXPRESSION_LANGUAGE
+            )
+            .await
+            .unwrap(),
             16
         );
+
+        // The params are evaluated with the language of the plan
+        let params = HashMap::from([(
+            EXPRESSION_LANGUAGE_PARAM.to_string(),
+            serde_json::json!("rhai"),
+        )]);
+        assert_eq!(
+            evaluate_expression_with_current_state(
+                expression,
+                current_state_map,
+                get_expression_language(&params)
+            )
+            .await
+            .unwrap(),
+            4
+        );
     }
 }
@@ -0,0 +1,118 @@
+/**
+ * Anomaly Detection
+ *
+ * The deviation score of the current value from the baseline for the traffic without a good static threshold.
+ * - get({ metric_id: 'metric_id', stats: 'zscore', period_sec: 300, baseline_sec: 86400 })
+ * - anomaly({ metric_id: 'metric_id', method: 'mad' })
+ *
+ * The current value is the average in period_sec and the baseline is the values in baseline_sec before period_sec,
+ * so that a spike in the current window doesn't raise the baseline.
+ * 1. zscore: (current - mean) / standard deviation
+ * 2. mad: (current - median) / (1.4826 * median absolute deviation) - robust to the outliers in the baseline
+ *
+ * The baseline is limited to the source metrics kept in memory (metric_buffer_size_kb).
+ */
+use anyhow::{anyhow, Result};
+
+pub const DEFAULT_BASELINE_SEC: u64 = 24 * 60 * 60;
+pub const ANOMALY_METHOD_ZSCORE: &str = "zscore";
+pub const ANOMALY_METHOD_MAD: &str = "mad";
+// To make MAD consistent with the standard deviation for the normal distribution
+const MAD_SCALE_FACTOR: f64 = 1.4826;
+
+fn get_mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn get_median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let middle = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+// The score is 0 if the baseline doesn't vary
+fn get_score(deviation: f64, scale: f64) -> f64 {
+    if scale == 0.0 {
+        return 0.0;
+    }
+    deviation / scale
+}
+
+pub fn get_zscore(current: f64, baseline: &[f64]) -> Result<f64> {
+    if baseline.is_empty() {
+        return Err(anyhow!("The baseline has no data"));
+    }
+    let mean = get_mean(baseline);
+    let variance = baseline
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / baseline.len() as f64;
+    Ok(get_score(current - mean, variance.sqrt()))
+}
+
+pub fn get_mad_score(current: f64, baseline: &[f64]) -> Result<f64> {
+    if baseline.is_empty() {
+        return Err(anyhow!("The baseline has no data"));
+    }
+    let median = get_median(baseline);
+    let absolute_deviations = baseline
+        .iter()
+        .map(|value| (value - median).abs())
+        .collect::<Vec<f64>>();
+    let mad = get_median(&absolute_deviations);
+    Ok(get_score(current - median, MAD_SCALE_FACTOR * mad))
+}
+
+pub fn is_anomaly_method(method: &str) -> bool {
+    let method = method.to_lowercase();
+    method == ANOMALY_METHOD_ZSCORE || method == ANOMALY_METHOD_MAD
+}
+
+// current: the values in period_sec, baseline: the values in baseline_sec
+pub fn get_anomaly_score(method: &str, current: &[f64], baseline: &[f64]) -> Result<f64> {
+    if current.is_empty() {
+        return Err(anyhow!("The metric has no data"));
+    }
+    let current = get_mean(current);
+    match method.to_lowercase().as_str() {
+        ANOMALY_METHOD_ZSCORE => get_zscore(current, baseline),
+        ANOMALY_METHOD_MAD => get_mad_score(current, baseline),
+        _ => Err(anyhow!("Unknown anomaly method: {}", method)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_anomaly_score() {
+        let baseline = vec![2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        // mean: 5, standard deviation: 2
+        assert_eq!(get_zscore(9.0, &baseline).unwrap(), 2.0);
+        assert_eq!(get_zscore(5.0, &baseline).unwrap(), 0.0);
+        // median: 4.5, MAD: 0.5
+        assert_eq!(
+            get_mad_score(6.0, &baseline).unwrap(),
+            1.5 / (MAD_SCALE_FACTOR * 0.5)
+        );
+        // The baseline doesn't vary
+        assert_eq!(get_zscore(10.0, &[1.0, 1.0]).unwrap(), 0.0);
+        assert!(get_zscore(1.0, &[]).is_err());
+
+        assert_eq!(
+            get_anomaly_score("ZSCORE", &[8.0, 10.0], &baseline).unwrap(),
+            2.0
+        );
+        assert!(get_anomaly_score("zscore", &[], &baseline).is_err());
+        assert!(get_anomaly_score("unknown", &[1.0], &baseline).is_err());
+        assert!(is_anomaly_method("mad"));
+        assert!(!is_anomaly_method("avg"));
+    }
+}
@@ -0,0 +1,976 @@
+/**
+ * Expression Engine
+ *
+ * The engines to evaluate the expressions of the scaling plans and the params of the scaling components.
+ * The engine is selected by metadata.language of the scaling plan.
+ *
+ * 1. javascript(default): QuickJS
+ * 2. rhai: Rhai - pure Rust, sandboxed and the syntax can be checked without evaluating
+ *
+ * Both engines provide get() to get the metric values.
+ * - javascript: get({ metric_id: 'metric_id', stats: 'max' })
+ * - rhai: get(#{ metric_id: "metric_id", stats: "max" })
+ *
+ * get_series() returns the values as [{ ts, value, tags }] and get_grouped() returns { 'tag values': stats value } by group_by.
+ * - javascript: get_grouped({ metric_id: 'metric_id', stats: 'avg', group_by: ['az'] })
+ * - rhai: get_grouped(#{ metric_id: "metric_id", stats: "avg", group_by: ["az"] })
+ * The tag values support '!value'(not equal), '~regex' and '!~regex'.
+ * anomaly() returns the deviation score from the baseline. (see anomaly.rs)
+ * log() captures the logs per evaluation. (see expression_log.rs)
+ * What get() returns for the metric without data is decided by on_missing. (see missing_metric.rs)
+ *
+ * Both engines provide the time helpers - now(), hour(tz), weekday(tz), in_window(window, tz) and is_holiday(calendar_id, tz).
+ * (see time_helpers.rs)
+ *
+ * The functions in the ExpressionLibrary definitions with the same language are loaded before the evaluation.
+ *
+ * Both engines limit the time and the memory of the evaluation, because the expressions can be evaluated ad-hoc by the API.
+ */
+use super::{
+    anomaly_in_js, anomaly_in_rhai,
+    expression_log::{ExpressionLogs, SharedExpressionLogs},
+    get_grouped_in_js, get_grouped_in_rhai, get_in_js, get_in_rhai, get_series_in_js,
+    get_series_in_rhai,
+    missing_metric::{MetricAccessState, MissingPolicy, SharedMetricAccessState},
+    time_helpers::{
+        get_hour, get_now_millis, get_weekday, is_holiday, is_in_window, HolidayCalendars,
+    },
+    to_js_error, to_rhai_error,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use data_layer::ExpressionLibraryDefinition;
+use rquickjs::{async_with, prelude::Opt};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+pub const EXPRESSION_LANGUAGE_JAVASCRIPT: &str = "javascript";
+pub const EXPRESSION_LANGUAGE_RHAI: &str = "rhai";
+pub const DEFAULT_EXPRESSION_LANGUAGE: &str = EXPRESSION_LANGUAGE_JAVASCRIPT;
+// The key of the params to deliver the language of the plan to the scaling components
+pub const EXPRESSION_LANGUAGE_PARAM: &str = "expression_language";
+
+// To prevent infinite loops in Rhai expressions
+const RHAI_MAX_OPERATIONS: u64 = 1_000_000;
+// To prevent the expressions from using up the memory of the process (e.g. doubling a string in a loop)
+const RHAI_MAX_STRING_SIZE: usize = 1024 * 1024;
+const RHAI_MAX_ARRAY_SIZE: usize = 100_000;
+const RHAI_MAX_MAP_SIZE: usize = 100_000;
+const JS_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+const JS_MAX_STACK_SIZE: usize = 1024 * 1024;
+// To prevent infinite loops in JavaScript expressions. QuickJS calls the interrupt handler while evaluating.
+const JS_EVALUATION_TIMEOUT: Duration = Duration::from_secs(3);
+// log(...args) in JavaScript joins the arguments like console.log and passes them to the native function
+const JS_LOG_FUNCTION: &str = r#"
+function log(...args) {
+  __log(args.map((arg) => (typeof arg === 'string' ? arg : JSON.stringify(arg))).join(' '));
+}
+"#;
+
+#[async_trait]
+pub trait ExpressionEngine: Send + Sync {
+    fn get_language(&self) -> &str;
+    fn get_metric_access_state(&self) -> &SharedMetricAccessState;
+    fn get_expression_logs(&self) -> &SharedExpressionLogs;
+    // The policy of get() for the metrics without data (metadata.on_missing of the scaling plan)
+    fn set_default_missing_policy(&self, policy: MissingPolicy) {
+        if let Ok(mut state) = self.get_metric_access_state().lock() {
+            state.default_policy = policy;
+        }
+    }
+    // The metrics without data since the last call
+    fn take_stale_metric_ids(&self) -> BTreeSet<String> {
+        match self.get_metric_access_state().lock() {
+            Ok(mut state) => std::mem::take(&mut state.stale_metric_ids),
+            Err(_) => BTreeSet::new(),
+        }
+    }
+    // get() reads the metrics as of {time_offset_sec} seconds ago (the ad-hoc evaluation of the past)
+    fn set_time_offset_sec(&self, time_offset_sec: u64) {
+        if let Ok(mut state) = self.get_metric_access_state().lock() {
+            state.time_offset_sec = time_offset_sec;
+        }
+    }
+    // The logs of log() since the last call
+    fn take_logs(&self) -> Vec<String> {
+        match self.get_expression_logs().lock() {
+            Ok(mut expression_logs) => expression_logs.take(),
+            Err(_) => Vec::new(),
+        }
+    }
+    // Load the code that defines the reusable functions(ExpressionLibrary)
+    async fn load_library(&mut self, code: &str) -> Result<()>;
+    // Register is_holiday() with the dates of the HolidayCalendar definitions
+    async fn set_holiday_calendars(&mut self, holiday_calendars: HolidayCalendars) -> Result<()>;
+    // Check the syntax of the expression without evaluating it
+    async fn validate(&self, expression: &str) -> Result<()>;
+    async fn evaluate_bool(&self, expression: &str) -> Result<bool>;
+    async fn evaluate_f64(&self, expression: &str) -> Result<f64>;
+    // The result of any type for the ad-hoc evaluation. None if the result is undefined.
+    async fn evaluate_json(&self, expression: &str) -> Result<Option<serde_json::Value>>;
+    // variables: e.g. { "$replicas": 1 } for the params of the scaling components
+    // The variables are visible only in this evaluation. A float result is rounded to the nearest integer (see round_to_i64).
+    async fn evaluate_i64_with_variables(
+        &self,
+        expression: &str,
+        variables: &HashMap<String, i64>,
+    ) -> Result<i64>;
+}
+
+pub async fn create_expression_engine(language: &str) -> Result<Box<dyn ExpressionEngine>> {
+    match language {
+        EXPRESSION_LANGUAGE_JAVASCRIPT => Ok(Box::new(QuickJsEngine::new().await?)),
+        EXPRESSION_LANGUAGE_RHAI => Ok(Box::new(RhaiEngine::new())),
+        _ => Err(anyhow::anyhow!("Unknown expression language: {}", language)),
+    }
+}
+
+// The params of the scaling components are integers, so both engines round the float results the same way
+fn round_to_i64(value: f64) -> Result<i64> {
+    let rounded = value.round();
+    if !rounded.is_finite() || rounded < i64::MIN as f64 || rounded >= i64::MAX as f64 {
+        return Err(anyhow::anyhow!("Invalid target value - {}", value));
+    }
+    Ok(rounded as i64)
+}
+
+// Load the expression libraries with the same language into the engine. It stops at the first invalid library.
+pub async fn load_expression_libraries(
+    expression_engine: &mut dyn ExpressionEngine,
+    expression_libraries: &[ExpressionLibraryDefinition],
+) -> Result<()> {
+    for expression_library in expression_libraries.iter() {
+        if expression_library.language.to_lowercase() != expression_engine.get_language() {
+            continue;
+        }
+        expression_engine
+            .load_library(expression_library.code.as_str())
+            .await
+            .map_err(|error| {
+                anyhow::anyhow!(
+                    "Failed to load the expression library({}): {}",
+                    expression_library.id,
+                    error
+                )
+            })?;
+    }
+    Ok(())
+}
+
+// Check whether the expression library can be loaded into a new engine of its language
+pub async fn validate_expression_library(
+    expression_library: &ExpressionLibraryDefinition,
+) -> Result<()> {
+    let mut expression_engine =
+        create_expression_engine(expression_library.language.to_lowercase().as_str()).await?;
+    load_expression_libraries(
+        expression_engine.as_mut(),
+        std::slice::from_ref(expression_library),
+    )
+    .await
+}
+
+pub struct QuickJsEngine {
+    // The runtime has to live as long as the context
+    _runtime: rquickjs::AsyncRuntime,
+    context: rquickjs::AsyncContext,
+    metric_access_state: SharedMetricAccessState,
+    expression_logs: SharedExpressionLogs,
+    // The evaluation is interrupted after the deadline
+    deadline: Arc<Mutex<Option<Instant>>>,
+}
+
+impl QuickJsEngine {
+    pub async fn new() -> Result<Self> {
+        let Ok(runtime) = rquickjs::AsyncRuntime::new() else {
+            return Err(anyhow::anyhow!("rquickjs::AsyncRuntime::new() error"));
+        };
+        let Ok(context) = rquickjs::AsyncContext::full(&runtime).await else {
+            return Err(anyhow::anyhow!("rquickjs::AsyncContext::full() error"));
+        };
+        // e.g. new Array(1e9).fill(0) fails with an out of memory error instead of crashing the process
+        runtime.set_memory_limit(JS_MEMORY_LIMIT).await;
+        runtime.set_max_stack_size(JS_MAX_STACK_SIZE).await;
+        let deadline: Arc<Mutex<Option<Instant>>> = Arc::new(Mutex::new(None));
+        let interrupt_deadline = deadline.clone();
+        runtime
+            .set_interrupt_handler(Some(Box::new(move || {
+                interrupt_deadline.lock().map_or(false, |deadline| {
+                    deadline.map_or(false, |deadline| Instant::now() > deadline)
+                })
+            })))
+            .await;
+        let metric_access_state: SharedMetricAccessState =
+            Arc::new(Mutex::new(MetricAccessState::default()));
+        let (get_state, get_series_state, get_grouped_state, anomaly_state) = (
+            metric_access_state.clone(),
+            metric_access_state.clone(),
+            metric_access_state.clone(),
+            metric_access_state.clone(),
+        );
+        let expression_logs: SharedExpressionLogs = Arc::new(Mutex::new(ExpressionLogs::default()));
+        let log_state = expression_logs.clone();
+        async_with!(context => |ctx| {
+            let globals = ctx.globals();
+            let _ = globals.set(
+                "get",
+                rquickjs::prelude::Func::new("get", move |args: rquickjs::Object| {
+                    get_in_js(&get_state, args)
+                }),
+            );
+            let _ = globals.set(
+                "get_series",
+                rquickjs::prelude::Func::new("get_series", move |args: rquickjs::Object| {
+                    get_series_in_js(&get_series_state, args)
+                }),
+            );
+            let _ = globals.set(
+                "get_grouped",
+                rquickjs::prelude::Func::new("get_grouped", move |args: rquickjs::Object| {
+                    get_grouped_in_js(&get_grouped_state, args)
+                }),
+            );
+            let _ = globals.set(
+                "anomaly",
+                rquickjs::prelude::Func::new("anomaly", move |args: rquickjs::Object| {
+                    anomaly_in_js(&anomaly_state, args)
+                }),
+            );
+            let _ = globals.set(
+                "__log",
+                rquickjs::prelude::Func::new("__log", move |message: String| {
+                    if let Ok(mut expression_logs) = log_state.lock() {
+                        expression_logs.push(message.as_str());
+                    }
+                }),
+            );
+            let _ = ctx.eval::<(), _>(JS_LOG_FUNCTION);
+            let _ = globals.set("now", rquickjs::prelude::Func::new("now", now_in_js));
+            let _ = globals.set("hour", rquickjs::prelude::Func::new("hour", hour_in_js));
+            let _ = globals.set(
+                "weekday",
+                rquickjs::prelude::Func::new("weekday", weekday_in_js),
+            );
+            let _ = globals.set(
+                "in_window",
+                rquickjs::prelude::Func::new("in_window", in_window_in_js),
+            );
+        })
+        .await;
+        Ok(QuickJsEngine {
+            _runtime: runtime,
+            context,
+            metric_access_state,
+            expression_logs,
+            deadline,
+        })
+    }
+
+    fn start_deadline(&self) {
+        if let Ok(mut deadline) = self.deadline.lock() {
+            *deadline = Some(Instant::now() + JS_EVALUATION_TIMEOUT);
+        }
+    }
+
+    // Clear the deadline and replace the error of the interrupted evaluation
+    fn finish_deadline<T>(&self, result: Result<T>) -> Result<T> {
+        let timed_out = match self.deadline.lock() {
+            Ok(mut deadline) => deadline
+                .take()
+                .map_or(false, |deadline| Instant::now() > deadline),
+            Err(_) => false,
+        };
+        if timed_out && result.is_err() {
+            return Err(anyhow::anyhow!(
+                "The evaluation is interrupted after {} seconds",
+                JS_EVALUATION_TIMEOUT.as_secs()
+            ));
+        }
+        result
+    }
+}
+
+// Declare the variables in a block instead of the globals, so they don't leak into the next evaluations.
+// The value of the block is the value of the last statement of the expression.
+fn get_js_expression_with_variables(expression: &str, variables: &HashMap<String, i64>) -> String {
+    let mut declarations = variables
+        .iter()
+        .map(|(key, value)| format!("let {} = {};", key, value))
+        .collect::<Vec<String>>();
+    declarations.sort();
+    format!("{{\n{}\n{}\n}}", declarations.join("\n"), expression)
+}
+
+fn now_in_js() -> f64 {
+    get_now_millis(&Utc::now()) as f64
+}
+
+fn hour_in_js(timezone: Opt<String>) -> Result<u32, rquickjs::Error> {
+    get_hour(&Utc::now(), timezone.0.as_deref()).map_err(to_js_error)
+}
+
+fn weekday_in_js(timezone: Opt<String>) -> Result<u32, rquickjs::Error> {
+    get_weekday(&Utc::now(), timezone.0.as_deref()).map_err(to_js_error)
+}
+
+fn in_window_in_js(window: String, timezone: Opt<String>) -> Result<bool, rquickjs::Error> {
+    is_in_window(&Utc::now(), window.as_str(), timezone.0.as_deref()).map_err(to_js_error)
+}
+
+#[async_trait]
+impl ExpressionEngine for QuickJsEngine {
+    fn get_language(&self) -> &str {
+        EXPRESSION_LANGUAGE_JAVASCRIPT
+    }
+    fn get_metric_access_state(&self) -> &SharedMetricAccessState {
+        &self.metric_access_state
+    }
+    fn get_expression_logs(&self) -> &SharedExpressionLogs {
+        &self.expression_logs
+    }
+    async fn load_library(&mut self, code: &str) -> Result<()> {
+        // The function declarations in the global scope become global functions
+        let code = code.to_string();
+        self.start_deadline();
+        let result = async_with!(self.context => |ctx| {
+            ctx.eval::<(), _>(code).map_err(|error| anyhow::anyhow!(error.to_string()))
+        })
+        .await;
+        self.finish_deadline(result)
+    }
+    async fn set_holiday_calendars(&mut self, holiday_calendars: HolidayCalendars) -> Result<()> {
+        let is_holiday_in_js =
+            move |calendar_id: String, timezone: Opt<String>| -> Result<bool, rquickjs::Error> {
+                is_holiday(
+                    &Utc::now(),
+                    &holiday_calendars,
+                    calendar_id.as_str(),
+                    timezone.0.as_deref(),
+                )
+                .map_err(to_js_error)
+            };
+        async_with!(self.context => |ctx| {
+            ctx.globals()
+                .set(
+                    "is_holiday",
+                    rquickjs::prelude::Func::new("is_holiday", is_holiday_in_js),
+                )
+                .map_err(|error| anyhow::anyhow!(error.to_string()))
+        })
+        .await
+    }
+    async fn validate(&self, expression: &str) -> Result<()> {
+        // new Function() parses the expression without evaluating it
+        let Ok(quoted_expression) = serde_json::to_string(expression) else {
+            return Err(anyhow::anyhow!("Failed to quote the expression"));
+        };
+        let script = format!("new Function({});", quoted_expression);
+        async_with!(self.context => |ctx| {
+            ctx.eval::<(), _>(script).map_err(|error| anyhow::anyhow!(error.to_string()))
+        })
+        .await
+    }
+    async fn evaluate_bool(&self, expression: &str) -> Result<bool> {
+        let expression = expression.to_string();
+        self.start_deadline();
+        let result = async_with!(self.context => |ctx| {
+            ctx.eval::<bool, _>(expression).map_err(|error| anyhow::anyhow!(error.to_string()))
+        })
+        .await;
+        self.finish_deadline(result)
+    }
+    async fn evaluate_f64(&self, expression: &str) -> Result<f64> {
+        let expression = expression.to_string();
+        self.start_deadline();
+        let result = async_with!(self.context => |ctx| {
+            ctx.eval::<f64, _>(expression).map_err(|error| anyhow::anyhow!(error.to_string()))
+        })
+        .await;
+        self.finish_deadline(result)
+    }
+    async fn evaluate_json(&self, expression: &str) -> Result<Option<serde_json::Value>> {
+        let expression = expression.to_string();
+        self.start_deadline();
+        let result = async_with!(self.context => |ctx| {
+            let value = ctx
+                .eval::<rquickjs::Value, _>(expression)
+                .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+            // JSON.stringify() returns undefined for undefined and functions
+            let stringify = ctx
+                .globals()
+                .get::<_, rquickjs::Object>("JSON")
+                .and_then(|json| json.get::<_, rquickjs::Function>("stringify"))
+                .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+            stringify
+                .call::<_, Option<String>>((value,))
+                .map_err(|error| anyhow::anyhow!(error.to_string()))
+        })
+        .await;
+        let result = self.finish_deadline(result)?;
+        match result {
+            Some(result) => Ok(Some(serde_json::from_str(result.as_str())?)),
+            None => Ok(None),
+        }
+    }
+    async fn evaluate_i64_with_variables(
+        &self,
+        expression: &str,
+        variables: &HashMap<String, i64>,
+    ) -> Result<i64> {
+        let expression = get_js_expression_with_variables(expression, variables);
+        self.start_deadline();
+        let result = async_with!(self.context => |ctx| {
+            let Result::Ok(result) = ctx.eval::<f64, _>(expression) else {
+                return Err(anyhow::anyhow!("Invalid target value"));
+            };
+            Ok(result)
+        })
+        .await;
+        round_to_i64(self.finish_deadline(result)?)
+    }
+}
+
+pub struct RhaiEngine {
+    engine: rhai::Engine,
+    metric_access_state: SharedMetricAccessState,
+    expression_logs: SharedExpressionLogs,
+}
+
+impl Default for RhaiEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RhaiEngine {
+    pub fn new() -> Self {
+        let mut engine = rhai::Engine::new();
+        engine.set_max_operations(RHAI_MAX_OPERATIONS);
+        engine.set_max_string_size(RHAI_MAX_STRING_SIZE);
+        engine.set_max_array_size(RHAI_MAX_ARRAY_SIZE);
+        engine.set_max_map_size(RHAI_MAX_MAP_SIZE);
+        // Undefined variables are found by validate()
+        engine.set_strict_variables(true);
+        let metric_access_state: SharedMetricAccessState =
+            Arc::new(Mutex::new(MetricAccessState::default()));
+        let state = metric_access_state.clone();
+        engine.register_fn("get", move |args: rhai::Map| get_in_rhai(&state, args));
+        let state = metric_access_state.clone();
+        engine.register_fn("get_series", move |args: rhai::Map| {
+            get_series_in_rhai(&state, args)
+        });
+        let state = metric_access_state.clone();
+        engine.register_fn("get_grouped", move |args: rhai::Map| {
+            get_grouped_in_rhai(&state, args)
+        });
+        let state = metric_access_state.clone();
+        engine.register_fn("anomaly", move |args: rhai::Map| {
+            anomaly_in_rhai(&state, args)
+        });
+        let expression_logs: SharedExpressionLogs = Arc::new(Mutex::new(ExpressionLogs::default()));
+        let log_state = expression_logs.clone();
+        engine.register_fn("log", move |value: rhai::Dynamic| {
+            if let Ok(mut expression_logs) = log_state.lock() {
+                expression_logs.push(value.to_string().as_str());
+            }
+        });
+        // The timezone is optional, so the functions are overloaded by the number of the arguments
+        engine.register_fn("now", || get_now_millis(&Utc::now()));
+        engine.register_fn("hour", || hour_in_rhai(None));
+        engine.register_fn("hour", |timezone: &str| hour_in_rhai(Some(timezone)));
+        engine.register_fn("weekday", || weekday_in_rhai(None));
+        engine.register_fn("weekday", |timezone: &str| weekday_in_rhai(Some(timezone)));
+        engine.register_fn("in_window", |window: &str| in_window_in_rhai(window, None));
+        engine.register_fn("in_window", |window: &str, timezone: &str| {
+            in_window_in_rhai(window, Some(timezone))
+        });
+        RhaiEngine {
+            engine,
+            metric_access_state,
+            expression_logs,
+        }
+    }
+    // Rhai doesn't allow '$' in the variable names, so $replicas is evaluated as replicas.
+    fn get_scope_with_variables(
+        expression: &str,
+        variables: &HashMap<String, i64>,
+    ) -> (String, rhai::Scope<'static>) {
+        let mut scope = rhai::Scope::new();
+        for (key, value) in variables.iter() {
+            scope.push(key.trim_start_matches('$').to_string(), *value);
+        }
+        (replace_rhai_variables(expression, variables), scope)
+    }
+}
+
+// Replace the whole $name of the variables with name outside the string literals
+// e.g. not $replicas_max for $replicas, nor "$replicas"
+fn replace_rhai_variables(expression: &str, variables: &HashMap<String, i64>) -> String {
+    let chars = expression.chars().collect::<Vec<char>>();
+    let mut result = String::with_capacity(expression.len());
+    let mut quote: Option<char> = None;
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        index += 1;
+        if let Some(quote_char) = quote {
+            result.push(c);
+            if c == '\\' && index < chars.len() {
+                result.push(chars[index]);
+                index += 1;
+            } else if c == quote_char {
+                quote = None;
+            }
+            continue;
+        }
+        if c == '"' || c == '\'' || c == '`' {
+            quote = Some(c);
+        } else if c == '$' {
+            let end = chars[index..]
+                .iter()
+                .position(|c| !(c.is_alphanumeric() || *c == '_'))
+                .map_or(chars.len(), |position| index + position);
+            let name = chars[index..end].iter().collect::<String>();
+            if variables.contains_key(&format!("${}", name)) {
+                result.push_str(&name);
+                index = end;
+                continue;
+            }
+        }
+        result.push(c);
+    }
+    result
+}
+
+fn rhai_to_json(value: rhai::Dynamic) -> serde_json::Value {
+    if value.is_unit() {
+        return serde_json::Value::Null;
+    }
+    if let Ok(value) = value.as_bool() {
+        return value.into();
+    }
+    if let Ok(value) = value.as_int() {
+        return value.into();
+    }
+    if let Ok(value) = value.as_float() {
+        return value.into();
+    }
+    if value.is_array() {
+        let array = value.cast::<rhai::Array>();
+        return array.into_iter().map(rhai_to_json).collect();
+    }
+    if value.is_map() {
+        let map = value.cast::<rhai::Map>();
+        return map
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), rhai_to_json(value)))
+            .collect::<serde_json::Map<String, serde_json::Value>>()
+            .into();
+    }
+    value.to_string().into()
+}
+
+fn hour_in_rhai(timezone: Option<&str>) -> Result<i64, Box<rhai::EvalAltResult>> {
+    get_hour(&Utc::now(), timezone)
+        .map(|hour| hour as i64)
+        .map_err(to_rhai_error)
+}
+
+fn weekday_in_rhai(timezone: Option<&str>) -> Result<i64, Box<rhai::EvalAltResult>> {
+    get_weekday(&Utc::now(), timezone)
+        .map(|weekday| weekday as i64)
+        .map_err(to_rhai_error)
+}
+
+fn in_window_in_rhai(
+    window: &str,
+    timezone: Option<&str>,
+) -> Result<bool, Box<rhai::EvalAltResult>> {
+    is_in_window(&Utc::now(), window, timezone).map_err(to_rhai_error)
+}
+
+#[async_trait]
+impl ExpressionEngine for RhaiEngine {
+    fn get_language(&self) -> &str {
+        EXPRESSION_LANGUAGE_RHAI
+    }
+    fn get_metric_access_state(&self) -> &SharedMetricAccessState {
+        &self.metric_access_state
+    }
+    fn get_expression_logs(&self) -> &SharedExpressionLogs {
+        &self.expression_logs
+    }
+    async fn load_library(&mut self, code: &str) -> Result<()> {
+        // The functions in the code are registered as a global module
+        let ast = self
+            .engine
+            .compile(code)
+            .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+        let module = rhai::Module::eval_ast_as_new(rhai::Scope::new(), &ast, &self.engine)
+            .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+        self.engine.register_global_module(module.into());
+        Ok(())
+    }
+    async fn set_holiday_calendars(&mut self, holiday_calendars: HolidayCalendars) -> Result<()> {
+        let holiday_calendars_with_timezone = holiday_calendars.clone();
+        self.engine
+            .register_fn("is_holiday", move |calendar_id: &str| {
+                is_holiday(&Utc::now(), &holiday_calendars, calendar_id, None)
+                    .map_err(to_rhai_error)
+            });
+        self.engine
+            .register_fn("is_holiday", move |calendar_id: &str, timezone: &str| {
+                is_holiday(
+                    &Utc::now(),
+                    &holiday_calendars_with_timezone,
+                    calendar_id,
+                    Some(timezone),
+                )
+                .map_err(to_rhai_error)
+            });
+        Ok(())
+    }
+    async fn validate(&self, expression: &str) -> Result<()> {
+        self.engine
+            .compile(expression)
+            .map(|_| ())
+            .map_err(|error| anyhow::anyhow!(error.to_string()))
+    }
+    async fn evaluate_bool(&self, expression: &str) -> Result<bool> {
+        self.engine
+            .eval::<bool>(expression)
+            .map_err(|error| anyhow::anyhow!(error.to_string()))
+    }
+    async fn evaluate_f64(&self, expression: &str) -> Result<f64> {
+        let result = self
+            .engine
+            .eval::<rhai::Dynamic>(expression)
+            .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+        // Rhai doesn't convert integers to floats implicitly
+        if let Ok(value) = result.as_float() {
+            return Ok(value);
+        }
+        if let Ok(value) = result.as_int() {
+            return Ok(value as f64);
+        }
+        Err(anyhow::anyhow!(
+            "The result is not a number: {}",
+            result.type_name()
+        ))
+    }
+    async fn evaluate_json(&self, expression: &str) -> Result<Option<serde_json::Value>> {
+        let result = self
+            .engine
+            .eval::<rhai::Dynamic>(expression)
+            .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+        if result.is_unit() {
+            return Ok(None);
+        }
+        Ok(Some(rhai_to_json(result)))
+    }
+    async fn evaluate_i64_with_variables(
+        &self,
+        expression: &str,
+        variables: &HashMap<String, i64>,
+    ) -> Result<i64> {
+        let (expression, mut scope) = RhaiEngine::get_scope_with_variables(expression, variables);
+        let result = self
+            .engine
+            .eval_with_scope::<rhai::Dynamic>(&mut scope, expression.as_str())
+            .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+        if let Ok(value) = result.as_int() {
+            return Ok(value);
+        }
+        if let Ok(value) = result.as_float() {
+            return round_to_i64(value);
+        }
+        Err(anyhow::anyhow!("Invalid target value"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::time_helpers::get_holiday_calendars;
+    use super::*;
+    use data_layer::HolidayCalendarDefinition;
+
+    #[tokio::test]
+    async fn test_create_expression_engine() {
+        let engine = create_expression_engine(EXPRESSION_LANGUAGE_JAVASCRIPT)
+            .await
+            .unwrap();
+        assert_eq!(engine.get_language(), EXPRESSION_LANGUAGE_JAVASCRIPT);
+        let engine = create_expression_engine(EXPRESSION_LANGUAGE_RHAI)
+            .await
+            .unwrap();
+        assert_eq!(engine.get_language(), EXPRESSION_LANGUAGE_RHAI);
+        assert!(create_expression_engine("python").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_expression_libraries() {
+        let expression_libraries = vec![
+            ExpressionLibraryDefinition {
+                id: "js_library".to_string(),
+                code: "function double(value) { return value * 2; }".to_string(),
+                ..Default::default()
+            },
+            ExpressionLibraryDefinition {
+                id: "rhai_library".to_string(),
+                language: EXPRESSION_LANGUAGE_RHAI.to_string(),
+                code: "fn triple(value) { value * 3 }".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let mut engine = create_expression_engine(EXPRESSION_LANGUAGE_JAVASCRIPT)
+            .await
+            .unwrap();
+        load_expression_libraries(engine.as_mut(), &expression_libraries)
+            .await
+            .unwrap();
+        assert!(engine.evaluate_bool("double(2) == 4").await.unwrap());
+
+        let mut engine = create_expression_engine(EXPRESSION_LANGUAGE_RHAI)
+            .await
+            .unwrap();
+        load_expression_libraries(engine.as_mut(), &expression_libraries)
+            .await
+            .unwrap();
+        assert!(engine.evaluate_bool("triple(2) == 6").await.unwrap());
+
+        for expression_library in expression_libraries.iter() {
+            assert!(validate_expression_library(expression_library)
+                .await
+                .is_ok());
+        }
+        let invalid_expression_library = ExpressionLibraryDefinition {
+            id: "invalid_library".to_string(),
+            code: "function double(value) { return value * 2;".to_string(),
+            ..Default::default()
+        };
+        assert!(validate_expression_library(&invalid_expression_library)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_time_helpers() {
+        let holiday_calendars = get_holiday_calendars(&[HolidayCalendarDefinition {
+            id: "today".to_string(),
+            dates: vec![Utc::now().date_naive().to_string()],
+            ..Default::default()
+        }]);
+        for language in [EXPRESSION_LANGUAGE_JAVASCRIPT, EXPRESSION_LANGUAGE_RHAI] {
+            let mut engine = create_expression_engine(language).await.unwrap();
+            engine
+                .set_holiday_calendars(holiday_calendars.clone())
+                .await
+                .unwrap();
+            // Rhai uses double quotes for the strings
+            assert!(engine.evaluate_bool("now() > 0").await.unwrap());
+            assert!(engine
+                .evaluate_bool(r#"hour() >= 0 && hour("Asia/Seoul") < 24"#)
+                .await
+                .unwrap());
+            assert!(engine
+                .evaluate_bool(r#"weekday() >= 0 && weekday("Asia/Seoul") < 7"#)
+                .await
+                .unwrap());
+            assert!(!engine
+                .evaluate_bool(r#"in_window("00:00-00:00")"#)
+                .await
+                .unwrap());
+            assert!(engine
+                .evaluate_bool(r#"in_window("00:00-23:59", "UTC") || hour() == 23"#)
+                .await
+                .unwrap());
+            assert!(engine
+                .evaluate_bool(r#"is_holiday("today")"#)
+                .await
+                .unwrap());
+            assert!(engine
+                .evaluate_bool(r#"is_holiday("unknown")"#)
+                .await
+                .is_err());
+            assert!(engine
+                .evaluate_bool(r#"hour("Unknown/Timezone") >= 0"#)
+                .await
+                .is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_log() {
+        let engine = QuickJsEngine::new().await.unwrap();
+        assert!(engine
+            .evaluate_bool("log('value', 1, { a: 1 }); true")
+            .await
+            .unwrap());
+        assert_eq!(engine.take_logs(), vec![r#"value 1 {"a":1}"#]);
+        assert!(engine.take_logs().is_empty());
+
+        let engine = RhaiEngine::new();
+        assert!(engine
+            .evaluate_bool(r#"log(`value ${1 + 1}`); true"#)
+            .await
+            .unwrap());
+        assert_eq!(engine.take_logs(), vec!["value 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_quickjs_engine() {
+        let engine = QuickJsEngine::new().await.unwrap();
+        assert!(engine.evaluate_bool("1 + 1 == 2").await.unwrap());
+        assert_eq!(engine.evaluate_f64("1.5 * 2").await.unwrap(), 3.0);
+        assert!(engine.validate("1 > 0 && 2 > 1").await.is_ok());
+        assert!(engine.validate("1 > ").await.is_err());
+        let variables = HashMap::from([("$replicas".to_string(), 2)]);
+        assert_eq!(
+            engine
+                .evaluate_i64_with_variables("$replicas * 2", &variables)
+                .await
+                .unwrap(),
+            4
+        );
+        // The variables don't leak into the next evaluations
+        assert!(engine
+            .evaluate_bool("typeof $replicas === 'undefined'")
+            .await
+            .unwrap());
+        assert_eq!(
+            engine
+                .evaluate_i64_with_variables(
+                    "const half = $replicas / 2; $replicas + half",
+                    &variables
+                )
+                .await
+                .unwrap(),
+            3
+        );
+        // The float results are rounded
+        assert_eq!(
+            engine
+                .evaluate_i64_with_variables("1.9", &HashMap::new())
+                .await
+                .unwrap(),
+            2
+        );
+        assert!(engine
+            .evaluate_i64_with_variables("1 / 0", &HashMap::new())
+            .await
+            .is_err());
+        assert_eq!(
+            engine.evaluate_json("[1, 'a']").await.unwrap(),
+            Some(serde_json::json!([1, "a"]))
+        );
+        assert_eq!(engine.evaluate_json("undefined").await.unwrap(), None);
+        // An infinite loop is interrupted and the engine is still usable
+        let error = engine.evaluate_bool("while (true) {}").await.unwrap_err();
+        assert!(error.to_string().contains("interrupted"));
+        assert!(engine.evaluate_bool("1 + 1 == 2").await.unwrap());
+        // A huge allocation fails instead of using up the memory
+        assert!(engine
+            .evaluate_bool("new Array(1e9).fill(0).length > 0")
+            .await
+            .is_err());
+        assert!(engine.evaluate_bool("1 + 1 == 2").await.unwrap());
+    }
+
+    #[test]
+    fn test_round_to_i64() {
+        assert_eq!(round_to_i64(1.4).unwrap(), 1);
+        assert_eq!(round_to_i64(1.5).unwrap(), 2);
+        assert_eq!(round_to_i64(-1.5).unwrap(), -2);
+        assert!(round_to_i64(f64::NAN).is_err());
+        assert!(round_to_i64(f64::INFINITY).is_err());
+        assert!(round_to_i64(1e19).is_err());
+    }
+
+    #[test]
+    fn test_replace_rhai_variables() {
+        let variables = HashMap::from([
+            ("$replicas".to_string(), 2),
+            ("$ready_replicas".to_string(), 1),
+        ]);
+        assert_eq!(
+            replace_rhai_variables("$replicas + $ready_replicas", &variables),
+            "replicas + ready_replicas"
+        );
+        // Only the whole names are replaced
+        assert_eq!(
+            replace_rhai_variables("$replicas_max + $replicas", &variables),
+            "$replicas_max + replicas"
+        );
+        // Not in the string literals (with the escaped quotes)
+        assert_eq!(
+            replace_rhai_variables(r#""$replicas \" $replicas" + $replicas"#, &variables),
+            r#""$replicas \" $replicas" + replicas"#
+        );
+        assert_eq!(
+            replace_rhai_variables("`$replicas` + '$'", &variables),
+            "`$replicas` + '$'"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rhai_engine() {
+        let engine = RhaiEngine::new();
+        assert!(engine.evaluate_bool("1 + 1 == 2").await.unwrap());
+        assert_eq!(engine.evaluate_f64("1.5 * 2.0").await.unwrap(), 3.0);
+        assert_eq!(engine.evaluate_f64("3").await.unwrap(), 3.0);
+        assert!(engine.validate("1 > 0 && 2 > 1").await.is_ok());
+        assert!(engine.validate("1 > ").await.is_err());
+        assert!(engine.validate("unknown_variable > 0").await.is_err());
+        let variables = HashMap::from([("$replicas".to_string(), 2)]);
+        assert_eq!(
+            engine
+                .evaluate_i64_with_variables("$replicas * 2", &variables)
+                .await
+                .unwrap(),
+            4
+        );
+        // The variables in the string literals are kept
+        assert_eq!(
+            engine
+                .evaluate_i64_with_variables(
+                    r#"if "$replicas" == "$" + "replicas" { $replicas } else { 0 }"#,
+                    &variables
+                )
+                .await
+                .unwrap(),
+            2
+        );
+        // The float results are rounded like javascript
+        assert_eq!(
+            engine
+                .evaluate_i64_with_variables("1.9", &HashMap::new())
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            engine
+                .evaluate_i64_with_variables("0.6 * 2.0", &variables)
+                .await
+                .unwrap(),
+            1
+        );
+        // An infinite loop is stopped
+        assert!(engine.evaluate_bool("loop {}").await.is_err());
+        // A huge string fails instead of using up the memory
+        assert!(engine
+            .evaluate_bool(r#"let s = "a"; loop { s += s; } s.len() > 0"#)
+            .await
+            .is_err());
+        // The metric doesn't exist
+        assert!(engine
+            .evaluate_bool(r#"get(#{ metric_id: "rhai_unknown_metric" }) > 0"#)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,206 @@
+/**
+ * Expression Evaluator
+ *
+ * Evaluates the ad-hoc expressions from the API server (POST /api/expressions/evaluate) e.g. "try expression" in the web app.
+ * Each expression is evaluated in a fresh engine with the same functions as ScalingPlanner
+ * (get(), the enabled ExpressionLibraries and HolidayCalendars), so it doesn't affect the running plans.
+ *
+ * time_offset_sec moves the period of the get() family back to evaluate the expression against the past buffered metrics.
+ * The time helpers like now() and hour() are not affected.
+ */
+use super::{
+    expression_engine::{
+        create_expression_engine, load_expression_libraries, DEFAULT_EXPRESSION_LANGUAGE,
+    },
+    expression_get_value,
+    time_helpers::get_holiday_calendars,
+};
+use data_layer::{
+    data_layer::DataLayer,
+    types::expression_evaluation::{
+        ExpressionEvaluationError, ExpressionEvaluationRequest, ExpressionEvaluationResult,
+    },
+};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+// Rhai: "... (line 1, position 5)"
+const RHAI_ERROR_POSITION_PATTERN: &str = r"\(line (\d+), position (\d+)\)";
+// QuickJS: "... eval_script:1 ..." or "at <eval> (eval_script:1)" in the stack
+const JS_ERROR_POSITION_PATTERN: &str = r"eval_script\]?:(\d+)(?::(\d+))?";
+
+// Run the evaluator that processes the evaluation jobs in the background
+pub fn run_expression_evaluator(data_layer: Arc<DataLayer>) -> Option<JoinHandle<()>> {
+    let Some(mut receiver) = data_layer.take_expression_evaluation_receiver() else {
+        error!("[ExpressionEvaluator] The expression evaluator is already running");
+        return None;
+    };
+    Some(tokio::spawn(async move {
+        while let Some(job) = receiver.recv().await {
+            let result = evaluate_expression(&data_layer, job.request).await;
+            if job.result_sender.send(result).is_err() {
+                debug!("[ExpressionEvaluator] The requester has gone");
+            }
+        }
+    }))
+}
+
+pub async fn evaluate_expression(
+    data_layer: &DataLayer,
+    request: ExpressionEvaluationRequest,
+) -> ExpressionEvaluationResult {
+    let language = request
+        .language
+        .unwrap_or(DEFAULT_EXPRESSION_LANGUAGE.to_string())
+        .to_lowercase();
+    let expression_engine = create_expression_engine(language.as_str()).await;
+    if expression_engine.is_err() {
+        return ExpressionEvaluationResult::from_error(get_evaluation_error(
+            expression_engine.err().unwrap(),
+        ));
+    }
+    let mut expression_engine = expression_engine.unwrap();
+    expression_engine.set_time_offset_sec(request.time_offset_sec.unwrap_or_default());
+
+    // The same libraries and calendars as ScalingPlanner
+    if let Ok(expression_libraries) = data_layer.get_enabled_expression_libraries().await {
+        let result =
+            load_expression_libraries(expression_engine.as_mut(), &expression_libraries).await;
+        if result.is_err() {
+            return ExpressionEvaluationResult::from_error(get_evaluation_error(
+                result.err().unwrap(),
+            ));
+        }
+    }
+    if let Ok(holiday_calendars) = data_layer.get_enabled_holiday_calendars().await {
+        let _ = expression_engine
+            .set_holiday_calendars(get_holiday_calendars(&holiday_calendars))
+            .await;
+    }
+
+    let result = expression_engine
+        .evaluate_json(request.expression.as_str())
+        .await;
+    let logs = expression_engine.take_logs();
+    let get_values = expression_get_value(request.expression, expression_engine.as_ref()).await;
+    let stale_metric_ids = expression_engine
+        .take_stale_metric_ids()
+        .into_iter()
+        .collect();
+    let (result, error) = match result {
+        Ok(result) => (result, None),
+        Err(error) => (None, Some(get_evaluation_error(error))),
+    };
+    ExpressionEvaluationResult {
+        result,
+        get_values,
+        logs,
+        stale_metric_ids,
+        error,
+    }
+}
+
+// The position of the error is parsed from the message of the engine
+fn get_evaluation_error(error: anyhow::Error) -> ExpressionEvaluationError {
+    let message = error.to_string();
+    let rhai_regex = regex::Regex::new(RHAI_ERROR_POSITION_PATTERN).unwrap();
+    let js_regex = regex::Regex::new(JS_ERROR_POSITION_PATTERN).unwrap();
+    let captures = rhai_regex
+        .captures(message.as_str())
+        .or_else(|| js_regex.captures(message.as_str()));
+    let get_number = |index: usize| {
+        captures
+            .as_ref()
+            .and_then(|captures| captures.get(index))
+            .and_then(|number| number.as_str().parse::<u32>().ok())
+    };
+    ExpressionEvaluationError {
+        line: get_number(1),
+        column: get_number(2),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_evaluate_expression() {
+        let data_layer = DataLayer::new("", 500_000, false).await;
+        data_layer.sync("").await;
+        let json_value = json!([{"name": "cpu", "tags": {}, "value": 20.0}]).to_string();
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "evaluator_metric", &json_value)
+            .await;
+
+        let result = evaluate_expression(
+            &data_layer,
+            ExpressionEvaluationRequest {
+                expression: "log('cpu'); get({ metric_id: 'evaluator_metric' }) * 2".to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(result.error.is_none());
+        assert_eq!(result.result, Some(json!(40)));
+        assert_eq!(result.logs, vec!["cpu".to_string()]);
+        assert!(result
+            .get_values
+            .iter()
+            .flat_map(|get_value| get_value.values())
+            .any(|value| *value == Some(20.0)));
+
+        // The metric was not buffered an hour ago
+        let result = evaluate_expression(
+            &data_layer,
+            ExpressionEvaluationRequest {
+                expression: "get({ metric_id: 'evaluator_metric' })".to_string(),
+                time_offset_sec: Some(3600),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(result.error.is_some());
+        assert_eq!(
+            result.stale_metric_ids,
+            vec!["evaluator_metric".to_string()]
+        );
+
+        let result = evaluate_expression(
+            &data_layer,
+            ExpressionEvaluationRequest {
+                expression: "let a = 1;\nlet b = ;".to_string(),
+                language: Some("rhai".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result.error.unwrap().line, Some(2));
+
+        let result = evaluate_expression(
+            &data_layer,
+            ExpressionEvaluationRequest {
+                expression: "#{ a: [1, true] }".to_string(),
+                language: Some("rhai".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result.result, Some(json!({ "a": [1, true] })));
+
+        // An infinite loop returns an error instead of blocking the evaluator
+        let result = evaluate_expression(
+            &data_layer,
+            ExpressionEvaluationRequest {
+                expression: "while (true) {}".to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(result.result.is_none());
+        assert!(result.error.is_some());
+    }
+}
@@ -0,0 +1,107 @@
+/**
+ * Expression Log
+ *
+ * log(...) in the plan expressions to debug them.
+ * - javascript: log('cpu', get({ metric_id: 'cpu' }))
+ * - rhai: log(`cpu ${get(#{ metric_id: "cpu" })}`)
+ *
+ * The logs are captured per evaluation and saved in the autoscaling history of the applied plan item.
+ * They are limited per evaluation and the output to the tracing log is rate limited so that they can't flood the logs.
+ */
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+// The logs kept per evaluation
+const MAX_LOGS_PER_EVALUATION: usize = 100;
+// The characters of a log
+const MAX_LOG_LENGTH: usize = 1000;
+// The logs written to the tracing log per second
+const MAX_TRACING_LOGS_PER_SECOND: usize = 10;
+
+#[derive(Debug)]
+pub struct ExpressionLogs {
+    logs: Vec<String>,
+    // The logs over MAX_LOGS_PER_EVALUATION
+    dropped_count: usize,
+    // For the rate limit of the tracing log
+    tracing_window_start: Instant,
+    tracing_count: usize,
+}
+pub type SharedExpressionLogs = Arc<Mutex<ExpressionLogs>>;
+
+impl Default for ExpressionLogs {
+    fn default() -> Self {
+        ExpressionLogs {
+            logs: Vec::new(),
+            dropped_count: 0,
+            tracing_window_start: Instant::now(),
+            tracing_count: 0,
+        }
+    }
+}
+
+impl ExpressionLogs {
+    pub fn push(&mut self, message: &str) {
+        let message = if message.chars().count() > MAX_LOG_LENGTH {
+            format!(
+                "{}...",
+                message.chars().take(MAX_LOG_LENGTH).collect::<String>()
+            )
+        } else {
+            message.to_string()
+        };
+
+        let now = Instant::now();
+        if now.duration_since(self.tracing_window_start) >= Duration::from_secs(1) {
+            self.tracing_window_start = now;
+            self.tracing_count = 0;
+        }
+        if self.tracing_count < MAX_TRACING_LOGS_PER_SECOND {
+            self.tracing_count += 1;
+            info!("[ScalingPlan expression log] {}", message);
+        }
+
+        if self.logs.len() < MAX_LOGS_PER_EVALUATION {
+            self.logs.push(message);
+        } else {
+            self.dropped_count += 1;
+        }
+    }
+    // The logs since the last take()
+    pub fn take(&mut self) -> Vec<String> {
+        let mut logs = std::mem::take(&mut self.logs);
+        if self.dropped_count > 0 {
+            logs.push(format!("... {} logs are dropped", self.dropped_count));
+            self.dropped_count = 0;
+        }
+        logs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expression_logs() {
+        let mut expression_logs = ExpressionLogs::default();
+        expression_logs.push("first");
+        expression_logs.push("x".repeat(MAX_LOG_LENGTH + 1).as_str());
+        let logs = expression_logs.take();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0], "first");
+        assert_eq!(logs[1].chars().count(), MAX_LOG_LENGTH + 3);
+        assert!(expression_logs.take().is_empty());
+
+        for index in 0..MAX_LOGS_PER_EVALUATION + 5 {
+            expression_logs.push(index.to_string().as_str());
+        }
+        let logs = expression_logs.take();
+        assert_eq!(logs.len(), MAX_LOGS_PER_EVALUATION + 1);
+        assert_eq!(logs[MAX_LOGS_PER_EVALUATION], "... 5 logs are dropped");
+        assert_eq!(expression_logs.tracing_count, MAX_TRACING_LOGS_PER_SECOND);
+    }
+}
@@ -0,0 +1,100 @@
+/**
+ * Missing Metric Policy
+ *
+ * What get() returns when the metric has no data in period_sec.
+ * It is set by the scaling plan(metadata.on_missing) and can be overridden by each call(get({ ..., on_missing: 'zero' })).
+ *
+ * 1. error(default): the expression fails
+ * 2. zero: 0
+ * 3. skip_plan: the plan item is skipped without an error
+ * 4. use_last: the latest value regardless of period_sec
+ *
+ * The metrics without data are recorded as stale during the evaluation,
+ * so that the planner can mark the autoscaling history and run the fallback plan item.
+ */
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::{
+    collections::{BTreeSet, HashMap},
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+use tracing::error;
+
+pub const ON_MISSING_ERROR: &str = "error";
+pub const ON_MISSING_ZERO: &str = "zero";
+pub const ON_MISSING_SKIP_PLAN: &str = "skip_plan";
+pub const ON_MISSING_USE_LAST: &str = "use_last";
+// The error message of skip_plan to distinguish it from the other errors in the expression
+const SKIP_PLAN_ERROR: &str = "[on_missing: skip_plan]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingPolicy {
+    #[default]
+    Error,
+    Zero,
+    SkipPlan,
+    UseLast,
+}
+
+impl FromStr for MissingPolicy {
+    type Err = anyhow::Error;
+    fn from_str(policy: &str) -> Result<Self> {
+        match policy.to_lowercase().as_str() {
+            ON_MISSING_ERROR => Ok(MissingPolicy::Error),
+            ON_MISSING_ZERO => Ok(MissingPolicy::Zero),
+            ON_MISSING_SKIP_PLAN => Ok(MissingPolicy::SkipPlan),
+            ON_MISSING_USE_LAST => Ok(MissingPolicy::UseLast),
+            _ => Err(anyhow!("Unknown on_missing: {}", policy)),
+        }
+    }
+}
+
+// The policy of the scaling plan from metadata.on_missing (default: error)
+pub fn get_missing_policy(metadata: &HashMap<String, Value>) -> MissingPolicy {
+    let Some(policy) = metadata.get("on_missing").and_then(Value::as_str) else {
+        return MissingPolicy::default();
+    };
+    match MissingPolicy::from_str(policy) {
+        Ok(policy) => policy,
+        Err(error) => {
+            error!("[ScalingPlanner] {}", error);
+            MissingPolicy::default()
+        }
+    }
+}
+
+pub fn get_skip_plan_error(metric_id: &str) -> anyhow::Error {
+    anyhow!("{} The metric has no data: {}", SKIP_PLAN_ERROR, metric_id)
+}
+
+pub fn is_skip_plan_error(error: &anyhow::Error) -> bool {
+    error.to_string().contains(SKIP_PLAN_ERROR)
+}
+
+// The state shared by get() calls in the expression engine
+#[derive(Debug, Default)]
+pub struct MetricAccessState {
+    pub default_policy: MissingPolicy,
+    // The metrics without data since the last take_stale_metric_ids()
+    pub stale_metric_ids: BTreeSet<String>,
+    // get() reads the metrics as of {time_offset_sec} seconds ago
+    pub time_offset_sec: u64,
+}
+pub type SharedMetricAccessState = Arc<Mutex<MetricAccessState>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_missing_policy() {
+        let metadata = HashMap::from([("on_missing".to_string(), Value::from("use_last"))]);
+        assert_eq!(get_missing_policy(&metadata), MissingPolicy::UseLast);
+        let metadata = HashMap::from([("on_missing".to_string(), Value::from("unknown"))]);
+        assert_eq!(get_missing_policy(&metadata), MissingPolicy::Error);
+        assert_eq!(get_missing_policy(&HashMap::new()), MissingPolicy::Error);
+        assert!(is_skip_plan_error(&get_skip_plan_error("metric_id")));
+        assert!(!is_skip_plan_error(&anyhow!("metric_id")));
+    }
+}
@@ -1,4 +1,22 @@
+pub mod anomaly;
+pub mod expression_engine;
+pub mod expression_evaluator;
+pub mod expression_log;
+pub mod missing_metric;
 pub mod scaling_planner_manager;
+pub mod time_helpers;
+use self::anomaly::{
+    get_anomaly_score, is_anomaly_method, ANOMALY_METHOD_ZSCORE, DEFAULT_BASELINE_SEC,
+};
+use self::expression_engine::{
+    create_expression_engine, load_expression_libraries, ExpressionEngine,
+    DEFAULT_EXPRESSION_LANGUAGE, EXPRESSION_LANGUAGE_PARAM,
+};
+use self::missing_metric::{
+    get_missing_policy, get_skip_plan_error, is_skip_plan_error, MissingPolicy,
+    SharedMetricAccessState,
+};
+use self::time_helpers::{get_holiday_calendars, HolidayCalendars};
 use crate::{
     metric_updater::SharedMetricUpdater, scaling_component::SharedScalingComponentManager,
 };
@@ -8,16 +26,32 @@ use data_layer::{
     data_layer::{DataLayer, SOURCE_METRICS_DATA},
     types::{
         autoscaling_history_definition::AutoscalingHistoryDefinition,
-        plan_item_definition::PlanItemDefinition, scaling_plan_definition::DEFAULT_PLAN_INTERVAL,
+        plan_item_definition::PlanItemDefinition,
+        scaling_plan_definition::{
+            DEFAULT_PLAN_DEBOUNCE, DEFAULT_PLAN_INTERVAL, PLAN_TRIGGER_ON_METRIC_UPDATE,
+        },
     },
-    ScalingPlanDefinition,
+    ExpressionLibraryDefinition, HolidayCalendarDefinition, ScalingPlanDefinition,
 };
-use rquickjs::async_with;
 use serde_json::{json, Value};
 use std::ops::Bound::Included;
 use std::str::FromStr;
-use std::{collections::HashMap, sync::Arc, time::Duration};
-use tokio::{sync::RwLock, task::JoinHandle, time};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    sync::{
+        broadcast::{
+            self,
+            error::{RecvError, TryRecvError},
+        },
+        RwLock,
+    },
+    task::JoinHandle,
+    time,
+};
 use tracing::{debug, error, info};
 use ulid::Ulid;
 
@@ -31,6 +65,8 @@ enum PlanExpressionStats {
     Count,
     Minimum,
     Maximum,
+    ZScore,
+    Mad,
 }
 impl std::fmt::Display for PlanExpressionStats {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -41,10 +77,171 @@ impl std::fmt::Display for PlanExpressionStats {
             PlanExpressionStats::Count => write!(f, "count"),
             PlanExpressionStats::Minimum => write!(f, "min"),
             PlanExpressionStats::Maximum => write!(f, "max"),
+            PlanExpressionStats::ZScore => write!(f, "zscore"),
+            PlanExpressionStats::Mad => write!(f, "mad"),
+        }
+    }
+}
+
+// Which plans to evaluate in a round
+#[derive(Debug, Clone, PartialEq)]
+enum PlanEvaluationTarget {
+    // All plans (trigger: interval, the first round, or the staleness check of on_missing and the fallback plan)
+    All,
+    // The plans with cron_expression (trigger: on_metric_update, every interval)
+    CronPlans,
+    // The plans referencing the updated metrics (trigger: on_metric_update)
+    UpdatedMetrics(HashSet<String>),
+}
+
+// An empty cron_expression is treated as absent
+fn has_cron_expression(plan: &PlanItemDefinition) -> bool {
+    plan.cron_expression
+        .as_ref()
+        .map_or(false, |cron_expression| !cron_expression.is_empty())
+}
+
+impl PlanEvaluationTarget {
+    // referenced_metric_ids: the metric ids in the expression of the plan
+    fn is_target(
+        &self,
+        plan: &PlanItemDefinition,
+        referenced_metric_ids: &HashSet<String>,
+    ) -> bool {
+        let has_cron_expression = has_cron_expression(plan);
+        match self {
+            PlanEvaluationTarget::All => true,
+            PlanEvaluationTarget::CronPlans => has_cron_expression,
+            PlanEvaluationTarget::UpdatedMetrics(updated_metric_ids) => {
+                if has_cron_expression {
+                    return false;
+                }
+                // If the metric ids are unknown(e.g. metric_id is a variable), evaluate it anyway
+                referenced_metric_ids.is_empty()
+                    || !referenced_metric_ids.is_disjoint(updated_metric_ids)
+            }
         }
     }
 }
 
+// The plan item to apply in a round and the values to save in the autoscaling history
+struct MatchedPlan<'a> {
+    plan: &'a PlanItemDefinition,
+    expression_value_map: Vec<HashMap<String, Option<f64>>>,
+    // The metrics without data in the expression
+    stale_metric_ids: BTreeSet<String>,
+    // The logs of log() in the expression
+    logs: Vec<String>,
+}
+
+/**
+ * Find the metric ids in the expression - get({ metric_id: 'metric_id' })
+ */
+fn get_metric_ids_in_expression(expression: &str) -> HashSet<String> {
+    let re = regex::Regex::new(r#"metric_id['"]?\s*:\s*['"`]([^'"`]+)['"`]"#).unwrap();
+    re.captures_iter(expression)
+        .filter_map(|cap| cap.get(1))
+        .map(|metric_id| metric_id.as_str().to_string())
+        .collect()
+}
+
+/**
+ * Find the function names in the code of the expression library - function name(), fn name() or const name = () => {}
+ */
+fn get_function_names_in_library(code: &str) -> HashSet<String> {
+    let re = regex::Regex::new(r"\b(?:function|fn|const|let|var)\s+([A-Za-z_$][\w$]*)").unwrap();
+    re.captures_iter(code)
+        .filter_map(|cap| cap.get(1))
+        .map(|function_name| function_name.as_str().to_string())
+        .collect()
+}
+
+/**
+ * Whether the expression calls any of the functions - name(...)
+ */
+fn is_calling_functions(expression: &str, function_names: &HashSet<String>) -> bool {
+    function_names.iter().any(|function_name| {
+        let re = regex::Regex::new(&format!(
+            r"(?:^|[^\w$.]){}\s*\(",
+            regex::escape(function_name)
+        ))
+        .unwrap();
+        re.is_match(expression)
+    })
+}
+
+/**
+ * Wait for the next round of the plan evaluation
+ * metric_ingestion_receiver is None: wait for the next interval
+ * metric_ingestion_receiver is Some: wait for the metrics in watched_metric_ids(empty means all) to be ingested and debounce
+ * has_missing_metric_handling: evaluate all plans every interval as well, because no metrics are ingested when a collector dies
+ */
+async fn wait_for_next_evaluation(
+    interval: &mut time::Interval,
+    metric_ingestion_receiver: &mut Option<broadcast::Receiver<String>>,
+    watched_metric_ids: &HashSet<String>,
+    has_cron_plans: bool,
+    has_missing_metric_handling: bool,
+    debounce: Duration,
+) -> PlanEvaluationTarget {
+    let Some(receiver) = metric_ingestion_receiver.as_mut() else {
+        interval.tick().await;
+        return PlanEvaluationTarget::All;
+    };
+    let is_watched = |metric_id: &String| {
+        watched_metric_ids.is_empty() || watched_metric_ids.contains(metric_id)
+    };
+
+    let mut updated_metric_ids: HashSet<String> = HashSet::new();
+    while updated_metric_ids.is_empty() {
+        tokio::select! {
+            // The cron expressions and the stale metrics have to be checked every interval
+            _ = interval.tick(), if has_cron_plans || has_missing_metric_handling => {
+                if has_missing_metric_handling {
+                    return PlanEvaluationTarget::All;
+                }
+                return PlanEvaluationTarget::CronPlans;
+            }
+            result = receiver.recv() => match result {
+                Ok(metric_id) => {
+                    if is_watched(&metric_id) {
+                        updated_metric_ids.insert(metric_id);
+                    }
+                }
+                // Some updates are missed, so evaluate all plans
+                Err(RecvError::Lagged(_)) => {
+                    return PlanEvaluationTarget::All;
+                }
+                Err(RecvError::Closed) => {
+                    error!("[ScalingPlanner] The metric ingestion channel is closed");
+                    interval.tick().await;
+                    return PlanEvaluationTarget::All;
+                }
+            }
+        }
+    }
+
+    // Debounce: collect the metrics ingested in the meantime
+    time::sleep(debounce).await;
+    loop {
+        match receiver.try_recv() {
+            Ok(metric_id) => {
+                if is_watched(&metric_id) {
+                    updated_metric_ids.insert(metric_id);
+                }
+            }
+            Err(TryRecvError::Lagged(_)) => {
+                return PlanEvaluationTarget::All;
+            }
+            Err(_) => break,
+        }
+    }
+    debug!(
+        "[ScalingPlanner] Updated metrics - {:?}",
+        updated_metric_ids
+    );
+    PlanEvaluationTarget::UpdatedMetrics(updated_metric_ids)
+}
 
 /**
  * Parse action from DataLayer
@@ -73,21 +270,36 @@ fn parse_action(action: serde_json::Value) -> Result<(String, String)> {
     Ok((plan_id.to_string(), plan_item_id.to_string()))
 }
 
+/**
+ * Get the language of the expressions from the metadata of the scaling plan (default: javascript)
+ */
+fn get_expression_language(metadata: &HashMap<String, Value>) -> String {
+    metadata
+        .get("language")
+        .and_then(Value::as_str)
+        .unwrap_or(DEFAULT_EXPRESSION_LANGUAGE)
+        .to_lowercase()
+}
 
 async fn apply_scaling_components(
     scaling_components_metadata: &[Value],
     shared_scaling_component_manager: &SharedScalingComponentManager,
+    language: &str,
 ) -> Vec<Result<()>> {
     let mut scaling_results: Vec<Result<()>> = Vec::new();
     for metadata in scaling_components_metadata.iter() {
         let scaling_component_id = metadata["component_id"].as_str().unwrap();
 
-        let params = metadata
+        let mut params = metadata
             .as_object()
             .unwrap()
             .iter()
             .map(|(key, value)| (key.to_string(), value.clone()))
             .collect::<HashMap<String, Value>>();
+        // The scaling components evaluate the params with the language of the plan
+        if language != DEFAULT_EXPRESSION_LANGUAGE {
+            params.insert(EXPRESSION_LANGUAGE_PARAM.to_string(), json!(language));
+        }
 
         {
             let shared_scaling_component_manager = shared_scaling_component_manager.read().await;
@@ -112,6 +324,10 @@ pub struct ScalingPlanner {
     action_task: Option<JoinHandle<()>>,
     last_plan_item_id_by_action: Arc<RwLock<String>>,
     last_plan_timestamp_by_action: Arc<RwLock<Option<DateTime<Utc>>>>,
+    // The reusable functions loaded into the expression engine
+    expression_libraries: Vec<ExpressionLibraryDefinition>,
+    // The dates for is_holiday() in the expressions
+    holiday_calendars: HolidayCalendars,
 }
 
 impl<'a> ScalingPlanner {
@@ -132,8 +348,19 @@ impl<'a> ScalingPlanner {
             action_task: None,
             last_plan_item_id_by_action: Arc::new(RwLock::new(String::new())),
             last_plan_timestamp_by_action: Arc::new(RwLock::new(None)),
+            expression_libraries: Vec::new(),
+            holiday_calendars: Arc::new(HashMap::new()),
         }
     }
+    pub fn set_expression_libraries(
+        &mut self,
+        expression_libraries: Vec<ExpressionLibraryDefinition>,
+    ) {
+        self.expression_libraries = expression_libraries;
+    }
+    pub fn set_holiday_calendars(&mut self, holiday_calendars: &[HolidayCalendarDefinition]) {
+        self.holiday_calendars = get_holiday_calendars(holiday_calendars);
+    }
     fn sort_plan_by_priority(&self) -> Vec<PlanItemDefinition> {
         let mut plans = self.definition.plans.clone();
         plans.sort_by(|a, b| a.priority.cmp(&b.priority).reverse());
@@ -182,19 +409,111 @@ impl<'a> ScalingPlanner {
 
         let mut interval = time::interval(Duration::from_millis(plan_interval as u64));
 
+        // For trigger: on_metric_update
+        let mut metric_ingestion_receiver = if plan_metadata
+            .get("trigger")
+            .and_then(Value::as_str)
+            .map_or(false, |trigger| trigger == PLAN_TRIGGER_ON_METRIC_UPDATE)
+        {
+            Some(data_layer.subscribe_metric_ingestion())
+        } else {
+            None
+        };
+        let debounce = Duration::from_millis(
+            plan_metadata
+                .get("debounce")
+                .and_then(Value::as_u64)
+                .unwrap_or(DEFAULT_PLAN_DEBOUNCE),
+        );
+        // For language: javascript(default) or rhai
+        let language = get_expression_language(&plan_metadata);
+        // The functions of the expression libraries can get any metrics
+        let library_function_names: HashSet<String> = self
+            .expression_libraries
+            .iter()
+            .filter(|expression_library| expression_library.language.to_lowercase() == language)
+            .flat_map(|expression_library| get_function_names_in_library(&expression_library.code))
+            .collect();
+        // key: plan item id, value: the metric ids in the expression (empty if the expression calls the library functions)
+        let referenced_metric_ids: HashMap<String, HashSet<String>> = plans
+            .iter()
+            .map(|plan| {
+                let metric_ids = plan
+                    .expression
+                    .as_ref()
+                    .filter(|expression| !is_calling_functions(expression, &library_function_names))
+                    .map(|expression| get_metric_ids_in_expression(expression))
+                    .unwrap_or_default();
+                (plan.id.clone(), metric_ids)
+            })
+            .collect();
+        // The metrics to wait for. Empty means all metrics if there is an expression without known metric ids.
+        let watched_metric_ids: HashSet<String> = if plans.iter().any(|plan| {
+            plan.expression.is_some()
+                && referenced_metric_ids
+                    .get(&plan.id)
+                    .map_or(true, HashSet::is_empty)
+        }) {
+            HashSet::new()
+        } else {
+            referenced_metric_ids.values().flatten().cloned().collect()
+        };
+        let has_cron_plans = plans.iter().any(has_cron_expression);
+        // For on_missing: error(default), zero, skip_plan or use_last
+        let missing_policy = get_missing_policy(&plan_metadata);
+        // The plan item to run when no plan item is applied and the metrics in the expressions have no data
+        let fallback_plan = plan_metadata
+            .get("fallback_plan_id")
+            .and_then(Value::as_str)
+            .and_then(|fallback_plan_id| plans.iter().find(|plan| plan.id == fallback_plan_id))
+            .cloned();
+        let has_missing_metric_handling =
+            plan_metadata.get("on_missing").is_some() || fallback_plan.is_some();
+        let expression_libraries = self.expression_libraries.clone();
+        let holiday_calendars = self.holiday_calendars.clone();
+
         let task = tokio::spawn(async move {
-            // Initialize the runtime and context to evaluate the scaling plan expressions
-            // TODO: Support Python and other languages
-            let Ok(runtime) = rquickjs::AsyncRuntime::new() else {
-                error!("[ScalingPlanner] Error creating runtime");
-                return;
+            // Initialize the engine to evaluate the scaling plan expressions
+            let mut expression_engine = match create_expression_engine(language.as_str()).await {
+                Ok(expression_engine) => expression_engine,
+                Err(error) => {
+                    error!(
+                        "[ScalingPlanner] Error creating expression engine: {}",
+                        error
+                    );
+                    return;
+                }
             };
-            let Ok(context) = rquickjs::AsyncContext::full(&runtime).await else {
-                error!("[ScalingPlanner] Error creating context");
+            // Load the reusable functions before evaluating the expressions
+            if let Err(error) =
+                load_expression_libraries(expression_engine.as_mut(), &expression_libraries).await
+            {
+                error!("[ScalingPlanner] {}", error);
                 return;
-            };
+            }
+            if let Err(error) = expression_engine
+                .set_holiday_calendars(holiday_calendars)
+                .await
+            {
+                error!("[ScalingPlanner] {}", error);
+                return;
+            }
+            expression_engine.set_default_missing_policy(missing_policy);
+            // Check the syntax of the expressions before running the plans
+            for plan in plans.iter() {
+                let Some(expression) = plan.expression.as_ref() else {
+                    continue;
+                };
+                if let Err(error) = expression_engine.validate(expression).await {
+                    error!(
+                        "[ScalingPlanner] Invalid {} expression in {}: {}",
+                        language, plan.id, error
+                    );
+                }
+            }
 
-            // Run the loop every interval
+            // Run the loop every interval (or on metric updates)
+            let mut evaluation_target = PlanEvaluationTarget::All;
             loop {
                 if let Some(cool_down) = plan_metadata.get("cool_down") {
                     // apply cool down
@@ -206,26 +525,46 @@ impl<'a> ScalingPlanner {
                             let time_left = last_plan_timestamp + cool_down_duration - now;
                             if time_left.num_milliseconds() > 0 {
                                 debug!("[ScalingPlanner] Cooling down. Skip the plan. {} seconds left.", time_left.num_seconds());
-                                interval.tick().await;
+                                evaluation_target = wait_for_next_evaluation(
+                                    &mut interval,
+                                    &mut metric_ingestion_receiver,
+                                    &watched_metric_ids,
+                                    has_cron_plans,
+                                    has_missing_metric_handling,
+                                    debounce,
+                                )
+                                .await;
                                 continue;
                             }
                         }
                     }
                 }
                 {
-                    async_with!(context => |ctx| {
-                        let _ = ctx.globals().set(
-                            "get",
-                            rquickjs::prelude::Func::new("get", get_in_js),
-                        );
-                    })
-                    .await;
-
                     let mut excuted = false;
+                    // The metrics without data in this round
+                    let mut round_stale_metric_ids: BTreeSet<String> = BTreeSet::new();
+                    // The plan that matches the expression
+                    let mut matched_plan: Option<MatchedPlan> = None;
                     // Find the plan that matches the expression
                     for plan in plans.iter() {
+                        // The fallback plan runs only when the metrics have no data
+                        if fallback_plan
+                            .as_ref()
+                            .map_or(false, |fallback_plan| fallback_plan.id == plan.id)
+                        {
+                            continue;
+                        }
+                        let empty_metric_ids = HashSet::new();
+                        let plan_metric_ids = referenced_metric_ids
+                            .get(&plan.id)
+                            .unwrap_or(&empty_metric_ids);
+                        if !evaluation_target.is_target(plan, plan_metric_ids) {
+                            continue;
+                        }
                         if plan.cron_expression.is_none() && plan.expression.is_none() {
-                            error!("[ScalingPlanner] Both cron_expression and expression are empty");
+                            error!(
+                                "[ScalingPlanner] Both cron_expression and expression are empty"
+                            );
                             continue;
                         }
                         // 1. Cron Expression
@@ -234,7 +573,10 @@ impl<'a> ScalingPlanner {
                             if !cron_expression.is_empty() {
                                 let schedule = cron::Schedule::from_str(cron_expression.as_str());
                                 if schedule.is_err() {
-                                    error!("[ScalingPlanner] Error parsing cron expression: {}", cron_expression);
+                                    error!(
+                                        "[ScalingPlanner] Error parsing cron expression: {}",
+                                        cron_expression
+                                    );
                                     continue;
                                 }
                                 let schedule = schedule.unwrap();
@@ -261,29 +603,45 @@ impl<'a> ScalingPlanner {
                             }
                         }
 
-                        // 2. JS Expression
+                        // 2. Expression (javascript or rhai)
                         let mut expression_value_map: Vec<HashMap<String, Option<f64>>> =
                             Vec::new();
+                        let mut stale_metric_ids: BTreeSet<String> = BTreeSet::new();
+                        let mut logs: Vec<String> = Vec::new();
                         if let Some(expression) = plan.expression.as_ref() {
                             if !expression.is_empty() {
                                 debug!("[ScalingPlanner] expression\n{}", expression);
+                                // Clear the stale metrics and the logs of the previous evaluation
+                                expression_engine.take_stale_metric_ids();
+                                expression_engine.take_logs();
                                 // Evaluate the expression
-                                let result = async_with!(context => |ctx| {
-                                    let result = ctx.eval::<bool, _>(expression.clone());
-                                    if result.is_err() {
-                                        let message = result.err().unwrap().to_string();
-                                        error!("[ScalingPlanner] Failed to evaluate expression\n{}\n\n{}", expression, message);
-                                        return false;
+                                let result = match expression_engine.evaluate_bool(expression).await
+                                {
+                                    Ok(result) => result,
+                                    Err(error) if is_skip_plan_error(&error) => {
+                                        info!(
+                                            "[ScalingPlanner] Skip the plan {} - {}",
+                                            plan.id, error
+                                        );
+                                        false
                                     }
-                                    true
-                                })
-                                .await;
-                                
+                                    Err(error) => {
+                                        error!("[ScalingPlanner] Failed to evaluate expression\n{}\n\n{}", expression, error);
+                                        false
+                                    }
+                                };
+
                                 debug!("[ScalingPlanner] expression result - {:?}", result);
                                 // expression get value (for history)
-                                let expression_map =
-                                    expression_get_value(expression.clone(), context.clone()).await;
+                                let expression_map = expression_get_value(
+                                    expression.clone(),
+                                    expression_engine.as_ref(),
+                                )
+                                .await;
                                 expression_value_map.append(&mut expression_map.clone());
+                                stale_metric_ids = expression_engine.take_stale_metric_ids();
+                                logs = expression_engine.take_logs();
+                                round_stale_metric_ids.extend(stale_metric_ids.iter().cloned());
 
                                 // If the expression is false, move to the next plan
                                 if !result {
@@ -291,9 +649,53 @@ impl<'a> ScalingPlanner {
                                 }
                             }
                         }
+                        // Stop the loop. We only want to execute one plan per interval.
+                        matched_plan = Some(MatchedPlan {
+                            plan,
+                            expression_value_map,
+                            stale_metric_ids,
+                            logs,
+                        });
+                        break;
+                    }
+
+                    // Run the fallback plan if no plan matches and the metrics have no data
+                    if matched_plan.is_none() && !round_stale_metric_ids.is_empty() {
+                        if let Some(fallback_plan) = fallback_plan.as_ref() {
+                            info!(
+                                "[ScalingPlanner] Run the fallback plan {} - the metrics have no data: {:?}",
+                                fallback_plan.id, round_stale_metric_ids
+                            );
+                            matched_plan = Some(MatchedPlan {
+                                plan: fallback_plan,
+                                expression_value_map: Vec::new(),
+                                stale_metric_ids: round_stale_metric_ids,
+                                logs: Vec::new(),
+                            });
+                        }
+                    }
+
+                    if let Some(MatchedPlan {
+                        plan,
+                        expression_value_map,
+                        stale_metric_ids,
+                        logs,
+                    }) = matched_plan
+                    {
+                        // Mark the history if the metrics have no data
+                        let stale_metric_ids_json = if stale_metric_ids.is_empty() {
+                            None
+                        } else {
+                            Some(json!(stale_metric_ids).to_string())
+                        };
+                        let logs_json = if logs.is_empty() {
+                            None
+                        } else {
+                            Some(json!(logs).to_string())
+                        };
+                        let results =
+                            run_plan_item(plan, &shared_scaling_component_manager, &language).await;
 
-                        let results = run_plan_item(plan, &shared_scaling_component_manager).await;
-                        
                         // update last plan timestamp
                         if !results.is_empty() {
                             let mut shared_last_plan_timestamp =
@@ -324,15 +726,18 @@ impl<'a> ScalingPlanner {
                                     json!(expression_value_map.clone()).to_string(),
                                     json!(scaling_components_metadata[index].clone()).to_string(),
                                     fail_message,
+                                    stale_metric_ids_json.clone(),
+                                    logs_json.clone(),
                                 );
-                            debug!("[ScalingPlanner] autoscaling_history - {:?}", autoscaling_history);
+                            debug!(
+                                "[ScalingPlanner] autoscaling_history - {:?}",
+                                autoscaling_history
+                            );
                             let _ = data_layer
                                 .add_autoscaling_history(autoscaling_history)
                                 .await;
                         }
-                        // Stop the loop. We only want to execute one plan per interval.
                         excuted = true;
-                        break;
                     }
 
                     // If no plan was executed
@@ -340,8 +745,16 @@ impl<'a> ScalingPlanner {
                         debug!("[ScalingPlanner] No scaling plan was executed");
                     }
                 }
-                // Wait for the next interval.
-                interval.tick().await;
+                // Wait for the next interval (or the metric updates).
+                evaluation_target = wait_for_next_evaluation(
+                    &mut interval,
+                    &mut metric_ingestion_receiver,
+                    &watched_metric_ids,
+                    has_cron_plans,
+                    has_missing_metric_handling,
+                    debounce,
+                )
+                .await;
             }
         });
         self.task = Some(task);
@@ -355,6 +768,7 @@ impl<'a> ScalingPlanner {
         let scaling_component_manager = self.scaling_component_manager.clone();
         let last_plan_id_by_action = self.last_plan_item_id_by_action.clone();
         let last_plan_timestamp_by_action = self.last_plan_timestamp_by_action.clone();
+        let language = get_expression_language(&definition.metadata);
         let action_task = tokio::spawn(async move {
             while let action = receiver.recv().await {
                 if action.is_err() {
@@ -372,10 +786,7 @@ impl<'a> ScalingPlanner {
                 if plan_id != definition.id {
                     continue;
                 }
-                let plan_item = definition
-                    .plans
-                    .iter()
-                    .find(|plan| plan.id == plan_item_id);
+                let plan_item = definition.plans.iter().find(|plan| plan.id == plan_item_id);
 
                 if plan_item.is_none() {
                     error!("Failed to find plan_item: {}", plan_item_id);
@@ -383,7 +794,8 @@ impl<'a> ScalingPlanner {
                 }
 
                 let plan_item = plan_item.unwrap();
-                let _results = run_plan_item(plan_item, &scaling_component_manager).await;
+                let _results =
+                    run_plan_item(plan_item, &scaling_component_manager, &language).await;
 
                 // Update the last run
                 {
@@ -431,12 +843,19 @@ impl<'a> ScalingPlanner {
     }
 }
 
-async fn run_plan_item(plan: &PlanItemDefinition, shared_scaling_component_manager: &Arc<RwLock<crate::scaling_component::ScalingComponentManager>>) -> Vec<Result<()>>{
+async fn run_plan_item(
+    plan: &PlanItemDefinition,
+    shared_scaling_component_manager: &Arc<
+        RwLock<crate::scaling_component::ScalingComponentManager>,
+    >,
+    language: &str,
+) -> Vec<Result<()>> {
     // Apply the scaling components
     let scaling_components_metadata = &plan.scaling_components;
     let results = apply_scaling_components(
         scaling_components_metadata,
         shared_scaling_component_manager,
+        language,
     )
     .await;
 
@@ -445,7 +864,100 @@ async fn run_plan_item(plan: &PlanItemDefinition, shared_scaling_component_manag
     results
 }
 
-fn get_in_js(args: rquickjs::Object<'_>) -> Result<f64, rquickjs::Error> {
+// The arguments of get(), get_series() and get_grouped() in the plan expressions
+#[derive(Debug, Clone)]
+struct GetMetricArgs {
+    metric_id: String,
+    name: Option<String>,
+    // value: exact match, '!value': not equal, '~regex': regex match, '!~regex': regex not match
+    tags: HashMap<String, String>,
+    stats: String,
+    period_sec: u64,
+    // Overrides the policy of the scaling plan
+    on_missing: Option<MissingPolicy>,
+    // The period of the baseline for stats: zscore or mad
+    baseline_sec: u64,
+    // The end of the period is {time_offset_sec} seconds ago (the ad-hoc evaluation of the past)
+    time_offset_sec: u64,
+}
+
+// A value of the metric returned by get_series()
+#[derive(Debug, Clone, PartialEq)]
+struct MetricPoint {
+    // Unix timestamp in milliseconds
+    ts: i64,
+    value: f64,
+    tags: HashMap<String, String>,
+}
+
+impl<'js> rquickjs::IntoJs<'js> for MetricPoint {
+    fn into_js(self, ctx: rquickjs::Ctx<'js>) -> rquickjs::Result<rquickjs::Value<'js>> {
+        let object = rquickjs::Object::new(ctx)?;
+        object.set("ts", self.ts)?;
+        object.set("value", self.value)?;
+        object.set("tags", self.tags)?;
+        rquickjs::IntoJs::into_js(object, ctx)
+    }
+}
+
+impl MetricPoint {
+    fn into_rhai(self) -> rhai::Dynamic {
+        let tags = self
+            .tags
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect::<rhai::Map>();
+        let mut point = rhai::Map::new();
+        point.insert("ts".into(), self.ts.into());
+        point.insert("value".into(), self.value.into());
+        point.insert("tags".into(), tags.into());
+        point.into()
+    }
+}
+
+// The matcher of a tag value in GetMetricArgs.tags
+#[derive(Debug)]
+enum TagMatcher {
+    Equal(String),
+    NotEqual(String),
+    Regex(regex::Regex),
+    NotRegex(regex::Regex),
+}
+
+impl TagMatcher {
+    fn new(pattern: &str) -> Result<Self> {
+        // The regex matches the whole value like PromQL
+        let compile = |pattern: &str| {
+            regex::Regex::new(format!("^(?:{})$", pattern).as_str())
+                .map_err(|error| anyhow::anyhow!("Invalid regex in tags: {}", error))
+        };
+        if let Some(pattern) = pattern.strip_prefix("!~") {
+            Ok(TagMatcher::NotRegex(compile(pattern)?))
+        } else if let Some(pattern) = pattern.strip_prefix('~') {
+            Ok(TagMatcher::Regex(compile(pattern)?))
+        } else if let Some(value) = pattern.strip_prefix('!') {
+            Ok(TagMatcher::NotEqual(value.to_string()))
+        } else {
+            Ok(TagMatcher::Equal(pattern.to_string()))
+        }
+    }
+    // A missing tag matches only the negations
+    fn is_match(&self, value: Option<&str>) -> bool {
+        match (self, value) {
+            (TagMatcher::Equal(expected), Some(value)) => expected == value,
+            (TagMatcher::NotEqual(expected), Some(value)) => expected != value,
+            (TagMatcher::Regex(regex), Some(value)) => regex.is_match(value),
+            (TagMatcher::NotRegex(regex), Some(value)) => !regex.is_match(value),
+            (TagMatcher::Equal(_), None) | (TagMatcher::Regex(_), None) => false,
+            (TagMatcher::NotEqual(_), None) | (TagMatcher::NotRegex(_), None) => true,
+        }
+    }
+}
+
+fn get_metric_args_in_js(
+    state: &SharedMetricAccessState,
+    args: &rquickjs::Object<'_>,
+) -> Result<GetMetricArgs, rquickjs::Error> {
     let metric_id = args
         .get::<String, String>("metric_id".to_string())
         .map_err(|_| {
@@ -464,41 +976,250 @@ fn get_in_js(args: rquickjs::Object<'_>) -> Result<f64, rquickjs::Error> {
     let period_sec = args
         .get::<String, u64>("period_sec".to_string())
         .unwrap_or(PLAN_EXPRESSION_PERIOD_SEC); // default 5 min
+    let baseline_sec = args
+        .get::<String, u64>("baseline_sec".to_string())
+        .unwrap_or(DEFAULT_BASELINE_SEC); // default 1 day
+    let on_missing = match args.get::<String, String>("on_missing".to_string()) {
+        Ok(on_missing) => Some(MissingPolicy::from_str(on_missing.as_str()).map_err(to_js_error)?),
+        Err(_) => None,
+    };
+
+    Ok(GetMetricArgs {
+        metric_id,
+        name,
+        tags,
+        stats,
+        period_sec,
+        on_missing,
+        baseline_sec,
+        time_offset_sec: get_time_offset_sec(state),
+    })
+}
+
+fn get_time_offset_sec(state: &SharedMetricAccessState) -> u64 {
+    state
+        .lock()
+        .map(|state| state.time_offset_sec)
+        .unwrap_or_default()
+}
+
+fn to_js_error(error: anyhow::Error) -> rquickjs::Error {
+    rquickjs::Error::new_loading(error.to_string().as_str())
+}
+
+fn to_rhai_error(error: anyhow::Error) -> Box<rhai::EvalAltResult> {
+    error.to_string().into()
+}
+
+fn get_in_js(
+    state: &SharedMetricAccessState,
+    args: rquickjs::Object<'_>,
+) -> Result<f64, rquickjs::Error> {
+    let args = get_metric_args_in_js(state, &args)?;
+    get_metric_value_with_policy(&args, state).map_err(to_js_error)
+}
+
+// anomaly({ ..., method: 'zscore' | 'mad' }) is get({ ..., stats: method })
+fn anomaly_in_js(
+    state: &SharedMetricAccessState,
+    args: rquickjs::Object<'_>,
+) -> Result<f64, rquickjs::Error> {
+    let method = args
+        .get::<String, String>("method".to_string())
+        .unwrap_or(ANOMALY_METHOD_ZSCORE.to_string());
+    let args = GetMetricArgs {
+        stats: method,
+        ..get_metric_args_in_js(state, &args)?
+    };
+    get_metric_value_with_policy(&args, state).map_err(to_js_error)
+}
+
+fn get_series_in_js(
+    state: &SharedMetricAccessState,
+    args: rquickjs::Object<'_>,
+) -> Result<Vec<MetricPoint>, rquickjs::Error> {
+    let args = get_metric_args_in_js(state, &args)?;
+    get_metric_points_with_state(&args, state).map_err(to_js_error)
+}
+
+fn get_grouped_in_js(
+    state: &SharedMetricAccessState,
+    args: rquickjs::Object<'_>,
+) -> Result<HashMap<String, f64>, rquickjs::Error> {
+    let group_by = args
+        .get::<String, Vec<String>>("group_by".to_string())
+        .unwrap_or_default();
+    let args = get_metric_args_in_js(state, &args)?;
+    get_grouped_metric_values(&args, &group_by, state).map_err(to_js_error)
+}
+
+fn get_metric_args_in_rhai(
+    state: &SharedMetricAccessState,
+    args: &rhai::Map,
+) -> Result<GetMetricArgs, Box<rhai::EvalAltResult>> {
+    let get_string = |key: &str| {
+        args.get(key)
+            .and_then(|value| value.clone().into_string().ok())
+    };
+    let Some(metric_id) = get_string("metric_id") else {
+        error!("[ScalingPlan expression error] Failed to get metric_id");
+        return Err("Failed to get metric_id".into());
+    };
+    let name = get_string("name");
+    // tags, stats, period_sec is optional
+    let tags = args
+        .get("tags")
+        .and_then(|tags| tags.clone().try_cast::<rhai::Map>())
+        .map(|tags| {
+            tags.into_iter()
+                .filter_map(|(key, value)| {
+                    value
+                        .into_string()
+                        .ok()
+                        .map(|value| (key.to_string(), value))
+                })
+                .collect::<HashMap<String, String>>()
+        })
+        .unwrap_or_default();
+    let stats = get_string("stats").unwrap_or("latest".to_string());
+    let period_sec = args
+        .get("period_sec")
+        .and_then(|period_sec| period_sec.as_int().ok())
+        .map(|period_sec| period_sec as u64)
+        .unwrap_or(PLAN_EXPRESSION_PERIOD_SEC); // default 5 min
+    let baseline_sec = args
+        .get("baseline_sec")
+        .and_then(|baseline_sec| baseline_sec.as_int().ok())
+        .map(|baseline_sec| baseline_sec as u64)
+        .unwrap_or(DEFAULT_BASELINE_SEC); // default 1 day
+    let on_missing = match get_string("on_missing") {
+        Some(on_missing) => {
+            Some(MissingPolicy::from_str(on_missing.as_str()).map_err(to_rhai_error)?)
+        }
+        None => None,
+    };
+
+    Ok(GetMetricArgs {
+        metric_id,
+        name,
+        tags,
+        stats,
+        period_sec,
+        on_missing,
+        baseline_sec,
+        time_offset_sec: get_time_offset_sec(state),
+    })
+}
+
+fn get_in_rhai(
+    state: &SharedMetricAccessState,
+    args: rhai::Map,
+) -> Result<f64, Box<rhai::EvalAltResult>> {
+    let args = get_metric_args_in_rhai(state, &args)?;
+    get_metric_value_with_policy(&args, state).map_err(to_rhai_error)
+}
+
+fn anomaly_in_rhai(
+    state: &SharedMetricAccessState,
+    args: rhai::Map,
+) -> Result<f64, Box<rhai::EvalAltResult>> {
+    let method = args
+        .get("method")
+        .and_then(|method| method.clone().into_string().ok())
+        .unwrap_or(ANOMALY_METHOD_ZSCORE.to_string());
+    let args = GetMetricArgs {
+        stats: method,
+        ..get_metric_args_in_rhai(state, &args)?
+    };
+    get_metric_value_with_policy(&args, state).map_err(to_rhai_error)
+}
+
+fn get_series_in_rhai(
+    state: &SharedMetricAccessState,
+    args: rhai::Map,
+) -> Result<rhai::Array, Box<rhai::EvalAltResult>> {
+    let args = get_metric_args_in_rhai(state, &args)?;
+    let points = get_metric_points_with_state(&args, state).map_err(to_rhai_error)?;
+    Ok(points.into_iter().map(MetricPoint::into_rhai).collect())
+}
+
+fn get_grouped_in_rhai(
+    state: &SharedMetricAccessState,
+    args: rhai::Map,
+) -> Result<rhai::Map, Box<rhai::EvalAltResult>> {
+    let group_by = args
+        .get("group_by")
+        .and_then(|group_by| group_by.clone().try_cast::<rhai::Array>())
+        .map(|group_by| {
+            group_by
+                .into_iter()
+                .filter_map(|key| key.into_string().ok())
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+    let args = get_metric_args_in_rhai(state, &args)?;
+    let grouped = get_grouped_metric_values(&args, &group_by, state).map_err(to_rhai_error)?;
+    Ok(grouped
+        .into_iter()
+        .map(|(key, value)| (key.into(), value.into()))
+        .collect())
+}
+
+// The values of the metric that match the name and the tags in the period (empty if the metric has no data)
+fn get_metric_points(args: &GetMetricArgs) -> Result<Vec<MetricPoint>> {
+    let GetMetricArgs {
+        metric_id,
+        name,
+        tags,
+        stats,
+        period_sec,
+        time_offset_sec,
+        ..
+    } = args;
+    let period_sec = *period_sec;
+    let tag_matchers = tags
+        .iter()
+        .map(|(key, pattern)| TagMatcher::new(pattern).map(|matcher| (key, matcher)))
+        .collect::<Result<Vec<(&String, TagMatcher)>>>()?;
 
     let Ok(source_metrics_data) = SOURCE_METRICS_DATA.read() else {
         error!("[get_in_js] Failed to get source_metrics_data");
-        return Err(rquickjs::Error::new_loading("Failed to get the metrics data"))
+        return Err(anyhow::anyhow!("Failed to get the metrics data"))
     };
-    let start_time = Ulid::from_datetime(
-        std::time::SystemTime::now() - Duration::from_millis(1000 * period_sec),
-    );
-    let end_time = Ulid::new();
 
-    debug!("[get_in_js] - metric_id: {}, name: {:?}, tags: {:?}, stats: {}, period_sec: {}", metric_id, name, tags, stats, period_sec);
+    let end_datetime = std::time::SystemTime::now() - Duration::from_secs(*time_offset_sec);
+    let start_time = Ulid::from_datetime(end_datetime - Duration::from_millis(1000 * period_sec));
+    let end_time = Ulid::from_datetime(end_datetime);
+
+    debug!(
+        "[get_in_js] - metric_id: {}, name: {:?}, tags: {:?}, stats: {}, period_sec: {}",
+        metric_id, name, tags, stats, period_sec
+    );
 
     // find metric_id
-    let Some(metric_values) = source_metrics_data.source_metrics.get(&metric_id) else {
-        return Err(rquickjs::Error::new_loading("Failed to get metric_id from the metrics data"))
+    let Some(metric_values) = source_metrics_data.source_metrics.get(metric_id) else {
+        return Ok(Vec::new())
     };
 
     // Filtered metric values
-    let mut target_value_arr: Vec<f64> = Vec::new();
-    
-    // Validate whether the start_time is before the last item in the metric_values. 
+    let mut target_points: Vec<MetricPoint> = Vec::new();
+
+    // Validate whether the start_time is before the last item in the metric_values.
     // If the start_time is after the last item, then BTreeMap will panic.
     let last_item = metric_values.iter().last();
     if last_item.is_none() {
-        return Err(rquickjs::Error::new_loading("Failed to get the last item in the metric_values"));
+        return Ok(Vec::new());
     }
     let last_item = last_item.unwrap();
     let last_item_time = Ulid::from_str(last_item.0.as_str()).unwrap();
     if last_item_time < start_time {
-        return Err(rquickjs::Error::new_loading("The start_time is before the last item in the metric_values"));
+        return Ok(Vec::new());
     }
 
     // Find the metric values between the time range (current time - period_sec, current time)
     metric_values.range((Included(start_time.to_string()), Included(end_time.to_string())))
-        .for_each(|(_ulid, source_metrics_value)| {
+        .for_each(|(ulid, source_metrics_value)| {
+            let ts = Ulid::from_str(ulid.as_str()).map(|ulid| ulid.timestamp_ms() as i64).unwrap_or_default();
             // Get the json string
             let Ok(value) = serde_json::to_value(source_metrics_value.clone()) else {
                 error!("[ScalingPlan expression error] Failed to convert source_metric_data to serde value");
@@ -511,7 +1232,6 @@ fn get_in_js(args: rquickjs::Object<'_>) -> Result<f64, rquickjs::Error> {
             let json_value_str = serde_json::from_str::<Value>(json_value_str)
                 .map_err(|_| {
                     error!("[ScalingPlan expression error] Failed to convert json_value to serde value");
-                    rquickjs::Error::Exception
                 })
                 .unwrap();
 
@@ -524,43 +1244,161 @@ fn get_in_js(args: rquickjs::Object<'_>) -> Result<f64, rquickjs::Error> {
                 if name.is_some() && item_name.is_some() && item_name.unwrap() != name.as_ref().unwrap() {
                     continue;
                 }
-                
+
                 // Check if the tags match
                 let item_tags = json_value_item.get("tags").and_then(Value::as_object);
-                if !tags.is_empty() {
-                    // If the tags are not empty but the item_tags is None, then it means that it doesn't match
-                    if item_tags.is_none() {
-                        continue;
-                    }
-                    let item_tags = item_tags.unwrap();
-
-                    let mut match_tags = true;
-                    for (key, value) in tags.iter() {
-                        let item_value = item_tags.get(key).and_then(Value::as_str);
-                        if item_value.is_none() || item_value.unwrap() != value {
-                            match_tags = false;
-                            break;
-                        }
-                    }
+                let match_tags = tag_matchers.iter().all(|(key, matcher)| {
+                    let item_value = item_tags.and_then(|item_tags| item_tags.get(key.as_str())).and_then(Value::as_str);
+                    matcher.is_match(item_value)
+                });
 
-                    // If the tags don't match, then skip
-                    if !match_tags {
-                        continue;
-                    }
+                // If the tags don't match, then skip
+                if !match_tags {
+                    continue;
                 }
 
-                // Put the value in the target_value_arr
+                // Put the value in the target_points
                 let item_value = json_value_item.get("value").and_then(Value::as_f64);
                 if item_value.is_some() {
-                    target_value_arr.append(&mut vec![item_value.unwrap()]);
+                    let item_tags = item_tags
+                        .map(|item_tags| {
+                            item_tags
+                                .iter()
+                                .filter_map(|(key, value)| value.as_str().map(|value| (key.clone(), value.to_string())))
+                                .collect::<HashMap<String, String>>()
+                        })
+                        .unwrap_or_default();
+                    target_points.push(MetricPoint {
+                        ts,
+                        value: item_value.unwrap(),
+                        tags: item_tags,
+                    });
                 }
             }
 
         });
+    Ok(target_points)
+}
+
+// Record the metric as stale if it has no data
+fn get_metric_points_with_state(
+    args: &GetMetricArgs,
+    state: &SharedMetricAccessState,
+) -> Result<Vec<MetricPoint>> {
+    let points = get_metric_points(args)?;
+    if points.is_empty() {
+        if let Ok(mut state) = state.lock() {
+            state.stale_metric_ids.insert(args.metric_id.clone());
+        }
+    }
+    Ok(points)
+}
+
+fn get_metric_value_with_policy(
+    args: &GetMetricArgs,
+    state: &SharedMetricAccessState,
+) -> Result<f64> {
+    let points = get_metric_points_with_state(args, state)?;
+    if !points.is_empty() {
+        let target_value_arr = points.into_iter().map(|point| point.value).collect();
+        if is_anomaly_method(args.stats.as_str()) {
+            return get_anomaly_value(args, target_value_arr);
+        }
+        return get_stats_value(target_value_arr, args.stats.as_str());
+    }
+    let policy = match args.on_missing {
+        Some(policy) => policy,
+        None => state
+            .lock()
+            .map(|state| state.default_policy)
+            .unwrap_or_default(),
+    };
+    match policy {
+        MissingPolicy::Error => Err(anyhow::anyhow!(
+            "The metric has no data in {} seconds: {}",
+            args.period_sec,
+            args.metric_id
+        )),
+        MissingPolicy::Zero => Ok(0.0),
+        MissingPolicy::SkipPlan => Err(get_skip_plan_error(args.metric_id.as_str())),
+        MissingPolicy::UseLast => {
+            // The latest value since the beginning
+            let period_sec = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|duration| duration.as_secs().saturating_sub(args.time_offset_sec))
+                .unwrap_or_default();
+            let last_args = GetMetricArgs {
+                stats: PlanExpressionStats::Latest.to_string(),
+                period_sec,
+                ..args.clone()
+            };
+            let target_value_arr = get_metric_points(&last_args)?
+                .into_iter()
+                .map(|point| point.value)
+                .collect();
+            get_stats_value(target_value_arr, last_args.stats.as_str())
+        }
+    }
+}
+
+// The deviation score of the values in period_sec from the values in baseline_sec before period_sec
+fn get_anomaly_value(args: &GetMetricArgs, target_value_arr: Vec<f64>) -> Result<f64> {
+    if args.baseline_sec <= args.period_sec {
+        return Err(anyhow::anyhow!(
+            "baseline_sec({}) should be greater than period_sec({})",
+            args.baseline_sec,
+            args.period_sec
+        ));
+    }
+    // The baseline excludes the current window
+    let baseline_args = GetMetricArgs {
+        period_sec: args.baseline_sec - args.period_sec,
+        time_offset_sec: args.time_offset_sec + args.period_sec,
+        ..args.clone()
+    };
+    let baseline_value_arr = get_metric_points(&baseline_args)?
+        .into_iter()
+        .map(|point| point.value)
+        .collect::<Vec<f64>>();
+    get_anomaly_score(args.stats.as_str(), &target_value_arr, &baseline_value_arr)
+}
+
+// The stats of each group by the values of the group_by tags. The key of the group is the tag values joined with ','.
+fn get_grouped_metric_values(
+    args: &GetMetricArgs,
+    group_by: &[String],
+    state: &SharedMetricAccessState,
+) -> Result<HashMap<String, f64>> {
+    if group_by.is_empty() {
+        return Err(anyhow::anyhow!("group_by is empty"));
+    }
+    let mut grouped_values: HashMap<String, Vec<f64>> = HashMap::new();
+    for point in get_metric_points_with_state(args, state)? {
+        // The values without the group_by tags are not grouped
+        let Some(group_values) = group_by
+            .iter()
+            .map(|key| point.tags.get(key).cloned())
+            .collect::<Option<Vec<String>>>() else {
+            continue;
+        };
+        grouped_values
+            .entry(group_values.join(","))
+            .or_default()
+            .push(point.value);
+    }
+    grouped_values
+        .into_iter()
+        .map(|(group, values)| {
+            get_stats_value(values, args.stats.as_str()).map(|value| (group, value))
+        })
+        .collect()
+}
+
+fn get_stats_value(target_value_arr: Vec<f64>, stats: &str) -> Result<f64> {
     let metric_stats = match stats.to_lowercase() {
         ms if PlanExpressionStats::Latest.to_string() == ms => {
             let Some(latest_value) = target_value_arr.iter().last() else {
-                return Err(rquickjs::Error::new_loading("Failed to get the value with the stats"));
+                return Err(anyhow::anyhow!("Failed to get the value with the stats"));
             };
             Ok(latest_value.to_owned())
         }
@@ -578,25 +1416,25 @@ fn get_in_js(args: rquickjs::Object<'_>) -> Result<f64, rquickjs::Error> {
             let min_value = target_value_arr
                 .into_iter()
                 .reduce(f64::min)
-                .ok_or(rquickjs::Error::new_loading("Failed to get the value with the stats"));
+                .ok_or(anyhow::anyhow!("Failed to get the value with the stats"));
             match min_value {
                 Ok(min_value) => Ok(min_value),
-                Err(_) => Err(rquickjs::Error::new_loading("Failed to get the value with the stats")),
+                Err(_) => Err(anyhow::anyhow!("Failed to get the value with the stats")),
             }
         }
         ms if PlanExpressionStats::Maximum.to_string() == ms => {
             let max_value = target_value_arr
                 .into_iter()
                 .reduce(f64::max)
-                .ok_or(rquickjs::Error::new_loading("Failed to get the value with the stats"));
+                .ok_or(anyhow::anyhow!("Failed to get the value with the stats"));
             match max_value {
                 Ok(max_value) => Ok(max_value),
-                Err(_) => Err(rquickjs::Error::new_loading("Failed to get the value with the stats")),
+                Err(_) => Err(anyhow::anyhow!("Failed to get the value with the stats")),
             }
         }
         _ => {
             error!("[get_in_js] stats is valid: {}", stats);
-            Err(rquickjs::Error::new_loading("Failed to get the value with the stats"))
+            Err(anyhow::anyhow!("Failed to get the value with the stats"))
         }
     };
     debug!("[get_in_js] metric_stats: {:?}", metric_stats);
@@ -605,23 +1443,16 @@ fn get_in_js(args: rquickjs::Object<'_>) -> Result<f64, rquickjs::Error> {
 
 async fn expression_get_value(
     expression: String,
-    context: rquickjs::AsyncContext,
+    expression_engine: &dyn ExpressionEngine,
 ) -> Vec<HashMap<String, Option<f64>>> {
     let re = regex::Regex::new(r"[get\()](.*?)[\)]").unwrap();
     let expression = expression.replace('\n', "");
     let mut expression_value_map: Vec<HashMap<String, Option<f64>>> = Vec::new();
     for cap in re.find_iter(expression.as_str()) {
-        let get_result = async_with!(context => |ctx| {
-            let get_value =  ctx.eval::<f64, _>(cap.as_str());
-            let mut history_map = HashMap::new();
-            history_map.insert(cap.as_str().to_string(), match get_value {
-                Ok(value) => Some(value),
-                Err(_) => None,
-            });
-            history_map
-        })
-        .await;
-        expression_value_map.append(&mut vec![get_result.clone()]);
+        let get_value = expression_engine.evaluate_f64(cap.as_str()).await;
+        let mut history_map = HashMap::new();
+        history_map.insert(cap.as_str().to_string(), get_value.ok());
+        expression_value_map.append(&mut vec![history_map]);
     }
 
     expression_value_map
@@ -629,12 +1460,14 @@ async fn expression_get_value(
 
 #[cfg(test)]
 mod tests {
+    use super::expression_engine::{EXPRESSION_LANGUAGE_JAVASCRIPT, EXPRESSION_LANGUAGE_RHAI};
     use super::*;
     use crate::metric_updater::MetricUpdater;
     use crate::scaling_component::ScalingComponentManager;
     use data_layer::data_layer::DataLayer;
     use data_layer::types::object_kind::ObjectKind;
     use data_layer::MetricDefinition;
+    use rquickjs::async_with;
 
     use serde_json::json;
     use std::collections::HashMap;
@@ -699,17 +1532,13 @@ mod tests {
 
     #[tokio::test]
     async fn test_expression_get_value() {
-        let Ok(runtime) = rquickjs::AsyncRuntime::new() else {
-            error!("Error creating runtime");
-            return;
-        };
-        let Ok(context) = rquickjs::AsyncContext::full(&runtime).await else {
-            error!("Error creating context");
+        let Ok(expression_engine) = create_expression_engine(DEFAULT_EXPRESSION_LANGUAGE).await else {
+            error!("Error creating expression engine");
             return;
         };
         let expression = "get({\n  metric_id: 'cloudwatch_dynamodb_id',\n  name: 'dynamodb_capacity_usage',\n  tags: {\n    tag1: 'value1'\n  },\n  stats: 'max',\n  period_sec: 120\n}) <= 30 || get({\n  metric_id: 'cloudwatch_dynamodb_id',\n  name: 'dynamodb_capacity_usage',\n  tags: {\n    tag1: 'value1'\n  },\n  stats: 'min',\n  period_sec: 120\n}) <= 40\n";
         assert_eq!(
-            expression_get_value(expression.to_string(), context)
+            expression_get_value(expression.to_string(), expression_engine.as_ref())
                 .await
                 .len(),
             2
@@ -731,10 +1560,13 @@ mod tests {
             return;
         };
 
+        let state: SharedMetricAccessState = Arc::new(std::sync::Mutex::new(Default::default()));
         async_with!(context => |ctx| {
             let _ = ctx.globals().set(
                 "get",
-                rquickjs::prelude::Func::new("get", get_in_js),
+                rquickjs::prelude::Func::new("get", move |args: rquickjs::Object| {
+                    get_in_js(&state, args)
+                }),
             );
         })
         .await;
@@ -907,6 +1739,87 @@ mod tests {
             assert_eq!(*shared_last_plan_id, plan_id);
         }
     }
+    #[tokio::test]
+    async fn test_simple_expression_in_rhai() {
+        let plan_id = uuid::Uuid::new_v4().to_string();
+        // Create a ScalingPlanner
+        let (data_layer, mut scaling_planner) = get_scaling_planner(vec![PlanItemDefinition {
+            id: plan_id.clone(),
+            description: None,
+            expression: Some(
+                r#"get(#{ metric_id: "metric_rhai", stats: "max", period_sec: 120, name: "test", tags: #{ tag1: "value1" } }) > 0"#
+                    .to_string(),
+            ),
+            cron_expression: None,
+            priority: 1,
+            scaling_components: vec![],
+            ui: None,
+        }])
+        .await;
+        scaling_planner.definition.metadata =
+            HashMap::from([("language".to_string(), json!("rhai"))]);
+        scaling_planner.run();
+
+        // Add a metric to the DataLayer
+        let metric = json!([
+            {
+                "name": "test",
+                "tags": {
+                    "tag1": "value1"
+                },
+                "value": 1,
+            }
+        ])
+        .to_string();
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "metric_rhai", metric.as_str())
+            .await;
+
+        // Wait for the scaling planner to execute the plan
+        tokio::time::sleep(tokio::time::Duration::from_millis(3000)).await;
+        {
+            let last_plan_id = scaling_planner.get_last_plan_item_id();
+            let shared_last_plan_id = last_plan_id.read().await;
+            assert_eq!(*shared_last_plan_id, plan_id);
+        }
+    }
+    #[tokio::test]
+    async fn test_expression_with_expression_library() {
+        let plan_id = uuid::Uuid::new_v4().to_string();
+        // Create a ScalingPlanner
+        let (data_layer, mut scaling_planner) = get_scaling_planner(vec![PlanItemDefinition {
+            id: plan_id.clone(),
+            description: None,
+            expression: Some("maxOf('metric_library') > 0".to_string()),
+            cron_expression: None,
+            priority: 1,
+            scaling_components: vec![],
+            ui: None,
+        }])
+        .await;
+        scaling_planner.set_expression_libraries(vec![ExpressionLibraryDefinition {
+            id: "library".to_string(),
+            code: "function maxOf(metric_id) { return get({ metric_id, stats: 'max', period_sec: 120 }); }"
+                .to_string(),
+            ..Default::default()
+        }]);
+        scaling_planner.run();
+
+        // Add a metric to the DataLayer
+        let metric = json!([{ "name": "test", "value": 1 }]).to_string();
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "metric_library", metric.as_str())
+            .await;
+
+        // Wait for the scaling planner to execute the plan
+        tokio::time::sleep(tokio::time::Duration::from_millis(3000)).await;
+        {
+            let last_plan_id = scaling_planner.get_last_plan_item_id();
+            let shared_last_plan_id = last_plan_id.read().await;
+            assert_eq!(*shared_last_plan_id, plan_id);
+        }
+    }
+
     #[tokio::test]
     async fn test_cron_expression() {
         let plan_id = uuid::Uuid::new_v4().to_string();
@@ -1082,6 +1995,447 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tag_matcher() {
+        let equal = TagMatcher::new("ap-northeast-2a").unwrap();
+        assert!(equal.is_match(Some("ap-northeast-2a")));
+        assert!(!equal.is_match(Some("ap-northeast-2b")));
+        assert!(!equal.is_match(None));
+        let not_equal = TagMatcher::new("!ap-northeast-2a").unwrap();
+        assert!(!not_equal.is_match(Some("ap-northeast-2a")));
+        assert!(not_equal.is_match(Some("ap-northeast-2b")));
+        assert!(not_equal.is_match(None));
+        let regex = TagMatcher::new("~ap-northeast-2[ab]").unwrap();
+        assert!(regex.is_match(Some("ap-northeast-2a")));
+        assert!(!regex.is_match(Some("ap-northeast-2c")));
+        // The regex matches the whole value
+        assert!(!regex.is_match(Some("ap-northeast-2a-1")));
+        let not_regex = TagMatcher::new("!~ap-northeast-2[ab]").unwrap();
+        assert!(!not_regex.is_match(Some("ap-northeast-2a")));
+        assert!(not_regex.is_match(Some("ap-northeast-2c")));
+        assert!(TagMatcher::new("~[").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_series_and_get_grouped() {
+        let data_layer = DataLayer::new("", 500_000, false).await;
+        data_layer.sync("").await;
+        let json_value = json!([
+            {"name": "cpu", "tags": {"az": "a", "host": "host-1"}, "value": 10.0},
+            {"name": "cpu", "tags": {"az": "a", "host": "host-2"}, "value": 30.0},
+            {"name": "cpu", "tags": {"az": "b", "host": "host-3"}, "value": 80.0},
+            {"name": "cpu", "tags": {"host": "host-4"}, "value": 50.0}
+        ])
+        .to_string();
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "series_metric", &json_value)
+            .await;
+
+        let args = GetMetricArgs {
+            metric_id: "series_metric".to_string(),
+            name: Some("cpu".to_string()),
+            tags: HashMap::from([("host".to_string(), "!~host-[12]".to_string())]),
+            stats: "avg".to_string(),
+            period_sec: 60,
+            on_missing: None,
+            baseline_sec: DEFAULT_BASELINE_SEC,
+            time_offset_sec: 0,
+        };
+        let state: SharedMetricAccessState = Arc::new(std::sync::Mutex::new(Default::default()));
+        let points = get_metric_points(&args).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].value, 80.0);
+        assert_eq!(points[0].tags.get("az").unwrap(), "b");
+        assert!(points[0].ts > 0);
+
+        let grouped = get_grouped_metric_values(
+            &GetMetricArgs {
+                tags: HashMap::new(),
+                ..args.clone()
+            },
+            &["az".to_string()],
+            &state,
+        )
+        .unwrap();
+        assert_eq!(
+            grouped,
+            HashMap::from([("a".to_string(), 20.0), ("b".to_string(), 80.0)])
+        );
+        assert!(get_grouped_metric_values(&args, &[], &state).is_err());
+
+        let expression_engine = create_expression_engine(EXPRESSION_LANGUAGE_JAVASCRIPT)
+            .await
+            .unwrap();
+        assert!(expression_engine
+            .evaluate_bool(
+                "get_series({ metric_id: 'series_metric', tags: { az: '~a|b' } }).length == 3"
+            )
+            .await
+            .unwrap());
+        assert!(expression_engine
+            .evaluate_bool("get_series({ metric_id: 'series_metric' }).map(p => p.value).reduce((a, b) => a + b) == 170")
+            .await
+            .unwrap());
+        assert!(expression_engine
+            .evaluate_bool("(g => g.b - g.a)(get_grouped({ metric_id: 'series_metric', stats: 'avg', group_by: ['az'] })) == 60")
+            .await
+            .unwrap());
+
+        let expression_engine = create_expression_engine(EXPRESSION_LANGUAGE_RHAI)
+            .await
+            .unwrap();
+        assert!(expression_engine
+            .evaluate_bool(
+                r#"get_series(#{ metric_id: "series_metric", tags: #{ az: "!a" } }).len() == 2"#
+            )
+            .await
+            .unwrap());
+        assert!(expression_engine
+            .evaluate_bool(r#"get_grouped(#{ metric_id: "series_metric", stats: "max", group_by: ["az"] })["a"] == 30.0"#)
+            .await
+            .unwrap());
+    }
+
+    #[test]
+    fn test_get_metric_ids_in_expression() {
+        let expression = "get({ metric_id: 'metric1', stats: 'max' }) > 0 && get({\n  \"metric_id\": \"metric2\"\n}) > 0";
+        let metric_ids = get_metric_ids_in_expression(expression);
+        assert_eq!(
+            metric_ids,
+            HashSet::from(["metric1".to_string(), "metric2".to_string()])
+        );
+        assert!(get_metric_ids_in_expression("get({ metric_id: id }) > 0").is_empty());
+    }
+
+    #[test]
+    fn test_is_calling_library_functions() {
+        let function_names = get_function_names_in_library(
+            "function get_queue_depth() { return get({ metric_id: 'queue' }); }\nconst is_busy = () => get_queue_depth() > 10;",
+        );
+        assert_eq!(
+            function_names,
+            HashSet::from(["get_queue_depth".to_string(), "is_busy".to_string()])
+        );
+        assert_eq!(
+            get_function_names_in_library(
+                "fn get_queue_depth() { get(#{ metric_id: \"queue\" }) }"
+            ),
+            HashSet::from(["get_queue_depth".to_string()])
+        );
+        assert!(is_calling_functions(
+            "get({ metric_id: 'metric1' }) > 0 && is_busy()",
+            &function_names
+        ));
+        assert!(is_calling_functions(
+            "get_queue_depth () > 0",
+            &function_names
+        ));
+        assert!(!is_calling_functions(
+            "get({ metric_id: 'metric1' }) > 0 && not_is_busy()",
+            &function_names
+        ));
+        assert!(!is_calling_functions(
+            "get({ metric_id: 'is_busy' }) > 0",
+            &function_names
+        ));
+    }
+
+    #[test]
+    fn test_plan_evaluation_target() {
+        let expression_plan = PlanItemDefinition {
+            id: "expression_plan".to_string(),
+            description: None,
+            expression: Some("get({ metric_id: 'metric1' }) > 0".to_string()),
+            cron_expression: None,
+            priority: 1,
+            scaling_components: vec![],
+            ui: None,
+        };
+        let cron_plan = PlanItemDefinition {
+            id: "cron_plan".to_string(),
+            description: None,
+            expression: None,
+            cron_expression: Some("*/2 * * * * * *".to_string()),
+            priority: 1,
+            scaling_components: vec![],
+            ui: None,
+        };
+        let metric1 = HashSet::from(["metric1".to_string()]);
+        let metric2 = HashSet::from(["metric2".to_string()]);
+        let unknown = HashSet::new();
+
+        assert!(PlanEvaluationTarget::All.is_target(&expression_plan, &metric1));
+        assert!(PlanEvaluationTarget::All.is_target(&cron_plan, &unknown));
+        assert!(!PlanEvaluationTarget::CronPlans.is_target(&expression_plan, &metric1));
+        assert!(PlanEvaluationTarget::CronPlans.is_target(&cron_plan, &unknown));
+
+        let updated_metric1 = PlanEvaluationTarget::UpdatedMetrics(metric1.clone());
+        assert!(updated_metric1.is_target(&expression_plan, &metric1));
+        assert!(!updated_metric1.is_target(&expression_plan, &metric2));
+        assert!(updated_metric1.is_target(&expression_plan, &unknown));
+        assert!(!updated_metric1.is_target(&cron_plan, &unknown));
+
+        // An empty cron_expression is not a cron plan
+        let empty_cron_plan = PlanItemDefinition {
+            cron_expression: Some("".to_string()),
+            ..expression_plan.clone()
+        };
+        assert!(has_cron_expression(&cron_plan));
+        assert!(!has_cron_expression(&empty_cron_plan));
+        assert!(!PlanEvaluationTarget::CronPlans.is_target(&empty_cron_plan, &metric1));
+        assert!(updated_metric1.is_target(&empty_cron_plan, &metric1));
+    }
+
+    #[tokio::test]
+    async fn test_trigger_on_metric_update() {
+        let plan_id = uuid::Uuid::new_v4().to_string();
+        // Create a ScalingPlanner
+        let (data_layer, mut scaling_planner) = get_scaling_planner(vec![PlanItemDefinition {
+            id: plan_id.clone(),
+            description: None,
+            expression: Some(
+                "get({ metric_id: 'metric_on_update', stats: 'max', period_sec: 120 }) > 0"
+                    .to_string(),
+            ),
+            cron_expression: None,
+            priority: 1,
+            scaling_components: vec![],
+            ui: None,
+        }])
+        .await;
+        scaling_planner.definition.metadata = HashMap::from([
+            ("trigger".to_string(), json!("on_metric_update")),
+            ("debounce".to_string(), json!(10)),
+        ]);
+        scaling_planner.run();
+
+        // There is no metric yet
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        {
+            let last_plan_id = scaling_planner.get_last_plan_item_id();
+            let shared_last_plan_id = last_plan_id.read().await;
+            assert_eq!(*shared_last_plan_id, "");
+        }
+
+        // An unrelated metric doesn't trigger the plan
+        let metric = json!([{ "name": "test", "value": 1 }]).to_string();
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "metric_unrelated", metric.as_str())
+            .await;
+        // The plan is evaluated right after the metric is ingested (before the next interval)
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "metric_on_update", metric.as_str())
+            .await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+        {
+            let last_plan_id = scaling_planner.get_last_plan_item_id();
+            let shared_last_plan_id = last_plan_id.read().await;
+            assert_eq!(*shared_last_plan_id, plan_id);
+        }
+        scaling_planner.stop();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_next_evaluation_without_metric_updates() {
+        let (_sender, receiver) = broadcast::channel::<String>(16);
+        let mut metric_ingestion_receiver = Some(receiver);
+        let watched_metric_ids = HashSet::from(["metric1".to_string()]);
+        // No metrics are ingested, so it keeps waiting
+        let mut interval = time::interval(Duration::from_millis(100));
+        let result = time::timeout(
+            Duration::from_millis(300),
+            wait_for_next_evaluation(
+                &mut interval,
+                &mut metric_ingestion_receiver,
+                &watched_metric_ids,
+                false,
+                false,
+                Duration::from_millis(10),
+            ),
+        )
+        .await;
+        assert!(result.is_err());
+        // on_missing or the fallback plan checks the stale metrics every interval
+        let mut interval = time::interval(Duration::from_millis(100));
+        let result = time::timeout(
+            Duration::from_millis(300),
+            wait_for_next_evaluation(
+                &mut interval,
+                &mut metric_ingestion_receiver,
+                &watched_metric_ids,
+                false,
+                true,
+                Duration::from_millis(10),
+            ),
+        )
+        .await;
+        assert_eq!(result.unwrap(), PlanEvaluationTarget::All);
+    }
+
+    #[tokio::test]
+    async fn test_on_missing() {
+        let expression_engine = create_expression_engine(EXPRESSION_LANGUAGE_JAVASCRIPT)
+            .await
+            .unwrap();
+        // error (default)
+        assert!(expression_engine
+            .evaluate_bool("get({ metric_id: 'metric_on_missing' }) == 0")
+            .await
+            .is_err());
+        assert_eq!(
+            expression_engine.take_stale_metric_ids(),
+            BTreeSet::from(["metric_on_missing".to_string()])
+        );
+        assert!(expression_engine.take_stale_metric_ids().is_empty());
+        // zero by the scaling plan and skip_plan by the call
+        expression_engine.set_default_missing_policy(MissingPolicy::Zero);
+        assert!(expression_engine
+            .evaluate_bool("get({ metric_id: 'metric_on_missing' }) == 0")
+            .await
+            .unwrap());
+        let error = expression_engine
+            .evaluate_bool("get({ metric_id: 'metric_on_missing', on_missing: 'skip_plan' }) == 0")
+            .await
+            .unwrap_err();
+        assert!(is_skip_plan_error(&error));
+        // use_last
+        let data_layer = DataLayer::new("", 500_000, false).await;
+        data_layer.sync("").await;
+        let metric = json!([{ "name": "test", "value": 5 }]).to_string();
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "metric_on_missing", metric.as_str())
+            .await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(1100)).await;
+        assert!(expression_engine
+            .evaluate_bool(
+                "get({ metric_id: 'metric_on_missing', period_sec: 1, on_missing: 'use_last' }) == 5"
+            )
+            .await
+            .unwrap());
+        assert!(expression_engine
+            .evaluate_bool("get({ metric_id: 'metric_on_missing', on_missing: 'unknown' }) == 5")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_anomaly() {
+        let data_layer = DataLayer::new("", 500_000, false).await;
+        data_layer.sync("").await;
+        let get_metrics_json = |values: Vec<i64>| {
+            json!(values
+                .iter()
+                .map(|value| json!({ "name": "requests", "value": value }))
+                .collect::<Vec<Value>>())
+            .to_string()
+        };
+        let baseline = get_metrics_json(vec![2, 4, 4, 4, 5, 5, 7, 9]);
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "metric_anomaly", baseline.as_str())
+            .await;
+        let baseline = get_metrics_json(vec![10, 11, 9, 10, 11, 9, 10]);
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "metric_anomaly_spike", baseline.as_str())
+            .await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(1100)).await;
+        let current = get_metrics_json(vec![9]);
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "metric_anomaly", current.as_str())
+            .await;
+        let current = get_metrics_json(vec![100]);
+        let _ = data_layer
+            .add_source_metrics_in_data_layer("vector", "metric_anomaly_spike", current.as_str())
+            .await;
+
+        // baseline: [2, 4, 4, 4, 5, 5, 7, 9] - mean: 5, standard deviation: 2 (the current value 9 is not in the baseline)
+        let expression_engine = create_expression_engine(EXPRESSION_LANGUAGE_JAVASCRIPT)
+            .await
+            .unwrap();
+        assert!(expression_engine
+            .evaluate_bool("get({ metric_id: 'metric_anomaly', stats: 'zscore', period_sec: 1, baseline_sec: 60 }) == 2")
+            .await
+            .unwrap());
+        assert!(expression_engine
+            .evaluate_bool("anomaly({ metric_id: 'metric_anomaly', period_sec: 1 }) == 2")
+            .await
+            .unwrap());
+        assert!(expression_engine
+            .evaluate_bool(
+                "anomaly({ metric_id: 'metric_anomaly', method: 'mad', period_sec: 1 }) > 2"
+            )
+            .await
+            .unwrap());
+        // A single spike is flagged because it doesn't raise the baseline
+        for method in ["zscore", "mad"] {
+            assert!(expression_engine
+                .evaluate_bool(
+                    format!(
+                        "anomaly({{ metric_id: 'metric_anomaly_spike', method: '{}', period_sec: 1 }}) > 3",
+                        method
+                    )
+                    .as_str()
+                )
+                .await
+                .unwrap());
+        }
+        // err: the baseline is not longer than the current window
+        assert!(expression_engine
+            .evaluate_bool(
+                "anomaly({ metric_id: 'metric_anomaly', period_sec: 60, baseline_sec: 60 }) > 0"
+            )
+            .await
+            .is_err());
+        let expression_engine = create_expression_engine(EXPRESSION_LANGUAGE_RHAI)
+            .await
+            .unwrap();
+        assert!(expression_engine
+            .evaluate_bool(r#"anomaly(#{ metric_id: "metric_anomaly", period_sec: 1 }) == 2.0"#)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_fallback_plan() {
+        let plan_id = uuid::Uuid::new_v4().to_string();
+        let fallback_plan_id = uuid::Uuid::new_v4().to_string();
+        // Create a ScalingPlanner
+        let (_data_layer, mut scaling_planner) = get_scaling_planner(vec![
+            PlanItemDefinition {
+                id: plan_id.clone(),
+                description: None,
+                expression: Some("get({ metric_id: 'metric_fallback' }) > 0".to_string()),
+                cron_expression: None,
+                priority: 2,
+                scaling_components: vec![],
+                ui: None,
+            },
+            PlanItemDefinition {
+                id: fallback_plan_id.clone(),
+                description: None,
+                expression: None,
+                cron_expression: None,
+                priority: 1,
+                scaling_components: vec![],
+                ui: None,
+            },
+        ])
+        .await;
+        scaling_planner.definition.metadata = HashMap::from([
+            ("on_missing".to_string(), json!("skip_plan")),
+            ("fallback_plan_id".to_string(), json!(fallback_plan_id)),
+        ]);
+        scaling_planner.run();
+
+        // The metric has no data, so the fallback plan is applied
+        tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+        {
+            let last_plan_id = scaling_planner.get_last_plan_item_id();
+            let shared_last_plan_id = last_plan_id.read().await;
+            assert_eq!(*shared_last_plan_id, fallback_plan_id);
+        }
+        scaling_planner.stop();
+    }
+
     #[tokio::test]
     async fn test_run_action_receiver() {
         let plan_item_id = uuid::Uuid::new_v4().to_string();
@@ -1,12 +1,19 @@
 use crate::{
-    metric_updater::SharedMetricUpdater, scaling_component::SharedScalingComponentManager,
+    metric_updater::SharedMetricUpdater,
+    scaling_component::SharedScalingComponentManager,
+    util::reconciler::{diff_definition_hashes, get_definition_hash, DefinitionDiff},
 };
 
-use super::ScalingPlanner;
+use super::expression_engine::{EXPRESSION_LANGUAGE_JAVASCRIPT, EXPRESSION_LANGUAGE_RHAI};
+use super::{get_expression_language, ScalingPlanner};
 use anyhow::Result;
-use data_layer::{data_layer::DataLayer, ScalingPlanDefinition};
+use data_layer::{
+    data_layer::DataLayer, ExpressionLibraryDefinition, HolidayCalendarDefinition,
+    ScalingPlanDefinition,
+};
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
+use tracing::error;
 //
 // PlannerManager
 //
@@ -14,9 +21,15 @@ pub type SharedScalingPlannerManager = Arc<RwLock<ScalingPlannerManager>>;
 
 pub struct ScalingPlannerManager {
     scaling_planners: HashMap<String, ScalingPlanner>,
+    // key: id, value: content hash of the definition
+    definition_hashes: HashMap<String, u64>,
     data_layer: Arc<DataLayer>,
     metric_updater: SharedMetricUpdater,
     scaling_component_manager: SharedScalingComponentManager,
+    // The expression libraries for all scaling planners
+    expression_libraries: Vec<ExpressionLibraryDefinition>,
+    // The holiday calendars for is_holiday() of all scaling planners
+    holiday_calendars: Vec<HolidayCalendarDefinition>,
 }
 
 impl ScalingPlannerManager {
@@ -27,9 +40,12 @@ impl ScalingPlannerManager {
     ) -> Self {
         ScalingPlannerManager {
             scaling_planners: HashMap::new(),
+            definition_hashes: HashMap::new(),
             data_layer,
             metric_updater,
             scaling_component_manager,
+            expression_libraries: Vec::new(),
+            holiday_calendars: Vec::new(),
         }
     }
     pub fn new_shared(
@@ -46,25 +62,129 @@ impl ScalingPlannerManager {
 
     // Factory method to create a scaling component.
     fn create_scaling_planner(&self, definition: ScalingPlanDefinition) -> Result<ScalingPlanner> {
-        Ok(ScalingPlanner::new(
+        let language = get_expression_language(&definition.metadata);
+        if language != EXPRESSION_LANGUAGE_JAVASCRIPT && language != EXPRESSION_LANGUAGE_RHAI {
+            return Err(anyhow::anyhow!(
+                "Unknown expression language of the scaling plan({}): {}",
+                definition.id,
+                language
+            ));
+        }
+        let mut scaling_planner = ScalingPlanner::new(
             definition,
             self.metric_updater.clone(),
             self.scaling_component_manager.clone(),
             self.data_layer.clone(),
+        );
+        scaling_planner.set_expression_libraries(self.expression_libraries.clone());
+        scaling_planner.set_holiday_calendars(&self.holiday_calendars);
+        Ok(scaling_planner)
+    }
+
+    // Set it before reconcile_definitions. The scaling planners restart when the libraries change.
+    pub fn set_expression_libraries(
+        &mut self,
+        expression_libraries: Vec<ExpressionLibraryDefinition>,
+    ) {
+        self.expression_libraries = expression_libraries;
+    }
+
+    // Set it before reconcile_definitions. The scaling planners restart when the calendars change.
+    pub fn set_holiday_calendars(&mut self, holiday_calendars: Vec<HolidayCalendarDefinition>) {
+        self.holiday_calendars = holiday_calendars;
+    }
+
+    // The hash includes the expression libraries and the holiday calendars so that the scaling planners restart when they change
+    fn get_scaling_planner_hash(&self, definition: &ScalingPlanDefinition) -> u64 {
+        get_definition_hash(&(
+            definition,
+            &self.expression_libraries,
+            &self.holiday_calendars,
         ))
     }
 
+    // It adds all the valid definitions and returns the errors of the invalid ones together
     pub fn add_definitions(
         &mut self,
         scaling_plan_definitions: Vec<ScalingPlanDefinition>,
     ) -> Result<()> {
+        let mut errors: Vec<String> = Vec::new();
         for scaling_plan_definition in scaling_plan_definitions {
-            let scaling_component = self.create_scaling_planner(scaling_plan_definition)?;
-            self.add_scaling_component(scaling_component);
+            let id = scaling_plan_definition.id.clone();
+            let hash = self.get_scaling_planner_hash(&scaling_plan_definition);
+            match self.create_scaling_planner(scaling_plan_definition) {
+                Ok(scaling_planner) => {
+                    self.add_scaling_component(scaling_planner);
+                    self.definition_hashes.insert(id, hash);
+                }
+                Err(error) => errors.push(error.to_string()),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Failed to create the scaling planners - {}",
+                errors.join(", ")
+            ));
         }
         Ok(())
     }
 
+    // Compare the new definitions with the running ones by id and content hash.
+    // Only the scaling planners that are added or changed are (re)started, and the removed ones are stopped.
+    // The unchanged scaling planners keep running with their state(e.g. cool down).
+    // The hash is saved after the scaling planner is created and started, so the failed ones are retried on the next reconcile.
+    // A failed changed definition keeps the running scaling planner.
+    pub fn reconcile_definitions(
+        &mut self,
+        scaling_plan_definitions: Vec<ScalingPlanDefinition>,
+    ) -> DefinitionDiff {
+        let new_hashes = scaling_plan_definitions
+            .iter()
+            .map(|definition| {
+                (
+                    definition.id.clone(),
+                    self.get_scaling_planner_hash(definition),
+                )
+            })
+            .collect::<HashMap<String, u64>>();
+        let mut diff = diff_definition_hashes(&self.definition_hashes, &new_hashes);
+
+        let ids_to_add = diff.get_ids_to_add();
+        let mut scaling_planners: Vec<ScalingPlanner> = Vec::new();
+        let mut failed: Vec<String> = Vec::new();
+        for scaling_plan_definition in scaling_plan_definitions {
+            if !ids_to_add.contains(&scaling_plan_definition.id) {
+                continue;
+            }
+            let id = scaling_plan_definition.id.clone();
+            match self.create_scaling_planner(scaling_plan_definition) {
+                Ok(scaling_planner) => scaling_planners.push(scaling_planner),
+                Err(error) => {
+                    error!("[ScalingPlannerManager] {}", error);
+                    failed.push(id);
+                }
+            }
+        }
+
+        for id in diff.removed.iter() {
+            self.remove_scaling_planner(id);
+        }
+        for mut scaling_planner in scaling_planners {
+            let id = scaling_planner.get_id();
+            // Stop the running one of the changed definition
+            if let Some(mut running_scaling_planner) = self.scaling_planners.remove(&id) {
+                running_scaling_planner.stop();
+            }
+            scaling_planner.run();
+            self.add_scaling_component(scaling_planner);
+            if let Some(new_hash) = new_hashes.get(&id) {
+                self.definition_hashes.insert(id, *new_hash);
+            }
+        }
+        diff.set_failed(failed);
+        diff
+    }
+
     pub fn add_scaling_component(&mut self, scaling_planner: ScalingPlanner) {
         self.scaling_planners
             .insert(scaling_planner.get_id(), scaling_planner);
@@ -76,6 +196,14 @@ impl ScalingPlannerManager {
 
     pub fn remove_all(&mut self) {
         self.scaling_planners.clear();
+        self.definition_hashes.clear();
+    }
+
+    pub fn remove_scaling_planner(&mut self, id: &str) {
+        if let Some(mut scaling_planner) = self.scaling_planners.remove(id) {
+            scaling_planner.stop();
+        }
+        self.definition_hashes.remove(id);
     }
 
     pub fn run(&mut self) {
@@ -89,3 +217,93 @@ impl ScalingPlannerManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric_updater::MetricUpdater;
+    use crate::scaling_component::ScalingComponentManager;
+    use data_layer::types::object_kind::ObjectKind;
+    use serde_json::json;
+
+    fn get_definition(id: &str, language: &str) -> ScalingPlanDefinition {
+        ScalingPlanDefinition {
+            id: id.to_string(),
+            db_id: "".to_string(),
+            kind: ObjectKind::ScalingPlan,
+            metadata: HashMap::from([("language".to_string(), json!(language))]),
+            plans: vec![],
+            enabled: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_definitions() {
+        let data_layer = DataLayer::new("", 500_000, false).await;
+        data_layer.sync("").await;
+        let data_layer = Arc::new(data_layer);
+        let metric_updater = Arc::new(RwLock::new(MetricUpdater::new(data_layer.clone(), 1000)));
+        let mut scaling_planner_manager = ScalingPlannerManager::new(
+            data_layer,
+            metric_updater,
+            ScalingComponentManager::new_shared(),
+        );
+
+        let diff = scaling_planner_manager.reconcile_definitions(vec![
+            get_definition("plan_1", "javascript"),
+            get_definition("plan_2", "javascript"),
+        ]);
+        assert_eq!(diff.added, vec!["plan_1", "plan_2"]);
+        assert_eq!(scaling_planner_manager.get_scaling_planners().len(), 2);
+
+        // Only the changed one is restarted
+        let diff = scaling_planner_manager.reconcile_definitions(vec![
+            get_definition("plan_1", "rhai"),
+            get_definition("plan_2", "javascript"),
+        ]);
+        assert_eq!(diff.changed, vec!["plan_1"]);
+        assert_eq!(diff.unchanged, vec!["plan_2"]);
+        assert_eq!(scaling_planner_manager.get_scaling_planners().len(), 2);
+        scaling_planner_manager.stop();
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_definitions_with_failed_definition() {
+        let data_layer = DataLayer::new("", 500_000, false).await;
+        data_layer.sync("").await;
+        let data_layer = Arc::new(data_layer);
+        let metric_updater = Arc::new(RwLock::new(MetricUpdater::new(data_layer.clone(), 1000)));
+        let mut scaling_planner_manager = ScalingPlannerManager::new(
+            data_layer,
+            metric_updater,
+            ScalingComponentManager::new_shared(),
+        );
+
+        // The first create fails but the valid one is started
+        let diff = scaling_planner_manager.reconcile_definitions(vec![
+            get_definition("plan_1", "python"),
+            get_definition("plan_2", "javascript"),
+        ]);
+        assert_eq!(diff.added, vec!["plan_2"]);
+        assert_eq!(diff.failed, vec!["plan_1"]);
+        assert_eq!(scaling_planner_manager.get_scaling_planners().len(), 1);
+
+        // The hash of the failed one is not saved, so it is retried instead of being unchanged
+        let diff = scaling_planner_manager.reconcile_definitions(vec![
+            get_definition("plan_1", "python"),
+            get_definition("plan_2", "javascript"),
+        ]);
+        assert!(diff.unchanged.contains(&"plan_2".to_string()));
+        assert_eq!(diff.failed, vec!["plan_1"]);
+
+        // The next reconcile succeeds
+        let diff = scaling_planner_manager.reconcile_definitions(vec![
+            get_definition("plan_1", "rhai"),
+            get_definition("plan_2", "javascript"),
+        ]);
+        assert_eq!(diff.added, vec!["plan_1"]);
+        assert!(diff.failed.is_empty());
+        assert_eq!(scaling_planner_manager.get_scaling_planners().len(), 2);
+        scaling_planner_manager.stop();
+    }
+}
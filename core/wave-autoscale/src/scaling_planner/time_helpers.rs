@@ -0,0 +1,152 @@
+/**
+ * Time Helpers
+ *
+ * The time and calendar functions for the expressions of the scaling plans.
+ * The timezone is an IANA name(e.g. 'Asia/Seoul') and UTC is used if it is omitted.
+ *
+ * - now(): the current Unix timestamp in milliseconds
+ * - hour(tz): 0 ~ 23
+ * - weekday(tz): 0(Sunday) ~ 6(Saturday) like Date.getDay() of JavaScript
+ * - in_window('09:00-18:00', tz): whether the current time is in the window. The window can span midnight(e.g. '22:00-06:00').
+ * - is_holiday(calendar_id, tz): whether today is in the dates of the HolidayCalendar definition
+ */
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+use data_layer::{types::holiday_calendar_definition::parse_date, HolidayCalendarDefinition};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+// HolidayCalendar id -> dates
+pub type HolidayCalendars = Arc<HashMap<String, HashSet<NaiveDate>>>;
+
+pub fn get_holiday_calendars(
+    holiday_calendar_definitions: &[HolidayCalendarDefinition],
+) -> HolidayCalendars {
+    let holiday_calendars = holiday_calendar_definitions
+        .iter()
+        .map(|holiday_calendar| {
+            let dates = holiday_calendar
+                .dates
+                .iter()
+                .filter_map(|date| parse_date(date))
+                .collect::<HashSet<NaiveDate>>();
+            (holiday_calendar.id.clone(), dates)
+        })
+        .collect::<HashMap<String, HashSet<NaiveDate>>>();
+    Arc::new(holiday_calendars)
+}
+
+pub fn parse_timezone(timezone: Option<&str>) -> Result<Tz> {
+    match timezone {
+        None => Ok(Tz::UTC),
+        Some(timezone) if timezone.is_empty() => Ok(Tz::UTC),
+        Some(timezone) => timezone
+            .parse::<Tz>()
+            .map_err(|_| anyhow!("Invalid timezone: {}", timezone)),
+    }
+}
+
+pub fn get_now_millis(now: &DateTime<Utc>) -> i64 {
+    now.timestamp_millis()
+}
+
+pub fn get_hour(now: &DateTime<Utc>, timezone: Option<&str>) -> Result<u32> {
+    let timezone = parse_timezone(timezone)?;
+    Ok(now.with_timezone(&timezone).hour())
+}
+
+pub fn get_weekday(now: &DateTime<Utc>, timezone: Option<&str>) -> Result<u32> {
+    let timezone = parse_timezone(timezone)?;
+    Ok(now
+        .with_timezone(&timezone)
+        .weekday()
+        .num_days_from_sunday())
+}
+
+// HH:MM
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    let (hour, minute) = time.trim().split_once(':')?;
+    NaiveTime::from_hms_opt(hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?, 0)
+}
+
+// window: HH:MM-HH:MM (the start is inclusive and the end is exclusive)
+pub fn is_in_window(now: &DateTime<Utc>, window: &str, timezone: Option<&str>) -> Result<bool> {
+    let timezone = parse_timezone(timezone)?;
+    let Some((start, end)) = window.split_once('-') else {
+        return Err(anyhow!("Invalid window: {}", window));
+    };
+    let (Some(start), Some(end)) = (parse_time(start), parse_time(end)) else {
+        return Err(anyhow!("Invalid window: {}", window));
+    };
+    let time = now.with_timezone(&timezone).time();
+    if start <= end {
+        Ok(start <= time && time < end)
+    } else {
+        // The window spans midnight
+        Ok(start <= time || time < end)
+    }
+}
+
+pub fn is_holiday(
+    now: &DateTime<Utc>,
+    holiday_calendars: &HolidayCalendars,
+    calendar_id: &str,
+    timezone: Option<&str>,
+) -> Result<bool> {
+    let timezone = parse_timezone(timezone)?;
+    let Some(dates) = holiday_calendars.get(calendar_id) else {
+        return Err(anyhow!("Unknown holiday calendar: {}", calendar_id));
+    };
+    Ok(dates.contains(&now.with_timezone(&timezone).date_naive()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // 2023-12-24 23:30:00 UTC (Sunday) = 2023-12-25 08:30:00 Asia/Seoul (Monday)
+    fn get_now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 12, 24, 23, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn test_hour_and_weekday() {
+        let now = get_now();
+        assert_eq!(get_hour(&now, None).unwrap(), 23);
+        assert_eq!(get_hour(&now, Some("Asia/Seoul")).unwrap(), 8);
+        assert_eq!(get_weekday(&now, None).unwrap(), 0);
+        assert_eq!(get_weekday(&now, Some("Asia/Seoul")).unwrap(), 1);
+        assert!(get_hour(&now, Some("Unknown/Timezone")).is_err());
+        assert_eq!(get_now_millis(&now), 1703460600000);
+    }
+
+    #[test]
+    fn test_is_in_window() {
+        let now = get_now();
+        assert!(!is_in_window(&now, "09:00-18:00", None).unwrap());
+        assert!(is_in_window(&now, "08:00-18:00", Some("Asia/Seoul")).unwrap());
+        assert!(!is_in_window(&now, "08:30-08:30", Some("Asia/Seoul")).unwrap());
+        // The window spans midnight
+        assert!(is_in_window(&now, "22:00-06:00", None).unwrap());
+        assert!(!is_in_window(&now, "22:00-06:00", Some("Asia/Seoul")).unwrap());
+        assert!(is_in_window(&now, "09:00", None).is_err());
+        assert!(is_in_window(&now, "09:00-25:00", None).is_err());
+    }
+
+    #[test]
+    fn test_is_holiday() {
+        let now = get_now();
+        let holiday_calendars = get_holiday_calendars(&[HolidayCalendarDefinition {
+            id: "kr".to_string(),
+            dates: vec!["2023-12-25".to_string()],
+            ..Default::default()
+        }]);
+        assert!(!is_holiday(&now, &holiday_calendars, "kr", None).unwrap());
+        assert!(is_holiday(&now, &holiday_calendars, "kr", Some("Asia/Seoul")).unwrap());
+        assert!(is_holiday(&now, &holiday_calendars, "unknown", None).is_err());
+    }
+}
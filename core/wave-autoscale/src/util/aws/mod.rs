@@ -7,8 +7,16 @@
  * https://github.com/awslabs/aws-sdk-rust
  */
 use super::aws_region::get_aws_region_static_str;
-use aws_config::{meta::region::RegionProviderChain, SdkConfig};
-use aws_credential_types::Credentials;
+use aws_config::{
+    default_provider::credentials::DefaultCredentialsChain,
+    meta::region::RegionProviderChain,
+    profile::{ProfileFileCredentialsProvider, ProfileFileRegionProvider},
+    sts::AssumeRoleProvider,
+    SdkConfig,
+};
+use aws_credential_types::{provider::SharedCredentialsProvider, Credentials};
+use aws_smithy_types::error::metadata::ProvideErrorMetadata;
+use serde_json::json;
 use std::{collections::HashMap, time::SystemTime};
 use thiserror::Error;
 use tracing::debug;
@@ -19,22 +27,80 @@ const PROVIDER_NAME: &str = "wave-autoscale";
 pub enum GetAwsConfigError {
     #[error("Failed to get AWS region: {0}")]
     GetAwsRegionError(String),
+    #[error("Invalid AWS endpoint url: {0}")]
+    InvalidEndpointUrl(String),
+    #[error("Unsupported AWS options: {0}")]
+    UnsupportedOptions(String),
 }
 
+// Options to build the SdkConfig. Every field is optional.
+#[derive(Debug, Default, Clone)]
+pub struct AwsConfigOptions {
+    pub region: Option<String>,
+    // Static credentials
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+    pub session_token: Option<String>,
+    pub expires_after: Option<SystemTime>,
+    // Named profile in ~/.aws/config and ~/.aws/credentials
+    pub profile: Option<String>,
+    // STS AssumeRole for cross-account scaling
+    pub role_arn: Option<String>,
+    pub external_id: Option<String>,
+    pub session_name: Option<String>,
+    // Override the endpoint of every service client (e.g. a local mock server)
+    // It can't be used with role_arn because the STS client of AssumeRoleProvider ignores it.
+    pub endpoint_url: Option<String>,
+}
+
+impl AwsConfigOptions {
+    pub fn from_metadata(metadata: &HashMap<String, serde_json::Value>) -> Self {
+        // as_str() instead of to_string() not to include the quotes
+        let get_string = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(|value| value.as_str())
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())
+        };
+        AwsConfigOptions {
+            region: get_string("region"),
+            access_key: get_string("access_key"),
+            secret_key: get_string("secret_key"),
+            session_token: get_string("session_token"),
+            expires_after: None,
+            profile: get_string("profile"),
+            role_arn: get_string("role_arn"),
+            external_id: get_string("external_id"),
+            session_name: get_string("session_name"),
+            endpoint_url: get_string("endpoint_url"),
+        }
+    }
+}
+
+// The error of the AWS SDK as the JSON error of the scaling components (message, code, extras)
+pub fn get_sdk_error<E: ProvideErrorMetadata + std::fmt::Display>(error: E) -> anyhow::Error {
+    let json = json!({
+        "message": error.message().unwrap_or(&error.to_string()),
+        "code": error.code(),
+        "extras": error.meta().to_string()
+    });
+    anyhow::anyhow!(json)
+}
+
+/**
+ * Get the SdkConfig with the metadata of the component
+ *
+ * metadata:
+ * - region, access_key, secret_key, session_token
+ * - profile: The named profile
+ * - role_arn, external_id, session_name: STS AssumeRole
+ * - endpoint_url: The endpoint override (not with role_arn)
+ */
 pub async fn get_aws_config_with_metadata(
     metadata: &HashMap<String, serde_json::Value>,
 ) -> Result<SdkConfig, GetAwsConfigError> {
-    let access_key = metadata
-        .get("access_key")
-        .map(|access_key| access_key.to_string());
-    let secret_key = metadata
-        .get("secret_key")
-        .map(|secret_key| secret_key.to_string());
-    let region: Option<String> = metadata
-        .get("region")
-        .and_then(|region| region.as_str().map(|region| region.to_string()));
-
-    get_aws_config(region, access_key, secret_key, None, None).await
+    get_aws_config_with_options(AwsConfigOptions::from_metadata(metadata)).await
 }
 
 pub async fn get_aws_config(
@@ -43,6 +109,20 @@ pub async fn get_aws_config(
     secret_key: Option<String>,
     session_token: Option<String>,
     expires_after: Option<SystemTime>,
+) -> Result<SdkConfig, GetAwsConfigError> {
+    get_aws_config_with_options(AwsConfigOptions {
+        region,
+        access_key,
+        secret_key,
+        session_token,
+        expires_after,
+        ..Default::default()
+    })
+    .await
+}
+
+pub async fn get_aws_config_with_options(
+    options: AwsConfigOptions,
 ) -> Result<SdkConfig, GetAwsConfigError> {
     // Provide your AWS credentials with the default credential provider chain, which currently looks in:
 
@@ -55,7 +135,7 @@ pub async fn get_aws_config(
     // Sep 5 2023: SSO Login is not working (https://github.com/awslabs/smithy-rs/pull/2917)
 
     // Region
-    let region_provider = if let Some(region) = region {
+    let region_static: Option<&'static str> = if let Some(region) = options.region.as_ref() {
         let region_static: &'static str = get_aws_region_static_str(region.as_str());
         if region_static.is_empty() {
             return Err(GetAwsConfigError::GetAwsRegionError(format!(
@@ -63,23 +143,89 @@ pub async fn get_aws_config(
                 region
             )));
         }
-        RegionProviderChain::first_try(region_static).or_default_provider()
+        Some(region_static)
+    } else {
+        None
+    };
+    let get_region_provider = || match (region_static, options.profile.as_ref()) {
+        (Some(region_static), _) => {
+            RegionProviderChain::first_try(region_static).or_default_provider()
+        }
+        // The region of the named profile
+        (None, Some(profile)) => RegionProviderChain::first_try(
+            ProfileFileRegionProvider::builder()
+                .profile_name(profile)
+                .build(),
+        )
+        .or_default_provider(),
+        (None, None) => RegionProviderChain::default_provider(),
+    };
+    let config = aws_config::from_env().region(get_region_provider());
+
+    // Endpoint
+    let config = if let Some(endpoint_url) = options.endpoint_url {
+        if !endpoint_url.starts_with("http://") && !endpoint_url.starts_with("https://") {
+            return Err(GetAwsConfigError::InvalidEndpointUrl(endpoint_url));
+        }
+        // AssumeRoleProvider(aws-config 0.55) builds its own STS client only with the region,
+        // so the credentials would be sent to the real STS instead of the endpoint.
+        if options.role_arn.is_some() {
+            return Err(GetAwsConfigError::UnsupportedOptions(
+                "endpoint_url can't be used with role_arn".to_string(),
+            ));
+        }
+        debug!("Using AWS endpoint url: {}", endpoint_url);
+        config.endpoint_url(endpoint_url)
     } else {
-        RegionProviderChain::default_provider()
+        config
     };
-    let config = aws_config::from_env().region(region_provider);
 
     // Credentials
-    let config = if let (Some(access_key), Some(secret_key)) = (access_key, secret_key) {
-        let credentials = Credentials::new(
-            access_key,
-            secret_key,
-            session_token,
-            expires_after,
-            PROVIDER_NAME,
+    let base_provider =
+        if let (Some(access_key), Some(secret_key)) = (options.access_key, options.secret_key) {
+            let credentials = Credentials::new(
+                access_key,
+                secret_key,
+                options.session_token,
+                options.expires_after,
+                PROVIDER_NAME,
+            );
+            debug!("Using provided AWS credentials");
+            Some(SharedCredentialsProvider::new(credentials))
+        } else if let Some(profile) = options.profile.as_ref() {
+            debug!("Using AWS profile: {}", profile);
+            Some(SharedCredentialsProvider::new(
+                ProfileFileCredentialsProvider::builder()
+                    .profile_name(profile)
+                    .build(),
+            ))
+        } else {
+            None
+        };
+    let config = if let Some(role_arn) = options.role_arn {
+        // The role is assumed with the provided credentials or the default chain
+        let base_provider = match base_provider {
+            Some(base_provider) => base_provider,
+            None => {
+                SharedCredentialsProvider::new(DefaultCredentialsChain::builder().build().await)
+            }
+        };
+        let mut assume_role = AssumeRoleProvider::builder(role_arn.as_str()).session_name(
+            options
+                .session_name
+                .unwrap_or_else(|| PROVIDER_NAME.to_string()),
         );
-        debug!("Using provided AWS credentials");
-        config.credentials_provider(credentials)
+        if let Some(external_id) = options.external_id {
+            assume_role = assume_role.external_id(external_id);
+        }
+        // STS needs the region as well
+        if let Some(region) = get_region_provider().region().await {
+            assume_role = assume_role.region(region);
+        }
+        debug!("Using AWS assume role: {}", role_arn);
+        config.credentials_provider(assume_role.build(base_provider))
+    } else if let Some(base_provider) = base_provider {
+        config.credentials_provider(base_provider)
     } else {
         debug!("Using default AWS credentials");
         config
@@ -106,4 +252,56 @@ mod tests {
         let config = get_aws_config(Some("us-east-1".to_string()), None, None, None, None).await;
         assert!(config.is_ok());
     }
+
+    #[test]
+    fn test_aws_config_options_from_metadata() {
+        let metadata: HashMap<String, serde_json::Value> = HashMap::from([
+            ("region".to_string(), serde_json::json!("us-east-1")),
+            ("access_key".to_string(), serde_json::json!("access_key")),
+            ("secret_key".to_string(), serde_json::json!("secret_key")),
+            ("role_arn".to_string(), serde_json::json!("role_arn")),
+            ("endpoint_url".to_string(), serde_json::json!("")),
+        ]);
+        let options = AwsConfigOptions::from_metadata(&metadata);
+        // No quotes
+        assert_eq!(options.access_key, Some("access_key".to_string()));
+        assert_eq!(options.role_arn, Some("role_arn".to_string()));
+        assert_eq!(options.endpoint_url, None);
+        assert_eq!(options.profile, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_aws_config_with_endpoint_url() {
+        let options = AwsConfigOptions {
+            region: Some("us-east-1".to_string()),
+            access_key: Some("test".to_string()),
+            secret_key: Some("test".to_string()),
+            endpoint_url: Some("http://localhost:4566".to_string()),
+            ..Default::default()
+        };
+        let config = get_aws_config_with_options(options).await;
+        assert!(config.is_ok());
+        assert_eq!(
+            config.unwrap().endpoint_url(),
+            Some("http://localhost:4566")
+        );
+
+        let options = AwsConfigOptions {
+            endpoint_url: Some("localhost:4566".to_string()),
+            ..Default::default()
+        };
+        assert!(get_aws_config_with_options(options).await.is_err());
+
+        // The assumed role doesn't use the endpoint
+        let options = AwsConfigOptions {
+            region: Some("us-east-1".to_string()),
+            role_arn: Some("arn:aws:iam::123456789012:role/wave-autoscale".to_string()),
+            endpoint_url: Some("http://localhost:4566".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            get_aws_config_with_options(options).await,
+            Err(GetAwsConfigError::UnsupportedOptions(_))
+        ));
+    }
 }
@@ -0,0 +1,146 @@
+use super::*;
+
+use reqwest::{Client, Error, Response};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_CLOUD_SQL_API_BASE_URL: &str = "https://sqladmin.googleapis.com";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CloudSqlInstanceSetting {
+    pub api_base_url: Option<String>,
+    pub project_name: String,
+    pub instance_name: String,
+    pub payload: Option<serde_json::Value>,
+}
+
+fn get_instances_url(cloud_sql_instance_setting: &CloudSqlInstanceSetting) -> String {
+    format!(
+        "{api_base_url}/v1/projects/{project_name}/instances",
+        api_base_url = get_api_base_url(
+            &cloud_sql_instance_setting.api_base_url,
+            DEFAULT_CLOUD_SQL_API_BASE_URL
+        ),
+        project_name = &cloud_sql_instance_setting.project_name,
+    )
+}
+
+// https://cloud.google.com/sql/docs/mysql/admin-api/rest/v1/instances/get
+pub async fn call_get_cloud_sql_instance(
+    cloud_sql_instance_setting: CloudSqlInstanceSetting,
+) -> Result<Response, Error> {
+    Client::new()
+        .get(format!(
+            "{}/{}",
+            get_instances_url(&cloud_sql_instance_setting),
+            &cloud_sql_instance_setting.instance_name
+        ))
+        .bearer_auth(get_gcp_credential_token().await.unwrap_or("".to_string()))
+        .send()
+        .await
+}
+
+// https://cloud.google.com/sql/docs/mysql/admin-api/rest/v1/instances/patch
+pub async fn call_patch_cloud_sql_instance(
+    cloud_sql_instance_setting: CloudSqlInstanceSetting,
+) -> Result<Response, Error> {
+    Client::new()
+        .patch(format!(
+            "{}/{}",
+            get_instances_url(&cloud_sql_instance_setting),
+            &cloud_sql_instance_setting.instance_name
+        ))
+        .bearer_auth(get_gcp_credential_token().await.unwrap_or("".to_string()))
+        .json(&cloud_sql_instance_setting.payload)
+        .send()
+        .await
+}
+
+// https://cloud.google.com/sql/docs/mysql/admin-api/rest/v1/instances/insert
+// The payload has the instance name (e.g. a read replica with masterInstanceName)
+pub async fn call_insert_cloud_sql_instance(
+    cloud_sql_instance_setting: CloudSqlInstanceSetting,
+) -> Result<Response, Error> {
+    Client::new()
+        .post(get_instances_url(&cloud_sql_instance_setting))
+        .bearer_auth(get_gcp_credential_token().await.unwrap_or("".to_string()))
+        .json(&cloud_sql_instance_setting.payload)
+        .send()
+        .await
+}
+
+// https://cloud.google.com/sql/docs/mysql/admin-api/rest/v1/instances/delete
+pub async fn call_delete_cloud_sql_instance(
+    cloud_sql_instance_setting: CloudSqlInstanceSetting,
+) -> Result<Response, Error> {
+    Client::new()
+        .delete(format!(
+            "{}/{}",
+            get_instances_url(&cloud_sql_instance_setting),
+            &cloud_sql_instance_setting.instance_name
+        ))
+        .bearer_auth(get_gcp_credential_token().await.unwrap_or("".to_string()))
+        .send()
+        .await
+}
+
+// https://cloud.google.com/sql/docs/mysql/admin-api/rest/v1/operations/get
+// The operation name is in the response of patch, insert and delete
+pub async fn call_get_cloud_sql_operation(
+    cloud_sql_instance_setting: CloudSqlInstanceSetting,
+    operation_name: &str,
+) -> Result<Response, Error> {
+    Client::new()
+        .get(format!(
+            "{api_base_url}/v1/projects/{project_name}/operations/{operation_name}",
+            api_base_url = get_api_base_url(
+                &cloud_sql_instance_setting.api_base_url,
+                DEFAULT_CLOUD_SQL_API_BASE_URL
+            ),
+            project_name = &cloud_sql_instance_setting.project_name,
+        ))
+        .bearer_auth(get_gcp_credential_token().await.unwrap_or("".to_string()))
+        .send()
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::mock_server::start_mock_server;
+
+    #[tokio::test]
+    async fn test_call_patch_cloud_sql_instance_with_mock() {
+        let (api_base_url, handle) = start_mock_server(vec![(200, "{}")]).await;
+        let cloud_sql_instance_setting = CloudSqlInstanceSetting {
+            api_base_url: Some(api_base_url),
+            project_name: "wave-autoscale-test".to_string(),
+            instance_name: "instance-1".to_string(),
+            payload: Some(serde_json::json!({ "settings": { "tier": "db-f1-micro" } })),
+        };
+
+        let response = call_patch_cloud_sql_instance(cloud_sql_instance_setting)
+            .await
+            .unwrap();
+        assert!(response.status() == reqwest::StatusCode::OK);
+        let requests = handle.await.unwrap();
+        assert_eq!(
+            requests[0],
+            "PATCH /v1/projects/wave-autoscale-test/instances/instance-1 {\"settings\":{\"tier\":\"db-f1-micro\"}}"
+        );
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_call_get_cloud_sql_instance() {
+        let cloud_sql_instance_setting = CloudSqlInstanceSetting {
+            api_base_url: None,
+            project_name: "wave-autoscale-test".to_string(),
+            instance_name: "instance-1".to_string(),
+            payload: None,
+        };
+        let response = call_get_cloud_sql_instance(cloud_sql_instance_setting)
+            .await
+            .unwrap();
+        assert!(response.status() == reqwest::StatusCode::OK);
+    }
+}
@@ -0,0 +1,119 @@
+use super::*;
+
+use reqwest::{Client, Error, Response};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_GKE_API_BASE_URL: &str = "https://container.googleapis.com";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GkeNodePoolSetting {
+    pub api_base_url: Option<String>,
+    pub project_name: String,
+    pub location_name: String,
+    pub cluster_name: String,
+    pub node_pool_name: String,
+    pub payload: Option<serde_json::Value>,
+}
+
+fn get_node_pool_url(gke_node_pool_setting: &GkeNodePoolSetting) -> String {
+    format!(
+        "{api_base_url}/v1/projects/{project_name}/locations/{location_name}/clusters/{cluster_name}/nodePools/{node_pool_name}",
+        api_base_url = get_api_base_url(&gke_node_pool_setting.api_base_url, DEFAULT_GKE_API_BASE_URL),
+        project_name = &gke_node_pool_setting.project_name,
+        location_name = &gke_node_pool_setting.location_name,
+        cluster_name = &gke_node_pool_setting.cluster_name,
+        node_pool_name = &gke_node_pool_setting.node_pool_name,
+    )
+}
+
+// https://cloud.google.com/kubernetes-engine/docs/reference/rest/v1/projects.locations.clusters.nodePools/get
+pub async fn call_get_gke_node_pool(
+    gke_node_pool_setting: GkeNodePoolSetting,
+) -> Result<Response, Error> {
+    Client::new()
+        .get(get_node_pool_url(&gke_node_pool_setting))
+        .bearer_auth(get_gcp_credential_token().await.unwrap_or("".to_string()))
+        .send()
+        .await
+}
+
+// https://cloud.google.com/kubernetes-engine/docs/reference/rest/v1/projects.locations.clusters.nodePools/setSize
+pub async fn call_post_gke_node_pool_set_size(
+    gke_node_pool_setting: GkeNodePoolSetting,
+) -> Result<Response, Error> {
+    Client::new()
+        .post(format!(
+            "{}:setSize",
+            get_node_pool_url(&gke_node_pool_setting)
+        ))
+        .bearer_auth(get_gcp_credential_token().await.unwrap_or("".to_string()))
+        .json(&gke_node_pool_setting.payload)
+        .send()
+        .await
+}
+
+// https://cloud.google.com/kubernetes-engine/docs/reference/rest/v1/projects.locations.clusters.nodePools/setAutoscaling
+pub async fn call_post_gke_node_pool_set_autoscaling(
+    gke_node_pool_setting: GkeNodePoolSetting,
+) -> Result<Response, Error> {
+    Client::new()
+        .post(format!(
+            "{}:setAutoscaling",
+            get_node_pool_url(&gke_node_pool_setting)
+        ))
+        .bearer_auth(get_gcp_credential_token().await.unwrap_or("".to_string()))
+        .json(&gke_node_pool_setting.payload)
+        .send()
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::mock_server::start_mock_server;
+
+    fn get_gke_node_pool_setting(api_base_url: Option<String>) -> GkeNodePoolSetting {
+        GkeNodePoolSetting {
+            api_base_url,
+            project_name: "wave-autoscale-test".to_string(),
+            location_name: "asia-northeast3".to_string(),
+            cluster_name: "cluster-1".to_string(),
+            node_pool_name: "pool-1".to_string(),
+            payload: None,
+        }
+    }
+
+    #[test]
+    fn test_get_node_pool_url() {
+        assert_eq!(
+            get_node_pool_url(&get_gke_node_pool_setting(None)),
+            "https://container.googleapis.com/v1/projects/wave-autoscale-test/locations/asia-northeast3/clusters/cluster-1/nodePools/pool-1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_call_post_gke_node_pool_set_size_with_mock() {
+        let (api_base_url, handle) = start_mock_server(vec![(200, "{}")]).await;
+        let mut gke_node_pool_setting = get_gke_node_pool_setting(Some(api_base_url));
+        gke_node_pool_setting.payload = Some(serde_json::json!({ "nodeCount": 3 }));
+
+        let response = call_post_gke_node_pool_set_size(gke_node_pool_setting)
+            .await
+            .unwrap();
+        assert!(response.status() == reqwest::StatusCode::OK);
+        let requests = handle.await.unwrap();
+        assert_eq!(
+            requests[0],
+            "POST /v1/projects/wave-autoscale-test/locations/asia-northeast3/clusters/cluster-1/nodePools/pool-1:setSize {\"nodeCount\":3}"
+        );
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_call_get_gke_node_pool() {
+        let response = call_get_gke_node_pool(get_gke_node_pool_setting(None))
+            .await
+            .unwrap();
+        assert!(response.status() == reqwest::StatusCode::OK);
+    }
+}
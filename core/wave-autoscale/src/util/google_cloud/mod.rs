@@ -1,8 +1,52 @@
 use gcp_auth::AuthenticationManager;
+use serde_json::json;
 use tracing::error;
 pub mod gcp_managed_instance_group;
 pub mod google_cloud_functions_instance_helper;
 pub mod google_cloud_run_service_helper;
+pub mod google_cloud_sql_instance_helper;
+pub mod google_kubernetes_engine_node_pool_helper;
+
+// The API base URL can be overridden in the metadata (e.g. util/mock_server in the tests)
+fn get_api_base_url(api_base_url: &Option<String>, default_api_base_url: &str) -> String {
+    api_base_url
+        .as_deref()
+        .unwrap_or(default_api_base_url)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+// Get the response body, or the error json of the failed call
+pub async fn get_gcp_response_body(
+    response: Result<reqwest::Response, reqwest::Error>,
+    message: &str,
+) -> Result<String, anyhow::Error> {
+    if response.is_err() {
+        return Err(anyhow::anyhow!(json!({
+            "message": format!("GCP API Call Error - {}", message),
+            "code": "500",
+            "extras": response.unwrap_err().to_string()
+        })));
+    }
+    let response = response.unwrap();
+    let status_code = response.status();
+    let Ok(body) = response.text().await else {
+        return Err(anyhow::anyhow!(json!({
+            "message": format!("GCP API Call Error - {}", message),
+            "code": "500",
+            "extras": "not found response text",
+        })));
+    };
+    if !status_code.is_success() {
+        error!("GCP API Call Error - {}: {:?}", message, body);
+        return Err(anyhow::anyhow!(json!({
+            "message": format!("GCP API Call Error: not success - {}", message),
+            "code": status_code.as_str(),
+            "extras": body
+        })));
+    }
+    Ok(body)
+}
 
 async fn get_gcp_credential_token() -> Result<String, anyhow::Error> {
     // Set ENV
@@ -39,6 +83,21 @@ async fn get_gcp_credential_token() -> Result<String, anyhow::Error> {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_get_api_base_url() {
+        assert_eq!(
+            get_api_base_url(&None, "https://container.googleapis.com"),
+            "https://container.googleapis.com"
+        );
+        assert_eq!(
+            get_api_base_url(
+                &Some("http://localhost:8080/".to_string()),
+                "https://container.googleapis.com"
+            ),
+            "http://localhost:8080"
+        );
+    }
+
     #[ignore]
     #[tokio::test]
     async fn test_get_gcp_credential_token() {
@@ -0,0 +1,277 @@
+/**
+ * Kubernetes Module
+ *
+ * Creates a Kubernetes client with the metadata of the scaling components.
+ * 1. kubeconfig and/or context: the kubeconfig file (default: KUBECONFIG or ~/.kube/config) and the context in it
+ * 2. api_server_endpoint: the API server with the credentials below
+ *    - ca_cert: the CA certificate of the API server (base64 encoded like certificate-authority-data in kubeconfig)
+ *    - token: the bearer token (e.g. a token of a ServiceAccount)
+ *    - client_cert, client_key: the client certificate and key (base64 encoded like client-certificate-data in kubeconfig)
+ *    - insecure_skip_tls_verify: skip the verification of the certificate of the API server
+ * 3. Otherwise, the in-cluster config or the default kubeconfig is inferred.
+ */
+use anyhow::Result;
+use kube::{
+    api::{Api, DynamicObject},
+    config::{KubeConfigOptions, Kubeconfig},
+    core::GroupVersion,
+    discovery::{self, Scope},
+    Client, Config,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+// The names of the cluster, user and context in the kubeconfig made from the metadata
+const KUBECONFIG_NAME: &str = "wave-autoscale";
+
+fn get_metadata_string(metadata: &HashMap<String, Value>, key: &str) -> Option<String> {
+    metadata
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+}
+
+pub async fn get_k8s_config_with_metadata(metadata: &HashMap<String, Value>) -> Result<Config> {
+    let kubeconfig_path = get_metadata_string(metadata, "kubeconfig");
+    let context = get_metadata_string(metadata, "context");
+    if kubeconfig_path.is_some() || context.is_some() {
+        debug!(
+            "[k8s] Using the kubeconfig: {:?}, context: {:?}",
+            kubeconfig_path, context
+        );
+        let kubeconfig = match kubeconfig_path {
+            Some(kubeconfig_path) => Kubeconfig::read_from(kubeconfig_path)?,
+            None => Kubeconfig::read()?,
+        };
+        let options = KubeConfigOptions {
+            context,
+            ..Default::default()
+        };
+        return Ok(Config::from_custom_kubeconfig(kubeconfig, &options).await?);
+    }
+
+    let Some(api_server_endpoint) = get_metadata_string(metadata, "api_server_endpoint") else {
+        debug!("[k8s] Inferring the config from the runtime environment");
+        return Ok(Config::infer().await?);
+    };
+    debug!("[k8s] Using the API server: {}", api_server_endpoint);
+    let mut cluster = json!({ "server": api_server_endpoint });
+    if let Some(ca_cert) = get_metadata_string(metadata, "ca_cert") {
+        cluster["certificate-authority-data"] = json!(ca_cert);
+    }
+    if let Some(Value::Bool(insecure_skip_tls_verify)) = metadata.get("insecure_skip_tls_verify") {
+        cluster["insecure-skip-tls-verify"] = json!(insecure_skip_tls_verify);
+    }
+    let mut user = json!({});
+    if let Some(token) = get_metadata_string(metadata, "token") {
+        user["token"] = json!(token);
+    }
+    if let (Some(client_cert), Some(client_key)) = (
+        get_metadata_string(metadata, "client_cert"),
+        get_metadata_string(metadata, "client_key"),
+    ) {
+        user["client-certificate-data"] = json!(client_cert);
+        user["client-key-data"] = json!(client_key);
+    }
+    let mut context = json!({ "cluster": KUBECONFIG_NAME, "user": KUBECONFIG_NAME });
+    if let Some(namespace) = get_metadata_string(metadata, "namespace") {
+        context["namespace"] = json!(namespace);
+    }
+    let kubeconfig: Kubeconfig = serde_json::from_value(json!({
+        "clusters": [{ "name": KUBECONFIG_NAME, "cluster": cluster }],
+        "users": [{ "name": KUBECONFIG_NAME, "user": user }],
+        "contexts": [{ "name": KUBECONFIG_NAME, "context": context }],
+        "current-context": KUBECONFIG_NAME,
+    }))?;
+    Ok(Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default()).await?)
+}
+
+pub async fn get_k8s_client_with_metadata(metadata: &HashMap<String, Value>) -> Result<Client> {
+    let config = get_k8s_config_with_metadata(metadata).await?;
+    Ok(Client::try_from(config)?)
+}
+
+// The API of any kind by api_version(e.g. apps/v1, argoproj.io/v1alpha1) and kind(e.g. StatefulSet) with the discovery
+pub async fn get_dynamic_api(
+    client: Client,
+    namespace: &str,
+    api_version: &str,
+    kind: &str,
+) -> Result<Api<DynamicObject>> {
+    let group_version = api_version.parse::<GroupVersion>()?;
+    let (api_resource, capabilities) =
+        discovery::pinned_kind(&client, &group_version.with_kind(kind)).await?;
+    let api = if capabilities.scope == Scope::Cluster {
+        Api::all_with(client, &api_resource)
+    } else {
+        Api::namespaced_with(client, namespace, &api_resource)
+    };
+    Ok(api)
+}
+
+// The client and the API of the resource of a scaling component.
+// They are created once per component (the component is recreated when the definition changes).
+#[derive(Default)]
+pub struct K8sClientCache {
+    client: OnceCell<Client>,
+    // The API of the resource is resolved with the discovery once
+    api: OnceCell<Api<DynamicObject>>,
+}
+
+impl K8sClientCache {
+    pub fn new() -> Self {
+        K8sClientCache::default()
+    }
+
+    pub async fn get_client(&self, metadata: &HashMap<String, Value>) -> Result<Client> {
+        let client = self
+            .client
+            .get_or_try_init(|| get_k8s_client_with_metadata(metadata))
+            .await?;
+        Ok(client.clone())
+    }
+
+    // The namespace, api_version and kind should not change while the component lives
+    pub async fn get_dynamic_api(
+        &self,
+        metadata: &HashMap<String, Value>,
+        namespace: &str,
+        api_version: &str,
+        kind: &str,
+    ) -> Result<Api<DynamicObject>> {
+        let api = self
+            .api
+            .get_or_try_init(|| async {
+                let client = self.get_client(metadata).await?;
+                get_dynamic_api(client, namespace, api_version, kind).await
+            })
+            .await?;
+        Ok(api.clone())
+    }
+
+    // For unit testing
+    #[cfg(test)]
+    pub fn set_api(&self, api: Api<DynamicObject>) {
+        let _ = self.api.set(api);
+    }
+}
+
+// The integer field in the status of the object. 0 if the field doesn't exist yet.
+pub fn get_status_i64(object: &DynamicObject, field: &str) -> i64 {
+    object
+        .data
+        .get("status")
+        .and_then(|status| status.get(field))
+        .and_then(Value::as_i64)
+        .unwrap_or(0)
+}
+
+// The CPU quantity(e.g. 500m, 1, 0.5) in millicores
+pub fn parse_cpu_millicores(quantity: &str) -> Option<i64> {
+    let quantity = quantity.trim();
+    if let Some(millicores) = quantity.strip_suffix('m') {
+        return millicores
+            .parse::<f64>()
+            .ok()
+            .map(|value| value.ceil() as i64);
+    }
+    quantity
+        .parse::<f64>()
+        .ok()
+        .map(|value| (value * 1000.0).ceil() as i64)
+}
+
+// The memory quantity(e.g. 512Mi, 1Gi, 1G, 1000000, 129e6, 1500m) in MiB
+pub fn parse_memory_mib(quantity: &str) -> Option<i64> {
+    const MIB: f64 = 1024.0 * 1024.0;
+    let quantity = quantity.trim();
+    // The exponent forms(e.g. 129e6, 1E3) are parsed as numbers, so "E" is the suffix only after a digit
+    let suffixes: [(&str, f64); 13] = [
+        ("Ki", 1024.0),
+        ("Mi", MIB),
+        ("Gi", MIB * 1024.0),
+        ("Ti", MIB * 1024.0 * 1024.0),
+        ("Pi", MIB * 1024.0 * 1024.0 * 1024.0),
+        ("Ei", MIB * 1024.0 * 1024.0 * 1024.0 * 1024.0),
+        ("m", 1e-3),
+        ("k", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+        ("T", 1e12),
+        ("P", 1e15),
+        ("E", 1e18),
+    ];
+    let (number, multiplier) = suffixes
+        .iter()
+        .find_map(|(suffix, multiplier)| {
+            quantity
+                .strip_suffix(suffix)
+                .filter(|number| number.ends_with(|c: char| c.is_ascii_digit() || c == '.'))
+                .map(|number| (number, *multiplier))
+        })
+        .unwrap_or((quantity, 1.0));
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .map(|value| (value * multiplier / MIB).ceil() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_k8s_config_with_metadata() {
+        let metadata = HashMap::from([
+            (
+                "api_server_endpoint".to_string(),
+                json!("https://10.0.0.1:6443"),
+            ),
+            ("namespace".to_string(), json!("wave")),
+            ("token".to_string(), json!("token")),
+        ]);
+        let config = get_k8s_config_with_metadata(&metadata).await.unwrap();
+        assert_eq!(config.cluster_url.to_string(), "https://10.0.0.1:6443/");
+        assert_eq!(config.default_namespace, "wave");
+        assert!(config.auth_info.token.is_some());
+
+        let metadata = HashMap::from([(
+            "kubeconfig".to_string(),
+            json!("./tests/not-found-kubeconfig"),
+        )]);
+        assert!(get_k8s_config_with_metadata(&metadata).await.is_err());
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(parse_cpu_millicores("500m"), Some(500));
+        assert_eq!(parse_cpu_millicores("1.5"), Some(1500));
+        assert_eq!(parse_cpu_millicores("cpu"), None);
+        assert_eq!(parse_memory_mib("512Mi"), Some(512));
+        assert_eq!(parse_memory_mib("2Gi"), Some(2048));
+        assert_eq!(parse_memory_mib("1G"), Some(954));
+        assert_eq!(parse_memory_mib("1048576"), Some(1));
+        assert_eq!(parse_memory_mib("1073741824000m"), Some(1024));
+        assert_eq!(parse_memory_mib("129e6"), Some(124));
+        assert_eq!(parse_memory_mib("1E9"), Some(954));
+        assert_eq!(parse_memory_mib("1E"), Some(953_674_316_407));
+        assert_eq!(parse_memory_mib("memory"), None);
+    }
+
+    #[test]
+    fn test_get_status_i64() {
+        let mut object = DynamicObject::new(
+            "web",
+            &kube::api::ApiResource::from_gvk(
+                &GroupVersion::gv("apps", "v1").with_kind("StatefulSet"),
+            ),
+        );
+        object.data = json!({ "status": { "replicas": 3, "readyReplicas": 2 } });
+        assert_eq!(get_status_i64(&object, "readyReplicas"), 2);
+        assert_eq!(get_status_i64(&object, "updatedReplicas"), 0);
+    }
+}
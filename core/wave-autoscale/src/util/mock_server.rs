@@ -0,0 +1,71 @@
+/**
+ * Mock Server
+ *
+ * A local HTTP server for the tests of the scaling components.
+ * The components send the requests to it with the endpoint override in the metadata
+ * (e.g. endpoint_url of util/aws, api_base_url of util/google_cloud).
+ */
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+// A local mock server that replies the responses in order (one request per connection)
+// and returns the received requests as "METHOD PATH BODY"
+pub async fn start_mock_server(
+    responses: Vec<(u16, &'static str)>,
+) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for (status, body) in responses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = Vec::new();
+            let mut chunk = [0u8; 4096];
+            // Read the headers and the body with the content length
+            loop {
+                let read = stream.read(&mut chunk).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                buffer.extend_from_slice(&chunk[..read]);
+                let request = String::from_utf8_lossy(&buffer).to_string();
+                let Some(header_end) = request.find("\r\n\r\n") else {
+                    continue;
+                };
+                let content_length = request[..header_end]
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        if name.eq_ignore_ascii_case("content-length") {
+                            value.trim().parse::<usize>().ok()
+                        } else {
+                            None
+                        }
+                    })
+                    .unwrap_or(0);
+                if buffer.len() >= header_end + 4 + content_length {
+                    break;
+                }
+            }
+            let request = String::from_utf8_lossy(&buffer).to_string();
+            let request_line = request.lines().next().unwrap_or_default();
+            let request_body = request.split_once("\r\n\r\n").unwrap_or_default().1;
+            let mut request_line = request_line.split(' ');
+            requests.push(format!(
+                "{} {} {}",
+                request_line.next().unwrap_or_default(),
+                request_line.next().unwrap_or_default(),
+                request_body
+            ));
+            let response = format!(
+                "HTTP/1.1 {} MOCK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            stream.shutdown().await.unwrap();
+        }
+        requests
+    });
+    (base_url, handle)
+}
@@ -3,5 +3,9 @@ pub mod aws_region;
 pub mod azure;
 pub mod cloudflare;
 pub mod google_cloud;
+pub mod k8s;
 pub mod log;
+#[cfg(test)]
+pub mod mock_server;
+pub mod reconciler;
 pub mod string;
@@ -0,0 +1,151 @@
+use serde::Serialize;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+// Hash of the content of a definition.
+// It is converted to serde_json::Value first because the key order of a serde_json::Map is sorted,
+// while the key order of HashMap<String, Value>(e.g. metadata) is not deterministic.
+pub fn get_definition_hash<T: Serialize>(definition: &T) -> u64 {
+    let content = serde_json::json!(definition).to_string();
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+// The result of comparing the running definitions with the new definitions by id and content hash
+#[derive(Debug, Default, PartialEq)]
+pub struct DefinitionDiff {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: Vec<String>,
+    // The ids that failed to be created. They are retried on the next reconcile.
+    pub failed: Vec<String>,
+}
+
+impl DefinitionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+    // The ids that have to be stopped or removed
+    pub fn get_ids_to_remove(&self) -> Vec<String> {
+        [self.changed.clone(), self.removed.clone()].concat()
+    }
+    // The ids that have to be created or restarted
+    pub fn get_ids_to_add(&self) -> Vec<String> {
+        [self.added.clone(), self.changed.clone()].concat()
+    }
+    // Move the ids that failed to be created out of added and changed
+    pub fn set_failed(&mut self, failed: Vec<String>) {
+        self.added.retain(|id| !failed.contains(id));
+        self.changed.retain(|id| !failed.contains(id));
+        self.failed = failed;
+        self.failed.sort();
+    }
+}
+
+impl std::fmt::Display for DefinitionDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "added: {:?}, changed: {:?}, removed: {:?}, unchanged: {}, failed: {:?}",
+            self.added,
+            self.changed,
+            self.removed,
+            self.unchanged.len(),
+            self.failed
+        )
+    }
+}
+
+// Compare the hashes(key: id, value: content hash) of the running definitions with the new ones
+pub fn diff_definition_hashes(
+    old_hashes: &HashMap<String, u64>,
+    new_hashes: &HashMap<String, u64>,
+) -> DefinitionDiff {
+    let mut diff = DefinitionDiff::default();
+    for (id, new_hash) in new_hashes.iter() {
+        match old_hashes.get(id) {
+            Some(old_hash) if old_hash == new_hash => diff.unchanged.push(id.clone()),
+            Some(_) => diff.changed.push(id.clone()),
+            None => diff.added.push(id.clone()),
+        }
+    }
+    for id in old_hashes.keys() {
+        if !new_hashes.contains_key(id) {
+            diff.removed.push(id.clone());
+        }
+    }
+    diff.added.sort();
+    diff.changed.sort();
+    diff.removed.sort();
+    diff.unchanged.sort();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_layer::ScalingComponentDefinition;
+    use serde_json::json;
+
+    #[test]
+    fn test_get_definition_hash() {
+        let definition = ScalingComponentDefinition {
+            id: "component_1".to_string(),
+            component_kind: "wa-logger".to_string(),
+            metadata: HashMap::from([
+                ("key1".to_string(), json!("value1")),
+                ("key2".to_string(), json!({"a": 1, "b": 2})),
+                ("key3".to_string(), json!([1, 2, 3])),
+            ]),
+            ..Default::default()
+        };
+        let same_definition = definition.clone();
+        assert_eq!(
+            get_definition_hash(&definition),
+            get_definition_hash(&same_definition)
+        );
+
+        let mut changed_definition = definition.clone();
+        changed_definition
+            .metadata
+            .insert("key1".to_string(), json!("value2"));
+        assert_ne!(
+            get_definition_hash(&definition),
+            get_definition_hash(&changed_definition)
+        );
+    }
+
+    #[test]
+    fn test_diff_definition_hashes() {
+        let old_hashes = HashMap::from([
+            ("unchanged".to_string(), 1),
+            ("changed".to_string(), 2),
+            ("removed".to_string(), 3),
+        ]);
+        let new_hashes = HashMap::from([
+            ("unchanged".to_string(), 1),
+            ("changed".to_string(), 4),
+            ("added".to_string(), 5),
+        ]);
+        let diff = diff_definition_hashes(&old_hashes, &new_hashes);
+        assert_eq!(diff.added, vec!["added"]);
+        assert_eq!(diff.changed, vec!["changed"]);
+        assert_eq!(diff.removed, vec!["removed"]);
+        assert_eq!(diff.unchanged, vec!["unchanged"]);
+        assert_eq!(diff.get_ids_to_remove(), vec!["changed", "removed"]);
+        assert_eq!(diff.get_ids_to_add(), vec!["added", "changed"]);
+        assert!(!diff.is_empty());
+
+        let mut diff = diff_definition_hashes(&old_hashes, &new_hashes);
+        diff.set_failed(vec!["added".to_string()]);
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.failed, vec!["added"]);
+
+        let diff = diff_definition_hashes(&new_hashes, &new_hashes);
+        assert!(diff.is_empty());
+    }
+}
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ExpressionEvaluationRequest { expression: string, language: string | null, time_offset_sec: bigint | null, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ExpressionEvaluationError } from "./expression-evaluation-error";
+
+export interface ExpressionEvaluationResult { result: any, get_values: Array<Record<string, number | null>>, logs: Array<string>, stale_metric_ids: Array<string>, error: ExpressionEvaluationError | null, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ObjectKind } from "./object-kind";
+
+export interface ExpressionLibraryDefinition { kind: ObjectKind, db_id: string, id: string, language: string, metadata: object, code: string, enabled: boolean, }
\ No newline at end of file
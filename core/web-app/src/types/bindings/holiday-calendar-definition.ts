@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ObjectKind } from "./object-kind";
+
+export interface HolidayCalendarDefinition { kind: ObjectKind, db_id: string, id: string, metadata: object, dates: Array<string>, ics: string | null, ics_path: string | null, enabled: boolean, }
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type ObjectKind = "Metric" | "ScalingPlan" | "ScalingComponent" | "SLO";
\ No newline at end of file
+export type ObjectKind = "Metric" | "ScalingPlan" | "ScalingComponent" | "SLO" | "ExpressionLibrary" | "HolidayCalendar";
\ No newline at end of file